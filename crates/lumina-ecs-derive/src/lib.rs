@@ -0,0 +1,314 @@
+//! Derive macros for `lumina-ecs`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Index};
+
+/// Derives `lumina_ecs::Reflect` for structs and fieldless enums.
+///
+/// Fields marked `#[reflect(ignore)]` are skipped and keep their value when
+/// a reflected value is applied.
+#[proc_macro_derive(Reflect, attributes(reflect))]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_reflect(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Derives `lumina_ecs::Bundle` for structs whose fields are components.
+///
+/// Fields marked `#[bundle]` are nested bundles and contribute all of their
+/// components.
+#[proc_macro_derive(Bundle, attributes(bundle))]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand_bundle(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn is_ignored(attrs: &[syn::Attribute]) -> syn::Result<bool> {
+    let mut ignored = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("reflect")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("ignore") {
+                ignored = true;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported reflect attribute"))
+            }
+        })?;
+    }
+    Ok(ignored)
+}
+
+fn expand_reflect(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let ecs = quote!(::lumina_ecs);
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            // (field name, access expression, field type) for every reflected field.
+            let mut fields = Vec::new();
+            for (index, field) in data.fields.iter().enumerate() {
+                if is_ignored(&field.attrs)? {
+                    continue;
+                }
+                match &field.ident {
+                    Some(ident) => fields.push((ident.to_string(), quote!(#ident), &field.ty)),
+                    None => {
+                        let index = Index::from(index);
+                        fields.push((index.index.to_string(), quote!(#index), &field.ty));
+                    }
+                }
+            }
+
+            let names: Vec<_> = fields.iter().map(|(name, _, _)| name).collect();
+            let accessors: Vec<_> = fields.iter().map(|(_, access, _)| access).collect();
+            let types: Vec<_> = fields.iter().map(|(_, _, ty)| ty).collect();
+
+            let (info_kind, value_ctor, apply_body) = match &data.fields {
+                Fields::Named(_) | Fields::Unit => (
+                    quote!(Struct),
+                    quote! {
+                        #ecs::Value::Struct(vec![
+                            #((#names.to_string(), #ecs::Reflect::to_value(&self.#accessors)),)*
+                        ])
+                    },
+                    quote! {
+                        match value {
+                            #ecs::Value::Struct(values) => {
+                                for (field, value) in values {
+                                    match field.as_str() {
+                                        #(#names => #ecs::Reflect::apply(&mut self.#accessors, value)?,)*
+                                        other => return Err(#ecs::ReflectError::NoSuchField(
+                                            format!("{}.{}", #name_str, other),
+                                        )),
+                                    }
+                                }
+                                Ok(())
+                            }
+                            other => Err(#ecs::ReflectError::TypeMismatch {
+                                expected: #name_str,
+                                found: other.kind(),
+                            }),
+                        }
+                    },
+                ),
+                Fields::Unnamed(_) => {
+                    let positions: Vec<_> = (0..fields.len()).collect();
+                    (
+                        quote!(TupleStruct),
+                        quote! {
+                            #ecs::Value::List(vec![
+                                #(#ecs::Reflect::to_value(&self.#accessors),)*
+                            ])
+                        },
+                        quote! {
+                            match value {
+                                #ecs::Value::List(values) => {
+                                    #(
+                                        if let Some(value) = values.get(#positions) {
+                                            #ecs::Reflect::apply(&mut self.#accessors, value)?;
+                                        }
+                                    )*
+                                    Ok(())
+                                }
+                                other => Err(#ecs::ReflectError::TypeMismatch {
+                                    expected: #name_str,
+                                    found: other.kind(),
+                                }),
+                            }
+                        },
+                    )
+                }
+            };
+
+            quote! {
+                fn type_info(&self) -> #ecs::TypeInfo {
+                    #ecs::TypeInfo::#info_kind {
+                        fields: vec![
+                            #(#ecs::FieldInfo::new(#names, ::std::any::type_name::<#types>()),)*
+                        ],
+                    }
+                }
+
+                fn field(&self, name: &str) -> Option<&dyn #ecs::Reflect> {
+                    match name {
+                        #(#names => Some(&self.#accessors),)*
+                        _ => None,
+                    }
+                }
+
+                fn field_mut(&mut self, name: &str) -> Option<&mut dyn #ecs::Reflect> {
+                    match name {
+                        #(#names => Some(&mut self.#accessors),)*
+                        _ => None,
+                    }
+                }
+
+                fn to_value(&self) -> #ecs::Value {
+                    #value_ctor
+                }
+
+                fn apply(&mut self, value: &#ecs::Value) -> ::core::result::Result<(), #ecs::ReflectError> {
+                    #apply_body
+                }
+            }
+        }
+        Data::Enum(data) => {
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                if !matches!(variant.fields, Fields::Unit) {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        "Reflect can only be derived for enums without fields",
+                    ));
+                }
+                variants.push(&variant.ident);
+            }
+            let variant_names: Vec<_> = variants.iter().map(|v| v.to_string()).collect();
+
+            quote! {
+                fn type_info(&self) -> #ecs::TypeInfo {
+                    #ecs::TypeInfo::Enum {
+                        variants: vec![#(#variant_names,)*],
+                    }
+                }
+
+                fn to_value(&self) -> #ecs::Value {
+                    let variant = match self {
+                        #(Self::#variants => #variant_names,)*
+                    };
+                    #ecs::Value::String(variant.to_string())
+                }
+
+                fn apply(&mut self, value: &#ecs::Value) -> ::core::result::Result<(), #ecs::ReflectError> {
+                    match value {
+                        #ecs::Value::String(variant) => {
+                            *self = match variant.as_str() {
+                                #(#variant_names => Self::#variants,)*
+                                other => return Err(#ecs::ReflectError::NoSuchVariant(
+                                    format!("{}::{}", #name_str, other),
+                                )),
+                            };
+                            Ok(())
+                        }
+                        other => Err(#ecs::ReflectError::TypeMismatch {
+                            expected: #name_str,
+                            found: other.kind(),
+                        }),
+                    }
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                input,
+                "Reflect cannot be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics #ecs::Reflect for #name #ty_generics #where_clause {
+            fn type_name(&self) -> &'static str {
+                #name_str
+            }
+
+            #body
+
+            fn as_any(&self) -> &dyn ::std::any::Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn ::std::any::Any {
+                self
+            }
+
+            fn into_any(self: Box<Self>) -> Box<dyn ::std::any::Any> {
+                self
+            }
+        }
+    })
+}
+
+fn expand_bundle(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let ecs = quote!(::lumina_ecs);
+
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            input,
+            "Bundle can only be derived for structs",
+        ));
+    };
+
+    let mut component_ids = Vec::new();
+    let mut inserts = Vec::new();
+    let mut takes = Vec::new();
+    let mut removes = Vec::new();
+    for (index, field) in data.fields.iter().enumerate() {
+        let ty = &field.ty;
+        let access = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = Index::from(index);
+                quote!(#index)
+            }
+        };
+        let nested = field.attrs.iter().any(|attr| attr.path().is_ident("bundle"));
+        if nested {
+            component_ids.push(quote!(<#ty as #ecs::Bundle>::component_ids(ids);));
+            inserts.push(quote!(#ecs::Bundle::insert_components(self.#access, world, entity, inserted);));
+            takes.push(quote!(<#ty as #ecs::Bundle>::take_components(world, entity)?));
+            removes.push(quote!(<#ty as #ecs::Bundle>::remove_components(world, entity);));
+        } else {
+            component_ids.push(quote!(ids.push(::std::any::TypeId::of::<#ty>());));
+            inserts.push(quote!(world.insert_bundle_component(entity, self.#access, inserted);));
+            takes.push(quote!(world.take_bundle_component::<#ty>(entity)?));
+            removes.push(quote!(world.take_bundle_component::<#ty>(entity);));
+        }
+    }
+
+    let construct = match &data.fields {
+        Fields::Named(fields) => {
+            let idents = fields.named.iter().map(|field| &field.ident);
+            quote!(Self { #(#idents: #takes,)* })
+        }
+        Fields::Unnamed(_) => quote!(Self(#(#takes,)*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    Ok(quote! {
+        impl #impl_generics #ecs::Bundle for #name #ty_generics #where_clause {
+            fn component_ids(ids: &mut Vec<::std::any::TypeId>) {
+                #(#component_ids)*
+            }
+
+            fn insert_components(
+                self,
+                world: &#ecs::World,
+                entity: #ecs::Entity,
+                inserted: &mut Vec<(::std::any::TypeId, bool)>,
+            ) {
+                #(#inserts)*
+            }
+
+            fn take_components(world: &#ecs::World, entity: #ecs::Entity) -> Option<Self> {
+                Some(#construct)
+            }
+
+            fn remove_components(world: &#ecs::World, entity: #ecs::Entity) {
+                #(#removes)*
+            }
+        }
+    })
+}
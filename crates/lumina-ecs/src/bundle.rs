@@ -0,0 +1,222 @@
+use crate::{Component, Entity, World};
+use std::any::TypeId;
+use std::collections::HashSet;
+
+/// A group of components inserted or removed together.
+///
+/// Implemented for tuples of up to eight components, and derivable with
+/// `#[derive(Bundle)]` for structs whose fields are components. Fields marked
+/// `#[bundle]` are nested bundles.
+///
+/// Insertion writes every component before any hook, index or observer runs,
+/// so they always see the complete bundle.
+pub trait Bundle: Send + Sync + Sized + 'static {
+    /// Appends the type id of each component, in insertion order.
+    fn component_ids(ids: &mut Vec<TypeId>);
+
+    #[doc(hidden)]
+    fn insert_components(self, world: &World, entity: Entity, inserted: &mut Vec<(TypeId, bool)>);
+
+    /// Removes every component from storage. Only called once they are all known to be present.
+    #[doc(hidden)]
+    fn take_components(world: &World, entity: Entity) -> Option<Self>;
+
+    #[doc(hidden)]
+    fn remove_components(world: &World, entity: Entity);
+}
+
+macro_rules! impl_bundle_tuple {
+    ($($component:ident),*) => {
+        #[allow(non_snake_case, unused_variables)]
+        impl<$($component: Component),*> Bundle for ($($component,)*) {
+            fn component_ids(ids: &mut Vec<TypeId>) {
+                $(ids.push(TypeId::of::<$component>());)*
+            }
+
+            fn insert_components(self, world: &World, entity: Entity, inserted: &mut Vec<(TypeId, bool)>) {
+                let ($($component,)*) = self;
+                $(world.insert_bundle_component(entity, $component, inserted);)*
+            }
+
+            fn take_components(world: &World, entity: Entity) -> Option<Self> {
+                Some(($(world.take_bundle_component::<$component>(entity)?,)*))
+            }
+
+            fn remove_components(world: &World, entity: Entity) {
+                $(world.take_bundle_component::<$component>(entity);)*
+            }
+        }
+    };
+}
+
+impl_bundle_tuple!();
+impl_bundle_tuple!(A);
+impl_bundle_tuple!(A, B);
+impl_bundle_tuple!(A, B, C);
+impl_bundle_tuple!(A, B, C, D);
+impl_bundle_tuple!(A, B, C, D, E);
+impl_bundle_tuple!(A, B, C, D, E, F);
+impl_bundle_tuple!(A, B, C, D, E, F, G);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H);
+
+/// The bundle's component type ids.
+///
+/// # Panics
+///
+/// Panics if the bundle repeats a component type, which `take_bundle` and
+/// `remove_bundle` can't handle.
+fn bundle_ids<B: Bundle>() -> Vec<TypeId> {
+    let mut ids = Vec::new();
+    B::component_ids(&mut ids);
+    let unique: HashSet<TypeId> = ids.iter().copied().collect();
+    assert_eq!(
+        unique.len(),
+        ids.len(),
+        "bundle {} repeats a component type",
+        std::any::type_name::<B>()
+    );
+    ids
+}
+
+impl World {
+    pub fn spawn_bundle<B: Bundle>(&self, bundle: B) -> Entity {
+        let entity = self.spawn().build(self);
+        self.insert_bundle(entity, bundle);
+        entity
+    }
+
+    /// Inserts every component of `bundle`, replacing existing ones, then runs their hooks.
+    pub fn insert_bundle<B: Bundle>(&self, entity: Entity, bundle: B) {
+        if !self.is_alive(entity) {
+            return;
+        }
+        let mut inserted = Vec::new();
+        bundle.insert_components(self, entity, &mut inserted);
+        for (type_id, added) in inserted {
+            self.run_insert_hooks(type_id, entity, added);
+        }
+    }
+
+    pub fn has_bundle<B: Bundle>(&self, entity: Entity) -> bool {
+        let ids = bundle_ids::<B>();
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let present = self.components().component_types_of(entity);
+        ids.iter().all(|id| present.contains(id))
+    }
+
+    /// Removes and returns the bundle if the entity has all of its components.
+    /// Otherwise nothing is removed.
+    pub fn take_bundle<B: Bundle>(&self, entity: Entity) -> Option<B> {
+        let ids = bundle_ids::<B>();
+        if !self.has_bundle::<B>(entity) {
+            return None;
+        }
+        for type_id in ids {
+            self.run_remove_hooks(type_id, entity);
+        }
+        B::take_components(self, entity)
+    }
+
+    /// Removes whichever of the bundle's components the entity has.
+    pub fn remove_bundle<B: Bundle>(&self, entity: Entity) {
+        let ids = bundle_ids::<B>();
+        if !self.is_alive(entity) {
+            return;
+        }
+        let present = self.components().component_types_of(entity);
+        for type_id in ids.into_iter().filter(|id| present.contains(id)) {
+            self.run_remove_hooks(type_id, entity);
+        }
+        B::remove_components(self, entity);
+    }
+
+    #[doc(hidden)]
+    pub fn insert_bundle_component<T: Component>(
+        &self,
+        entity: Entity,
+        component: T,
+        inserted: &mut Vec<(TypeId, bool)>,
+    ) {
+        if self.components().has_component::<T>(entity) {
+            self.run_replace_hooks(TypeId::of::<T>(), entity);
+        }
+        let added = self.components().add_component(entity, component);
+        inserted.push((TypeId::of::<T>(), added));
+    }
+
+    #[doc(hidden)]
+    pub fn take_bundle_component<T: Component>(&self, entity: Entity) -> Option<T> {
+        self.components().remove_component::<T>(entity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bundle;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Position(f32);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Velocity(f32);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(u32);
+    struct Player;
+
+    #[derive(Bundle)]
+    struct Body {
+        position: Position,
+        velocity: Velocity,
+    }
+
+    #[derive(Bundle)]
+    struct PlayerBundle {
+        #[bundle]
+        body: Body,
+        health: Health,
+        player: Player,
+    }
+
+    #[test]
+    fn bundles_insert_and_remove_together() {
+        let world = World::new();
+        let seen = std::sync::Arc::new(parking_lot::Mutex::new(None));
+        let hook_seen = seen.clone();
+        world.components().on_add::<Position>(move |world, entity| {
+            *hook_seen.lock() = world.get_component::<Health>(entity);
+        });
+
+        let player = world.spawn_bundle(PlayerBundle {
+            body: Body {
+                position: Position(1.0),
+                velocity: Velocity(2.0),
+            },
+            health: Health(3),
+            player: Player,
+        });
+        assert_eq!(*seen.lock(), Some(Health(3)));
+        assert!(world.has_bundle::<PlayerBundle>(player));
+
+        let body = world.take_bundle::<Body>(player).unwrap();
+        assert_eq!((body.position, body.velocity), (Position(1.0), Velocity(2.0)));
+        assert!(world.take_bundle::<Body>(player).is_none());
+
+        world.insert_bundle(player, (Position(0.0), Velocity(0.0)));
+        world.remove_bundle::<(Velocity, Health)>(player);
+        assert!(world.has_component::<Position>(player));
+        assert!(!world.has_component::<Velocity>(player) && !world.has_component::<Health>(player));
+
+        let built = world.spawn().with(Player).with_bundle((Position(5.0), Health(1))).build(&world);
+        assert!(world.has_bundle::<(Player, Position, Health)>(built));
+    }
+
+    #[test]
+    #[should_panic(expected = "repeats a component type")]
+    fn bundles_with_repeated_components_are_rejected() {
+        let world = World::new();
+        let entity = world.spawn_with(Position(1.0));
+        world.take_bundle::<(Position, Position)>(entity);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{Component, Entity, QueryFilter, World};
+use std::marker::PhantomData;
+
+/// How often, in ticks, `World::check_change_ticks` clamps stored ticks.
+pub const CHECK_TICK_THRESHOLD: u32 = 1 << 29;
+
+/// The oldest a stored tick may get before it is clamped. Together with
+/// `CHECK_TICK_THRESHOLD` this keeps every live tick within half the `u32`
+/// range of the current one, which the wrapping comparison relies on.
+pub const MAX_CHANGE_AGE: u32 = 1 << 30;
+
+/// A wrapping counter used to order component mutations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Tick(u32);
+
+impl Tick {
+    pub const fn new(tick: u32) -> Self {
+        Self(tick)
+    }
+
+    pub fn get(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if this tick happened after `last_run`.
+    /// Compares with wrapping arithmetic so the counter may overflow.
+    pub fn is_newer_than(&self, last_run: Tick) -> bool {
+        (self.0.wrapping_sub(last_run.0) as i32) > 0
+    }
+
+    /// How many ticks ago this tick happened, as seen from `now`.
+    pub fn age(&self, now: Tick) -> u32 {
+        now.0.wrapping_sub(self.0)
+    }
+
+    /// Clamps this tick to at most `MAX_CHANGE_AGE` behind `now`, so it
+    /// never reads as newer once the counter wraps around.
+    pub fn check_tick(&mut self, now: Tick) {
+        if self.age(now) > MAX_CHANGE_AGE {
+            self.0 = now.0.wrapping_sub(MAX_CHANGE_AGE);
+        }
+    }
+}
+
+/// The ticks at which a component was added and last mutably accessed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComponentTicks {
+    pub added: Tick,
+    pub changed: Tick,
+}
+
+impl ComponentTicks {
+    pub fn new(tick: Tick) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+
+    pub fn is_added(&self, last_run: Tick) -> bool {
+        self.added.is_newer_than(last_run)
+    }
+
+    pub fn is_changed(&self, last_run: Tick) -> bool {
+        self.changed.is_newer_than(last_run)
+    }
+
+    pub fn set_changed(&mut self, tick: Tick) {
+        self.changed = tick;
+    }
+
+    pub fn check_ticks(&mut self, now: Tick) {
+        self.added.check_tick(now);
+        self.changed.check_tick(now);
+    }
+}
+
+/// Filter matching entities whose `T` was added since the last run.
+pub struct Added<T: Component> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Component> QueryFilter for Added<T> {
+    fn matches(world: &World, entity: Entity) -> bool {
+        let last_run = world.last_change_tick();
+        world
+            .component_ticks::<T>(entity)
+            .map(|ticks| ticks.is_added(last_run))
+            .unwrap_or(false)
+    }
+}
+
+/// Filter matching entities whose `T` was added or mutably accessed since the last run.
+pub struct Changed<T: Component> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Component> QueryFilter for Changed<T> {
+    fn matches(world: &World, entity: Entity) -> bool {
+        let last_run = world.last_change_tick();
+        world
+            .component_ticks::<T>(entity)
+            .map(|ticks| ticks.is_changed(last_run))
+            .unwrap_or(false)
+    }
+}
+
+/// Entities that lost their `T` component since the last run, including despawned ones.
+pub struct RemovedComponents<T: Component> {
+    entities: Vec<Entity>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Component> RemovedComponents<T> {
+    pub fn new(entities: Vec<Entity>) -> Self {
+        Self {
+            entities,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().copied()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+}
+
+impl<T: Component> IntoIterator for RemovedComponents<T> {
+    type Item = Entity;
+    type IntoIter = std::vec::IntoIter<Entity>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entities.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Position(f32);
+
+    #[test]
+    fn added_and_changed_are_reset_by_clear_trackers() {
+        let world = World::new();
+        let entity = world.spawn_with(Position(0.0));
+
+        assert_eq!(world.query_filtered::<Position, Added<Position>>().len(), 1);
+        assert_eq!(world.query_filtered::<Position, Changed<Position>>().len(), 1);
+
+        world.clear_trackers();
+        assert!(world.query_filtered::<Position, Added<Position>>().is_empty());
+        assert!(world.query_filtered::<Position, Changed<Position>>().is_empty());
+
+        world.with_component_mut::<Position, _>(entity, |position| position.unwrap().0 = 5.0);
+        assert!(world.query_filtered::<Position, Added<Position>>().is_empty());
+        assert_eq!(
+            world.query_filtered::<Position, Changed<Position>>(),
+            vec![(entity, Position(5.0))]
+        );
+
+        // Query borrows only count as changes once they are written through.
+        world.clear_trackers();
+        let query = crate::Query::<&mut Position>::new(&world);
+        query.for_each(|position| assert_eq!(position.0, 5.0));
+        assert!(world.query_filtered::<Position, Changed<Position>>().is_empty());
+        query.for_each(|mut position| position.0 += 1.0);
+        assert_eq!(
+            world.query_filtered::<Position, Changed<Position>>(),
+            vec![(entity, Position(6.0))]
+        );
+    }
+
+    #[test]
+    fn removed_components_include_despawned_entities() {
+        let world = World::new();
+        let a = world.spawn_with(Position(1.0));
+        let b = world.spawn_with(Position(2.0));
+        world.clear_trackers();
+
+        world.remove_component::<Position>(a);
+        world.despawn(b);
+
+        let removed = world.removed_components::<Position>();
+        assert_eq!(removed.len(), 2);
+        assert!(removed.contains(a) && removed.contains(b));
+
+        world.clear_trackers();
+        assert!(world.removed_components::<Position>().is_empty());
+    }
+}
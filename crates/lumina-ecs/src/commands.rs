@@ -0,0 +1,210 @@
+use crate::{Bundle, Component, Entity, Event, World};
+
+type Command = Box<dyn FnOnce(&World) + Send + Sync>;
+
+/// World changes deferred until the end of the system that queued them.
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: Vec<Command>,
+}
+
+impl CommandQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, command: impl FnOnce(&World) + Send + Sync + 'static) {
+        self.commands.push(Box::new(command));
+    }
+
+    /// Runs the queued commands in order and empties the queue.
+    pub fn apply(&mut self, world: &World) {
+        for command in std::mem::take(&mut self.commands) {
+            command(world);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+/// System parameter for structural changes that would otherwise conflict with
+/// the queries and resources the system is borrowing. Applied after the system returns.
+pub struct Commands<'w, 's> {
+    world: &'w World,
+    queue: &'s mut CommandQueue,
+}
+
+impl<'w, 's> Commands<'w, 's> {
+    pub fn new(queue: &'s mut CommandQueue, world: &'w World) -> Self {
+        Self { world, queue }
+    }
+
+    /// Spawns an empty entity right away; components added through the
+    /// returned `EntityCommands` are inserted when the commands are applied.
+    pub fn spawn(&mut self) -> EntityCommands<'_> {
+        let entity = self.world.spawn().build(self.world);
+        self.entity(entity)
+    }
+
+    pub fn spawn_bundle<B: Bundle>(&mut self, bundle: B) -> EntityCommands<'_> {
+        let mut entity = self.spawn();
+        entity.insert_bundle(bundle);
+        entity
+    }
+
+    pub fn entity(&mut self, entity: Entity) -> EntityCommands<'_> {
+        EntityCommands {
+            entity,
+            queue: self.queue,
+        }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.queue.push(move |world| {
+            world.despawn(entity);
+        });
+    }
+
+    pub fn insert_resource<T: Send + Sync + 'static>(&mut self, resource: T) {
+        self.queue.push(move |world| world.add_resource(resource));
+    }
+
+    pub fn remove_resource<T: Send + Sync + 'static>(&mut self) {
+        self.queue.push(|world| {
+            world.remove_resource::<T>();
+        });
+    }
+
+    pub fn send_event<T: Event>(&mut self, event: T) {
+        self.queue.push(move |world| world.send_event(event));
+    }
+
+    /// Queues an arbitrary world mutation.
+    pub fn add(&mut self, command: impl FnOnce(&World) + Send + Sync + 'static) {
+        self.queue.push(command);
+    }
+}
+
+/// Deferred changes to a single entity.
+pub struct EntityCommands<'a> {
+    entity: Entity,
+    queue: &'a mut CommandQueue,
+}
+
+impl EntityCommands<'_> {
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn insert<T: Component>(&mut self, component: T) -> &mut Self {
+        let entity = self.entity;
+        self.queue.push(move |world| world.add_component(entity, component));
+        self
+    }
+
+    pub fn insert_bundle<B: Bundle>(&mut self, bundle: B) -> &mut Self {
+        let entity = self.entity;
+        self.queue.push(move |world| world.insert_bundle(entity, bundle));
+        self
+    }
+
+    pub fn remove_bundle<B: Bundle>(&mut self) -> &mut Self {
+        let entity = self.entity;
+        self.queue.push(move |world| world.remove_bundle::<B>(entity));
+        self
+    }
+
+    pub fn remove<T: Component>(&mut self) -> &mut Self {
+        let entity = self.entity;
+        self.queue.push(move |world| {
+            world.remove_component::<T>(entity);
+        });
+        self
+    }
+
+    pub fn set_parent(&mut self, parent: Entity) -> &mut Self {
+        let entity = self.entity;
+        self.queue.push(move |world| {
+            if let Err(error) = world.set_parent(entity, parent) {
+                log::warn!("Deferred set_parent failed: {}", error);
+            }
+        });
+        self
+    }
+
+    pub fn despawn(&mut self) {
+        let entity = self.entity;
+        self.queue.push(move |world| {
+            world.despawn(entity);
+        });
+    }
+
+    pub fn despawn_recursive(&mut self) {
+        let entity = self.entity;
+        self.queue.push(move |world| {
+            world.despawn_recursive(entity);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Health(u32);
+
+    struct Wave(u32);
+
+    #[test]
+    fn commands_are_deferred_until_applied() {
+        let world = World::new();
+        let doomed = world.spawn_with(Health(1));
+        let mut queue = CommandQueue::new();
+
+        let (spawned, parent) = {
+            let mut commands = Commands::new(&mut queue, &world);
+            let parent = commands.spawn().id();
+            let spawned = commands.spawn().insert(Health(10)).set_parent(parent).id();
+            commands.despawn(doomed);
+            commands.insert_resource(Wave(2));
+            (spawned, parent)
+        };
+
+        // Entities are reserved immediately, everything else waits for `apply`.
+        assert!(world.is_alive(spawned));
+        assert_eq!(world.get_component::<Health>(spawned), None);
+        assert!(world.is_alive(doomed));
+        assert!(!world.has_resource::<Wave>());
+        assert_eq!(queue.len(), 4);
+
+        queue.apply(&world);
+        assert!(queue.is_empty());
+        assert_eq!(world.get_component::<Health>(spawned), Some(Health(10)));
+        assert_eq!(world.parent(spawned), Some(parent));
+        assert!(!world.is_alive(doomed));
+        assert_eq!(world.with_resource::<Wave, _>(|wave| wave.map(|wave| wave.0)), Some(2));
+    }
+
+    #[test]
+    fn queued_commands_run_in_order() {
+        let world = World::new();
+        let entity = world.spawn().build(&world);
+        let mut queue = CommandQueue::new();
+        {
+            let mut commands = Commands::new(&mut queue, &world);
+            commands.entity(entity).insert(Health(1)).remove::<Health>().insert(Health(3));
+            commands.add(move |world| {
+                world.with_component_mut::<Health, _>(entity, |health| health.unwrap().0 += 1);
+            });
+        }
+        queue.apply(&world);
+        assert_eq!(world.get_component::<Health>(entity), Some(Health(4)));
+    }
+}
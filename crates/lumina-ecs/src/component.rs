@@ -1,28 +1,57 @@
-use crate::{Component, Entity};
+use crate::{Component, ComponentTicks, CHECK_TICK_THRESHOLD, Entity, Reflect, ReflectError, Tick, TypeRegistry, World};
 use parking_lot::RwLock;
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
 pub trait ComponentStorage: Send + Sync {
-    fn remove(&mut self, entity: Entity) -> bool;
+    fn remove(&mut self, entity: Entity, tick: Tick) -> bool;
     fn clear(&mut self);
+    fn prune_removed(&mut self, up_to: Tick);
+    fn check_change_ticks(&mut self, now: Tick);
+    fn contains(&self, entity: Entity) -> bool;
+    fn stats(&self) -> StorageStats;
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 }
 
+/// Size and occupancy of one component storage, as reported by `World::storage_stats`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct StorageStats {
+    pub type_name: &'static str,
+    pub count: usize,
+    pub component_size: usize,
+    /// Bytes allocated for components, change ticks and removal records.
+    /// Heap data owned by the components themselves is not included.
+    pub estimated_bytes: usize,
+}
+
 pub struct TypedComponentStorage<T: Component> {
     components: RwLock<HashMap<Entity, T>>,
+    ticks: RwLock<HashMap<Entity, ComponentTicks>>,
+    removed: RwLock<Vec<(Entity, Tick)>>,
 }
 
 impl<T: Component> TypedComponentStorage<T> {
     pub fn new() -> Self {
         Self {
             components: RwLock::new(HashMap::new()),
+            ticks: RwLock::new(HashMap::new()),
+            removed: RwLock::new(Vec::new()),
         }
     }
 
-    pub fn insert(&self, entity: Entity, component: T) {
-        self.components.write().insert(entity, component);
+    /// Inserts or replaces a component, returning the previous value.
+    /// Replacing counts as a change, not an addition.
+    pub fn insert(&self, entity: Entity, component: T, tick: Tick) -> Option<T> {
+        let previous = self.components.write().insert(entity, component);
+        self.ticks
+            .write()
+            .entry(entity)
+            .and_modify(|ticks| ticks.set_changed(tick))
+            .or_insert_with(|| ComponentTicks::new(tick));
+        previous
     }
 
     pub fn get<R>(&self, entity: Entity, f: impl FnOnce(Option<&T>) -> R) -> R {
@@ -30,13 +59,45 @@ impl<T: Component> TypedComponentStorage<T> {
         f(components.get(&entity))
     }
 
-    pub fn get_mut<R>(&self, entity: Entity, f: impl FnOnce(Option<&mut T>) -> R) -> R {
+    /// Mutably borrows a component, marking it changed at `tick`.
+    pub fn get_mut<R>(&self, entity: Entity, tick: Tick, f: impl FnOnce(Option<&mut T>) -> R) -> R {
+        self.set_changed(entity, tick);
+        self.get_mut_untracked(entity, f)
+    }
+
+    /// Mutably borrows a component without marking it changed.
+    pub fn get_mut_untracked<R>(&self, entity: Entity, f: impl FnOnce(Option<&mut T>) -> R) -> R {
         let mut components = self.components.write();
         f(components.get_mut(&entity))
     }
 
-    pub fn remove(&self, entity: Entity) -> Option<T> {
-        self.components.write().remove(&entity)
+    pub fn set_changed(&self, entity: Entity, tick: Tick) {
+        if let Some(ticks) = self.ticks.write().get_mut(&entity) {
+            ticks.set_changed(tick);
+        }
+    }
+
+    pub fn remove(&self, entity: Entity, tick: Tick) -> Option<T> {
+        let component = self.components.write().remove(&entity);
+        if component.is_some() {
+            self.ticks.write().remove(&entity);
+            self.removed.write().push((entity, tick));
+        }
+        component
+    }
+
+    pub fn ticks(&self, entity: Entity) -> Option<ComponentTicks> {
+        self.ticks.read().get(&entity).copied()
+    }
+
+    /// Entities whose component was removed after `last_run`.
+    pub fn removed_since(&self, last_run: Tick) -> Vec<Entity> {
+        self.removed
+            .read()
+            .iter()
+            .filter(|(_, tick)| tick.is_newer_than(last_run))
+            .map(|(entity, _)| *entity)
+            .collect()
     }
 
     pub fn contains(&self, entity: Entity) -> bool {
@@ -64,12 +125,40 @@ impl<T: Component> Default for TypedComponentStorage<T> {
 }
 
 impl<T: Component> ComponentStorage for TypedComponentStorage<T> {
-    fn remove(&mut self, entity: Entity) -> bool {
-        self.components.write().remove(&entity).is_some()
+    fn remove(&mut self, entity: Entity, tick: Tick) -> bool {
+        TypedComponentStorage::remove(self, entity, tick).is_some()
     }
 
     fn clear(&mut self) {
         self.components.write().clear();
+        self.ticks.write().clear();
+        self.removed.write().clear();
+    }
+
+    fn prune_removed(&mut self, up_to: Tick) {
+        self.removed.write().retain(|(_, tick)| tick.is_newer_than(up_to));
+    }
+
+    fn check_change_ticks(&mut self, now: Tick) {
+        self.ticks.get_mut().values_mut().for_each(|ticks| ticks.check_ticks(now));
+        self.removed.get_mut().iter_mut().for_each(|(_, tick)| tick.check_tick(now));
+    }
+
+    fn contains(&self, entity: Entity) -> bool {
+        TypedComponentStorage::contains(self, entity)
+    }
+
+    fn stats(&self) -> StorageStats {
+        let components = self.components.read();
+        let estimated_bytes = components.capacity() * std::mem::size_of::<(Entity, T)>()
+            + self.ticks.read().capacity() * std::mem::size_of::<(Entity, ComponentTicks)>()
+            + self.removed.read().capacity() * std::mem::size_of::<(Entity, Tick)>();
+        StorageStats {
+            type_name: std::any::type_name::<T>(),
+            count: components.len(),
+            component_size: std::mem::size_of::<T>(),
+            estimated_bytes,
+        }
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -81,17 +170,77 @@ impl<T: Component> ComponentStorage for TypedComponentStorage<T> {
     }
 }
 
+/// Callback run by `World` when a component is added, inserted or removed.
+pub type ComponentHook = Arc<dyn Fn(&World, Entity) + Send + Sync>;
+
+/// Lifecycle hooks registered for one component type.
+///
+/// `on_add` runs when an entity gains the component, `on_insert` on every
+/// insertion including replacements, and `on_remove` just before the
+/// component is removed or its entity despawned, while it is still readable.
+///
+/// `on_replace` runs just before an existing value is overwritten by an insert
+/// or edited through reflection, while the old value is still readable.
+/// `on_change` runs after such a reflected edit, in place of `on_insert`.
+#[derive(Clone, Default)]
+pub struct ComponentHooks {
+    pub on_add: Vec<ComponentHook>,
+    pub on_insert: Vec<ComponentHook>,
+    pub on_replace: Vec<ComponentHook>,
+    pub on_change: Vec<ComponentHook>,
+    pub on_remove: Vec<ComponentHook>,
+}
+
+impl ComponentHooks {
+    pub fn is_empty(&self) -> bool {
+        self.on_add.is_empty()
+            && self.on_insert.is_empty()
+            && self.on_replace.is_empty()
+            && self.on_change.is_empty()
+            && self.on_remove.is_empty()
+    }
+}
+
 pub struct ComponentManager {
     storages: RwLock<HashMap<TypeId, Box<dyn ComponentStorage>>>,
+    hooks: RwLock<HashMap<TypeId, ComponentHooks>>,
+    change_tick: AtomicU32,
+    last_change_tick: AtomicU32,
+    last_check_tick: AtomicU32,
 }
 
 impl ComponentManager {
     pub fn new() -> Self {
         Self {
             storages: RwLock::new(HashMap::new()),
+            hooks: RwLock::new(HashMap::new()),
+            // Start one ahead of `last_change_tick` so components inserted
+            // before the first system run are reported as added.
+            change_tick: AtomicU32::new(1),
+            last_change_tick: AtomicU32::new(0),
+            last_check_tick: AtomicU32::new(0),
         }
     }
 
+    /// The tick stamped on component insertions and mutations.
+    pub fn change_tick(&self) -> Tick {
+        Tick::new(self.change_tick.load(Ordering::Acquire))
+    }
+
+    /// Advances the change tick and returns the new value, wrapping at `u32::MAX`.
+    pub fn increment_change_tick(&self) -> Tick {
+        Tick::new(self.change_tick.fetch_add(1, Ordering::AcqRel).wrapping_add(1))
+    }
+
+    /// The tick that `Added`/`Changed`/removal queries compare against.
+    pub fn last_change_tick(&self) -> Tick {
+        Tick::new(self.last_change_tick.load(Ordering::Acquire))
+    }
+
+    pub fn set_last_change_tick(&self, tick: Tick) {
+        self.last_change_tick.store(tick.get(), Ordering::Release);
+    }
+
     pub fn register<T: Component>(&self) {
         let type_id = TypeId::of::<T>();
         let mut storages = self.storages.write();
@@ -101,16 +250,65 @@ impl ComponentManager {
         }
     }
 
-    pub fn add_component<T: Component>(&self, entity: Entity, component: T) {
+    pub fn on_add<T: Component>(&self, hook: impl Fn(&World, Entity) + Send + Sync + 'static) {
+        self.hooks.write().entry(TypeId::of::<T>()).or_default().on_add.push(Arc::new(hook));
+    }
+
+    pub fn on_insert<T: Component>(&self, hook: impl Fn(&World, Entity) + Send + Sync + 'static) {
+        self.hooks.write().entry(TypeId::of::<T>()).or_default().on_insert.push(Arc::new(hook));
+    }
+
+    pub fn on_replace<T: Component>(&self, hook: impl Fn(&World, Entity) + Send + Sync + 'static) {
+        self.hooks.write().entry(TypeId::of::<T>()).or_default().on_replace.push(Arc::new(hook));
+    }
+
+    pub fn on_change<T: Component>(&self, hook: impl Fn(&World, Entity) + Send + Sync + 'static) {
+        self.hooks.write().entry(TypeId::of::<T>()).or_default().on_change.push(Arc::new(hook));
+    }
+
+    pub fn on_remove<T: Component>(&self, hook: impl Fn(&World, Entity) + Send + Sync + 'static) {
+        self.hooks.write().entry(TypeId::of::<T>()).or_default().on_remove.push(Arc::new(hook));
+    }
+
+    /// A snapshot of the hooks for a component type, so callers can run them without holding a lock.
+    pub fn hooks(&self, type_id: TypeId) -> Option<ComponentHooks> {
+        self.hooks.read().get(&type_id).filter(|hooks| !hooks.is_empty()).cloned()
+    }
+
+    pub fn clear_hooks<T: Component>(&self) {
+        self.hooks.write().remove(&TypeId::of::<T>());
+    }
+
+    /// Adds or replaces a component. Returns true if the entity did not have it before.
+    pub fn add_component<T: Component>(&self, entity: Entity, component: T) -> bool {
         self.register::<T>();
         let type_id = TypeId::of::<T>();
         let storages = self.storages.read();
         
         if let Some(storage) = storages.get(&type_id) {
             if let Some(typed_storage) = storage.as_any().downcast_ref::<TypedComponentStorage<T>>() {
-                typed_storage.insert(entity, component);
+                return typed_storage.insert(entity, component, self.change_tick()).is_none();
             }
         }
+        false
+    }
+
+    /// Inserts a reflected component whose concrete type is registered in `registry`.
+    /// Returns true if the entity did not have the component before.
+    pub fn add_reflect_component(
+        &self,
+        entity: Entity,
+        component: Box<dyn Reflect>,
+        registry: &TypeRegistry,
+    ) -> Result<bool, ReflectError> {
+        let type_id = component.as_any().type_id();
+        let registration = registry
+            .get(type_id)
+            .ok_or_else(|| ReflectError::UnregisteredType(component.type_name().to_string()))?;
+        let fns = registration
+            .component()
+            .ok_or_else(|| ReflectError::UnregisteredType(registration.name.to_string()))?;
+        (fns.insert)(self, entity, component)
     }
 
     pub fn get_component<T: Component + Clone>(&self, entity: Entity) -> Option<T> {
@@ -140,18 +338,35 @@ impl ComponentManager {
         
         match storages.get(&type_id)
             .and_then(|storage| storage.as_any().downcast_ref::<TypedComponentStorage<T>>()) {
-            Some(typed_storage) => typed_storage.get_mut(entity, f),
+            Some(typed_storage) => typed_storage.get_mut(entity, self.change_tick(), f),
             None => f(None),
         }
     }
 
+    /// Like `with_component_mut`, but leaves the change tick alone; see `set_changed`.
+    pub fn with_component_mut_untracked<T: Component, R>(&self, entity: Entity, f: impl FnOnce(Option<&mut T>) -> R) -> R {
+        self.with_storage::<T, _>(|storage| match storage {
+            Some(storage) => storage.get_mut_untracked(entity, f),
+            None => f(None),
+        })
+    }
+
+    /// Marks the entity's `T` changed at the current tick.
+    pub fn set_changed<T: Component>(&self, entity: Entity) {
+        self.with_storage::<T, _>(|storage| {
+            if let Some(storage) = storage {
+                storage.set_changed(entity, self.change_tick());
+            }
+        });
+    }
+
     pub fn remove_component<T: Component>(&self, entity: Entity) -> Option<T> {
         let type_id = TypeId::of::<T>();
         let storages = self.storages.read();
         
         storages.get(&type_id)
             .and_then(|storage| storage.as_any().downcast_ref::<TypedComponentStorage<T>>())
-            .and_then(|typed_storage| typed_storage.remove(entity))
+            .and_then(|typed_storage| typed_storage.remove(entity, self.change_tick()))
     }
 
     pub fn has_component<T: Component>(&self, entity: Entity) -> bool {
@@ -164,10 +379,82 @@ impl ComponentManager {
             .unwrap_or(false)
     }
 
+    pub fn component_ticks<T: Component>(&self, entity: Entity) -> Option<ComponentTicks> {
+        self.with_storage::<T, _>(|storage| storage.and_then(|storage| storage.ticks(entity)))
+    }
+
+    pub fn removed_since<T: Component>(&self, last_run: Tick) -> Vec<Entity> {
+        self.with_storage::<T, _>(|storage| {
+            storage.map(|storage| storage.removed_since(last_run)).unwrap_or_default()
+        })
+    }
+
+    /// Clamps stored ticks that are more than `MAX_CHANGE_AGE` behind `now`.
+    /// Returns false without touching storages if this ran less than
+    /// `CHECK_TICK_THRESHOLD` ticks ago.
+    pub fn check_change_ticks(&self, now: Tick) -> bool {
+        let last_check = Tick::new(self.last_check_tick.load(Ordering::Acquire));
+        if last_check.age(now) < CHECK_TICK_THRESHOLD {
+            return false;
+        }
+        self.last_check_tick.store(now.get(), Ordering::Release);
+
+        for storage in self.storages.write().values_mut() {
+            storage.check_change_ticks(now);
+        }
+        let mut last_change_tick = self.last_change_tick();
+        last_change_tick.check_tick(now);
+        self.set_last_change_tick(last_change_tick);
+        true
+    }
+
+    /// Drops removal records that every reader has already observed.
+    pub fn prune_removed(&self, up_to: Tick) {
+        let mut storages = self.storages.write();
+        for storage in storages.values_mut() {
+            storage.prune_removed(up_to);
+        }
+    }
+
+    /// Type ids of every component the entity currently has.
+    pub fn component_types_of(&self, entity: Entity) -> Vec<TypeId> {
+        self.storages
+            .read()
+            .iter()
+            .filter(|(_, storage)| storage.contains(entity))
+            .map(|(type_id, _)| *type_id)
+            .collect()
+    }
+
+    /// Statistics for every component storage, largest first.
+    pub fn storage_stats(&self) -> Vec<StorageStats> {
+        let mut stats: Vec<StorageStats> = self.storages.read().values().map(|storage| storage.stats()).collect();
+        stats.sort_by(|a, b| b.estimated_bytes.cmp(&a.estimated_bytes).then(a.type_name.cmp(b.type_name)));
+        stats
+    }
+
+    /// Storage statistics for each component the entity has.
+    pub fn component_stats_of(&self, entity: Entity) -> Vec<(TypeId, StorageStats)> {
+        self.storages
+            .read()
+            .iter()
+            .filter(|(_, storage)| storage.contains(entity))
+            .map(|(type_id, storage)| (*type_id, storage.stats()))
+            .collect()
+    }
+
+    /// Every entity with a `T` component, in storage order.
+    pub fn entities_with<T: Component>(&self) -> Vec<Entity> {
+        self.with_storage::<T, _>(|storage| {
+            storage.map_or_else(Vec::new, |storage| storage.with_iter(|iter| iter.map(|(entity, _)| *entity).collect()))
+        })
+    }
+
     pub fn remove_all_components(&self, entity: Entity) {
+        let tick = self.change_tick();
         let mut storages = self.storages.write();
         for storage in storages.values_mut() {
-            storage.remove(entity);
+            storage.remove(entity, tick);
         }
     }
 
@@ -192,4 +479,86 @@ impl Default for ComponentManager {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Added, Changed, World};
+    use parking_lot::Mutex;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Body(u32);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Position(f32);
+
+    #[test]
+    fn lifecycle_hooks_fire_in_order() {
+        let world = World::new();
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        // Each hook records the value it can read at the time it runs.
+        let hook = |name: &'static str| {
+            let log = log.clone();
+            move |world: &World, entity| {
+                let value = world.get_component::<Body>(entity).map(|body| body.0);
+                log.lock().push((name, value));
+            }
+        };
+        world.components().on_add::<Body>(hook("add"));
+        world.components().on_insert::<Body>(hook("insert"));
+        world.components().on_replace::<Body>(hook("replace"));
+        world.components().on_remove::<Body>(hook("remove"));
+
+        let entity = world.spawn_with(Body(1));
+        world.add_component(entity, Body(2));
+        world.despawn(entity);
+        assert_eq!(
+            *log.lock(),
+            vec![
+                ("add", Some(1)),
+                ("insert", Some(1)),
+                ("replace", Some(1)),
+                ("insert", Some(2)),
+                ("remove", Some(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn change_detection_survives_tick_wraparound() {
+        let world = World::new();
+        world.components().change_tick.store(u32::MAX - 1, Ordering::SeqCst);
+        world.clear_trackers();
+
+        let entity = world.spawn_with(Position(0.0));
+        assert_eq!(world.query_filtered::<Position, Added<Position>>().len(), 1);
+
+        world.clear_trackers();
+        assert_eq!(world.change_tick().get(), 0);
+        assert!(world.query_filtered::<Position, Added<Position>>().is_empty());
+
+        world.with_component_mut::<Position, _>(entity, |position| position.unwrap().0 = 1.0);
+        assert_eq!(
+            world.query_filtered::<Position, Changed<Position>>(),
+            vec![(entity, Position(1.0))]
+        );
+        world.clear_trackers();
+        assert!(world.query_filtered::<Position, Changed<Position>>().is_empty());
+    }
+
+    #[test]
+    fn stale_ticks_are_clamped_before_they_read_as_new() {
+        let world = World::new();
+        world.spawn_with(Position(0.0));
+        world.clear_trackers();
+
+        // Far enough ahead that the unclamped tick would compare as newer.
+        world.components().change_tick.store(1 + (3 << 30), Ordering::SeqCst);
+        world.clear_trackers();
+        assert!(world.query_filtered::<Position, Changed<Position>>().is_empty());
+        assert!(!world.check_change_ticks());
+    }
+}
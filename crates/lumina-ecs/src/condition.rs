@@ -0,0 +1,165 @@
+use crate::{State, States, World};
+use lumina_core::engine::SystemContext;
+use std::time::Duration;
+
+/// Decides each frame whether a system or system set runs.
+pub trait Condition: Send + Sync + 'static {
+    fn evaluate(&mut self, world: &World, context: &SystemContext) -> bool;
+}
+
+impl<F> Condition for F
+where
+    F: FnMut(&World, &SystemContext) -> bool + Send + Sync + 'static,
+{
+    fn evaluate(&mut self, world: &World, context: &SystemContext) -> bool {
+        self(world, context)
+    }
+}
+
+pub type BoxedCondition = Box<dyn Condition>;
+
+/// Combinators for conditions. Both sides are always evaluated so stateful
+/// conditions such as timers keep ticking.
+pub trait ConditionExt: Condition + Sized {
+    fn and<C: Condition>(mut self, mut other: C) -> impl Condition {
+        move |world: &World, context: &SystemContext| {
+            let a = self.evaluate(world, context);
+            let b = other.evaluate(world, context);
+            a && b
+        }
+    }
+
+    fn or<C: Condition>(mut self, mut other: C) -> impl Condition {
+        move |world: &World, context: &SystemContext| {
+            let a = self.evaluate(world, context);
+            let b = other.evaluate(world, context);
+            a || b
+        }
+    }
+}
+
+impl<C: Condition> ConditionExt for C {}
+
+pub fn not<C: Condition>(mut condition: C) -> impl Condition {
+    move |world: &World, context: &SystemContext| !condition.evaluate(world, context)
+}
+
+/// True while `State<S>` equals `state`.
+pub fn in_state<S: States>(state: S) -> impl Condition {
+    move |world: &World, _: &SystemContext| {
+        world.with_resource::<State<S>, _>(|current| current.is_some_and(|current| *current.get() == state))
+    }
+}
+
+/// True on the first evaluation after `State<S>` changes.
+pub fn state_changed<S: States>() -> impl Condition {
+    let mut last: Option<S> = None;
+    move |world: &World, _: &SystemContext| {
+        let current = world.with_resource::<State<S>, _>(|state| state.map(|state| state.get().clone()));
+        let changed = current.is_some() && last.is_some() && current != last;
+        last = current;
+        changed
+    }
+}
+
+pub fn resource_exists<T: Send + Sync + 'static>() -> impl Condition {
+    |world: &World, _: &SystemContext| world.has_resource::<T>()
+}
+
+pub fn resource_equals<T: PartialEq + Send + Sync + 'static>(value: T) -> impl Condition {
+    move |world: &World, _: &SystemContext| world.with_resource::<T, _>(|resource| resource == Some(&value))
+}
+
+/// True when the resource exists and `predicate` accepts it.
+pub fn resource_matches<T: Send + Sync + 'static>(
+    predicate: impl Fn(&T) -> bool + Send + Sync + 'static,
+) -> impl Condition {
+    move |world: &World, _: &SystemContext| world.with_resource::<T, _>(|resource| resource.is_some_and(&predicate))
+}
+
+/// True once every `interval` of game time, measured with `Time::delta`.
+pub fn on_timer(interval: Duration) -> impl Condition {
+    let mut elapsed = Duration::ZERO;
+    move |_: &World, context: &SystemContext| {
+        elapsed += context.time.read().delta();
+        if elapsed >= interval {
+            elapsed -= interval;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// True only the first time it is evaluated.
+pub fn run_once() -> impl Condition {
+    let mut has_run = false;
+    move |_: &World, _: &SystemContext| !std::mem::replace(&mut has_run, true)
+}
+
+/// Evaluates every condition, without short-circuiting so timers keep ticking.
+pub(crate) fn evaluate_all(conditions: &mut [BoxedCondition], world: &World, context: &SystemContext) -> bool {
+    let mut met = true;
+    for condition in conditions {
+        met &= condition.evaluate(world, context);
+    }
+    met
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Mode {
+        Edit,
+        Play,
+    }
+
+    #[test]
+    fn on_timer_fires_once_per_interval() {
+        let world = World::new();
+        let context = SystemContext::new();
+        std::thread::sleep(Duration::from_millis(5));
+        context.time.write().update();
+        let delta = context.time.read().delta();
+
+        // The delta stays fixed until the next update, so the timer advances by it each evaluation.
+        let mut timer = on_timer(delta + delta / 2);
+        let fired: Vec<bool> = (0..4).map(|_| timer.evaluate(&world, &context)).collect();
+        assert_eq!(fired, vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn state_changed_reports_one_evaluation_per_transition() {
+        let world = World::new();
+        let context = SystemContext::new();
+        let mut changed = state_changed::<Mode>();
+        assert!(!changed.evaluate(&world, &context));
+
+        world.add_resource(State::new(Mode::Edit));
+        assert!(!changed.evaluate(&world, &context));
+        assert!(!changed.evaluate(&world, &context));
+
+        world.add_resource(State::new(Mode::Play));
+        assert!(changed.evaluate(&world, &context));
+        assert!(!changed.evaluate(&world, &context));
+    }
+
+    #[test]
+    fn combinators_evaluate_both_sides() {
+        let world = World::new();
+        let context = SystemContext::new();
+        // If `and` short-circuited, the second `run_once` would still be pending on the second frame.
+        let mut both = not(run_once()).and(run_once());
+        assert!(!both.evaluate(&world, &context));
+        assert!(!both.evaluate(&world, &context));
+
+        let mut either = run_once().or(resource_exists::<u32>());
+        assert!(either.evaluate(&world, &context));
+        assert!(!either.evaluate(&world, &context));
+        world.add_resource(7u32);
+        assert!(either.evaluate(&world, &context));
+        assert!(resource_equals(7u32).evaluate(&world, &context));
+    }
+}
@@ -3,6 +3,10 @@ use lumina_core::BitSet;
 use parking_lot::RwLock;
 use smallvec::SmallVec;
 
+/// Largest id `EntityManager::create_exact` accepts. Ids skipped on the way are
+/// kept in the free list, so this bounds what a corrupt snapshot can allocate.
+pub const MAX_EXACT_ENTITY_ID: u32 = (1 << 24) - 1;
+
 pub struct EntityManager {
     next_id: RwLock<u32>,
     free_entities: RwLock<SmallVec<[Entity; 16]>>,
@@ -37,6 +41,32 @@ impl EntityManager {
         }
     }
 
+    /// Makes a specific entity id alive, e.g. when restoring a snapshot.
+    /// Returns false if it is already alive or above `MAX_EXACT_ENTITY_ID`.
+    pub fn create_exact(&self, entity: Entity) -> bool {
+        if entity.index() > MAX_EXACT_ENTITY_ID {
+            return false;
+        }
+
+        let mut free_entities = self.free_entities.write();
+        let mut next_id = self.next_id.write();
+        let mut alive = self.alive_entities.write();
+
+        if alive.get(entity.index() as usize) {
+            return false;
+        }
+
+        if entity.index() >= *next_id {
+            // Ids skipped over stay available for later spawns.
+            free_entities.extend((*next_id..entity.index()).map(Entity::new));
+            *next_id = entity.index() + 1;
+        } else {
+            free_entities.retain(|free| *free != entity);
+        }
+        alive.set(entity.index() as usize);
+        true
+    }
+
     pub fn destroy(&self, entity: Entity) -> bool {
         let mut alive = self.alive_entities.write();
         
@@ -104,6 +134,14 @@ impl EntityBuilder {
         self
     }
 
+    /// Adds every component of a bundle in one step, so hooks see the whole group.
+    pub fn with_bundle<B: crate::Bundle>(mut self, bundle: B) -> Self {
+        self.components.push(Box::new(move |world| {
+            world.insert_bundle(self.entity, bundle);
+        }));
+        self
+    }
+
     pub fn build(self, world: &crate::World) -> Entity {
         for add_component in self.components {
             add_component(world);
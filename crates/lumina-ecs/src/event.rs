@@ -0,0 +1,323 @@
+use crate::{EcsSystemRunner, World};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Marker for types sent between systems as frame-scoped events.
+pub trait Event: Send + Sync + 'static {}
+
+impl<T: Send + Sync + 'static> Event for T {}
+
+#[derive(Debug, Clone)]
+struct EventInstance<T> {
+    id: usize,
+    event: T,
+}
+
+/// Double-buffered event queue stored as a world resource.
+///
+/// Events stay readable for the update they were sent in and the next one,
+/// so every system gets a chance to see them regardless of system order.
+#[derive(Debug)]
+pub struct Events<T: Event> {
+    previous: Vec<EventInstance<T>>,
+    current: Vec<EventInstance<T>>,
+    event_count: usize,
+}
+
+impl<T: Event> Default for Events<T> {
+    fn default() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            event_count: 0,
+        }
+    }
+}
+
+impl<T: Event> Events<T> {
+    pub fn send(&mut self, event: T) {
+        self.current.push(EventInstance {
+            id: self.event_count,
+            event,
+        });
+        self.event_count += 1;
+    }
+
+    /// Drops events from two updates ago. Called once per frame by `EcsSystemRunner`.
+    pub fn update(&mut self) {
+        self.previous = std::mem::take(&mut self.current);
+    }
+
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    /// Number of events currently buffered.
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.previous.is_empty() && self.current.is_empty()
+    }
+
+    /// Buffered events, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.instances().map(|instance| &instance.event)
+    }
+
+    fn instances(&self) -> impl Iterator<Item = &EventInstance<T>> {
+        self.previous.iter().chain(self.current.iter())
+    }
+
+    fn oldest_id(&self) -> usize {
+        self.instances().next().map_or(self.event_count, |instance| instance.id)
+    }
+}
+
+/// Sends events of type `T` into the world.
+pub struct EventWriter<'w, T: Event> {
+    world: &'w World,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T: Event> EventWriter<'_, T> {
+    pub fn send(&self, event: T) {
+        self.world.send_event(event);
+    }
+
+    pub fn send_batch(&self, events: impl IntoIterator<Item = T>) {
+        let sent = self.world.with_resource_mut::<Events<T>, _>(|queue| {
+            queue.map(|queue| events.into_iter().for_each(|event| queue.send(event))).is_some()
+        });
+        if !sent {
+            warn_unregistered::<T>();
+        }
+    }
+}
+
+/// Tracks which events of type `T` have been read.
+///
+/// Each system keeps its own cursor, so several systems can consume the same
+/// events independently. Function systems get one automatically through `EventReader`.
+pub struct EventCursor<T: Event> {
+    last_read: AtomicUsize,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: Event> Default for EventCursor<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Event> EventCursor<T> {
+    pub fn new() -> Self {
+        Self {
+            last_read: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Calls `f` with each unread event, oldest first, and marks them as read.
+    pub fn for_each(&self, world: &World, mut f: impl FnMut(&T)) {
+        world.with_resource::<Events<T>, _>(|events| {
+            let Some(events) = events else {
+                return;
+            };
+            let last_read = self.last_read.load(Ordering::Relaxed);
+            if last_read < events.oldest_id() {
+                log::debug!(
+                    "Missed {} {} events that were not read within two frames",
+                    events.oldest_id() - last_read,
+                    std::any::type_name::<T>()
+                );
+            }
+            events
+                .instances()
+                .filter(|instance| instance.id >= last_read)
+                .for_each(|instance| f(&instance.event));
+            self.last_read.store(events.event_count, Ordering::Relaxed);
+        });
+    }
+
+    /// Clones out the unread events and marks them as read.
+    pub fn read(&self, world: &World) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let mut unread = Vec::new();
+        self.for_each(world, |event| unread.push(event.clone()));
+        unread
+    }
+
+    /// Number of unread events, without marking them as read.
+    pub fn len(&self, world: &World) -> usize {
+        let last_read = self.last_read.load(Ordering::Relaxed);
+        world.with_resource::<Events<T>, _>(|events| {
+            events.map_or(0, |events| events.instances().filter(|instance| instance.id >= last_read).count())
+        })
+    }
+
+    pub fn is_empty(&self, world: &World) -> bool {
+        self.len(world) == 0
+    }
+
+    /// Marks every buffered event as read.
+    pub fn clear(&self, world: &World) {
+        if let Some(count) = world.with_resource::<Events<T>, _>(|events| events.map(|events| events.event_count)) {
+            self.last_read.store(count, Ordering::Relaxed);
+        }
+    }
+}
+
+/// System parameter reading the events of type `T` this system has not seen yet.
+pub struct EventReader<'w, 's, T: Event> {
+    world: &'w World,
+    cursor: &'s EventCursor<T>,
+}
+
+impl<'w, 's, T: Event> EventReader<'w, 's, T> {
+    pub fn new(world: &'w World, cursor: &'s EventCursor<T>) -> Self {
+        Self { world, cursor }
+    }
+
+    pub fn for_each(&self, f: impl FnMut(&T)) {
+        self.cursor.for_each(self.world, f);
+    }
+
+    pub fn read(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.cursor.read(self.world)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cursor.len(self.world)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cursor.is_empty(self.world)
+    }
+
+    pub fn clear(&self) {
+        self.cursor.clear(self.world);
+    }
+}
+
+/// Per-type update functions for every event type added to a world.
+#[derive(Default)]
+struct EventRegistry {
+    updaters: HashMap<TypeId, fn(&World)>,
+}
+
+fn warn_unregistered<T: Event>() {
+    log::warn!(
+        "Dropped {} event: add it with World::add_event first",
+        std::any::type_name::<T>()
+    );
+}
+
+impl World {
+    /// Adds the `Events<T>` resource and has it updated once per frame.
+    pub fn add_event<T: Event>(&self) {
+        if self.has_resource::<Events<T>>() {
+            return;
+        }
+        self.add_resource(Events::<T>::default());
+
+        if !self.has_resource::<EventRegistry>() {
+            self.add_resource(EventRegistry::default());
+        }
+        self.with_resource_mut::<EventRegistry, _>(|registry| {
+            let registry = registry.expect("event registry was just added");
+            registry.updaters.entry(TypeId::of::<T>()).or_insert(|world: &World| {
+                world.with_resource_mut::<Events<T>, _>(|events| {
+                    if let Some(events) = events {
+                        events.update();
+                    }
+                });
+            });
+        });
+    }
+
+    /// Sends an event. It is dropped with a warning if `T` was never added with `add_event`.
+    pub fn send_event<T: Event>(&self, event: T) {
+        let sent = self.with_resource_mut::<Events<T>, _>(|events| events.map(|events| events.send(event)).is_some());
+        if !sent {
+            warn_unregistered::<T>();
+        }
+    }
+
+    pub fn event_writer<T: Event>(&self) -> EventWriter<'_, T> {
+        EventWriter {
+            world: self,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Advances every event queue by one frame, dropping events older than two frames.
+    pub fn update_events(&self) {
+        let updaters = self.with_resource::<EventRegistry, _>(|registry| {
+            registry.map(|registry| registry.updaters.values().copied().collect::<Vec<_>>()).unwrap_or_default()
+        });
+        for update in updaters {
+            update(self);
+        }
+    }
+}
+
+impl EcsSystemRunner {
+    pub fn add_event<T: Event>(&mut self) {
+        self.world().add_event::<T>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::make_system;
+    use lumina_core::engine::SystemContext;
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Damage(u32);
+
+    #[test]
+    fn events_flow_between_systems_and_expire() {
+        let context = SystemContext::new();
+        let mut runner = EcsSystemRunner::new();
+        runner.add_event::<Damage>();
+        runner.world().add_resource(Vec::<u32>::new());
+
+        // The reader runs before the writer, so it sees events a frame later.
+        let reader = Arc::new(EventCursor::<Damage>::new());
+        let system_reader = reader.clone();
+        runner.add_system(make_system(move |world, _| {
+            for Damage(amount) in system_reader.read(world) {
+                world.with_resource_mut::<Vec<u32>, _>(|log| log.unwrap().push(amount));
+            }
+            Ok(())
+        }));
+        runner.add_system(make_system(|world, _| {
+            world.event_writer::<Damage>().send(Damage(5));
+            Ok(())
+        }));
+
+        runner.run_systems(&context).unwrap();
+        runner.run_systems(&context).unwrap();
+        runner.run_systems(&context).unwrap();
+        assert_eq!(runner.world().with_resource::<Vec<u32>, _>(|log| log.unwrap().clone()), vec![5, 5]);
+
+        // A late reader still sees the last two frames, and nothing older.
+        let late = EventCursor::<Damage>::new();
+        assert_eq!(late.len(runner.world()), 2);
+        runner.world().update_events();
+        runner.world().update_events();
+        assert!(late.read(runner.world()).is_empty());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::{Component, Entity, Reflect, World};
+use parking_lot::RwLock;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// Name of the built-in index over `Name` components.
+pub const NAME_INDEX: &str = "Name";
+/// Name of the built-in index over `Tags` components.
+pub const TAG_INDEX: &str = "Tags";
+
+/// A human-readable entity name, indexed for lookup with `World::find_by_name`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Reflect)]
+pub struct Name(String);
+
+impl Name {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn set(&mut self, name: impl Into<String>) {
+        self.0 = name.into();
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl std::fmt::Display for Name {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Free-form labels such as "Player" or "Enemy", indexed for `World::entities_with_tag`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Reflect)]
+pub struct Tags(Vec<String>);
+
+impl Tags {
+    pub fn new<S: Into<String>>(tags: impl IntoIterator<Item = S>) -> Self {
+        let mut result = Self::default();
+        for tag in tags {
+            result.insert(tag);
+        }
+        result
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.iter().any(|t| t == tag)
+    }
+
+    /// Adds a tag, returning false if it was already present.
+    pub fn insert(&mut self, tag: impl Into<String>) -> bool {
+        let tag = tag.into();
+        if self.contains(&tag) {
+            return false;
+        }
+        self.0.push(tag);
+        true
+    }
+
+    pub fn remove(&mut self, tag: &str) -> bool {
+        let before = self.0.len();
+        self.0.retain(|t| t != tag);
+        self.0.len() != before
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Maps keys extracted from a component to the entities that have them.
+pub struct ComponentIndex<K> {
+    entities: RwLock<HashMap<K, Vec<Entity>>>,
+    keys: RwLock<HashMap<Entity, Vec<K>>>,
+}
+
+impl<K: Eq + Hash + Clone> ComponentIndex<K> {
+    fn new() -> Self {
+        Self {
+            entities: RwLock::new(HashMap::new()),
+            keys: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn update(&self, entity: Entity, keys: Vec<K>) {
+        self.remove(entity);
+        let mut entities = self.entities.write();
+        for key in &keys {
+            let bucket = entities.entry(key.clone()).or_default();
+            if !bucket.contains(&entity) {
+                bucket.push(entity);
+            }
+        }
+        self.keys.write().insert(entity, keys);
+    }
+
+    fn remove(&self, entity: Entity) {
+        let Some(keys) = self.keys.write().remove(&entity) else {
+            return;
+        };
+        let mut entities = self.entities.write();
+        for key in keys {
+            if let Some(bucket) = entities.get_mut(&key) {
+                bucket.retain(|e| *e != entity);
+                if bucket.is_empty() {
+                    entities.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn clear(&self) {
+        self.entities.write().clear();
+        self.keys.write().clear();
+    }
+
+    /// Entities with `key`, in the order they were indexed.
+    pub fn get(&self, key: &K) -> Vec<Entity> {
+        self.entities.read().get(key).cloned().unwrap_or_default()
+    }
+
+    pub fn first(&self, key: &K) -> Option<Entity> {
+        self.entities.read().get(key).and_then(|bucket| bucket.first().copied())
+    }
+
+    pub fn keys_of(&self, entity: Entity) -> Vec<K> {
+        self.keys.read().get(&entity).cloned().unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities.read().is_empty()
+    }
+}
+
+type IndexUpdateFn = Arc<dyn Fn(&World, Entity) + Send + Sync>;
+type IndexRemoveFn = Arc<dyn Fn(Entity) + Send + Sync>;
+type IndexClearFn = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Clone)]
+struct IndexHandlers {
+    name: &'static str,
+    update: IndexUpdateFn,
+    remove: IndexRemoveFn,
+    clear: IndexClearFn,
+}
+
+/// The indices of a `World`, kept up to date as components are inserted,
+/// mutated through the world, removed, or despawned.
+#[derive(Default)]
+pub struct Indices {
+    indices: RwLock<HashMap<&'static str, Arc<dyn Any + Send + Sync>>>,
+    handlers: RwLock<HashMap<TypeId, Vec<IndexHandlers>>>,
+}
+
+impl Indices {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn handlers(&self, type_id: TypeId) -> Vec<IndexHandlers> {
+        self.handlers.read().get(&type_id).cloned().unwrap_or_default()
+    }
+
+    pub(crate) fn component_changed(&self, world: &World, type_id: TypeId, entity: Entity) {
+        for handlers in self.handlers(type_id) {
+            (handlers.update)(world, entity);
+        }
+    }
+
+    pub(crate) fn component_removed(&self, type_id: TypeId, entity: Entity) {
+        for handlers in self.handlers(type_id) {
+            (handlers.remove)(entity);
+        }
+    }
+
+    /// Drops all indexed entries but keeps the index definitions.
+    pub(crate) fn clear_entries(&self) {
+        for handlers in self.handlers.read().values().flatten() {
+            (handlers.clear)();
+        }
+    }
+
+    pub fn get<K: Send + Sync + 'static>(&self, name: &str) -> Option<Arc<ComponentIndex<K>>> {
+        self.indices.read().get(name)?.clone().downcast::<ComponentIndex<K>>().ok()
+    }
+}
+
+impl World {
+    /// Creates an index over `T`, keyed by whatever `extract` returns for each component.
+    /// Replaces any existing index with the same name.
+    ///
+    /// The index stays current when `T` is changed through `World` methods;
+    /// mutating storage through `World::components()` directly bypasses it.
+    pub fn add_index<T, K>(&self, name: &'static str, extract: impl Fn(&T) -> Vec<K> + Send + Sync + 'static)
+    where
+        T: Component,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        let index = Arc::new(ComponentIndex::<K>::new());
+        let type_id = TypeId::of::<T>();
+
+        let handlers = IndexHandlers {
+            name,
+            update: {
+                let index = index.clone();
+                Arc::new(move |world: &World, entity| {
+                    match world.components().with_component::<T, _>(entity, |c| c.map(&extract)) {
+                        Some(keys) => index.update(entity, keys),
+                        None => index.remove(entity),
+                    }
+                })
+            },
+            remove: {
+                let index = index.clone();
+                Arc::new(move |entity| index.remove(entity))
+            },
+            clear: {
+                let index = index.clone();
+                Arc::new(move || index.clear())
+            },
+        };
+
+        let indices = self.indices();
+        indices.indices.write().insert(name, index.clone());
+        {
+            let mut by_type = indices.handlers.write();
+            for existing in by_type.values_mut() {
+                existing.retain(|h| h.name != name);
+            }
+            by_type.entry(type_id).or_default().push(handlers.clone());
+        }
+
+        for entity in self.iter_entities() {
+            (handlers.update)(self, entity);
+        }
+    }
+
+    pub fn index<K: Send + Sync + 'static>(&self, name: &str) -> Option<Arc<ComponentIndex<K>>> {
+        self.indices().get(name)
+    }
+
+    /// Entities whose indexed key equals `key`, or nothing if the index does not exist.
+    pub fn lookup<K>(&self, name: &str, key: &K) -> Vec<Entity>
+    where
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        self.index::<K>(name).map(|index| index.get(key)).unwrap_or_default()
+    }
+
+    pub(crate) fn register_builtin_indices(&self) {
+        self.register_component::<Name>();
+        self.register_component::<Tags>();
+        self.add_index::<Name, String>(NAME_INDEX, |name| vec![name.0.clone()]);
+        self.add_index::<Tags, String>(TAG_INDEX, |tags| tags.0.clone());
+    }
+
+    /// An entity with this name, if there is one.
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.index::<String>(NAME_INDEX)?.first(&name.to_string())
+    }
+
+    pub fn entities_named(&self, name: &str) -> Vec<Entity> {
+        self.lookup(NAME_INDEX, &name.to_string())
+    }
+
+    pub fn entities_with_tag(&self, tag: &str) -> Vec<Entity> {
+        self.lookup(TAG_INDEX, &tag.to_string())
+    }
+
+    pub fn has_tag(&self, entity: Entity, tag: &str) -> bool {
+        self.with_component::<Tags, _>(entity, |tags| tags.is_some_and(|tags| tags.contains(tag)))
+    }
+
+    /// Adds `tag` to the entity, creating its `Tags` component if needed.
+    pub fn add_tag(&self, entity: Entity, tag: &str) {
+        let updated = self.with_component_mut::<Tags, _>(entity, |tags| tags.map(|tags| tags.insert(tag)).is_some());
+        if !updated {
+            self.add_component(entity, Tags::new([tag]));
+        }
+    }
+
+    pub fn remove_tag(&self, entity: Entity, tag: &str) -> bool {
+        self.with_component_mut::<Tags, _>(entity, |tags| tags.is_some_and(|tags| tags.remove(tag)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Team(u8);
+
+    #[test]
+    fn names_and_tags_are_indexed() {
+        let world = World::new();
+        let player = world.spawn().with(Name::new("Player")).with(Tags::new(["Player", "Hero"])).build(&world);
+        let goblin = world.spawn().with(Name::new("Goblin")).with(Tags::new(["Enemy"])).build(&world);
+        let orc = world.spawn().with(Name::new("Orc")).build(&world);
+
+        assert_eq!(world.find_by_name("Player"), Some(player));
+        assert_eq!(world.entities_with_tag("Enemy"), vec![goblin]);
+
+        world.add_tag(orc, "Enemy");
+        world.remove_tag(goblin, "Enemy");
+        world.with_component_mut::<Name, _>(goblin, |name| name.unwrap().set("Boss"));
+        assert_eq!(world.entities_with_tag("Enemy"), vec![orc]);
+        assert_eq!(world.find_by_name("Boss"), Some(goblin));
+        assert_eq!(world.find_by_name("Goblin"), None);
+
+        world.set_component_field(orc, "Name", "0", &crate::Value::String("Troll".into())).unwrap();
+        assert_eq!(world.find_by_name("Troll"), Some(orc));
+
+        world.despawn(player);
+        assert_eq!(world.find_by_name("Player"), None);
+        assert!(world.entities_with_tag("Hero").is_empty());
+    }
+
+    #[test]
+    fn user_defined_index() {
+        let world = World::new();
+        let red = world.spawn_with(Team(1));
+        world.add_index::<Team, u8>("team", |team| vec![team.0]);
+        let blue = world.spawn_with(Team(2));
+        let red2 = world.spawn_with(Team(1));
+
+        assert_eq!(world.lookup("team", &1u8), vec![red, red2]);
+        world.add_component(red, Team(2));
+        assert_eq!(world.lookup("team", &2u8), vec![blue, red]);
+        world.remove_component::<Team>(blue);
+        assert_eq!(world.lookup("team", &2u8), vec![red]);
+    }
+}
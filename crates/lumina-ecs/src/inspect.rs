@@ -0,0 +1,425 @@
+use crate::serialization::value_to_json;
+use crate::{Entity, EntitySnapshot, Name, StorageStats, Value, World, WorldSnapshot};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// One component of an inspected entity. `value` is `None` for components
+/// whose type is not registered for reflection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentReport {
+    pub name: String,
+    pub value: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityReport {
+    pub entity: Entity,
+    pub name: Option<String>,
+    pub components: Vec<ComponentReport>,
+}
+
+impl EntityReport {
+    pub fn component(&self, name: &str) -> Option<&ComponentReport> {
+        self.components.iter().find(|component| component.name == name)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let components = self
+            .components
+            .iter()
+            .map(|component| {
+                let value = component.value.as_ref().map_or(serde_json::Value::Null, value_to_json);
+                (component.name.clone(), value)
+            })
+            .collect();
+        serde_json::json!({
+            "id": self.entity.index(),
+            "name": self.name,
+            "components": serde_json::Value::Object(components),
+        })
+    }
+}
+
+impl fmt::Display for EntityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Entity {}", self.entity.index())?;
+        if let Some(name) = &self.name {
+            write!(f, " {:?}", name)?;
+        }
+        writeln!(f)?;
+        for component in &self.components {
+            match &component.value {
+                Some(value) => writeln!(f, "  {} = {}", component.name, value)?,
+                None => writeln!(f, "  {} (not reflected)", component.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A readable dump of everything in a world, for debugging and tests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldReport {
+    pub entities: Vec<EntityReport>,
+    pub resources: Vec<(String, Value)>,
+    pub storages: Vec<StorageStats>,
+}
+
+impl WorldReport {
+    pub fn entity(&self, entity: Entity) -> Option<&EntityReport> {
+        self.entities.iter().find(|report| report.entity == entity)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "entities": self.entities.iter().map(EntityReport::to_json).collect::<Vec<_>>(),
+            "resources": crate::serialization::named_values_to_json(&self.resources),
+            "storages": self.storages,
+        })
+    }
+
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string_pretty(&self.to_json()).expect("reports only contain JSON-safe values")
+    }
+}
+
+impl fmt::Display for WorldReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "World: {} entities, {} resources, {} component storages",
+            self.entities.len(),
+            self.resources.len(),
+            self.storages.len()
+        )?;
+        for entity in &self.entities {
+            write!(f, "{}", entity)?;
+        }
+        if !self.resources.is_empty() {
+            writeln!(f, "Resources")?;
+            for (name, value) in &self.resources {
+                writeln!(f, "  {} = {}", name, value)?;
+            }
+        }
+        if !self.storages.is_empty() {
+            writeln!(f, "Storages")?;
+            for storage in &self.storages {
+                writeln!(
+                    f,
+                    "  {}: {} x {} B, ~{} B allocated",
+                    storage.type_name, storage.count, storage.component_size, storage.estimated_bytes
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl World {
+    /// Lists every component of `entity`, with values for reflected components.
+    pub fn inspect_entity(&self, entity: Entity) -> Option<EntityReport> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+
+        let registry = self.type_registry();
+        let mut components: Vec<ComponentReport> = self
+            .components()
+            .component_stats_of(entity)
+            .into_iter()
+            .map(|(type_id, stats)| match registry.get(type_id) {
+                Some(registration) => ComponentReport {
+                    name: registration.name.to_string(),
+                    value: self.reflect_component(entity, registration.name),
+                },
+                None => ComponentReport {
+                    name: stats.type_name.to_string(),
+                    value: None,
+                },
+            })
+            .collect();
+        components.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Some(EntityReport {
+            entity,
+            name: self.with_component::<Name, _>(entity, |name| name.map(|name| name.to_string())),
+            components,
+        })
+    }
+
+    /// Dumps every entity, reflected resource and component storage.
+    pub fn inspect(&self) -> WorldReport {
+        let mut entities = self.iter_entities();
+        entities.sort_by_key(|entity| entity.index());
+
+        let resources = self
+            .type_registry()
+            .resources()
+            .into_iter()
+            .filter_map(|registration| {
+                self.reflect_resource(registration.name)
+                    .map(|value| (registration.name.to_string(), value))
+            })
+            .collect();
+
+        WorldReport {
+            entities: entities.into_iter().filter_map(|entity| self.inspect_entity(entity)).collect(),
+            resources,
+            storages: self.storage_stats(),
+        }
+    }
+
+    pub fn storage_stats(&self) -> Vec<StorageStats> {
+        self.components().storage_stats()
+    }
+}
+
+/// A value that differs between two snapshots. `path` uses the same syntax
+/// as `World::set_component_field` and is empty when the whole value changed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValueChange {
+    pub component: String,
+    pub path: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl fmt::Display for ValueChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}: {} -> {}", self.component, self.before, self.after)
+        } else {
+            write!(f, "{}.{}: {} -> {}", self.component, self.path, self.before, self.after)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EntityDiff {
+    pub id: u32,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<ValueChange>,
+}
+
+/// Differences between two snapshots of the same world.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SnapshotDiff {
+    pub spawned: Vec<u32>,
+    pub despawned: Vec<u32>,
+    pub entities: Vec<EntityDiff>,
+    pub resources: Vec<ValueChange>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.spawned.is_empty() && self.despawned.is_empty() && self.entities.is_empty() && self.resources.is_empty()
+    }
+
+    pub fn entity(&self, id: u32) -> Option<&EntityDiff> {
+        self.entities.iter().find(|diff| diff.id == id)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).expect("diffs only contain JSON-safe values")
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        for id in &self.spawned {
+            writeln!(f, "+ Entity {}", id)?;
+        }
+        for id in &self.despawned {
+            writeln!(f, "- Entity {}", id)?;
+        }
+        for diff in &self.entities {
+            writeln!(f, "~ Entity {}", diff.id)?;
+            for name in &diff.added {
+                writeln!(f, "    + {}", name)?;
+            }
+            for name in &diff.removed {
+                writeln!(f, "    - {}", name)?;
+            }
+            for change in &diff.changed {
+                writeln!(f, "    {}", change)?;
+            }
+        }
+        for change in &self.resources {
+            writeln!(f, "~ Resource {}", change)?;
+        }
+        Ok(())
+    }
+}
+
+impl WorldSnapshot {
+    /// What changed between `self` and a later snapshot. Entities are matched by id.
+    pub fn diff(&self, after: &WorldSnapshot) -> SnapshotDiff {
+        let before: HashMap<u32, &EntitySnapshot> = self.entities.iter().map(|entity| (entity.id, entity)).collect();
+        let after_ids: HashMap<u32, &EntitySnapshot> = after.entities.iter().map(|entity| (entity.id, entity)).collect();
+
+        let mut diff = SnapshotDiff::default();
+        for entity in &self.entities {
+            if !after_ids.contains_key(&entity.id) {
+                diff.despawned.push(entity.id);
+            }
+        }
+        for entity in &after.entities {
+            let Some(previous) = before.get(&entity.id) else {
+                diff.spawned.push(entity.id);
+                continue;
+            };
+
+            let mut entity_diff = EntityDiff {
+                id: entity.id,
+                ..Default::default()
+            };
+            for (name, value) in &entity.components {
+                match previous.component(name) {
+                    Some(old) => diff_values(name, String::new(), old, value, &mut entity_diff.changed),
+                    None => entity_diff.added.push(name.clone()),
+                }
+            }
+            for (name, _) in &previous.components {
+                if entity.component(name).is_none() {
+                    entity_diff.removed.push(name.clone());
+                }
+            }
+            if !entity_diff.added.is_empty() || !entity_diff.removed.is_empty() || !entity_diff.changed.is_empty() {
+                diff.entities.push(entity_diff);
+            }
+        }
+
+        for (name, value) in &after.resources {
+            let old = self.resources.iter().find(|(old, _)| old == name).map(|(_, value)| value);
+            match old {
+                Some(old) => diff_values(name, String::new(), old, value, &mut diff.resources),
+                None => diff.resources.push(ValueChange {
+                    component: name.clone(),
+                    path: String::new(),
+                    before: Value::Unit,
+                    after: value.clone(),
+                }),
+            }
+        }
+        for (name, value) in &self.resources {
+            if !after.resources.iter().any(|(new, _)| new == name) {
+                diff.resources.push(ValueChange {
+                    component: name.clone(),
+                    path: String::new(),
+                    before: value.clone(),
+                    after: Value::Unit,
+                });
+            }
+        }
+        diff
+    }
+}
+
+/// Records the leaf fields that differ, so a changed `x` reports `position.x`
+/// rather than the whole component.
+fn diff_values(component: &str, path: String, before: &Value, after: &Value, changes: &mut Vec<ValueChange>) {
+    if before == after {
+        return;
+    }
+    let join = |field: &str| if path.is_empty() { field.to_string() } else { format!("{}.{}", path, field) };
+
+    match (before, after) {
+        (Value::Struct(old), Value::Struct(new))
+            if old.len() == new.len() && old.iter().zip(new).all(|((a, _), (b, _))| a == b) =>
+        {
+            for ((field, old), (_, new)) in old.iter().zip(new) {
+                diff_values(component, join(field), old, new, changes);
+            }
+        }
+        (Value::List(old), Value::List(new)) if old.len() == new.len() => {
+            for (i, (old, new)) in old.iter().zip(new).enumerate() {
+                diff_values(component, join(&i.to_string()), old, new, changes);
+            }
+        }
+        _ => changes.push(ValueChange {
+            component: component.to_string(),
+            path,
+            before: before.clone(),
+            after: after.clone(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Reflect, SnapshotFilter};
+
+    #[derive(Debug, Clone, Default, PartialEq, Reflect)]
+    struct Health {
+        current: u32,
+        max: u32,
+    }
+
+    struct Opaque;
+
+    #[test]
+    fn reports_and_diffs_world_state() {
+        let world = World::new();
+        world.register_component::<Health>();
+        let player = world
+            .spawn()
+            .with(Name::new("Player"))
+            .with(Health { current: 10, max: 10 })
+            .with(Opaque)
+            .build(&world);
+        let enemy = world.spawn().with(Health { current: 3, max: 3 }).build(&world);
+
+        let report = world.inspect_entity(player).unwrap();
+        assert_eq!(report.name.as_deref(), Some("Player"));
+        assert_eq!(
+            report.component("Health").and_then(|health| health.value.as_ref()).map(ToString::to_string),
+            Some("{ current: 10, max: 10 }".to_string())
+        );
+        assert!(report.components.iter().any(|component| component.name.ends_with("Opaque") && component.value.is_none()));
+
+        let dump = world.inspect();
+        assert!(dump.to_string().contains("Entity 0 \"Player\""));
+        assert_eq!(dump.to_json()["entities"][1]["components"]["Health"]["current"], 3);
+        assert!(dump.storages.iter().any(|storage| storage.type_name.ends_with("Health") && storage.count == 2));
+
+        let before = world.snapshot(&SnapshotFilter::all());
+        world.with_component_mut::<Health, _>(player, |health| health.unwrap().current = 7);
+        world.despawn(enemy);
+        let diff = before.diff(&world.snapshot(&SnapshotFilter::all()));
+
+        assert_eq!(diff.despawned, vec![enemy.index()]);
+        let change = &diff.entity(player.index()).unwrap().changed[0];
+        assert_eq!(change.to_string(), "Health.current: 10 -> 7");
+        assert!(before.diff(&before).is_empty());
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Reflect)]
+    struct Wave {
+        number: u32,
+    }
+
+    #[test]
+    fn diffs_report_added_and_removed_resources() {
+        let world = World::new();
+        world.register_resource::<Wave>();
+        let empty = world.snapshot(&SnapshotFilter::all());
+        world.add_resource(Wave { number: 2 });
+        let with_wave = world.snapshot(&SnapshotFilter::all());
+
+        let added = empty.diff(&with_wave);
+        assert_eq!(added.resources.len(), 1);
+        assert_eq!(added.resources[0].before, Value::Unit);
+
+        let removed = with_wave.diff(&empty);
+        assert_eq!(removed.resources.len(), 1);
+        assert_eq!(removed.resources[0].component, "Wave");
+        assert_eq!(removed.resources[0].after, Value::Unit);
+        assert!(removed.resources[0].before.to_string().contains("number: 2"));
+    }
+}
@@ -1,21 +1,70 @@
+pub mod bundle;
+pub mod change_detection;
+pub mod commands;
 pub mod component;
+pub mod condition;
 pub mod entity;
+pub mod event;
+pub mod index;
+pub mod inspect;
+pub mod observer;
+pub mod prefab;
 pub mod query;
+pub mod reflect;
+pub mod relationship;
 pub mod resource;
+pub mod serialization;
+pub mod spatial;
+pub mod state;
 pub mod system;
+pub mod system_param;
+pub mod transfer;
+pub mod type_registry;
 pub mod world;
 
+pub use bundle::*;
+pub use change_detection::*;
+pub use commands::*;
 pub use component::*;
+pub use condition::*;
 pub use entity::*;
+pub use event::*;
+pub use index::*;
+pub use inspect::*;
+pub use observer::*;
+pub use prefab::*;
 pub use query::*;
+pub use reflect::*;
+pub use relationship::*;
 pub use resource::*;
+pub use serialization::*;
+pub use spatial::*;
+pub use state::*;
 pub use system::*;
+pub use system_param::*;
+pub use type_registry::*;
 pub use world::*;
 
 pub use lumina_core::{define_handle, Id};
+pub use lumina_ecs_derive::{Bundle, Reflect};
+
+// Lets `#[derive(Reflect)]` refer to `::lumina_ecs` from inside this crate.
+extern crate self as lumina_ecs;
 
 define_handle!(Entity);
 
+impl Entity {
+    /// An id that never refers to a live entity. Used as the default value so
+    /// reflected components holding entity references can be built before loading.
+    pub const PLACEHOLDER: Entity = Entity(u32::MAX);
+}
+
+impl Default for Entity {
+    fn default() -> Self {
+        Self::PLACEHOLDER
+    }
+}
+
 pub trait Component: Send + Sync + 'static {}
 
 impl<T: Send + Sync + 'static> Component for T {}
@@ -0,0 +1,142 @@
+use crate::{Entity, World};
+use parking_lot::RwLock;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Identifies a registered observer so it can be removed later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+type ObserverFn = Arc<dyn Fn(&World, Entity, &dyn Any) + Send + Sync>;
+
+#[derive(Clone)]
+struct ObserverEntry {
+    id: ObserverId,
+    /// `None` observes the event on every entity.
+    target: Option<Entity>,
+    callback: ObserverFn,
+}
+
+/// Observers keyed by event type, triggered synchronously by `World::trigger`.
+pub struct Observers {
+    next_id: AtomicU64,
+    observers: RwLock<HashMap<TypeId, Vec<ObserverEntry>>>,
+}
+
+impl Observers {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(0),
+            observers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn add<E: Send + Sync + 'static>(
+        &self,
+        target: Option<Entity>,
+        observer: impl Fn(&World, Entity, &E) + Send + Sync + 'static,
+    ) -> ObserverId {
+        let id = ObserverId(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let callback: ObserverFn = Arc::new(move |world, entity, event| {
+            if let Some(event) = event.downcast_ref::<E>() {
+                observer(world, entity, event);
+            }
+        });
+
+        self.observers
+            .write()
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push(ObserverEntry { id, target, callback });
+        id
+    }
+
+    pub fn remove(&self, id: ObserverId) -> bool {
+        let mut observers = self.observers.write();
+        for entries in observers.values_mut() {
+            if let Some(index) = entries.iter().position(|entry| entry.id == id) {
+                entries.remove(index);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Drops every observer targeting `entity`.
+    pub fn remove_target(&self, entity: Entity) {
+        let mut observers = self.observers.write();
+        for entries in observers.values_mut() {
+            entries.retain(|entry| entry.target != Some(entity));
+        }
+    }
+
+    /// Runs the observers of `entity`, then the global ones, each in registration order.
+    pub fn trigger<E: Send + Sync + 'static>(&self, world: &World, entity: Entity, event: &E) {
+        // Clone the matching callbacks so observers can register or remove
+        // other observers while they run.
+        let callbacks: Vec<ObserverFn> = match self.observers.read().get(&TypeId::of::<E>()) {
+            Some(entries) => {
+                let targeted = entries.iter().filter(|entry| entry.target == Some(entity));
+                let global = entries.iter().filter(|entry| entry.target.is_none());
+                targeted.chain(global).map(|entry| entry.callback.clone()).collect()
+            }
+            None => return,
+        };
+
+        for callback in callbacks {
+            callback(world, entity, event as &dyn Any);
+        }
+    }
+
+    pub fn clear(&self) {
+        self.observers.write().clear();
+    }
+}
+
+impl Default for Observers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::World;
+    use parking_lot::Mutex;
+    use std::sync::Arc;
+
+    struct Damage(u32);
+
+    #[test]
+    fn observers_receive_targeted_and_global_events() {
+        let world = World::new();
+        let player = world.spawn().build(&world);
+        let enemy = world.spawn().build(&world);
+        let log = Arc::new(Mutex::new(Vec::new()));
+
+        // Entity observers run first even when registered after global ones.
+        let global = log.clone();
+        let global_id = world.add_observer::<Damage>(move |_, entity, damage| {
+            global.lock().push(("any", entity, damage.0));
+        });
+        let targeted = log.clone();
+        world.observe::<Damage>(player, move |_, entity, damage| {
+            targeted.lock().push(("player", entity, damage.0));
+        });
+
+        world.trigger(player, Damage(5));
+        world.trigger(enemy, Damage(3));
+        assert_eq!(
+            *log.lock(),
+            vec![("player", player, 5), ("any", player, 5), ("any", enemy, 3)]
+        );
+
+        log.lock().clear();
+        assert!(world.remove_observer(global_id));
+        world.despawn(player);
+        world.trigger(player, Damage(1));
+        assert!(log.lock().is_empty());
+    }
+}
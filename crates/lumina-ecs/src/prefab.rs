@@ -0,0 +1,424 @@
+use crate::serialization::{named_values_from_json, named_values_to_json};
+use crate::{Entity, EntityMap, Reflect, ReflectError, SerializationError, Value, World};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+/// A reusable entity template: reflected components plus child nodes.
+///
+/// Entity references inside a prefab (`Value::Entity(n)`) refer to the n-th
+/// node in depth-first order, with the root at 0, and are remapped to the
+/// spawned entities on instantiation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Prefab {
+    pub name: String,
+    pub components: Vec<(String, Value)>,
+    pub children: Vec<Prefab>,
+}
+
+impl Prefab {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            components: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with(self, component: impl Reflect) -> Self {
+        let name = component.type_name();
+        self.with_value(name, component.to_value())
+    }
+
+    pub fn with_value(mut self, component: impl Into<String>, value: Value) -> Self {
+        self.set_component(component, value);
+        self
+    }
+
+    pub fn with_child(mut self, child: Prefab) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn component(&self, name: &str) -> Option<&Value> {
+        self.components
+            .iter()
+            .find(|(component, _)| component == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn set_component(&mut self, name: impl Into<String>, value: Value) {
+        let name = name.into();
+        match self.components.iter_mut().find(|(component, _)| *component == name) {
+            Some((_, existing)) => *existing = value,
+            None => self.components.push((name, value)),
+        }
+    }
+
+    pub fn remove_component(&mut self, name: &str) -> Option<Value> {
+        let index = self.components.iter().position(|(component, _)| component == name)?;
+        Some(self.components.remove(index).1)
+    }
+
+    /// Looks up a node by the child indices leading to it; `&[]` is the root.
+    pub fn node(&self, path: &[usize]) -> Option<&Prefab> {
+        path.iter().try_fold(self, |node, &index| node.children.get(index))
+    }
+
+    pub fn node_mut(&mut self, path: &[usize]) -> Option<&mut Prefab> {
+        path.iter().try_fold(self, |node, &index| node.children.get_mut(index))
+    }
+
+    /// Every node with its path, in depth-first order.
+    pub fn nodes(&self) -> Vec<(Vec<usize>, &Prefab)> {
+        fn visit<'a>(node: &'a Prefab, path: &mut Vec<usize>, out: &mut Vec<(Vec<usize>, &'a Prefab)>) {
+            out.push((path.clone(), node));
+            for (index, child) in node.children.iter().enumerate() {
+                path.push(index);
+                visit(child, path, out);
+                path.pop();
+            }
+        }
+
+        let mut nodes = Vec::new();
+        visit(self, &mut Vec::new(), &mut nodes);
+        nodes
+    }
+
+    pub fn to_json(&self) -> Result<String, SerializationError> {
+        Ok(serde_json::to_string_pretty(&self.to_json_value())?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SerializationError> {
+        Self::from_json_value(&serde_json::from_str(json)?)
+    }
+
+    fn to_json_value(&self) -> serde_json::Value {
+        serde_json::json!({
+            "name": self.name,
+            "components": named_values_to_json(&self.components),
+            "children": self.children.iter().map(Prefab::to_json_value).collect::<Vec<_>>(),
+        })
+    }
+
+    fn from_json_value(json: &serde_json::Value) -> Result<Self, SerializationError> {
+        let name = json
+            .get("name")
+            .and_then(|name| name.as_str())
+            .ok_or_else(|| SerializationError::InvalidFormat("prefab without a name".to_string()))?;
+
+        let mut children = Vec::new();
+        for child in json.get("children").and_then(|c| c.as_array()).into_iter().flatten() {
+            children.push(Self::from_json_value(child)?);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            components: named_values_from_json(json.get("components"))?,
+            children,
+        })
+    }
+}
+
+/// Serializes in the same format as `Prefab::to_json`.
+impl Serialize for Prefab {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json_value().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Prefab {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = serde_json::Value::deserialize(deserializer)?;
+        Self::from_json_value(&json).map_err(serde::de::Error::custom)
+    }
+}
+
+/// A field that an instance sets differently from its prefab.
+/// An empty `path` overrides the whole component.
+#[derive(Debug, Clone, Default, PartialEq, Reflect, Serialize, Deserialize)]
+pub struct PrefabOverride {
+    pub component: String,
+    pub path: String,
+    pub value: Value,
+}
+
+/// Links an entity to the prefab node it was spawned from.
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct PrefabInstance {
+    pub prefab: String,
+    /// Child indices from the prefab root to this entity's node.
+    pub node: Vec<usize>,
+    pub root: Entity,
+    /// Components this entity received from the prefab.
+    pub components: Vec<String>,
+    pub overrides: Vec<PrefabOverride>,
+}
+
+impl World {
+    /// Spawns one entity per prefab node, parented like the prefab tree, and returns the root entity.
+    ///
+    /// Nothing is spawned if any component fails to build.
+    pub fn instantiate(&self, prefab: &Prefab) -> Result<Entity, ReflectError> {
+        self.register_component::<PrefabInstance>();
+
+        let nodes = prefab.nodes();
+        let mut entity_map = EntityMap::new();
+        for index in 0..nodes.len() {
+            entity_map.insert(Entity::new(index as u32), self.spawn().build(self));
+        }
+        let root = entity_map.get(Entity::new(0)).expect("prefab has a root node");
+
+        for (index, (path, node)) in nodes.iter().enumerate() {
+            let entity = entity_map.get(Entity::new(index as u32)).expect("node entity was just spawned");
+            let mut instance = PrefabInstance {
+                prefab: prefab.name.clone(),
+                node: path.clone(),
+                root,
+                ..Default::default()
+            };
+            if let Err(err) = self.apply_prefab_node(entity, node, &entity_map, &mut instance) {
+                for (_, spawned) in entity_map.iter() {
+                    self.despawn(spawned);
+                }
+                return Err(err);
+            }
+        }
+        self.link_prefab_hierarchy(&nodes, &entity_map);
+
+        Ok(root)
+    }
+
+    /// Root entities of every live instance of the named prefab.
+    pub fn prefab_instances(&self, prefab: &str) -> Vec<Entity> {
+        let mut roots: Vec<Entity> = self
+            .query::<PrefabInstance>()
+            .into_iter()
+            .filter(|(entity, instance)| instance.prefab == prefab && instance.root == *entity)
+            .map(|(entity, _)| entity)
+            .collect();
+        roots.sort_by_key(|entity| entity.index());
+        roots
+    }
+
+    /// Sets a field on an instance and records it so `sync_prefab` keeps it.
+    pub fn set_prefab_override(&self, entity: Entity, component: &str, path: &str, value: Value) -> Result<(), ReflectError> {
+        if !self.has_component::<PrefabInstance>(entity) {
+            return Err(ReflectError::MissingComponent(entity, "PrefabInstance".to_string()));
+        }
+
+        let entry = PrefabOverride {
+            component: component.to_string(),
+            path: path.to_string(),
+            value,
+        };
+        self.apply_prefab_override(entity, &entry)?;
+
+        self.with_component_mut::<PrefabInstance, _>(entity, |instance| {
+            if let Some(instance) = instance {
+                instance
+                    .overrides
+                    .retain(|existing| existing.component != entry.component || existing.path != entry.path);
+                instance.overrides.push(entry);
+            }
+        });
+        Ok(())
+    }
+
+    /// Forgets an override. The prefab value is restored on the next `sync_prefab`.
+    pub fn clear_prefab_override(&self, entity: Entity, component: &str, path: &str) -> bool {
+        self.with_component_mut::<PrefabInstance, _>(entity, |instance| {
+            let Some(instance) = instance else {
+                return false;
+            };
+            let before = instance.overrides.len();
+            instance
+                .overrides
+                .retain(|existing| existing.component != component || existing.path != path);
+            instance.overrides.len() != before
+        })
+    }
+
+    /// Pushes an edited prefab to all of its instances and returns how many were updated.
+    ///
+    /// Nodes are matched by position: new nodes are spawned, removed nodes are
+    /// despawned, and components are reset to the prefab before overrides are reapplied.
+    pub fn sync_prefab(&self, prefab: &Prefab) -> Result<usize, ReflectError> {
+        let nodes = prefab.nodes();
+
+        let mut by_root: HashMap<Entity, Vec<(Entity, PrefabInstance)>> = HashMap::new();
+        for (entity, instance) in self.query::<PrefabInstance>() {
+            if instance.prefab == prefab.name {
+                by_root.entry(instance.root).or_default().push((entity, instance));
+            }
+        }
+        let mut roots: Vec<Entity> = by_root.keys().copied().filter(|root| self.is_alive(*root)).collect();
+        roots.sort_by_key(|entity| entity.index());
+
+        for root in &roots {
+            let mut instances: HashMap<Vec<usize>, (Entity, PrefabInstance)> = HashMap::new();
+            for (entity, instance) in by_root.remove(root).unwrap_or_default() {
+                if prefab.node(&instance.node).is_some() {
+                    instances.insert(instance.node.clone(), (entity, instance));
+                } else {
+                    self.despawn(entity);
+                }
+            }
+
+            let mut entity_map = EntityMap::new();
+            for (index, (path, _)) in nodes.iter().enumerate() {
+                let entity = match instances.get(path) {
+                    Some((entity, _)) => *entity,
+                    None => {
+                        let entity = self.spawn().build(self);
+                        let instance = PrefabInstance {
+                            prefab: prefab.name.clone(),
+                            node: path.clone(),
+                            root: *root,
+                            ..Default::default()
+                        };
+                        instances.insert(path.clone(), (entity, instance));
+                        entity
+                    }
+                };
+                entity_map.insert(Entity::new(index as u32), entity);
+            }
+
+            for (path, node) in &nodes {
+                if let Some((entity, instance)) = instances.get_mut(path) {
+                    self.apply_prefab_node(*entity, node, &entity_map, instance)?;
+                }
+            }
+            self.link_prefab_hierarchy(&nodes, &entity_map);
+        }
+
+        Ok(roots.len())
+    }
+
+    fn apply_prefab_node(
+        &self,
+        entity: Entity,
+        node: &Prefab,
+        entity_map: &EntityMap,
+        instance: &mut PrefabInstance,
+    ) -> Result<(), ReflectError> {
+        for (name, value) in &node.components {
+            let mut value = value.clone();
+            entity_map.remap_value(&mut value);
+            self.add_component_by_name(entity, name, &value)?;
+        }
+
+        for stale in instance
+            .components
+            .iter()
+            .filter(|name| node.component(name).is_none())
+        {
+            self.remove_component_by_name(entity, stale);
+        }
+        instance.components = node.components.iter().map(|(name, _)| name.clone()).collect();
+
+        for entry in &instance.overrides {
+            self.apply_prefab_override(entity, entry)?;
+        }
+
+        self.add_component(entity, instance.clone());
+        Ok(())
+    }
+
+    /// Parents each node's entity to the entity of its parent node.
+    fn link_prefab_hierarchy(&self, nodes: &[(Vec<usize>, &Prefab)], entity_map: &EntityMap) {
+        for (index, (path, _)) in nodes.iter().enumerate() {
+            let Some((_, parent_path)) = path.split_last() else {
+                continue;
+            };
+            let parent_index = nodes.iter().position(|(path, _)| path == parent_path);
+            let entity = entity_map.get(Entity::new(index as u32));
+            let parent = parent_index.and_then(|index| entity_map.get(Entity::new(index as u32)));
+            if let (Some(entity), Some(parent)) = (entity, parent) {
+                if let Err(err) = self.set_parent(entity, parent) {
+                    log::warn!("Failed to parent prefab node {:?}: {}", path, err);
+                }
+            }
+        }
+    }
+
+    fn apply_prefab_override(&self, entity: Entity, entry: &PrefabOverride) -> Result<(), ReflectError> {
+        if entry.path.is_empty() {
+            self.with_reflect_component_mut(entity, &entry.component, |component| component.apply(&entry.value))
+                .unwrap_or_else(|| Err(ReflectError::MissingComponent(entity, entry.component.clone())))
+        } else {
+            self.set_component_field(entity, &entry.component, &entry.path, &entry.value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reflect;
+
+    #[derive(Debug, Default, Clone, PartialEq, Reflect)]
+    struct Health {
+        current: u32,
+        max: u32,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Reflect)]
+    struct Turret {
+        owner: Entity,
+        range: f32,
+    }
+
+    fn world() -> World {
+        let world = World::new();
+        world.register_component::<Health>();
+        world.register_component::<Turret>();
+        world
+    }
+
+    fn tank() -> Prefab {
+        Prefab::new("Tank")
+            .with(Health { current: 100, max: 100 })
+            .with_child(Prefab::new("Turret").with(Turret { owner: Entity::new(0), range: 8.0 }))
+    }
+
+    #[test]
+    fn instances_remap_internal_references() {
+        let world = world();
+        let first = world.instantiate(&tank()).unwrap();
+        let second = world.instantiate(&tank()).unwrap();
+        assert_eq!(world.prefab_instances("Tank"), vec![first, second]);
+
+        let owners: Vec<Entity> = world.query::<Turret>().into_iter().map(|(_, turret)| turret.owner).collect();
+        assert!(owners.contains(&first) && owners.contains(&second));
+        assert_eq!(world.children(first).len(), 1);
+        assert_eq!(world.descendants(second), world.children(second));
+
+        let json = tank().to_json().unwrap();
+        assert_eq!(Prefab::from_json(&json).unwrap(), tank());
+        assert_eq!(serde_json::from_str::<Prefab>(&json).unwrap(), tank());
+    }
+
+    #[test]
+    fn prefab_edits_propagate_and_keep_overrides() {
+        let world = world();
+        let plain = world.instantiate(&tank()).unwrap();
+        let veteran = world.instantiate(&tank()).unwrap();
+        world.set_prefab_override(veteran, "Health", "max", Value::UInt(150)).unwrap();
+
+        let mut edited = tank();
+        edited.set_component("Health", Health { current: 120, max: 120 }.to_value());
+        edited.node_mut(&[0]).unwrap().remove_component("Turret");
+        edited = edited.with_child(Prefab::new("Radar").with(Health { current: 5, max: 5 }));
+
+        assert_eq!(world.sync_prefab(&edited).unwrap(), 2);
+        assert_eq!(world.get_component::<Health>(plain), Some(Health { current: 120, max: 120 }));
+        assert_eq!(world.get_component::<Health>(veteran), Some(Health { current: 120, max: 150 }));
+        assert!(world.query::<Turret>().is_empty());
+        assert_eq!(world.query::<Health>().len(), 4);
+        assert_eq!(world.children(veteran).len(), 2);
+
+        assert!(world.clear_prefab_override(veteran, "Health", "max"));
+        world.sync_prefab(&edited).unwrap();
+        assert_eq!(world.get_component::<Health>(veteran), Some(Health { current: 120, max: 120 }));
+    }
+}
@@ -1,67 +1,392 @@
-use crate::{Component, Entity, World};
+use crate::{Component, Entity, SystemAccess, World};
 use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
 
-pub trait Query {
-    type Item;
-    
-    fn query(world: &World) -> Self;
-    fn get(&self, entity: Entity) -> Option<Self::Item>;
+pub struct With<T: Component> {
+    _phantom: PhantomData<T>,
 }
 
-pub struct With<T: Component> {
+pub struct Without<T: Component> {
     _phantom: PhantomData<T>,
 }
 
-impl<T: Component> Query for With<T> {
+/// Per-entity predicate used by `World::query_filtered`.
+pub trait QueryFilter {
+    fn matches(world: &World, entity: Entity) -> bool;
+}
+
+impl QueryFilter for () {
+    fn matches(_world: &World, _entity: Entity) -> bool {
+        true
+    }
+}
+
+impl<T: Component> QueryFilter for With<T> {
+    fn matches(world: &World, entity: Entity) -> bool {
+        world.has_component::<T>(entity)
+    }
+}
+
+impl<T: Component> QueryFilter for Without<T> {
+    fn matches(world: &World, entity: Entity) -> bool {
+        !world.has_component::<T>(entity)
+    }
+}
+
+macro_rules! impl_query_filter_tuple {
+    ($($filter:ident),+) => {
+        impl<$($filter: QueryFilter),+> QueryFilter for ($($filter,)+) {
+            fn matches(world: &World, entity: Entity) -> bool {
+                $($filter::matches(world, entity))&&+
+            }
+        }
+    };
+}
+
+impl_query_filter_tuple!(A);
+impl_query_filter_tuple!(A, B);
+impl_query_filter_tuple!(A, B, C);
+impl_query_filter_tuple!(A, B, C, D);
+
+/// The query trait from before function systems, kept so existing code still compiles.
+/// It was called `Query`, which now names the system parameter.
+#[deprecated(note = "use the `Query` system parameter or `World::query`")]
+pub trait LegacyQuery {
+    type Item;
+
+    fn query(world: &World) -> Self;
+    fn get(&self, entity: Entity) -> Option<Self::Item>;
+}
+
+#[allow(deprecated)]
+impl<T: Component> LegacyQuery for With<T> {
     type Item = &'static T;
-    
+
     fn query(_world: &World) -> Self {
         Self {
             _phantom: PhantomData,
         }
     }
-    
-    fn get(&self, entity: Entity) -> Option<Self::Item> {
+
+    fn get(&self, _entity: Entity) -> Option<Self::Item> {
         None
     }
 }
 
+#[deprecated(note = "use `Query<&mut T>`")]
 pub struct WithMut<T: Component> {
     _phantom: PhantomData<T>,
 }
 
-impl<T: Component> Query for WithMut<T> {
+#[allow(deprecated)]
+impl<T: Component> LegacyQuery for WithMut<T> {
     type Item = &'static mut T;
-    
+
     fn query(_world: &World) -> Self {
         Self {
             _phantom: PhantomData,
         }
     }
-    
-    fn get(&self, entity: Entity) -> Option<Self::Item> {
+
+    fn get(&self, _entity: Entity) -> Option<Self::Item> {
         None
     }
 }
 
+#[deprecated(note = "use the `Query` system parameter or `World::query`")]
 pub struct QueryBuilder<Q> {
     query: Q,
 }
 
-impl<Q: Query> QueryBuilder<Q> {
+#[allow(deprecated)]
+impl<Q: LegacyQuery> QueryBuilder<Q> {
     pub fn new(world: &World) -> Self {
         Self {
             query: Q::query(world),
         }
     }
-    
+
     pub fn get(&self, entity: Entity) -> Option<Q::Item> {
         self.query.get(entity)
     }
 }
 
 impl World {
-    pub fn query_builder<Q: Query>(&self) -> QueryBuilder<Q> {
+    #[deprecated(note = "use the `Query` system parameter or `World::query`")]
+    #[allow(deprecated)]
+    pub fn query_builder<Q: LegacyQuery>(&self) -> QueryBuilder<Q> {
         QueryBuilder::new(self)
     }
-}
\ No newline at end of file
+
+    /// Like `query`, but only returns entities matching the filter `F`.
+    pub fn query_filtered<T: Component + Clone, F: QueryFilter>(&self) -> Vec<(Entity, T)> {
+        self.query::<T>()
+            .into_iter()
+            .filter(|(entity, _)| F::matches(self, *entity))
+            .collect()
+    }
+}
+
+/// The data a `Query` system parameter fetches per entity: `&T`, `&mut T`
+/// (fetched as a `Mut<T>`), `Entity`, `Option<D>`, or tuples of those.
+pub trait QueryData {
+    type Item<'q>;
+
+    fn add_access(access: &mut SystemAccess);
+
+    /// Whether the entity has everything this query needs.
+    fn matches(world: &World, entity: Entity) -> bool;
+
+    /// Entities that may match, or `None` to consider every entity.
+    fn candidates(world: &World) -> Option<Vec<Entity>>;
+
+    /// Borrows the data for `entity` for the duration of `f`.
+    fn fetch<R>(world: &World, entity: Entity, f: impl FnOnce(Self::Item<'_>) -> R) -> Option<R>;
+
+    fn shrink<'long: 'short, 'short>(item: Self::Item<'long>) -> Self::Item<'short>;
+}
+
+impl QueryData for Entity {
+    type Item<'q> = Entity;
+
+    fn add_access(_access: &mut SystemAccess) {}
+
+    fn matches(_world: &World, _entity: Entity) -> bool {
+        true
+    }
+
+    fn candidates(_world: &World) -> Option<Vec<Entity>> {
+        None
+    }
+
+    fn fetch<R>(_world: &World, entity: Entity, f: impl FnOnce(Self::Item<'_>) -> R) -> Option<R> {
+        Some(f(entity))
+    }
+
+    fn shrink<'long: 'short, 'short>(item: Self::Item<'long>) -> Self::Item<'short> {
+        item
+    }
+}
+
+impl<T: Component> QueryData for &T {
+    type Item<'q> = &'q T;
+
+    fn add_access(access: &mut SystemAccess) {
+        access.read_component::<T>();
+    }
+
+    fn matches(world: &World, entity: Entity) -> bool {
+        world.has_component::<T>(entity)
+    }
+
+    fn candidates(world: &World) -> Option<Vec<Entity>> {
+        Some(world.components().entities_with::<T>())
+    }
+
+    fn fetch<R>(world: &World, entity: Entity, f: impl FnOnce(Self::Item<'_>) -> R) -> Option<R> {
+        world.components().with_component::<T, _>(entity, |component| component.map(f))
+    }
+
+    fn shrink<'long: 'short, 'short>(item: Self::Item<'long>) -> Self::Item<'short> {
+        item
+    }
+}
+
+/// Exclusive borrow of a component fetched by `Query<&mut T>`. Marks the
+/// component changed when it is mutably dereferenced.
+pub struct Mut<'q, T> {
+    value: &'q mut T,
+    changed: &'q mut bool,
+}
+
+impl<T> Mut<'_, T> {
+    /// Marks the component changed without writing to it.
+    pub fn set_changed(&mut self) {
+        *self.changed = true;
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        *self.changed = true;
+        self.value
+    }
+}
+
+impl<T: Component> QueryData for &mut T {
+    type Item<'q> = Mut<'q, T>;
+
+    fn add_access(access: &mut SystemAccess) {
+        access.write_component::<T>();
+    }
+
+    fn matches(world: &World, entity: Entity) -> bool {
+        world.has_component::<T>(entity)
+    }
+
+    fn candidates(world: &World) -> Option<Vec<Entity>> {
+        Some(world.components().entities_with::<T>())
+    }
+
+    fn fetch<R>(world: &World, entity: Entity, f: impl FnOnce(Self::Item<'_>) -> R) -> Option<R> {
+        // The tick is only bumped, and indices updated, once the borrow is released.
+        let mut changed = false;
+        let result = world.components().with_component_mut_untracked::<T, _>(entity, |component| {
+            component.map(|value| f(Mut { value, changed: &mut changed }))
+        });
+        if changed {
+            world.set_component_changed::<T>(entity);
+        }
+        result
+    }
+
+    fn shrink<'long: 'short, 'short>(item: Self::Item<'long>) -> Self::Item<'short> {
+        item
+    }
+}
+
+impl<D: QueryData> QueryData for Option<D> {
+    type Item<'q> = Option<D::Item<'q>>;
+
+    fn add_access(access: &mut SystemAccess) {
+        D::add_access(access);
+    }
+
+    fn matches(_world: &World, _entity: Entity) -> bool {
+        true
+    }
+
+    fn candidates(_world: &World) -> Option<Vec<Entity>> {
+        None
+    }
+
+    fn fetch<R>(world: &World, entity: Entity, f: impl FnOnce(Self::Item<'_>) -> R) -> Option<R> {
+        let mut f = Some(f);
+        let fetched = D::fetch(world, entity, |data| (f.take().expect("fetch calls f at most once"))(Some(data)));
+        match (fetched, f) {
+            (Some(result), _) => Some(result),
+            (None, Some(f)) => Some(f(None)),
+            (None, None) => None,
+        }
+    }
+
+    fn shrink<'long: 'short, 'short>(item: Self::Item<'long>) -> Self::Item<'short> {
+        item.map(D::shrink)
+    }
+}
+
+macro_rules! impl_query_data_tuple {
+    ($($data:ident),+) => {
+        impl<$($data: QueryData),+> QueryData for ($($data,)+) {
+            type Item<'q> = ($($data::Item<'q>,)+);
+
+            fn add_access(access: &mut SystemAccess) {
+                $($data::add_access(access);)+
+            }
+
+            fn matches(world: &World, entity: Entity) -> bool {
+                $($data::matches(world, entity))&&+
+            }
+
+            fn candidates(world: &World) -> Option<Vec<Entity>> {
+                None$(.or_else(|| $data::candidates(world)))+
+            }
+
+            fn fetch<R>(world: &World, entity: Entity, f: impl FnOnce(Self::Item<'_>) -> R) -> Option<R> {
+                impl_query_data_tuple!(@fetch world, entity, f, (), $($data),+)
+            }
+
+            #[allow(non_snake_case)]
+            fn shrink<'long: 'short, 'short>(item: Self::Item<'long>) -> Self::Item<'short> {
+                let ($($data,)+) = item;
+                ($($data::shrink($data),)+)
+            }
+        }
+    };
+    // Nests one fetch closure per element, then shrinks every borrow to the innermost scope.
+    (@fetch $world:ident, $entity:ident, $f:ident, ($($fetched:ident),*), $head:ident $(, $tail:ident)*) => {
+        #[allow(non_snake_case)]
+        $head::fetch($world, $entity, |$head| {
+            impl_query_data_tuple!(@fetch $world, $entity, $f, ($($fetched,)* $head), $($tail),*)
+        })
+        .flatten()
+    };
+    (@fetch $world:ident, $entity:ident, $f:ident, ($($fetched:ident),*), ) => {
+        Some($f(($($fetched::shrink($fetched),)*)))
+    };
+}
+
+impl_query_data_tuple!(A);
+impl_query_data_tuple!(A, B);
+impl_query_data_tuple!(A, B, C);
+impl_query_data_tuple!(A, B, C, D);
+impl_query_data_tuple!(A, B, C, D, E);
+impl_query_data_tuple!(A, B, C, D, E, F);
+
+/// System parameter visiting every entity that has `D` and passes the filter `F`.
+///
+/// Data is borrowed per entity inside closures, so the system must not access
+/// the same components through `World` while a visit is in progress.
+pub struct Query<'w, D: QueryData, F: QueryFilter = ()> {
+    world: &'w World,
+    _marker: PhantomData<fn() -> (D, F)>,
+}
+
+impl<'w, D: QueryData, F: QueryFilter> Query<'w, D, F> {
+    pub fn new(world: &'w World) -> Self {
+        Self {
+            world,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.world.is_alive(entity) && D::matches(self.world, entity) && F::matches(self.world, entity)
+    }
+
+    /// Matching entities, in storage order.
+    pub fn entities(&self) -> Vec<Entity> {
+        D::candidates(self.world)
+            .unwrap_or_else(|| self.world.iter_entities())
+            .into_iter()
+            .filter(|entity| self.contains(*entity))
+            .collect()
+    }
+
+    pub fn for_each(&self, mut f: impl FnMut(D::Item<'_>)) {
+        for entity in self.entities() {
+            D::fetch(self.world, entity, &mut f);
+        }
+    }
+
+    pub fn get<R>(&self, entity: Entity, f: impl FnOnce(D::Item<'_>) -> R) -> Option<R> {
+        if !self.contains(entity) {
+            return None;
+        }
+        D::fetch(self.world, entity, f)
+    }
+
+    /// Runs `f` on the only match, or returns `None` if there are zero or several.
+    pub fn single<R>(&self, f: impl FnOnce(D::Item<'_>) -> R) -> Option<R> {
+        match self.entities().as_slice() {
+            [entity] => D::fetch(self.world, *entity, f),
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entities().is_empty()
+    }
+}
@@ -0,0 +1,661 @@
+use crate::Entity;
+use lumina_core::math::{Quat, Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+
+/// A dynamically typed snapshot of a reflected value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Value {
+    #[default]
+    Unit,
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    String(String),
+    /// An entity reference, stored by index so it can be remapped on load.
+    Entity(u32),
+    List(Vec<Value>),
+    /// Named fields in declaration order.
+    Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// A short description of the variant, used in error messages.
+    pub fn kind(&self) -> String {
+        match self {
+            Value::Unit => "unit",
+            Value::Bool(_) => "bool",
+            Value::Int(_) => "int",
+            Value::UInt(_) => "uint",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Entity(_) => "entity",
+            Value::List(_) => "list",
+            Value::Struct(_) => "struct",
+        }
+        .to_string()
+    }
+
+    /// Rewrites every entity reference inside this value.
+    pub fn map_entities(&mut self, map: &mut dyn FnMut(u32) -> u32) {
+        match self {
+            Value::Entity(index) => *index = map(*index),
+            Value::List(values) => values.iter_mut().for_each(|value| value.map_entities(map)),
+            Value::Struct(fields) => fields.iter_mut().for_each(|(_, value)| value.map_entities(map)),
+            _ => {}
+        }
+    }
+
+    /// Rewrites every entity reference inside this value. Entries of entity lists that
+    /// `map` returns `None` for are removed; any other such reference becomes
+    /// `Entity::PLACEHOLDER`.
+    pub fn filter_map_entities(&mut self, map: &mut dyn FnMut(u32) -> Option<u32>) {
+        match self {
+            Value::Entity(index) => *index = map(*index).unwrap_or(Entity::PLACEHOLDER.index()),
+            Value::List(values) => values.retain_mut(|value| match value {
+                Value::Entity(index) => map(*index).map(|mapped| *index = mapped).is_some(),
+                value => {
+                    value.filter_map_entities(map);
+                    true
+                }
+            }),
+            Value::Struct(fields) => fields.iter_mut().for_each(|(_, value)| value.filter_map_entities(map)),
+            _ => {}
+        }
+    }
+
+    /// Looks up a named field of a `Value::Struct`.
+    pub fn field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Struct(fields) => fields.iter().find(|(field, _)| field == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ReflectError {
+    #[error("No such field: {0}")]
+    NoSuchField(String),
+    #[error("No such variant: {0}")]
+    NoSuchVariant(String),
+    #[error("Type mismatch: expected {expected}, found {found}")]
+    TypeMismatch { expected: &'static str, found: String },
+    #[error("Value out of range for {0}")]
+    OutOfRange(&'static str),
+    #[error("Type is not registered: {0}")]
+    UnregisteredType(String),
+    #[error("Entity {0:?} does not have component {1}")]
+    MissingComponent(Entity, String),
+    #[error("Entity {0:?} is not alive")]
+    DeadEntity(Entity),
+    #[error("Resource not found: {0}")]
+    MissingResource(String),
+}
+
+/// Static description of a field, used by inspectors to build editors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub type_name: &'static str,
+}
+
+impl FieldInfo {
+    pub fn new(name: &'static str, type_name: &'static str) -> Self {
+        Self { name, type_name }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeInfo {
+    /// A primitive or opaque value edited as a whole.
+    Value,
+    Struct { fields: Vec<FieldInfo> },
+    TupleStruct { fields: Vec<FieldInfo> },
+    Enum { variants: Vec<&'static str> },
+    List { item_type_name: &'static str },
+    Option { inner_type_name: &'static str },
+}
+
+/// Runtime access to a type's fields, usually implemented with `#[derive(Reflect)]`.
+///
+/// `apply` is a partial update: struct values only touch the fields they name,
+/// which is what editors and prefab overrides rely on.
+pub trait Reflect: Any + Send + Sync {
+    fn type_name(&self) -> &'static str;
+
+    fn type_info(&self) -> TypeInfo {
+        TypeInfo::Value
+    }
+
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+
+    fn to_value(&self) -> Value;
+
+    fn apply(&mut self, value: &Value) -> Result<(), ReflectError>;
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl dyn Reflect {
+    pub fn downcast_ref<T: Reflect>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+
+    pub fn downcast_mut<T: Reflect>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut()
+    }
+
+    pub fn downcast<T: Reflect>(self: Box<Self>) -> Result<Box<T>, Box<dyn Any>> {
+        self.into_any().downcast()
+    }
+
+    /// Resolves a dot-separated field path such as `"position.x"` or `"points.2"`.
+    pub fn path(&self, path: &str) -> Option<&dyn Reflect> {
+        path.split('.')
+            .filter(|segment| !segment.is_empty())
+            .try_fold(self, |current, segment| current.field(segment))
+    }
+
+    pub fn path_mut(&mut self, path: &str) -> Option<&mut dyn Reflect> {
+        let mut current = self;
+        for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+            current = current.field_mut(segment)?;
+        }
+        Some(current)
+    }
+
+    /// Applies `value` to the field at `path`.
+    pub fn set_path(&mut self, path: &str, value: &Value) -> Result<(), ReflectError> {
+        let type_name = self.type_name();
+        self.path_mut(path)
+            .ok_or_else(|| ReflectError::NoSuchField(format!("{}.{}", type_name, path)))?
+            .apply(value)
+    }
+}
+
+/// Compact, Rust-like formatting used by world dumps and diffs.
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Unit => f.write_str("()"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Int(value) => write!(f, "{}", value),
+            Value::UInt(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::String(value) => write!(f, "{:?}", value),
+            Value::Entity(index) => write!(f, "Entity({})", index),
+            Value::List(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            Value::Struct(fields) => {
+                f.write_str("{ ")?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", name, value)?;
+                }
+                f.write_str(" }")
+            }
+        }
+    }
+}
+
+macro_rules! impl_reflect_any {
+    () => {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+
+        fn into_any(self: Box<Self>) -> Box<dyn Any> {
+            self
+        }
+    };
+}
+
+macro_rules! impl_reflect_int {
+    ($($ty:ty => $variant:ident),* $(,)?) => {
+        $(
+            impl Reflect for $ty {
+                fn type_name(&self) -> &'static str {
+                    stringify!($ty)
+                }
+
+                fn to_value(&self) -> Value {
+                    Value::$variant((*self).into())
+                }
+
+                fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+                    *self = match value {
+                        Value::Int(v) => <$ty>::try_from(*v).map_err(|_| ReflectError::OutOfRange(stringify!($ty)))?,
+                        Value::UInt(v) => <$ty>::try_from(*v).map_err(|_| ReflectError::OutOfRange(stringify!($ty)))?,
+                        Value::Float(v) if v.fract() == 0.0 => <$ty>::try_from(*v as i128)
+                            .map_err(|_| ReflectError::OutOfRange(stringify!($ty)))?,
+                        other => {
+                            return Err(ReflectError::TypeMismatch {
+                                expected: stringify!($ty),
+                                found: other.kind(),
+                            })
+                        }
+                    };
+                    Ok(())
+                }
+
+                impl_reflect_any!();
+            }
+        )*
+    };
+}
+
+impl_reflect_int!(
+    i8 => Int, i16 => Int, i32 => Int, i64 => Int,
+    u8 => UInt, u16 => UInt, u32 => UInt, u64 => UInt,
+);
+
+impl Reflect for usize {
+    fn type_name(&self) -> &'static str {
+        "usize"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::UInt(*self as u64)
+    }
+
+    fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+        let mut wide = *self as u64;
+        wide.apply(value)?;
+        *self = usize::try_from(wide).map_err(|_| ReflectError::OutOfRange("usize"))?;
+        Ok(())
+    }
+
+    impl_reflect_any!();
+}
+
+macro_rules! impl_reflect_float {
+    ($($ty:ty),*) => {
+        $(
+            impl Reflect for $ty {
+                fn type_name(&self) -> &'static str {
+                    stringify!($ty)
+                }
+
+                fn to_value(&self) -> Value {
+                    Value::Float(*self as f64)
+                }
+
+                fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+                    *self = match value {
+                        Value::Float(v) => *v as $ty,
+                        Value::Int(v) => *v as $ty,
+                        Value::UInt(v) => *v as $ty,
+                        other => {
+                            return Err(ReflectError::TypeMismatch {
+                                expected: stringify!($ty),
+                                found: other.kind(),
+                            })
+                        }
+                    };
+                    Ok(())
+                }
+
+                impl_reflect_any!();
+            }
+        )*
+    };
+}
+
+impl_reflect_float!(f32, f64);
+
+impl Reflect for bool {
+    fn type_name(&self) -> &'static str {
+        "bool"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+        match value {
+            Value::Bool(v) => {
+                *self = *v;
+                Ok(())
+            }
+            other => Err(ReflectError::TypeMismatch {
+                expected: "bool",
+                found: other.kind(),
+            }),
+        }
+    }
+
+    impl_reflect_any!();
+}
+
+impl Reflect for String {
+    fn type_name(&self) -> &'static str {
+        "String"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+        match value {
+            Value::String(v) => {
+                self.clone_from(v);
+                Ok(())
+            }
+            other => Err(ReflectError::TypeMismatch {
+                expected: "String",
+                found: other.kind(),
+            }),
+        }
+    }
+
+    impl_reflect_any!();
+}
+
+impl Reflect for Entity {
+    fn type_name(&self) -> &'static str {
+        "Entity"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Entity(self.index())
+    }
+
+    fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+        match value {
+            Value::Entity(index) => {
+                *self = Entity::new(*index);
+                Ok(())
+            }
+            other => Err(ReflectError::TypeMismatch {
+                expected: "Entity",
+                found: other.kind(),
+            }),
+        }
+    }
+
+    impl_reflect_any!();
+}
+
+/// Lets components store arbitrary reflected data, e.g. recorded overrides.
+impl Reflect for Value {
+    fn type_name(&self) -> &'static str {
+        "Value"
+    }
+
+    fn to_value(&self) -> Value {
+        self.clone()
+    }
+
+    fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+        self.clone_from(value);
+        Ok(())
+    }
+
+    impl_reflect_any!();
+}
+
+impl<T: Reflect + Default> Reflect for Vec<T> {
+    fn type_name(&self) -> &'static str {
+        "Vec"
+    }
+
+    fn type_info(&self) -> TypeInfo {
+        TypeInfo::List {
+            item_type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        let index: usize = name.parse().ok()?;
+        self.get(index).map(|item| item as &dyn Reflect)
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        let index: usize = name.parse().ok()?;
+        self.get_mut(index).map(|item| item as &mut dyn Reflect)
+    }
+
+    fn to_value(&self) -> Value {
+        Value::List(self.iter().map(Reflect::to_value).collect())
+    }
+
+    /// Replaces the whole list; items are rebuilt from their defaults.
+    fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+        match value {
+            Value::List(values) => {
+                let mut items = Vec::with_capacity(values.len());
+                for value in values {
+                    let mut item = T::default();
+                    item.apply(value)?;
+                    items.push(item);
+                }
+                *self = items;
+                Ok(())
+            }
+            other => Err(ReflectError::TypeMismatch {
+                expected: "Vec",
+                found: other.kind(),
+            }),
+        }
+    }
+
+    impl_reflect_any!();
+}
+
+impl<T: Reflect + Default> Reflect for Option<T> {
+    fn type_name(&self) -> &'static str {
+        "Option"
+    }
+
+    fn type_info(&self) -> TypeInfo {
+        TypeInfo::Option {
+            inner_type_name: std::any::type_name::<T>(),
+        }
+    }
+
+    fn field(&self, name: &str) -> Option<&dyn Reflect> {
+        self.as_ref().and_then(|inner| inner.field(name))
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+        self.as_mut().and_then(|inner| inner.field_mut(name))
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Some(inner) => inner.to_value(),
+            None => Value::Unit,
+        }
+    }
+
+    fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+        match value {
+            Value::Unit => *self = None,
+            value => self.get_or_insert_with(T::default).apply(value)?,
+        }
+        Ok(())
+    }
+
+    impl_reflect_any!();
+}
+
+macro_rules! impl_reflect_math {
+    ($($ty:ident { $($field:ident),* }),* $(,)?) => {
+        $(
+            impl Reflect for $ty {
+                fn type_name(&self) -> &'static str {
+                    stringify!($ty)
+                }
+
+                fn type_info(&self) -> TypeInfo {
+                    TypeInfo::Struct {
+                        fields: vec![$(FieldInfo::new(stringify!($field), "f32")),*],
+                    }
+                }
+
+                fn field(&self, name: &str) -> Option<&dyn Reflect> {
+                    match name {
+                        $(stringify!($field) => Some(&self.$field),)*
+                        _ => None,
+                    }
+                }
+
+                fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+                    match name {
+                        $(stringify!($field) => Some(&mut self.$field),)*
+                        _ => None,
+                    }
+                }
+
+                fn to_value(&self) -> Value {
+                    Value::Struct(vec![$((stringify!($field).to_string(), self.$field.to_value())),*])
+                }
+
+                fn apply(&mut self, value: &Value) -> Result<(), ReflectError> {
+                    match value {
+                        Value::Struct(values) => {
+                            for (name, value) in values {
+                                self.field_mut(name)
+                                    .ok_or_else(|| ReflectError::NoSuchField(format!("{}.{}", stringify!($ty), name)))?
+                                    .apply(value)?;
+                            }
+                            Ok(())
+                        }
+                        Value::List(values) => {
+                            let names = [$(stringify!($field)),*];
+                            for (name, value) in names.iter().zip(values) {
+                                if let Some(field) = self.field_mut(name) {
+                                    field.apply(value)?;
+                                }
+                            }
+                            Ok(())
+                        }
+                        other => Err(ReflectError::TypeMismatch {
+                            expected: stringify!($ty),
+                            found: other.kind(),
+                        }),
+                    }
+                }
+
+                impl_reflect_any!();
+            }
+        )*
+    };
+}
+
+impl_reflect_math!(
+    Vec2 { x, y },
+    Vec3 { x, y, z },
+    Vec4 { x, y, z, w },
+    Quat { x, y, z, w },
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reflect;
+
+    #[derive(Debug, Default, PartialEq, Reflect)]
+    struct Transform {
+        position: Vec2,
+        rotation: f32,
+        layers: Vec<u8>,
+        #[reflect(ignore)]
+        cached: u32,
+    }
+
+    #[derive(Debug, Default, PartialEq, Reflect)]
+    enum BodyType {
+        #[default]
+        Static,
+        Dynamic,
+    }
+
+    #[test]
+    fn derived_struct_exposes_fields_by_path() {
+        let mut transform = Transform {
+            position: Vec2::new(1.0, 2.0),
+            rotation: 0.5,
+            layers: vec![3],
+            cached: 7,
+        };
+        let reflect: &mut dyn Reflect = &mut transform;
+
+        assert_eq!(reflect.path("position.y").unwrap().to_value(), Value::Float(2.0));
+        assert_eq!(reflect.path("layers.0").unwrap().to_value(), Value::UInt(3));
+        assert!(reflect.path("cached").is_none());
+
+        reflect.set_path("position.x", &Value::Int(10)).unwrap();
+        assert_eq!(transform.position.x, 10.0);
+        assert!(matches!(
+            (&mut transform as &mut dyn Reflect).set_path("rotation", &Value::String("a".into())),
+            Err(ReflectError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn values_round_trip_through_apply() {
+        let original = Transform {
+            position: Vec2::new(4.0, 5.0),
+            rotation: 1.5,
+            layers: vec![1, 2],
+            cached: 9,
+        };
+        let mut copy = Transform::default();
+        copy.apply(&original.to_value()).unwrap();
+        assert_eq!(copy, Transform { cached: 0, ..original });
+
+        let mut body = BodyType::Static;
+        body.apply(&BodyType::Dynamic.to_value()).unwrap();
+        assert_eq!(body, BodyType::Dynamic);
+        assert!(body.apply(&Value::String("Floating".into())).is_err());
+    }
+
+    #[derive(Debug, Default, PartialEq, Reflect)]
+    struct Score(u32, f32);
+
+    #[test]
+    fn display_is_compact_and_rust_like() {
+        let transform = Transform {
+            position: Vec2::new(1.0, 2.0),
+            rotation: 0.5,
+            layers: vec![3],
+            cached: 0,
+        };
+        assert_eq!(
+            transform.to_value().to_string(),
+            "{ position: { x: 1.0, y: 2.0 }, rotation: 0.5, layers: [3] }"
+        );
+        // Tuple structs reflect as lists.
+        assert_eq!(Score(3, 0.5).to_value().to_string(), "[3, 0.5]");
+        assert_eq!(BodyType::Dynamic.to_value().to_string(), "\"Dynamic\"");
+    }
+}
@@ -0,0 +1,475 @@
+use crate::{Component, Entity, Reflect, World};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RelationshipError {
+    #[error("Entity {0:?} is not alive")]
+    DeadEntity(Entity),
+    #[error("Entity {0:?} cannot be related to itself")]
+    SelfRelation(Entity),
+    #[error("Making {parent:?} the parent of {child:?} would create a cycle")]
+    Cycle { child: Entity, parent: Entity },
+    #[error("Entity {0:?} has no parent")]
+    NoParent(Entity),
+}
+
+/// A component on a source entity pointing at one target entity.
+///
+/// Once registered with `World::register_relationship`, the target's
+/// `Self::Target` list is kept in sync when the relationship is added,
+/// removed, or either entity is despawned.
+pub trait Relationship: Component + Clone {
+    type Target: RelationshipTarget;
+
+    /// Whether sources are despawned together with their target.
+    const DESPAWN_WITH_TARGET: bool = false;
+
+    fn new(target: Entity) -> Self;
+    fn get(&self) -> Entity;
+}
+
+/// The ordered list of sources stored on the target of a `Relationship`.
+pub trait RelationshipTarget: Component + Clone + Default {
+    fn sources(&self) -> &[Entity];
+    fn sources_mut(&mut self) -> &mut Vec<Entity>;
+}
+
+macro_rules! relationship {
+    ($(#[$source_meta:meta])* $source:ident, $(#[$target_meta:meta])* $target:ident, $despawn:expr) => {
+        $(#[$source_meta])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+        pub struct $source(Entity);
+
+        impl $source {
+            pub fn get(&self) -> Entity {
+                self.0
+            }
+        }
+
+        impl Relationship for $source {
+            type Target = $target;
+            const DESPAWN_WITH_TARGET: bool = $despawn;
+
+            fn new(target: Entity) -> Self {
+                Self(target)
+            }
+
+            fn get(&self) -> Entity {
+                self.0
+            }
+        }
+
+        $(#[$target_meta])*
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Reflect)]
+        pub struct $target(Vec<Entity>);
+
+        impl $target {
+            pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+                self.0.iter().copied()
+            }
+
+            pub fn len(&self) -> usize {
+                self.0.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.0.is_empty()
+            }
+        }
+
+        impl RelationshipTarget for $target {
+            fn sources(&self) -> &[Entity] {
+                &self.0
+            }
+
+            fn sources_mut(&mut self) -> &mut Vec<Entity> {
+                &mut self.0
+            }
+        }
+    };
+}
+
+relationship!(
+    /// The entity's parent in the scene hierarchy.
+    Parent,
+    /// Children of an entity in sibling order.
+    Children,
+    false
+);
+
+relationship!(
+    /// The entity is owned by another and is despawned with it.
+    OwnedBy,
+    /// Entities owned by this entity.
+    Owned,
+    true
+);
+
+relationship!(
+    /// The entity targets another, e.g. for AI or projectiles. Removed when the target is despawned.
+    Targets,
+    /// Entities targeting this entity.
+    TargetedBy,
+    false
+);
+
+impl World {
+    pub(crate) fn register_builtin_relationships(&self) {
+        self.register_relationship::<Parent>();
+        self.register_relationship::<OwnedBy>();
+        self.register_relationship::<Targets>();
+
+        self.register_component::<Parent>();
+        self.register_component::<Children>();
+        self.register_component::<OwnedBy>();
+        self.register_component::<Owned>();
+        self.register_component::<Targets>();
+        self.register_component::<TargetedBy>();
+    }
+
+    /// Installs the hooks that keep `R` and its target list consistent.
+    pub fn register_relationship<R: Relationship>(&self) {
+        let components = self.components();
+
+        // Targets that a replacing insert or reflected edit is about to overwrite,
+        // so the relinking hook can detach the source without searching for it.
+        let replaced: Arc<Mutex<HashMap<Entity, Entity>>> = Arc::default();
+
+        let stash = replaced.clone();
+        components.on_replace::<R>(move |world, source| {
+            if let Some(target) = world.relation_target::<R>(source) {
+                stash.lock().insert(source, target);
+            }
+        });
+
+        let inserted = replaced.clone();
+        components.on_insert::<R>(move |world, source| {
+            let previous = inserted.lock().remove(&source);
+            link_source::<R>(world, source, previous);
+        });
+        components.on_change::<R>(move |world, source| {
+            let previous = replaced.lock().remove(&source);
+            link_source::<R>(world, source, previous);
+        });
+
+        components.on_remove::<R>(|world, source| {
+            if let Some(target) = world.get_component::<R>(source).map(|relation| relation.get()) {
+                detach_source::<R>(world, source, target);
+            }
+        });
+
+        // Runs when the target is despawned or its list is removed by hand.
+        components.on_remove::<R::Target>(|world, target| {
+            let sources = world
+                .get_component::<R::Target>(target)
+                .map(|list| list.sources().to_vec())
+                .unwrap_or_default();
+            for source in sources {
+                if R::DESPAWN_WITH_TARGET {
+                    world.despawn(source);
+                } else if world.get_component::<R>(source).is_some_and(|relation| relation.get() == target) {
+                    world.remove_component::<R>(source);
+                }
+            }
+        });
+    }
+
+    /// Points `source` at `target` through `R`, replacing any previous target.
+    pub fn relate<R: Relationship>(&self, source: Entity, target: Entity) -> Result<(), RelationshipError> {
+        for entity in [source, target] {
+            if !self.is_alive(entity) {
+                return Err(RelationshipError::DeadEntity(entity));
+            }
+        }
+        if source == target {
+            return Err(RelationshipError::SelfRelation(source));
+        }
+        if self.relation_target::<R>(source) == Some(target) {
+            return Ok(());
+        }
+
+        // Overwrites in place, so the replace hooks detach the old target
+        // and no removal is recorded.
+        self.add_component(source, R::new(target));
+        Ok(())
+    }
+
+    /// Removes `R` from `source`, returning the previous target.
+    pub fn unrelate<R: Relationship>(&self, source: Entity) -> Option<Entity> {
+        self.remove_component::<R>(source).map(|relation| relation.get())
+    }
+
+    pub fn relation_target<R: Relationship>(&self, source: Entity) -> Option<Entity> {
+        self.with_component::<R, _>(source, |relation| relation.map(|relation| relation.get()))
+    }
+
+    /// Entities related to `target` through `R`, in insertion order.
+    pub fn relation_sources<R: Relationship>(&self, target: Entity) -> Vec<Entity> {
+        self.with_component::<R::Target, _>(target, |list| {
+            list.map(|list| list.sources().to_vec()).unwrap_or_default()
+        })
+    }
+
+    /// Makes `child` the last child of `parent`. Fails if it would create a cycle.
+    pub fn set_parent(&self, child: Entity, parent: Entity) -> Result<(), RelationshipError> {
+        if child == parent || self.ancestors(parent).contains(&child) {
+            return Err(RelationshipError::Cycle { child, parent });
+        }
+        self.relate::<Parent>(child, parent)
+    }
+
+    /// Makes `child` a child of `parent` at `index` among its siblings.
+    pub fn insert_child(&self, parent: Entity, index: usize, child: Entity) -> Result<(), RelationshipError> {
+        self.set_parent(child, parent)?;
+        self.set_sibling_index(child, index)
+    }
+
+    pub fn remove_parent(&self, child: Entity) -> Option<Entity> {
+        self.unrelate::<Parent>(child)
+    }
+
+    pub fn parent(&self, entity: Entity) -> Option<Entity> {
+        self.relation_target::<Parent>(entity)
+    }
+
+    pub fn children(&self, entity: Entity) -> Vec<Entity> {
+        self.relation_sources::<Parent>(entity)
+    }
+
+    /// Parents of `entity`, nearest first.
+    pub fn ancestors(&self, entity: Entity) -> Vec<Entity> {
+        let mut ancestors = Vec::new();
+        let mut current = entity;
+        while let Some(parent) = self.parent(current) {
+            if parent == entity || ancestors.contains(&parent) {
+                log::error!("Hierarchy cycle detected at {:?}", parent);
+                break;
+            }
+            ancestors.push(parent);
+            current = parent;
+        }
+        ancestors
+    }
+
+    /// All descendants of `entity` in depth-first, sibling order.
+    pub fn descendants(&self, entity: Entity) -> Vec<Entity> {
+        let mut descendants = Vec::new();
+        let mut visited = HashSet::from([entity]);
+        let mut stack: Vec<Entity> = self.children(entity).into_iter().rev().collect();
+        while let Some(next) = stack.pop() {
+            if !visited.insert(next) {
+                log::error!("Hierarchy cycle detected at {:?}", next);
+                continue;
+            }
+            descendants.push(next);
+            stack.extend(self.children(next).into_iter().rev());
+        }
+        descendants
+    }
+
+    /// The top-most ancestor, or `entity` itself if it has no parent.
+    pub fn root(&self, entity: Entity) -> Entity {
+        self.ancestors(entity).last().copied().unwrap_or(entity)
+    }
+
+    pub fn sibling_index(&self, entity: Entity) -> Option<usize> {
+        let parent = self.parent(entity)?;
+        self.children(parent).iter().position(|child| *child == entity)
+    }
+
+    /// Moves `entity` to `index` among its siblings, clamped to the last position.
+    pub fn set_sibling_index(&self, entity: Entity, index: usize) -> Result<(), RelationshipError> {
+        let parent = self.parent(entity).ok_or(RelationshipError::NoParent(entity))?;
+        self.with_component_mut::<Children, _>(parent, |children| {
+            if let Some(children) = children {
+                let list = children.sources_mut();
+                list.retain(|child| *child != entity);
+                list.insert(index.min(list.len()), entity);
+            }
+        });
+        Ok(())
+    }
+
+    /// Despawns `entity` and all of its descendants, deepest first.
+    pub fn despawn_recursive(&self, entity: Entity) -> bool {
+        for descendant in self.descendants(entity).into_iter().rev() {
+            self.despawn(descendant);
+        }
+        self.despawn(entity)
+    }
+}
+
+/// Adds `source` to its current target's list, detaching it from `previous` if the target changed.
+fn link_source<R: Relationship>(world: &World, source: Entity, previous: Option<Entity>) {
+    let Some(target) = world.relation_target::<R>(source) else {
+        return;
+    };
+    if let Some(previous) = previous.filter(|previous| *previous != target) {
+        detach_source::<R>(world, source, previous);
+    }
+
+    let linked = world.with_component_mut::<R::Target, _>(target, |list| {
+        list.map(|list| {
+            if !list.sources().contains(&source) {
+                list.sources_mut().push(source);
+            }
+        })
+        .is_some()
+    });
+    if !linked {
+        let mut list = R::Target::default();
+        list.sources_mut().push(source);
+        world.add_component(target, list);
+    }
+}
+
+/// Removes `source` from `target`'s list, dropping the list once it is empty.
+fn detach_source<R: Relationship>(world: &World, source: Entity, target: Entity) {
+    let now_empty = world.with_component_mut::<R::Target, _>(target, |list| {
+        list.map(|list| {
+            list.sources_mut().retain(|entity| *entity != source);
+            list.sources().is_empty()
+        })
+        .unwrap_or(false)
+    });
+    if now_empty {
+        world.remove_component::<R::Target>(target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hierarchy_queries_and_integrity() {
+        let world = World::new();
+        let root = world.spawn().build(&world);
+        let [a, b, c, leaf] = [(); 4].map(|_| world.spawn().build(&world));
+
+        world.set_parent(a, root).unwrap();
+        world.set_parent(b, root).unwrap();
+        world.insert_child(root, 0, c).unwrap();
+        world.set_parent(leaf, a).unwrap();
+
+        assert_eq!(world.children(root), vec![c, a, b]);
+        assert_eq!(world.descendants(root), vec![c, a, leaf, b]);
+        assert_eq!(world.ancestors(leaf), vec![a, root]);
+        assert_eq!(world.root(leaf), root);
+        assert_eq!(
+            world.set_parent(root, leaf),
+            Err(RelationshipError::Cycle { child: root, parent: leaf })
+        );
+
+        world.set_sibling_index(b, 0).unwrap();
+        assert_eq!(world.children(root), vec![b, c, a]);
+
+        // Reparenting removes the child from its old parent.
+        world.set_parent(c, a).unwrap();
+        assert_eq!(world.children(root), vec![b, a]);
+        assert_eq!(world.children(a), vec![leaf, c]);
+
+        // Plain despawn orphans children; recursive despawn takes the subtree.
+        world.despawn(b);
+        assert_eq!(world.children(root), vec![a]);
+        world.despawn(a);
+        assert_eq!(world.parent(leaf), None);
+        assert!(!world.has_component::<Children>(root));
+
+        world.set_parent(leaf, root).unwrap();
+        world.set_parent(c, leaf).unwrap();
+        assert!(world.despawn_recursive(root));
+        assert_eq!(world.entity_count(), 0);
+    }
+
+    #[test]
+    fn reparenting_overwrites_the_parent_in_place() {
+        let world = World::new();
+        let [old, new, child] = [(); 3].map(|_| world.spawn().build(&world));
+        world.set_parent(child, old).unwrap();
+        world.clear_trackers();
+
+        world.set_parent(child, new).unwrap();
+        assert!(world.removed_components::<Parent>().is_empty());
+        assert_eq!(world.parent(child), Some(new));
+        assert!(world.children(old).is_empty());
+        assert_eq!(world.children(new), vec![child]);
+    }
+
+    #[test]
+    fn cycles_inserted_directly_do_not_hang_traversals() {
+        let world = World::new();
+        let [a, b, c] = [(); 3].map(|_| world.spawn().build(&world));
+        world.set_parent(b, a).unwrap();
+        world.set_parent(c, b).unwrap();
+        world.add_component(a, Parent::new(c));
+
+        assert_eq!(world.descendants(a), vec![b, c]);
+        assert!(world.despawn_recursive(a));
+        assert_eq!(world.entity_count(), 0);
+    }
+
+    #[test]
+    fn reflected_edits_relink_without_running_insert_hooks() {
+        let world = World::new();
+        let [root, first, second, child] = [(); 4].map(|_| world.spawn().build(&world));
+        world.set_parent(first, root).unwrap();
+        world.set_parent(child, root).unwrap();
+        world.set_parent(second, root).unwrap();
+
+        let inserts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = inserts.clone();
+        world.components().on_insert::<Parent>(move |_, _| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        });
+
+        // Re-applying the same parent keeps the sibling order.
+        world
+            .with_reflect_component_mut(child, "Parent", |parent| parent.apply(&Parent::new(root).to_value()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(world.children(root), vec![first, child, second]);
+
+        world
+            .with_reflect_component_mut(child, "Parent", |parent| parent.apply(&Parent::new(first).to_value()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(world.children(root), vec![first, second]);
+        assert_eq!(world.children(first), vec![child]);
+        assert_eq!(inserts.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn owned_entities_follow_owner_and_targets_are_cleared() {
+        let world = World::new();
+        let ship = world.spawn().build(&world);
+        let turret = world.spawn().build(&world);
+        let missile = world.spawn().build(&world);
+
+        world.relate::<OwnedBy>(turret, ship).unwrap();
+        world.relate::<Targets>(missile, ship).unwrap();
+        assert_eq!(world.relation_sources::<OwnedBy>(ship), vec![turret]);
+        assert_eq!(world.relation_sources::<Targets>(ship), vec![missile]);
+
+        // Replacing the relationship directly or through reflection moves the source.
+        let station = world.spawn().build(&world);
+        world.add_component(turret, OwnedBy::new(station));
+        assert!(world.relation_sources::<OwnedBy>(ship).is_empty());
+        assert_eq!(world.relation_sources::<OwnedBy>(station), vec![turret]);
+        world
+            .with_reflect_component_mut(missile, "Targets", |targets| targets.apply(&Targets::new(station).to_value()))
+            .unwrap()
+            .unwrap();
+        assert!(!world.has_component::<TargetedBy>(ship));
+        assert_eq!(world.relation_sources::<Targets>(station), vec![missile]);
+        world.relate::<OwnedBy>(turret, ship).unwrap();
+        world.relate::<Targets>(missile, ship).unwrap();
+
+        world.despawn(ship);
+        assert!(!world.is_alive(turret));
+        assert!(world.is_alive(missile));
+        assert_eq!(world.relation_target::<Targets>(missile), None);
+    }
+}
@@ -1,22 +1,62 @@
-use parking_lot::RwLock;
-use std::any::{Any, TypeId};
+use crate::{ComponentTicks, Tick};
+use parking_lot::{Mutex, MutexGuard, RwLock};
+use std::any::{type_name, Any, TypeId};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread::{self, ThreadId};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ResourceError {
+    #[error("Resource not found: {0}")]
+    Missing(&'static str),
+    #[error("Resource {0} can only be accessed from the thread that created the world")]
+    WrongThread(&'static str),
+    #[error("Resource {0} is already borrowed")]
+    AlreadyBorrowed(&'static str),
+}
 
 pub struct ResourceManager {
     resources: RwLock<HashMap<TypeId, Box<dyn Any + Send + Sync>>>,
+    ticks: RwLock<HashMap<TypeId, ComponentTicks>>,
+    non_send: NonSendResources,
 }
 
 impl ResourceManager {
     pub fn new() -> Self {
         Self {
             resources: RwLock::new(HashMap::new()),
+            ticks: RwLock::new(HashMap::new()),
+            non_send: NonSendResources::new(),
         }
     }
 
+    /// Adds or replaces a resource at tick 0, so it never reads as added or changed.
+    /// `World::add_resource` records the world's current tick instead.
     pub fn add<T: Send + Sync + 'static>(&self, resource: T) {
+        self.insert(resource, Tick::default());
+    }
+
+    /// Adds or replaces a resource, recording it as added at `tick`.
+    pub fn insert<T: Send + Sync + 'static>(&self, resource: T, tick: Tick) {
         let type_id = TypeId::of::<T>();
         let mut resources = self.resources.write();
-        resources.insert(type_id, Box::new(RwLock::new(resource)));
+        resources.insert(type_id, Box::new(Arc::new(RwLock::new(resource))));
+        self.ticks.write().insert(type_id, ComponentTicks::new(tick));
+    }
+
+    pub fn ticks<T: Send + Sync + 'static>(&self) -> Option<ComponentTicks> {
+        self.ticks.read().get(&TypeId::of::<T>()).copied()
+    }
+
+    pub fn set_changed<T: Send + Sync + 'static>(&self, tick: Tick) {
+        if let Some(ticks) = self.ticks.write().get_mut(&TypeId::of::<T>()) {
+            ticks.set_changed(tick);
+        }
+    }
+
+    /// Clamps resource ticks that are more than `MAX_CHANGE_AGE` behind `now`.
+    pub fn check_change_ticks(&self, now: Tick) {
+        self.ticks.write().values_mut().for_each(|ticks| ticks.check_ticks(now));
     }
 
     pub fn with_resource<T: Send + Sync + 'static, R>(&self, f: impl FnOnce(Option<&T>) -> R) -> R {
@@ -24,7 +64,7 @@ impl ResourceManager {
         let resources = self.resources.read();
         
         match resources.get(&type_id)
-            .and_then(|resource| resource.downcast_ref::<RwLock<T>>()) {
+            .and_then(|resource| resource.downcast_ref::<Arc<RwLock<T>>>()) {
             Some(lock) => {
                 let guard = lock.read();
                 f(Some(&*guard))
@@ -38,7 +78,7 @@ impl ResourceManager {
         let resources = self.resources.read();
         
         match resources.get(&type_id)
-            .and_then(|resource| resource.downcast_ref::<RwLock<T>>()) {
+            .and_then(|resource| resource.downcast_ref::<Arc<RwLock<T>>>()) {
             Some(lock) => {
                 let mut guard = lock.write();
                 f(Some(&mut *guard))
@@ -50,10 +90,26 @@ impl ResourceManager {
     pub fn remove<T: Send + Sync + 'static>(&self) -> Option<T> {
         let type_id = TypeId::of::<T>();
         let mut resources = self.resources.write();
-        
+        self.ticks.write().remove(&type_id);
+
         resources.remove(&type_id)
-            .and_then(|resource| resource.downcast::<RwLock<T>>().ok())
-            .map(|lock| lock.into_inner())
+            .and_then(|resource| resource.downcast::<Arc<RwLock<T>>>().ok())
+            .and_then(|lock| match Arc::try_unwrap(*lock) {
+                Ok(lock) => Some(lock.into_inner()),
+                Err(_) => {
+                    log::warn!("Removed resource {} while it was still borrowed", type_name::<T>());
+                    None
+                }
+            })
+    }
+
+    /// The shared lock around a resource, for borrows that outlive a closure.
+    pub(crate) fn lock<T: Send + Sync + 'static>(&self) -> Option<Arc<RwLock<T>>> {
+        self.resources
+            .read()
+            .get(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_ref::<Arc<RwLock<T>>>())
+            .cloned()
     }
 
     pub fn has<T: Send + Sync + 'static>(&self) -> bool {
@@ -62,9 +118,84 @@ impl ResourceManager {
         resources.contains_key(&type_id)
     }
 
+    /// Adds or replaces a resource that is not `Send`, such as a window handle.
+    /// Fails unless called from the thread that created the manager.
+    pub fn add_non_send<T: 'static>(&self, resource: T) -> Result<(), ResourceError> {
+        let mut values = self.non_send.values::<T>()?;
+        values.insert(TypeId::of::<T>(), Box::new(Arc::new(RwLock::new(resource))));
+        Ok(())
+    }
+
+    pub fn remove_non_send<T: 'static>(&self) -> Result<Option<T>, ResourceError> {
+        let mut values = self.non_send.values::<T>()?;
+        Ok(values
+            .remove(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast::<Arc<RwLock<T>>>().ok())
+            .and_then(|lock| Arc::try_unwrap(*lock).ok())
+            .map(RwLock::into_inner))
+    }
+
+    pub fn has_non_send<T: 'static>(&self) -> bool {
+        self.non_send
+            .values::<T>()
+            .map(|values| values.contains_key(&TypeId::of::<T>()))
+            .unwrap_or(false)
+    }
+
+    pub(crate) fn lock_non_send<T: 'static>(&self) -> Result<Arc<RwLock<T>>, ResourceError> {
+        self.non_send
+            .values::<T>()?
+            .get(&TypeId::of::<T>())
+            .and_then(|resource| resource.downcast_ref::<Arc<RwLock<T>>>())
+            .cloned()
+            .ok_or(ResourceError::Missing(type_name::<T>()))
+    }
+
+    /// Removes every `Send` resource. Non-`Send` resources stay with their thread.
     pub fn clear(&self) {
         let mut resources = self.resources.write();
         resources.clear();
+        self.ticks.write().clear();
+    }
+}
+
+type NonSendMap = HashMap<TypeId, Box<dyn Any>>;
+
+/// Resources pinned to the thread that created the world.
+struct NonSendResources {
+    thread: ThreadId,
+    values: Mutex<NonSendMap>,
+}
+
+// SAFETY: the values are only reachable through `values`, which refuses every
+// thread but the owning one, and the guards handed out are themselves `!Send`.
+// `Drop` leaks rather than drops the values when run on another thread.
+unsafe impl Send for NonSendResources {}
+unsafe impl Sync for NonSendResources {}
+
+impl NonSendResources {
+    fn new() -> Self {
+        Self {
+            thread: thread::current().id(),
+            values: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn values<T>(&self) -> Result<MutexGuard<'_, NonSendMap>, ResourceError> {
+        if thread::current().id() == self.thread {
+            Ok(self.values.lock())
+        } else {
+            Err(ResourceError::WrongThread(type_name::<T>()))
+        }
+    }
+}
+
+impl Drop for NonSendResources {
+    fn drop(&mut self) {
+        if thread::current().id() != self.thread && !self.values.get_mut().is_empty() {
+            log::error!("World dropped off its main thread; leaking its non-Send resources");
+            std::mem::forget(std::mem::take(self.values.get_mut()));
+        }
     }
 }
 
@@ -72,4 +203,63 @@ impl Default for ResourceManager {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::World;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct Score(u32);
+
+    #[test]
+    fn resource_guards_track_changes_and_threads() {
+        let world = World::new();
+        assert_eq!(world.resource::<Score>().err(), Some(ResourceError::Missing(type_name::<Score>())));
+
+        world.init_resource::<Score>();
+        world.resource_mut::<Score>().unwrap().0 += 5;
+        world.init_resource::<Score>();
+        assert_eq!(world.resource::<Score>().unwrap().0, 5);
+
+        world.clear_trackers();
+        assert!(!world.resource::<Score>().unwrap().is_changed());
+        let _ = world.resource_mut::<Score>().unwrap().0;
+        assert!(!world.resource::<Score>().unwrap().is_changed());
+        world.resource_mut::<Score>().unwrap().0 += 1;
+        assert!(world.resource::<Score>().unwrap().is_changed());
+
+        world.add_non_send_resource(Rc::new(7)).unwrap();
+        assert_eq!(**world.non_send_resource::<Rc<i32>>().unwrap(), 7);
+        std::thread::scope(|scope| {
+            let result = scope.spawn(|| world.non_send_resource::<Rc<i32>>().err()).join().unwrap();
+            assert!(matches!(result, Some(ResourceError::WrongThread(_))));
+        });
+    }
+
+    #[test]
+    fn overlapping_borrows_fail_instead_of_deadlocking() {
+        let world = World::new();
+        world.init_resource::<Score>();
+
+        let score = world.resource_mut::<Score>().unwrap();
+        let conflict = Some(ResourceError::AlreadyBorrowed(type_name::<Score>()));
+        assert_eq!(world.resource::<Score>().err(), conflict);
+        assert_eq!(world.resource_mut::<Score>().err(), conflict);
+        assert!(world.get_resource::<Score>().is_none());
+        drop(score);
+
+        let first = world.resource::<Score>().unwrap();
+        let second = world.resource::<Score>().unwrap();
+        assert_eq!(world.resource_mut::<Score>().err(), conflict);
+        drop((first, second));
+        assert!(world.resource_mut::<Score>().is_ok());
+
+        world.add_non_send_resource(Rc::new(7)).unwrap();
+        let value = world.non_send_resource_mut::<Rc<i32>>().unwrap();
+        assert!(matches!(world.non_send_resource::<Rc<i32>>().err(), Some(ResourceError::AlreadyBorrowed(_))));
+        drop(value);
+    }
+}
@@ -0,0 +1,438 @@
+use crate::{Component, EcsSystem, EcsSystemRunner, Entity, Reflect, SystemAccess, SystemConfig, World};
+use lumina_core::engine::SystemContext;
+use lumina_core::math::{Transform2D, Vec2};
+use lumina_core::Result;
+use std::collections::{HashMap, HashSet};
+use std::marker::PhantomData;
+
+/// A component that places its entity in a `SpatialIndex`.
+pub trait SpatialPosition: Component {
+    fn spatial_position(&self) -> Vec2;
+}
+
+impl SpatialPosition for Transform2D {
+    fn spatial_position(&self) -> Vec2 {
+        self.position
+    }
+}
+
+/// Half-size of an entity's bounds in a `SpatialIndex`. Entities without it are indexed as points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect)]
+pub struct SpatialExtent(pub Vec2);
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    pub fn from_center_half_extents(center: Vec2, half_extents: Vec2) -> Self {
+        Self::new(center - half_extents.abs(), center + half_extents.abs())
+    }
+
+    pub fn point(point: Vec2) -> Self {
+        Self { min: point, max: point }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.cmpge(self.min).all() && point.cmple(self.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+
+    pub fn distance_to(&self, point: Vec2) -> f32 {
+        (point.clamp(self.min, self.max) - point).length()
+    }
+
+    /// Distance along a normalized ray to where it enters the box, or 0 if it starts inside.
+    pub fn ray_intersection(&self, origin: Vec2, direction: Vec2) -> Option<f32> {
+        let mut t_min = 0.0_f32;
+        let mut t_max = f32::INFINITY;
+        for axis in 0..2 {
+            if direction[axis].abs() < f32::EPSILON {
+                if origin[axis] < self.min[axis] || origin[axis] > self.max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let inverse = 1.0 / direction[axis];
+            let near = (self.min[axis] - origin[axis]) * inverse;
+            let far = (self.max[axis] - origin[axis]) * inverse;
+            t_min = t_min.max(near.min(far));
+            t_max = t_max.min(near.max(far));
+            if t_min > t_max {
+                return None;
+            }
+        }
+        Some(t_min)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub entity: Entity,
+    pub distance: f32,
+}
+
+type Cell = (i32, i32);
+
+/// Uniform-grid spatial index over entities with a `T` component.
+///
+/// Kept current by the system `EcsSystemRunner::add_spatial_index` adds,
+/// which only re-indexes entities whose `T` or `SpatialExtent` changed.
+pub struct SpatialIndex<T: SpatialPosition> {
+    cell_size: f32,
+    cells: HashMap<Cell, Vec<Entity>>,
+    bounds: HashMap<Entity, Aabb>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: SpatialPosition> SpatialIndex<T> {
+    /// # Panics
+    ///
+    /// Panics if `cell_size` is not positive.
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0, "SpatialIndex cell size must be positive, got {}", cell_size);
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            bounds: HashMap::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    pub fn bounds(&self, entity: Entity) -> Option<Aabb> {
+        self.bounds.get(&entity).copied()
+    }
+
+    fn cell_of(&self, point: Vec2) -> Cell {
+        let cell = (point / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+
+    fn cells_overlapping(&self, aabb: &Aabb) -> impl Iterator<Item = Cell> {
+        let (min_x, min_y) = self.cell_of(aabb.min);
+        let (max_x, max_y) = self.cell_of(aabb.max);
+        (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+    }
+
+    pub fn insert(&mut self, entity: Entity, aabb: Aabb) {
+        if self.bounds.get(&entity) == Some(&aabb) {
+            return;
+        }
+        self.remove(entity);
+        for cell in self.cells_overlapping(&aabb).collect::<Vec<_>>() {
+            self.cells.entry(cell).or_default().push(entity);
+        }
+        self.bounds.insert(entity, aabb);
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> bool {
+        let Some(aabb) = self.bounds.remove(&entity) else {
+            return false;
+        };
+        for cell in self.cells_overlapping(&aabb).collect::<Vec<_>>() {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|e| *e != entity);
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds.clear();
+    }
+
+    /// Re-indexes every entity with a `T` component from scratch.
+    pub fn rebuild(&mut self, world: &World) {
+        self.clear();
+        for entity in world.components().entities_with::<T>() {
+            if let Some(aabb) = entity_bounds::<T>(world, entity) {
+                self.insert(entity, aabb);
+            }
+        }
+    }
+
+    /// Entities whose bounds overlap `aabb`.
+    pub fn query_aabb(&self, aabb: Aabb) -> Vec<Entity> {
+        let (min_x, min_y) = self.cell_of(aabb.min);
+        let (max_x, max_y) = self.cell_of(aabb.max);
+        let area = (max_x as i64 - min_x as i64 + 1) * (max_y as i64 - min_y as i64 + 1);
+
+        // For boxes covering more cells than are occupied, checking every entity is cheaper.
+        if area > self.cells.len() as i64 {
+            return self
+                .bounds
+                .iter()
+                .filter(|(_, bounds)| bounds.intersects(&aabb))
+                .map(|(entity, _)| *entity)
+                .collect();
+        }
+
+        let mut seen = HashSet::new();
+        self.cells_overlapping(&aabb)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(|entity| seen.insert(**entity))
+            .filter(|entity| self.bounds[entity].intersects(&aabb))
+            .copied()
+            .collect()
+    }
+
+    /// Entities whose bounds come within `radius` of `center`, nearest first.
+    pub fn query_radius(&self, center: Vec2, radius: f32) -> Vec<Entity> {
+        let mut hits: Vec<(Entity, f32)> = self
+            .query_aabb(Aabb::from_center_half_extents(center, Vec2::splat(radius)))
+            .into_iter()
+            .map(|entity| (entity, self.bounds[&entity].distance_to(center)))
+            .filter(|(_, distance)| *distance <= radius)
+            .collect();
+        hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+        hits.into_iter().map(|(entity, _)| entity).collect()
+    }
+
+    /// The entity closest to `point` within `max_distance`.
+    pub fn nearest(&self, point: Vec2, max_distance: f32) -> Option<Entity> {
+        self.query_radius(point, max_distance).first().copied()
+    }
+
+    /// Entities hit by a ray within `max_distance`, nearest first.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Vec<RayHit> {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec2::ZERO || !max_distance.is_finite() {
+            return Vec::new();
+        }
+
+        let mut seen = HashSet::new();
+        let mut hits = Vec::new();
+        for cell in self.cells_along_ray(origin, direction, max_distance) {
+            for entity in self.cells.get(&cell).into_iter().flatten() {
+                if !seen.insert(*entity) {
+                    continue;
+                }
+                if let Some(distance) = self.bounds[entity].ray_intersection(origin, direction) {
+                    if distance <= max_distance {
+                        hits.push(RayHit {
+                            entity: *entity,
+                            distance,
+                        });
+                    }
+                }
+            }
+        }
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+
+    /// Grid cells a ray passes through, in order (Amanatides & Woo traversal).
+    ///
+    /// Cells are produced lazily and the walk stops once the ray has left the
+    /// occupied cells for good, so huge distances don't visit empty space.
+    fn cells_along_ray(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> impl Iterator<Item = Cell> + '_ {
+        let occupied = self.cells.keys().fold(None, |bounds: Option<(Cell, Cell)>, cell| {
+            Some(match bounds {
+                Some((min, max)) => ((min.0.min(cell.0), min.1.min(cell.1)), (max.0.max(cell.0), max.1.max(cell.1))),
+                None => (*cell, *cell),
+            })
+        });
+
+        let mut cell = self.cell_of(origin);
+        let step = (direction.x.signum() as i32, direction.y.signum() as i32);
+        let next_boundary = |cell: i32, step: i32| (cell + step.max(0)) as f32 * self.cell_size;
+        let axis = |origin: f32, direction: f32, cell: i32, step: i32| {
+            if direction.abs() < f32::EPSILON {
+                (f32::INFINITY, f32::INFINITY)
+            } else {
+                ((next_boundary(cell, step) - origin) / direction, self.cell_size / direction.abs())
+            }
+        };
+        let (mut t_max_x, t_delta_x) = axis(origin.x, direction.x, cell.0, step.0);
+        let (mut t_max_y, t_delta_y) = axis(origin.y, direction.y, cell.1, step.1);
+        // Whether the ray can never reach an occupied cell again along one axis.
+        let past = |cell: i32, step: i32, min: i32, max: i32| {
+            (step >= 0 && cell > max) || (step <= 0 && cell < min)
+        };
+
+        let mut first = true;
+        std::iter::from_fn(move || {
+            let (min, max) = occupied?;
+            if first {
+                first = false;
+            } else if t_max_x < t_max_y {
+                if t_max_x > max_distance {
+                    return None;
+                }
+                cell.0 += step.0;
+                t_max_x += t_delta_x;
+            } else {
+                if t_max_y > max_distance {
+                    return None;
+                }
+                cell.1 += step.1;
+                t_max_y += t_delta_y;
+            }
+            if past(cell.0, step.0, min.0, max.0) || past(cell.1, step.1, min.1, max.1) {
+                return None;
+            }
+            Some(cell)
+        })
+    }
+}
+
+fn entity_bounds<T: SpatialPosition>(world: &World, entity: Entity) -> Option<Aabb> {
+    let position = world.with_component::<T, _>(entity, |component| component.map(T::spatial_position))?;
+    let extent = world.with_component::<SpatialExtent, _>(entity, |extent| extent.map(|extent| extent.0));
+    Some(match extent {
+        Some(extent) => Aabb::from_center_half_extents(position, extent),
+        None => Aabb::point(position),
+    })
+}
+
+/// Applies changes to `T` and `SpatialExtent` since its last run to `SpatialIndex<T>`.
+pub struct SpatialIndexSystem<T: SpatialPosition> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T: SpatialPosition> Default for SpatialIndexSystem<T> {
+    fn default() -> Self {
+        Self { _marker: PhantomData }
+    }
+}
+
+impl<T: SpatialPosition> EcsSystem for SpatialIndexSystem<T> {
+    fn run(&mut self, world: &World, _context: &SystemContext) -> Result<()> {
+        let last_run = world.last_change_tick();
+        let changed_since = |entity: Entity| {
+            world.component_ticks::<T>(entity).is_some_and(|ticks| ticks.is_changed(last_run))
+                || world
+                    .component_ticks::<SpatialExtent>(entity)
+                    .is_some_and(|ticks| ticks.is_changed(last_run))
+        };
+
+        let removed_extents = world.removed_components::<SpatialExtent>();
+        let updates: Vec<(Entity, Aabb)> = world
+            .components()
+            .entities_with::<T>()
+            .into_iter()
+            .filter(|entity| changed_since(*entity) || removed_extents.contains(*entity))
+            .filter_map(|entity| entity_bounds::<T>(world, entity).map(|aabb| (entity, aabb)))
+            .collect();
+        let removed = world.removed_components::<T>();
+
+        world.with_resource_mut::<SpatialIndex<T>, _>(|index| {
+            let Some(index) = index else {
+                return;
+            };
+            for entity in removed {
+                if !world.has_component::<T>(entity) {
+                    index.remove(entity);
+                }
+            }
+            for (entity, aabb) in updates {
+                index.insert(entity, aabb);
+            }
+        });
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    fn access(&self) -> SystemAccess {
+        let mut access = SystemAccess::default();
+        access.read_component::<T>();
+        access.read_component::<SpatialExtent>();
+        access.write_resource::<SpatialIndex<T>>();
+        access
+    }
+}
+
+impl EcsSystemRunner {
+    /// Adds a `SpatialIndex<T>` resource and the system that maintains it.
+    /// Add it before the systems that query the index so they see this frame's positions.
+    pub fn add_spatial_index<T: SpatialPosition>(&mut self, cell_size: f32) -> SystemConfig<'_> {
+        self.world().add_resource(SpatialIndex::<T>::new(cell_size));
+        self.add_system(SpatialIndexSystem::<T>::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32, y: f32) -> Transform2D {
+        Transform2D {
+            position: Vec2::new(x, y),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn index_follows_changes_and_answers_queries() {
+        let context = SystemContext::new();
+        let mut runner = EcsSystemRunner::new();
+        runner.add_spatial_index::<Transform2D>(10.0);
+        let world = runner.world().clone();
+
+        let near = world.spawn_with(at(1.0, 1.0));
+        let far = world.spawn_with(at(50.0, 0.0));
+        let wall = world.spawn().with(at(25.0, 0.0)).with(SpatialExtent(Vec2::new(1.0, 20.0))).build(&world);
+        runner.run_systems(&context).unwrap();
+
+        let query = |f: &dyn Fn(&SpatialIndex<Transform2D>) -> Vec<Entity>| {
+            world.with_resource::<SpatialIndex<Transform2D>, _>(|index| f(index.unwrap()))
+        };
+        assert_eq!(query(&|index| index.query_radius(Vec2::ZERO, 5.0)), vec![near]);
+        assert_eq!(query(&|index| index.query_aabb(Aabb::new(Vec2::new(20.0, -5.0), Vec2::new(60.0, 5.0)))).len(), 2);
+        let hits = world.with_resource::<SpatialIndex<Transform2D>, _>(|index| {
+            index.unwrap().raycast(Vec2::new(0.0, 5.0), Vec2::X, 100.0)
+        });
+        assert_eq!(hits.first().map(|hit| (hit.entity, hit.distance)), Some((wall, 24.0)));
+        // Rays stop walking the grid once they leave the occupied cells.
+        let (bounded, unbounded) = world.with_resource::<SpatialIndex<Transform2D>, _>(|index| {
+            let index = index.unwrap();
+            let origin = Vec2::new(-1000.0, 5.0);
+            (index.raycast(origin, Vec2::X, 2000.0), index.raycast(origin, Vec2::X, 1.0e30))
+        });
+        assert_eq!(bounded.first().map(|hit| hit.entity), Some(wall));
+        assert_eq!(bounded, unbounded);
+
+        world.with_component_mut::<Transform2D, _>(far, |transform| transform.unwrap().position = Vec2::new(2.0, 0.0));
+        world.despawn(near);
+        runner.run_systems(&context).unwrap();
+        assert_eq!(query(&|index| index.query_radius(Vec2::ZERO, 5.0)), vec![far]);
+        assert_eq!(query(&|index| vec![index.nearest(Vec2::new(30.0, 0.0), 10.0).unwrap()]), vec![wall]);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{EcsSystem, SystemEntry, Tick, World};
+use lumina_core::{engine::SystemContext, Result};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// App-level states such as menu, playing and paused.
+pub trait States: Clone + PartialEq + Eq + Hash + Debug + Send + Sync + 'static {}
+
+impl<T: Clone + PartialEq + Eq + Hash + Debug + Send + Sync + 'static> States for T {}
+
+/// The current state, stored as a world resource.
+#[derive(Debug, Clone, PartialEq)]
+pub struct State<S: States> {
+    current: S,
+    previous: Option<S>,
+}
+
+impl<S: States> State<S> {
+    pub fn new(state: S) -> Self {
+        Self {
+            current: state,
+            previous: None,
+        }
+    }
+
+    pub fn get(&self) -> &S {
+        &self.current
+    }
+
+    pub fn previous(&self) -> Option<&S> {
+        self.previous.as_ref()
+    }
+}
+
+/// A queued transition, applied by `EcsSystemRunner` at the start of the next frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NextState<S: States>(Option<S>);
+
+impl<S: States> NextState<S> {
+    pub fn set(&mut self, state: S) {
+        self.0 = Some(state);
+    }
+
+    pub fn pending(&self) -> Option<&S> {
+        self.0.as_ref()
+    }
+}
+
+impl<S: States> Default for NextState<S> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+/// Systems that run once when `S` becomes the current state.
+pub struct OnEnter<S>(pub S);
+
+/// Systems that run once when `S` stops being the current state.
+pub struct OnExit<S>(pub S);
+
+pub enum StateSchedule<S> {
+    Enter(S),
+    Exit(S),
+}
+
+impl<S> From<OnEnter<S>> for StateSchedule<S> {
+    fn from(schedule: OnEnter<S>) -> Self {
+        StateSchedule::Enter(schedule.0)
+    }
+}
+
+impl<S> From<OnExit<S>> for StateSchedule<S> {
+    fn from(schedule: OnExit<S>) -> Self {
+        StateSchedule::Exit(schedule.0)
+    }
+}
+
+impl World {
+    pub fn state<S: States>(&self) -> Option<S> {
+        self.with_resource::<State<S>, _>(|state| state.map(|state| state.get().clone()))
+    }
+
+    /// Queues a transition to `state`. Does nothing if `S` was never added to the runner.
+    pub fn set_next_state<S: States>(&self, state: S) {
+        self.with_resource_mut::<NextState<S>, _>(|next| {
+            if let Some(next) = next {
+                next.set(state);
+            }
+        });
+    }
+}
+
+pub(crate) trait StateTransitions: Send + Sync {
+    fn apply(&mut self, world: &World, context: &SystemContext) -> Result<()>;
+    fn check_ticks(&mut self, now: Tick);
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+pub(crate) struct StateDriver<S: States> {
+    on_enter: HashMap<S, Vec<SystemEntry>>,
+    on_exit: HashMap<S, Vec<SystemEntry>>,
+    entered_initial: bool,
+}
+
+impl<S: States> StateDriver<S> {
+    pub(crate) fn new() -> Self {
+        Self {
+            on_enter: HashMap::new(),
+            on_exit: HashMap::new(),
+            entered_initial: false,
+        }
+    }
+
+    pub(crate) fn add_system(&mut self, schedule: StateSchedule<S>, system: Box<dyn EcsSystem>) -> &mut SystemEntry {
+        let (systems, state) = match schedule {
+            StateSchedule::Enter(state) => (&mut self.on_enter, state),
+            StateSchedule::Exit(state) => (&mut self.on_exit, state),
+        };
+        let entries = systems.entry(state).or_default();
+        entries.push(SystemEntry::new(system));
+        entries.last_mut().expect("entry was just pushed")
+    }
+
+    fn run_schedule(systems: &mut HashMap<S, Vec<SystemEntry>>, state: &S, world: &World, context: &SystemContext) -> Result<()> {
+        for entry in systems.get_mut(state).into_iter().flatten() {
+            if entry.conditions_met(world, context) {
+                entry.run(world, context)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S: States> StateTransitions for StateDriver<S> {
+    fn apply(&mut self, world: &World, context: &SystemContext) -> Result<()> {
+        let Some(current) = world.state::<S>() else {
+            return Ok(());
+        };
+
+        if !self.entered_initial {
+            self.entered_initial = true;
+            Self::run_schedule(&mut self.on_enter, &current, world, context)?;
+        }
+
+        let next = world.with_resource_mut::<NextState<S>, _>(|next| next.and_then(|next| next.0.take()));
+        let Some(next) = next.filter(|next| *next != current) else {
+            return Ok(());
+        };
+
+        log::debug!("State transition {:?} -> {:?}", current, next);
+        Self::run_schedule(&mut self.on_exit, &current, world, context)?;
+        world.add_resource(State {
+            current: next.clone(),
+            previous: Some(current),
+        });
+        Self::run_schedule(&mut self.on_enter, &next, world, context)
+    }
+
+    fn check_ticks(&mut self, now: Tick) {
+        self.on_enter
+            .values_mut()
+            .chain(self.on_exit.values_mut())
+            .flatten()
+            .for_each(|entry| entry.check_ticks(now));
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{make_system, EcsSystemRunner, OnEnter, OnExit};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum Screen {
+        Title,
+        Level,
+    }
+
+    #[derive(Default)]
+    struct Transitions(Vec<String>);
+
+    fn record(entry: &'static str) -> impl EcsSystem {
+        make_system(move |world, _| {
+            let state = world.state::<Screen>();
+            world.with_resource_mut::<Transitions, _>(|log| log.unwrap().0.push(format!("{} {:?}", entry, state)));
+            Ok(())
+        })
+    }
+
+    fn take(world: &World) -> Vec<String> {
+        world.with_resource_mut::<Transitions, _>(|log| std::mem::take(&mut log.unwrap().0))
+    }
+
+    #[test]
+    fn transitions_run_exit_then_enter_schedules() {
+        let context = SystemContext::new();
+        let mut runner = EcsSystemRunner::new();
+        runner.world().add_resource(Transitions::default());
+        runner.add_state(Screen::Title);
+        runner.add_system_to(OnEnter(Screen::Title), record("enter title"));
+        runner.add_system_to(OnExit(Screen::Title), record("exit title"));
+        runner.add_system_to(OnEnter(Screen::Level), record("enter level"));
+        runner.add_system_to(OnExit(Screen::Level), record("exit level"));
+
+        runner.run_systems(&context).unwrap();
+        runner.run_systems(&context).unwrap();
+        assert_eq!(take(runner.world()), vec!["enter title Some(Title)"]);
+
+        // Exit systems still see the old state; enter systems see the new one.
+        runner.world().set_next_state(Screen::Level);
+        runner.run_systems(&context).unwrap();
+        assert_eq!(take(runner.world()), vec!["exit title Some(Title)", "enter level Some(Level)"]);
+        let previous = runner
+            .world()
+            .with_resource::<State<Screen>, _>(|state| state.and_then(|state| state.previous().cloned()));
+        assert_eq!(previous, Some(Screen::Title));
+
+        // Queuing the current state is not a transition.
+        runner.world().set_next_state(Screen::Level);
+        runner.run_systems(&context).unwrap();
+        assert!(take(runner.world()).is_empty());
+        assert_eq!(runner.world().with_resource::<NextState<Screen>, _>(|next| next.unwrap().pending().cloned()), None);
+    }
+
+    #[test]
+    fn next_state_is_ignored_for_unregistered_states() {
+        let world = World::new();
+        world.set_next_state(Screen::Level);
+        assert!(!world.has_resource::<NextState<Screen>>());
+        assert_eq!(world.state::<Screen>(), None);
+    }
+}
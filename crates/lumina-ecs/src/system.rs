@@ -1,14 +1,121 @@
-use crate::World;
+use crate::condition::evaluate_all;
+use crate::{
+    BoxedCondition, Condition, NextState, State, StateDriver, StateSchedule, StateTransitions, States, SystemAccess,
+    SystemParam, SystemParamItem, Tick, World,
+};
 use lumina_core::{engine::SystemContext, Result};
+use std::collections::HashMap;
+use std::marker::PhantomData;
 use std::sync::Arc;
 
 pub trait EcsSystem: Send + Sync {
     fn run(&mut self, world: &World, context: &SystemContext) -> Result<()>;
+
+    /// Called once when the system is added to a runner.
+    fn initialize(&mut self, _world: &World) {}
+
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// What the system reads and writes. Empty unless the system declares it.
+    fn access(&self) -> SystemAccess {
+        SystemAccess::default()
+    }
+}
+
+/// Anything `EcsSystemRunner::add_system` accepts: an `EcsSystem`, or a
+/// function whose parameters are all `SystemParam`s.
+pub trait IntoSystem<Marker> {
+    type System: EcsSystem + 'static;
+
+    fn into_system(self) -> Self::System;
+}
+
+#[doc(hidden)]
+pub struct IsEcsSystem;
+
+impl<S: EcsSystem + 'static> IntoSystem<IsEcsSystem> for S {
+    type System = S;
+
+    fn into_system(self) -> S {
+        self
+    }
+}
+
+pub(crate) struct SystemEntry {
+    system: Box<dyn EcsSystem>,
+    last_run: Tick,
+    conditions: Vec<BoxedCondition>,
+    sets: Vec<&'static str>,
+}
+
+impl SystemEntry {
+    pub(crate) fn new(system: Box<dyn EcsSystem>) -> Self {
+        Self {
+            system,
+            last_run: Tick::default(),
+            conditions: Vec::new(),
+            sets: Vec::new(),
+        }
+    }
+
+    pub(crate) fn conditions_met(&mut self, world: &World, context: &SystemContext) -> bool {
+        evaluate_all(&mut self.conditions, world, context)
+    }
+
+    /// Runs the system so it sees `Added`/`Changed`/removed data relative to its own previous run.
+    pub(crate) fn run(&mut self, world: &World, context: &SystemContext) -> Result<()> {
+        let this_run = world.increment_change_tick();
+        world.set_last_change_tick(self.last_run);
+        let result = self.system.run(world, context);
+        self.last_run = this_run;
+        result
+    }
+
+    pub(crate) fn check_ticks(&mut self, now: Tick) {
+        self.last_run.check_tick(now);
+    }
+}
+
+/// Configures a system just added to an `EcsSystemRunner`.
+pub struct SystemConfig<'a> {
+    entry: &'a mut SystemEntry,
+}
+
+impl SystemConfig<'_> {
+    /// Only runs the system on frames where `condition` is true. Multiple conditions must all hold.
+    pub fn run_if(self, condition: impl Condition) -> Self {
+        self.entry.conditions.push(Box::new(condition));
+        self
+    }
+
+    /// Adds the system to a named set, so it also obeys the set's run conditions.
+    pub fn in_set(self, set: &'static str) -> Self {
+        if !self.entry.sets.contains(&set) {
+            self.entry.sets.push(set);
+        }
+        self
+    }
+}
+
+/// Configures a named group of systems.
+pub struct SystemSetConfig<'a> {
+    conditions: &'a mut Vec<BoxedCondition>,
+}
+
+impl SystemSetConfig<'_> {
+    pub fn run_if(self, condition: impl Condition) -> Self {
+        self.conditions.push(Box::new(condition));
+        self
+    }
 }
 
 pub struct EcsSystemRunner {
     world: Arc<World>,
-    systems: Vec<Box<dyn EcsSystem>>,
+    systems: Vec<SystemEntry>,
+    sets: HashMap<&'static str, Vec<BoxedCondition>>,
+    states: Vec<Box<dyn StateTransitions>>,
 }
 
 impl EcsSystemRunner {
@@ -16,20 +123,115 @@ impl EcsSystemRunner {
         Self {
             world: Arc::new(World::new()),
             systems: Vec::new(),
+            sets: HashMap::new(),
+            states: Vec::new(),
+        }
+    }
+
+    pub fn add_system<M>(&mut self, system: impl IntoSystem<M>) -> SystemConfig<'_> {
+        let mut system = system.into_system();
+        system.initialize(&self.world);
+        self.systems.push(SystemEntry::new(Box::new(system)));
+        SystemConfig {
+            entry: self.systems.last_mut().expect("system was just pushed"),
+        }
+    }
+
+    pub fn configure_set(&mut self, set: &'static str) -> SystemSetConfig<'_> {
+        SystemSetConfig {
+            conditions: self.sets.entry(set).or_default(),
+        }
+    }
+
+    /// Adds the `State<S>` and `NextState<S>` resources. The initial state's
+    /// `OnEnter` systems run at the start of the first frame.
+    pub fn add_state<S: States>(&mut self, initial: S) {
+        if self.state_driver::<S>().is_some() {
+            log::warn!("State {} was already added", std::any::type_name::<S>());
+            return;
+        }
+        self.world.add_resource(State::new(initial));
+        self.world.add_resource(NextState::<S>::default());
+        self.states.push(Box::new(StateDriver::<S>::new()));
+    }
+
+    /// Adds a system to an `OnEnter` or `OnExit` schedule.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `S` was not added with `add_state` first.
+    pub fn add_system_to<S: States, M>(
+        &mut self,
+        schedule: impl Into<StateSchedule<S>>,
+        system: impl IntoSystem<M>,
+    ) -> SystemConfig<'_> {
+        let mut system = system.into_system();
+        system.initialize(&self.world);
+        let driver = self.state_driver::<S>().unwrap_or_else(|| {
+            panic!("State {} must be added with add_state before adding systems to it", std::any::type_name::<S>())
+        });
+        SystemConfig {
+            entry: driver.add_system(schedule.into(), Box::new(system)),
         }
     }
 
-    pub fn add_system<S: EcsSystem + 'static>(&mut self, system: S) {
-        self.systems.push(Box::new(system));
+    fn state_driver<S: States>(&mut self) -> Option<&mut StateDriver<S>> {
+        self.states
+            .iter_mut()
+            .find_map(|driver| driver.as_any_mut().downcast_mut::<StateDriver<S>>())
     }
 
     pub fn world(&self) -> &Arc<World> {
         &self.world
     }
 
+    /// Name and declared access of every system in the main schedule, in run order.
+    pub fn system_access(&self) -> Vec<(&str, SystemAccess)> {
+        self.systems
+            .iter()
+            .map(|entry| (entry.system.name(), entry.system.access()))
+            .collect()
+    }
+
+    /// Advances event queues and applies queued state transitions, then runs
+    /// every system whose run conditions and set conditions hold.
     pub fn run_systems(&mut self, context: &SystemContext) -> Result<()> {
-        for system in &mut self.systems {
-            system.run(&self.world, context)?;
+        let frame_start = self.world.change_tick();
+        self.world.update_events();
+
+        for driver in &mut self.states {
+            driver.apply(&self.world, context)?;
+        }
+
+        let world = &self.world;
+        let active_sets: HashMap<&'static str, bool> = self
+            .sets
+            .iter_mut()
+            .map(|(set, conditions)| (*set, evaluate_all(conditions, world, context)))
+            .collect();
+
+        for entry in &mut self.systems {
+            let sets_met = entry.sets.iter().all(|set| active_sets.get(set).copied().unwrap_or(true));
+            if sets_met && entry.conditions_met(&self.world, context) {
+                entry.run(&self.world, context)?;
+            }
+        }
+
+        // State schedules run rarely and don't hold back pruning.
+        let now = self.world.change_tick();
+        let oldest = self.systems.iter().map(|entry| entry.last_run).max_by_key(|tick| tick.age(now));
+        if let Some(oldest) = oldest {
+            self.world.components().prune_removed(oldest);
+        }
+
+        // Code running between frames observes changes made during this frame,
+        // and its own mutations land after every system's last run.
+        self.world.set_last_change_tick(frame_start);
+        self.world.increment_change_tick();
+        if self.world.check_change_ticks() {
+            let now = self.world.change_tick();
+            self.systems.iter_mut().for_each(|entry| entry.check_ticks(now));
+            self.states.iter_mut().for_each(|driver| driver.check_ticks(now));
         }
         Ok(())
     }
@@ -78,4 +280,265 @@ where
     fn run(&mut self, world: &World, context: &SystemContext) -> Result<()> {
         (self.func)(world, context)
     }
-}
\ No newline at end of file
+
+    fn name(&self) -> &str {
+        std::any::type_name::<F>()
+    }
+
+    fn access(&self) -> SystemAccess {
+        let mut access = SystemAccess::default();
+        access.read_world();
+        access
+    }
+}
+
+/// A function usable as a system, implemented for functions of up to eight `SystemParam`s.
+pub trait SystemParamFunction<Marker>: Send + Sync + 'static {
+    type Param: SystemParam;
+
+    fn run(&mut self, param: SystemParamItem<'_, '_, Self::Param>) -> Result<()>;
+}
+
+#[doc(hidden)]
+pub struct IsParamFunction;
+
+impl<Marker: 'static, F: SystemParamFunction<Marker>> IntoSystem<(IsParamFunction, Marker)> for F {
+    type System = ParamFunctionSystem<Marker, F>;
+
+    fn into_system(self) -> Self::System {
+        ParamFunctionSystem {
+            func: self,
+            state: None,
+            access: SystemAccess::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// A system built from a function whose parameters are extracted from the world each run.
+pub struct ParamFunctionSystem<Marker, F: SystemParamFunction<Marker>> {
+    func: F,
+    state: Option<<F::Param as SystemParam>::State>,
+    access: SystemAccess,
+    _marker: PhantomData<fn() -> Marker>,
+}
+
+impl<Marker: 'static, F: SystemParamFunction<Marker>> EcsSystem for ParamFunctionSystem<Marker, F> {
+    fn run(&mut self, world: &World, context: &SystemContext) -> Result<()> {
+        if self.state.is_none() {
+            self.initialize(world);
+        }
+        let state = self.state.as_mut().expect("state was just initialized");
+        let result = match F::Param::get_param(state, world, context) {
+            Ok(param) => self.func.run(param),
+            Err(error) => Err(error.context(format!("Could not run system {}", std::any::type_name::<F>()))),
+        };
+        F::Param::apply(state, world);
+        result
+    }
+
+    fn initialize(&mut self, world: &World) {
+        let mut access = SystemAccess::default();
+        self.state = Some(F::Param::init_state(world, &mut access));
+        self.access = access;
+    }
+
+    fn name(&self) -> &str {
+        std::any::type_name::<F>()
+    }
+
+    fn access(&self) -> SystemAccess {
+        self.access.clone()
+    }
+}
+
+macro_rules! impl_system_param_function {
+    ($($param:ident),*) => {
+        #[allow(non_snake_case)]
+        impl<Func, $($param: SystemParam),*> SystemParamFunction<fn($($param,)*)> for Func
+        where
+            Func: Send + Sync + 'static,
+            for<'a> &'a mut Func:
+                FnMut($($param),*) -> Result<()> + FnMut($(SystemParamItem<$param>),*) -> Result<()>,
+        {
+            type Param = ($($param,)*);
+
+            fn run(&mut self, param: SystemParamItem<'_, '_, ($($param,)*)>) -> Result<()> {
+                // Pins the call to the concrete parameter types rather than the HRTB signature.
+                #[allow(clippy::too_many_arguments)]
+                fn call_inner<$($param,)*>(mut f: impl FnMut($($param,)*) -> Result<()>, $($param: $param,)*) -> Result<()> {
+                    f($($param,)*)
+                }
+                let ($($param,)*) = param;
+                call_inner(self, $($param),*)
+            }
+        }
+    };
+}
+
+impl_system_param_function!();
+impl_system_param_function!(A);
+impl_system_param_function!(A, B);
+impl_system_param_function!(A, B, C);
+impl_system_param_function!(A, B, C, D);
+impl_system_param_function!(A, B, C, D, E);
+impl_system_param_function!(A, B, C, D, E, F);
+impl_system_param_function!(A, B, C, D, E, F, G);
+impl_system_param_function!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        in_state, not, resource_matches, run_once, Commands, ConditionExt, Entity, EventReader, EventWriter, Local,
+        OnEnter, OnExit, Query, Res, ResMut, With,
+    };
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum GameState {
+        Menu,
+        Playing,
+        Paused,
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    fn log(entry: &'static str) -> impl EcsSystem {
+        make_system(move |world, _| {
+            world.with_resource_mut::<Log, _>(|log| log.unwrap().0.push(entry));
+            Ok(())
+        })
+    }
+
+    fn take_log(world: &World) -> Vec<&'static str> {
+        world.with_resource_mut::<Log, _>(|log| std::mem::take(&mut log.unwrap().0))
+    }
+
+    #[test]
+    fn states_drive_schedules_and_conditions() {
+        let context = SystemContext::new();
+        let mut runner = EcsSystemRunner::new();
+        runner.world().add_resource(Log::default());
+        runner.add_state(GameState::Menu);
+
+        runner.add_system_to(OnEnter(GameState::Menu), log("enter menu"));
+        runner.add_system_to(OnExit(GameState::Menu), log("exit menu"));
+        runner.add_system_to(OnEnter(GameState::Playing), log("enter playing"));
+        runner.add_system(log("menu")).run_if(in_state(GameState::Menu));
+        runner
+            .add_system(log("gameplay"))
+            .in_set("gameplay")
+            .run_if(not(in_state(GameState::Paused)));
+        runner.configure_set("gameplay").run_if(in_state(GameState::Playing).or(in_state(GameState::Paused)));
+        runner.add_system(log("once")).run_if(run_once());
+
+        runner.run_systems(&context).unwrap();
+        assert_eq!(take_log(runner.world()), vec!["enter menu", "menu", "once"]);
+
+        runner.world().set_next_state(GameState::Playing);
+        runner.run_systems(&context).unwrap();
+        assert_eq!(take_log(runner.world()), vec!["exit menu", "enter playing", "gameplay"]);
+        assert_eq!(runner.world().state::<GameState>(), Some(GameState::Playing));
+
+        runner.world().set_next_state(GameState::Paused);
+        runner.run_systems(&context).unwrap();
+        assert!(take_log(runner.world()).is_empty());
+    }
+
+    #[test]
+    fn resource_predicates_gate_systems() {
+        struct Score(u32);
+
+        let context = SystemContext::new();
+        let mut runner = EcsSystemRunner::new();
+        runner.world().add_resource(Log::default());
+        runner.world().add_resource(Score(0));
+        runner.add_system(log("won")).run_if(resource_matches::<Score>(|score| score.0 >= 10));
+
+        runner.run_systems(&context).unwrap();
+        assert!(take_log(runner.world()).is_empty());
+
+        runner.world().with_resource_mut::<Score, _>(|score| score.unwrap().0 = 10);
+        runner.run_systems(&context).unwrap();
+        assert_eq!(take_log(runner.world()), vec!["won"]);
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Position(f32);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Velocity(f32);
+
+    struct Enemy;
+
+    struct Gravity(f32);
+
+    struct Hit(Entity);
+
+    fn apply_gravity(gravity: Res<Gravity>, velocities: Query<&mut Velocity>) -> Result<()> {
+        velocities.for_each(|mut velocity| velocity.0 -= gravity.0);
+        Ok(())
+    }
+
+    fn integrate(
+        query: Query<(Entity, &mut Position, &Velocity), With<Enemy>>,
+        hits: EventWriter<Hit>,
+        mut frames: Local<u32>,
+    ) -> Result<()> {
+        *frames += 1;
+        query.for_each(|(entity, mut position, velocity)| {
+            position.0 += velocity.0;
+            if position.0 < 0.0 {
+                hits.send(Hit(entity));
+            }
+        });
+        Ok(())
+    }
+
+    fn despawn_hit(hits: EventReader<Hit>, mut commands: Commands, mut log: ResMut<Log>) -> Result<()> {
+        hits.for_each(|hit| {
+            commands.entity(hit.0).despawn();
+            log.0.push("hit");
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn function_systems_receive_parameters() {
+        let context = SystemContext::new();
+        let mut runner = EcsSystemRunner::new();
+        let world = runner.world().clone();
+        world.add_resource(Log::default());
+        world.add_resource(Gravity(1.0));
+        let enemy = world.spawn().with(Position(1.5)).with(Velocity(0.0)).with(Enemy).build(&world);
+        let rock = world.spawn().with(Position(1.5)).with(Velocity(0.0)).build(&world);
+
+        runner.add_system(apply_gravity);
+        runner.add_system(integrate);
+        runner.add_system(despawn_hit);
+
+        runner.run_systems(&context).unwrap();
+        assert_eq!(world.get_component::<Position>(enemy), Some(Position(0.5)));
+        assert_eq!(world.get_component::<Position>(rock), Some(Position(1.5)));
+
+        runner.run_systems(&context).unwrap();
+        assert!(!world.is_alive(enemy));
+        assert_eq!(take_log(&world), vec!["hit"]);
+
+        let access = runner.system_access();
+        assert!(access[0].1.component_writes().any(|name| name.ends_with("Velocity")));
+        assert!(!access[0].1.is_compatible(&access[1].1));
+        // `integrate` writes `Events<Hit>`, which `despawn_hit` reads.
+        assert_eq!(access[1].1.conflicts_with(&access[2].1).len(), 1);
+    }
+
+    #[test]
+    fn missing_resources_fail_the_system() {
+        let context = SystemContext::new();
+        let mut runner = EcsSystemRunner::new();
+        runner.add_system(apply_gravity);
+        let error = runner.run_systems(&context).unwrap_err();
+        assert!(format!("{:#}", error).contains("Gravity"));
+    }
+}
@@ -0,0 +1,633 @@
+use crate::{
+    CommandQueue, Commands, ComponentTicks, Event, EventCursor, EventReader, EventWriter, Query, QueryData,
+    QueryFilter, ResourceError, Tick, World,
+};
+use lumina_core::{engine::SystemContext, Result};
+use parking_lot::{ArcRwLockReadGuard, ArcRwLockWriteGuard, RawRwLock};
+use std::any::{type_name, TypeId};
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+
+#[derive(Debug, Clone, Default)]
+struct TypeAccess {
+    reads: Vec<(TypeId, &'static str)>,
+    writes: Vec<(TypeId, &'static str)>,
+}
+
+impl TypeAccess {
+    fn reads(&self, type_id: TypeId) -> bool {
+        self.reads.iter().any(|(id, _)| *id == type_id)
+    }
+
+    fn writes(&self, type_id: TypeId) -> bool {
+        self.writes.iter().any(|(id, _)| *id == type_id)
+    }
+
+    fn add_read<T: 'static>(&mut self, kind: &str) {
+        let type_id = TypeId::of::<T>();
+        assert!(
+            !self.writes(type_id),
+            "{} {} is read and written by the same system",
+            kind,
+            type_name::<T>()
+        );
+        if !self.reads(type_id) {
+            self.reads.push((type_id, type_name::<T>()));
+        }
+    }
+
+    fn add_write<T: 'static>(&mut self, kind: &str) {
+        let type_id = TypeId::of::<T>();
+        assert!(
+            !self.reads(type_id) && !self.writes(type_id),
+            "{} {} is borrowed more than once by the same system, and one borrow is mutable",
+            kind,
+            type_name::<T>()
+        );
+        self.writes.push((type_id, type_name::<T>()));
+    }
+
+    fn conflicts(&self, other: &TypeAccess) -> Vec<&'static str> {
+        let mut conflicts: Vec<&'static str> = self
+            .writes
+            .iter()
+            .filter(|(id, _)| other.reads(*id) || other.writes(*id))
+            .chain(self.reads.iter().filter(|(id, _)| other.writes(*id)))
+            .map(|(_, name)| *name)
+            .collect();
+        conflicts.dedup();
+        conflicts
+    }
+}
+
+/// The components and resources a system reads and writes, collected from its parameters.
+///
+/// Two systems whose accesses are compatible can safely run in either order
+/// or at the same time.
+#[derive(Debug, Clone, Default)]
+pub struct SystemAccess {
+    components: TypeAccess,
+    resources: TypeAccess,
+    world: bool,
+}
+
+impl SystemAccess {
+    /// # Panics
+    ///
+    /// Panics if the system also writes `T`, since the borrows would deadlock.
+    pub fn read_component<T: 'static>(&mut self) {
+        self.components.add_read::<T>("Component");
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the system already reads or writes `T`.
+    pub fn write_component<T: 'static>(&mut self) {
+        self.components.add_write::<T>("Component");
+    }
+
+    pub fn read_resource<T: 'static>(&mut self) {
+        self.resources.add_read::<T>("Resource");
+    }
+
+    pub fn write_resource<T: 'static>(&mut self) {
+        self.resources.add_write::<T>("Resource");
+    }
+
+    /// Marks the system as taking `&World`, which may touch anything.
+    pub fn read_world(&mut self) {
+        self.world = true;
+    }
+
+    pub fn reads_world(&self) -> bool {
+        self.world
+    }
+
+    pub fn component_reads(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.components.reads.iter().map(|(_, name)| *name)
+    }
+
+    pub fn component_writes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.components.writes.iter().map(|(_, name)| *name)
+    }
+
+    pub fn resource_reads(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.resources.reads.iter().map(|(_, name)| *name)
+    }
+
+    pub fn resource_writes(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.resources.writes.iter().map(|(_, name)| *name)
+    }
+
+    /// Names of the component and resource types that one side writes and the other uses.
+    pub fn conflicts_with(&self, other: &SystemAccess) -> Vec<&'static str> {
+        let mut conflicts = self.components.conflicts(&other.components);
+        conflicts.extend(self.resources.conflicts(&other.resources));
+        conflicts
+    }
+
+    /// False if either system writes something the other uses, or either takes `&World`.
+    pub fn is_compatible(&self, other: &SystemAccess) -> bool {
+        !self.world && !other.world && self.conflicts_with(other).is_empty()
+    }
+}
+
+/// A value a function system can take as a parameter, extracted by the runner each run.
+pub trait SystemParam: Sized {
+    /// Data kept between runs, such as `Local` values or event cursors.
+    type State: Send + Sync + 'static;
+    type Item<'w, 's>: SystemParam<State = Self::State>;
+
+    fn init_state(world: &World, access: &mut SystemAccess) -> Self::State;
+
+    fn get_param<'w, 's>(
+        state: &'s mut Self::State,
+        world: &'w World,
+        context: &'w SystemContext,
+    ) -> Result<Self::Item<'w, 's>>;
+
+    /// Applies deferred work, such as `Commands`, after the system has run.
+    fn apply(_state: &mut Self::State, _world: &World) {}
+}
+
+pub type SystemParamItem<'w, 's, P> = <P as SystemParam>::Item<'w, 's>;
+
+/// Shared borrow of a resource, returned by `World::resource` and usable as a system parameter.
+pub struct Res<'w, T: Send + Sync + 'static> {
+    guard: ArcRwLockReadGuard<RawRwLock, T>,
+    ticks: ComponentTicks,
+    last_run: Tick,
+    _marker: PhantomData<&'w T>,
+}
+
+impl<T: Send + Sync + 'static> Deref for Res<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+/// Exclusive borrow of a resource. Marks the resource changed when it is
+/// mutably dereferenced.
+pub struct ResMut<'w, T: Send + Sync + 'static> {
+    guard: ArcRwLockWriteGuard<RawRwLock, T>,
+    ticks: ComponentTicks,
+    last_run: Tick,
+    world: &'w World,
+    changed: bool,
+}
+
+impl<T: Send + Sync + 'static> Deref for ResMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: Send + Sync + 'static> DerefMut for ResMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.changed = true;
+        &mut self.guard
+    }
+}
+
+impl<T: Send + Sync + 'static> Drop for ResMut<'_, T> {
+    fn drop(&mut self) {
+        if self.changed {
+            self.world.resources().set_changed::<T>(self.world.change_tick());
+        }
+    }
+}
+
+impl<'w, T: Send + Sync + 'static> Res<'w, T> {
+    /// Fails instead of blocking if the resource is mutably borrowed.
+    pub(crate) fn fetch(world: &'w World) -> std::result::Result<Self, ResourceError> {
+        let lock = world.resources().lock::<T>().ok_or(ResourceError::Missing(type_name::<T>()))?;
+        Ok(Res {
+            guard: lock.try_read_arc().ok_or(ResourceError::AlreadyBorrowed(type_name::<T>()))?,
+            ticks: resource_ticks::<T>(world),
+            last_run: world.last_change_tick(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// True if the resource was inserted since the current system last ran.
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added(self.last_run)
+    }
+
+    /// True if the resource was inserted or mutated since the current system last ran.
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.last_run)
+    }
+}
+
+impl<'w, T: Send + Sync + 'static> ResMut<'w, T> {
+    /// Fails instead of blocking if the resource is already borrowed.
+    pub(crate) fn fetch(world: &'w World) -> std::result::Result<Self, ResourceError> {
+        let lock = world.resources().lock::<T>().ok_or(ResourceError::Missing(type_name::<T>()))?;
+        Ok(ResMut {
+            guard: lock.try_write_arc().ok_or(ResourceError::AlreadyBorrowed(type_name::<T>()))?,
+            ticks: resource_ticks::<T>(world),
+            last_run: world.last_change_tick(),
+            world,
+            changed: false,
+        })
+    }
+
+    pub fn is_added(&self) -> bool {
+        self.ticks.is_added(self.last_run)
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.ticks.is_changed(self.last_run)
+    }
+
+    /// Marks the resource changed without writing to it.
+    pub fn set_changed(&mut self) {
+        self.changed = true;
+    }
+}
+
+/// Treats a missing resource as `None` but keeps borrow conflicts as errors.
+fn optional<R>(fetched: std::result::Result<R, ResourceError>) -> std::result::Result<Option<R>, ResourceError> {
+    match fetched {
+        Ok(resource) => Ok(Some(resource)),
+        Err(ResourceError::Missing(_)) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn resource_ticks<T: Send + Sync + 'static>(world: &World) -> ComponentTicks {
+    world.resources().ticks::<T>().unwrap_or_else(|| ComponentTicks::new(Tick::default()))
+}
+
+/// Shared borrow of a non-`Send` resource. Only available on the world's main thread.
+pub struct NonSend<'w, T: 'static> {
+    guard: ArcRwLockReadGuard<RawRwLock, T>,
+    _marker: PhantomData<&'w T>,
+}
+
+impl<T: 'static> Deref for NonSend<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+/// Exclusive borrow of a non-`Send` resource. Only available on the world's main thread.
+pub struct NonSendMut<'w, T: 'static> {
+    guard: ArcRwLockWriteGuard<RawRwLock, T>,
+    _marker: PhantomData<&'w mut T>,
+}
+
+impl<T: 'static> Deref for NonSendMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T: 'static> DerefMut for NonSendMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<'w, T: 'static> NonSend<'w, T> {
+    pub(crate) fn fetch(world: &'w World) -> std::result::Result<Self, ResourceError> {
+        let lock = world.resources().lock_non_send::<T>()?;
+        Ok(NonSend {
+            guard: lock.try_read_arc().ok_or(ResourceError::AlreadyBorrowed(type_name::<T>()))?,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<'w, T: 'static> NonSendMut<'w, T> {
+    pub(crate) fn fetch(world: &'w World) -> std::result::Result<Self, ResourceError> {
+        let lock = world.resources().lock_non_send::<T>()?;
+        Ok(NonSendMut {
+            guard: lock.try_write_arc().ok_or(ResourceError::AlreadyBorrowed(type_name::<T>()))?,
+            _marker: PhantomData,
+        })
+    }
+}
+
+/// Per-system value that persists between runs, starting from `T::default()`.
+pub struct Local<'s, T: Default + Send + Sync + 'static>(&'s mut T);
+
+impl<T: Default + Send + Sync + 'static> Deref for Local<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<T: Default + Send + Sync + 'static> DerefMut for Local<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.0
+    }
+}
+
+impl SystemParam for &World {
+    type State = ();
+    type Item<'w, 's> = &'w World;
+
+    fn init_state(_world: &World, access: &mut SystemAccess) {
+        access.read_world();
+    }
+
+    fn get_param<'w>(_state: &mut (), world: &'w World, _context: &'w SystemContext) -> Result<&'w World> {
+        Ok(world)
+    }
+}
+
+impl SystemParam for &SystemContext {
+    type State = ();
+    type Item<'w, 's> = &'w SystemContext;
+
+    fn init_state(_world: &World, _access: &mut SystemAccess) {}
+
+    fn get_param<'w>(
+        _state: &mut (),
+        _world: &'w World,
+        context: &'w SystemContext,
+    ) -> Result<&'w SystemContext> {
+        Ok(context)
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for Res<'_, T> {
+    type State = ();
+    type Item<'w, 's> = Res<'w, T>;
+
+    fn init_state(_world: &World, access: &mut SystemAccess) {
+        access.read_resource::<T>();
+    }
+
+    fn get_param<'w>(_state: &mut (), world: &'w World, _context: &'w SystemContext) -> Result<Res<'w, T>> {
+        Ok(world.resource::<T>()?)
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for Option<Res<'_, T>> {
+    type State = ();
+    type Item<'w, 's> = Option<Res<'w, T>>;
+
+    fn init_state(_world: &World, access: &mut SystemAccess) {
+        access.read_resource::<T>();
+    }
+
+    fn get_param<'w>(
+        _state: &mut (),
+        world: &'w World,
+        _context: &'w SystemContext,
+    ) -> Result<Option<Res<'w, T>>> {
+        Ok(optional(Res::fetch(world))?)
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for ResMut<'_, T> {
+    type State = ();
+    type Item<'w, 's> = ResMut<'w, T>;
+
+    fn init_state(_world: &World, access: &mut SystemAccess) {
+        access.write_resource::<T>();
+    }
+
+    fn get_param<'w>(_state: &mut (), world: &'w World, _context: &'w SystemContext) -> Result<ResMut<'w, T>> {
+        Ok(world.resource_mut::<T>()?)
+    }
+}
+
+impl<T: Send + Sync + 'static> SystemParam for Option<ResMut<'_, T>> {
+    type State = ();
+    type Item<'w, 's> = Option<ResMut<'w, T>>;
+
+    fn init_state(_world: &World, access: &mut SystemAccess) {
+        access.write_resource::<T>();
+    }
+
+    fn get_param<'w>(
+        _state: &mut (),
+        world: &'w World,
+        _context: &'w SystemContext,
+    ) -> Result<Option<ResMut<'w, T>>> {
+        Ok(optional(ResMut::fetch(world))?)
+    }
+}
+
+impl<T: 'static> SystemParam for NonSend<'_, T> {
+    type State = ();
+    type Item<'w, 's> = NonSend<'w, T>;
+
+    fn init_state(_world: &World, access: &mut SystemAccess) {
+        access.read_resource::<T>();
+    }
+
+    fn get_param<'w>(_state: &mut (), world: &'w World, _context: &'w SystemContext) -> Result<NonSend<'w, T>> {
+        Ok(NonSend::fetch(world)?)
+    }
+}
+
+impl<T: 'static> SystemParam for NonSendMut<'_, T> {
+    type State = ();
+    type Item<'w, 's> = NonSendMut<'w, T>;
+
+    fn init_state(_world: &World, access: &mut SystemAccess) {
+        access.write_resource::<T>();
+    }
+
+    fn get_param<'w>(_state: &mut (), world: &'w World, _context: &'w SystemContext) -> Result<NonSendMut<'w, T>> {
+        Ok(NonSendMut::fetch(world)?)
+    }
+}
+
+impl<T: Default + Send + Sync + 'static> SystemParam for Local<'_, T> {
+    type State = T;
+    type Item<'w, 's> = Local<'s, T>;
+
+    fn init_state(_world: &World, _access: &mut SystemAccess) -> T {
+        T::default()
+    }
+
+    fn get_param<'w, 's>(state: &'s mut T, _world: &'w World, _context: &'w SystemContext) -> Result<Local<'s, T>> {
+        Ok(Local(state))
+    }
+}
+
+impl<D: QueryData + 'static, F: QueryFilter + 'static> SystemParam for Query<'_, D, F> {
+    type State = ();
+    type Item<'w, 's> = Query<'w, D, F>;
+
+    fn init_state(_world: &World, access: &mut SystemAccess) {
+        D::add_access(access);
+    }
+
+    fn get_param<'w>(_state: &mut (), world: &'w World, _context: &'w SystemContext) -> Result<Query<'w, D, F>> {
+        Ok(Query::new(world))
+    }
+}
+
+impl SystemParam for Commands<'_, '_> {
+    type State = CommandQueue;
+    type Item<'w, 's> = Commands<'w, 's>;
+
+    fn init_state(_world: &World, _access: &mut SystemAccess) -> CommandQueue {
+        CommandQueue::new()
+    }
+
+    fn get_param<'w, 's>(
+        state: &'s mut CommandQueue,
+        world: &'w World,
+        _context: &'w SystemContext,
+    ) -> Result<Commands<'w, 's>> {
+        Ok(Commands::new(state, world))
+    }
+
+    fn apply(state: &mut CommandQueue, world: &World) {
+        state.apply(world);
+    }
+}
+
+impl<T: Event> SystemParam for EventReader<'_, '_, T> {
+    type State = EventCursor<T>;
+    type Item<'w, 's> = EventReader<'w, 's, T>;
+
+    fn init_state(world: &World, access: &mut SystemAccess) -> EventCursor<T> {
+        world.add_event::<T>();
+        access.read_resource::<crate::Events<T>>();
+        EventCursor::new()
+    }
+
+    fn get_param<'w, 's>(
+        state: &'s mut EventCursor<T>,
+        world: &'w World,
+        _context: &'w SystemContext,
+    ) -> Result<EventReader<'w, 's, T>> {
+        Ok(EventReader::new(world, state))
+    }
+}
+
+impl<T: Event> SystemParam for EventWriter<'_, T> {
+    type State = ();
+    type Item<'w, 's> = EventWriter<'w, T>;
+
+    fn init_state(world: &World, access: &mut SystemAccess) {
+        world.add_event::<T>();
+        access.write_resource::<crate::Events<T>>();
+    }
+
+    fn get_param<'w>(_state: &mut (), world: &'w World, _context: &'w SystemContext) -> Result<EventWriter<'w, T>> {
+        Ok(world.event_writer())
+    }
+}
+
+macro_rules! impl_system_param_tuple {
+    ($($param:ident),*) => {
+        #[allow(non_snake_case, unused_variables, clippy::unused_unit)]
+        impl<$($param: SystemParam),*> SystemParam for ($($param,)*) {
+            type State = ($($param::State,)*);
+            type Item<'w, 's> = ($($param::Item<'w, 's>,)*);
+
+            fn init_state(world: &World, access: &mut SystemAccess) -> Self::State {
+                ($($param::init_state(world, access),)*)
+            }
+
+            fn get_param<'w, 's>(
+                state: &'s mut Self::State,
+                world: &'w World,
+                context: &'w SystemContext,
+            ) -> Result<Self::Item<'w, 's>> {
+                let ($($param,)*) = state;
+                Ok(($($param::get_param($param, world, context)?,)*))
+            }
+
+            fn apply(state: &mut Self::State, world: &World) {
+                let ($($param,)*) = state;
+                $($param::apply($param, world);)*
+            }
+        }
+    };
+}
+
+impl_system_param_tuple!();
+impl_system_param_tuple!(A);
+impl_system_param_tuple!(A, B);
+impl_system_param_tuple!(A, B, C);
+impl_system_param_tuple!(A, B, C, D);
+impl_system_param_tuple!(A, B, C, D, E);
+impl_system_param_tuple!(A, B, C, D, E, F);
+impl_system_param_tuple!(A, B, C, D, E, F, G);
+impl_system_param_tuple!(A, B, C, D, E, F, G, H);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EcsSystem, IntoSystem, ResMut};
+
+    struct Position;
+
+    struct Score;
+
+    fn initialize<M>(system: impl IntoSystem<M>) -> SystemAccess {
+        let world = World::new();
+        let mut system = system.into_system();
+        system.initialize(&world);
+        system.access()
+    }
+
+    #[test]
+    #[should_panic(expected = "is read and written by the same system")]
+    fn reading_a_written_component_panics() {
+        fn system(_writes: Query<&mut Position>, _reads: Query<&Position>) -> Result<()> {
+            Ok(())
+        }
+        initialize(system);
+    }
+
+    #[test]
+    #[should_panic(expected = "is borrowed more than once by the same system")]
+    fn writing_a_read_component_panics() {
+        fn system(_reads: Query<&Position>, _writes: Query<&mut Position>) -> Result<()> {
+            Ok(())
+        }
+        initialize(system);
+    }
+
+    #[test]
+    #[should_panic(expected = "is borrowed more than once by the same system")]
+    fn writing_a_resource_twice_panics() {
+        fn system(_first: ResMut<Score>, _second: ResMut<Score>) -> Result<()> {
+            Ok(())
+        }
+        initialize(system);
+    }
+
+    #[test]
+    fn shared_reads_are_compatible() {
+        fn reader(_positions: Query<&Position>, _score: Res<Score>, _again: Res<Score>) -> Result<()> {
+            Ok(())
+        }
+        fn writer(_score: ResMut<Score>) -> Result<()> {
+            Ok(())
+        }
+        fn inspector(_world: &World) -> Result<()> {
+            Ok(())
+        }
+
+        let reads = initialize(reader);
+        assert_eq!(reads.resource_reads().count(), 1);
+        assert!(reads.is_compatible(&initialize(reader)));
+
+        let writes = initialize(writer);
+        assert_eq!(reads.conflicts_with(&writes), vec![type_name::<Score>()]);
+        assert!(!initialize(inspector).is_compatible(&reads));
+    }
+}
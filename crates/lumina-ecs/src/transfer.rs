@@ -0,0 +1,248 @@
+use crate::{
+    Entity, EntityMap, OwnedBy, Parent, Reflect, Relationship, SerializationError, SnapshotFilter, Targets, World,
+    WorldSnapshot,
+};
+use std::collections::HashSet;
+
+impl World {
+    /// Makes this world a copy of `source`: same entity ids, reflected components and resources.
+    ///
+    /// Only types registered for reflection are copied. Registrations from `source`
+    /// are added to this world's registry first.
+    pub fn clone_from(&self, source: &World) -> Result<(), SerializationError> {
+        if !std::ptr::eq(self.type_registry(), source.type_registry()) {
+            self.type_registry().extend_from(source.type_registry());
+        }
+        self.restore_snapshot(&source.snapshot(&SnapshotFilter::all()))
+    }
+
+    /// Creates a sandbox copy of this world that shares its type registry,
+    /// e.g. to simulate ahead without touching the real world.
+    pub fn fork(&self) -> Result<World, SerializationError> {
+        let sub_world = World::with_type_registry(self.shared_type_registry());
+        sub_world.clone_from(self)?;
+        Ok(sub_world)
+    }
+
+    /// Moves `entities` into `destination` and despawns them here.
+    ///
+    /// References between moved entities are remapped. Relationships with
+    /// entities that stay behind are removed first, and other references to
+    /// them become `Entity::PLACEHOLDER`.
+    ///
+    /// Only reflected components can be copied, so nothing moves if any of the
+    /// entities has a component that is not registered for reflection. This world
+    /// is only changed once the entities were loaded into `destination`.
+    pub fn move_entities(&self, entities: &[Entity], destination: &World) -> Result<EntityMap, SerializationError> {
+        let moving: HashSet<Entity> = entities.iter().copied().filter(|entity| self.is_alive(*entity)).collect();
+
+        let mut unregistered: Vec<String> = moving
+            .iter()
+            .flat_map(|&entity| self.components().component_stats_of(entity))
+            .filter(|(type_id, _)| self.type_registry().get(*type_id).is_none())
+            .map(|(_, stats)| stats.type_name.to_string())
+            .collect();
+        if !unregistered.is_empty() {
+            unregistered.sort();
+            unregistered.dedup();
+            return Err(SerializationError::UnregisteredComponents(unregistered));
+        }
+
+        let mut snapshot = self.snapshot(&SnapshotFilter::all().entities(moving.iter().copied()).without_resources());
+        let moving_ids: HashSet<u32> = moving.iter().map(|entity| entity.index()).collect();
+        strip_crossing_relationship::<Parent>(&mut snapshot, &moving_ids);
+        strip_crossing_relationship::<OwnedBy>(&mut snapshot, &moving_ids);
+        strip_crossing_relationship::<Targets>(&mut snapshot, &moving_ids);
+        for entity in &mut snapshot.entities {
+            for (_, value) in &mut entity.components {
+                value.filter_map_entities(&mut |index| moving_ids.contains(&index).then_some(index));
+            }
+        }
+
+        if !std::ptr::eq(self.type_registry(), destination.type_registry()) {
+            destination.type_registry().extend_from(self.type_registry());
+        }
+        let entity_map = destination.load_snapshot(&snapshot)?;
+
+        self.detach_relationship::<Parent>(&moving);
+        self.detach_relationship::<OwnedBy>(&moving);
+        self.detach_relationship::<Targets>(&moving);
+        for entity in moving {
+            self.despawn(entity);
+        }
+        Ok(entity_map)
+    }
+
+    /// Removes `R` wherever it crosses the boundary of `set`, in either direction.
+    fn detach_relationship<R: Relationship>(&self, set: &HashSet<Entity>) {
+        for &entity in set {
+            if self.relation_target::<R>(entity).is_some_and(|target| !set.contains(&target)) {
+                self.unrelate::<R>(entity);
+            }
+            for source in self.relation_sources::<R>(entity) {
+                if !set.contains(&source) {
+                    self.unrelate::<R>(source);
+                }
+            }
+        }
+    }
+}
+
+/// Drops `R` links that cross the boundary of the moved entities from `snapshot`:
+/// relations to entities that stay behind, and their entries in target lists.
+fn strip_crossing_relationship<R>(snapshot: &mut WorldSnapshot, moving: &HashSet<u32>)
+where
+    R: Relationship + Reflect + Default,
+    R::Target: Reflect,
+{
+    let source_name = R::default().type_name();
+    let target_name = R::Target::default().type_name();
+    let empty_target = R::Target::default().to_value();
+    for entity in &mut snapshot.entities {
+        entity.components.retain_mut(|(name, value)| {
+            if name == source_name {
+                let mut inside = true;
+                value.map_entities(&mut |index| {
+                    inside &= moving.contains(&index);
+                    index
+                });
+                inside
+            } else if name == target_name {
+                value.filter_map_entities(&mut |index| moving.contains(&index).then_some(index));
+                *value != empty_target
+            } else {
+                true
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Reflect;
+
+    #[derive(Debug, Default, Clone, PartialEq, Reflect)]
+    struct Position {
+        x: f32,
+        y: f32,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Reflect)]
+    struct Follow {
+        target: Entity,
+    }
+
+    fn world() -> World {
+        let world = World::new();
+        world.register_component::<Position>();
+        world.register_component::<Follow>();
+        world
+    }
+
+    #[test]
+    fn clone_and_fork_keep_entity_ids() {
+        let world = world();
+        let gap = world.spawn().build(&world);
+        let player = world.spawn_with(Position { x: 1.0, y: 2.0 });
+        let pet = world.spawn_with(Follow { target: player });
+        world.set_parent(pet, player).unwrap();
+        world.despawn(gap);
+
+        let copy = World::new();
+        copy.clone_from(&world).unwrap();
+        assert_eq!(copy.iter_entities(), vec![player, pet]);
+        assert_eq!(copy.get_component::<Follow>(pet), Some(Follow { target: player }));
+        assert_eq!(copy.children(player), vec![pet]);
+
+        let sandbox = world.fork().unwrap();
+        sandbox.with_component_mut::<Position, _>(player, |p| p.unwrap().x = 50.0);
+        sandbox.despawn(pet);
+        assert_eq!(world.get_component::<Position>(player), Some(Position { x: 1.0, y: 2.0 }));
+        assert!(world.is_alive(pet));
+    }
+
+    #[test]
+    fn moving_entities_remaps_references() {
+        let source = world();
+        let anchor = source.spawn_with(Position::default());
+        let leader = source.spawn_with(Position { x: 3.0, y: 0.0 });
+        let follower = source.spawn_with(Follow { target: leader });
+        let stray = source.spawn_with(Follow { target: anchor });
+        source.set_parent(leader, anchor).unwrap();
+        source.set_parent(follower, leader).unwrap();
+
+        let destination = World::new();
+        destination.spawn().build(&destination);
+        let map = source.move_entities(&[leader, follower, stray], &destination).unwrap();
+
+        assert_eq!(source.iter_entities(), vec![anchor]);
+        assert!(source.children(anchor).is_empty());
+
+        let (leader, follower, stray) = (map.get(leader).unwrap(), map.get(follower).unwrap(), map.get(stray).unwrap());
+        assert_eq!(destination.get_component::<Follow>(follower), Some(Follow { target: leader }));
+        assert_eq!(destination.parent(follower), Some(leader));
+        assert_eq!(destination.parent(leader), None);
+        assert_eq!(destination.get_component::<Follow>(stray), Some(Follow { target: Entity::PLACEHOLDER }));
+
+        // Unreflected components can't be copied, so nothing moves.
+        struct Unreflected;
+        source.add_component(anchor, Unreflected);
+        let err = source.move_entities(&[anchor], &destination).unwrap_err();
+        assert!(matches!(err, SerializationError::UnregisteredComponents(names) if names[0].ends_with("Unreflected")));
+        assert!(source.is_alive(anchor));
+        assert_eq!(destination.entity_count(), 4);
+        assert!(!destination.entities().create_exact(Entity::PLACEHOLDER));
+    }
+
+    /// Serializes fine but refuses to load, to make `load_snapshot` fail.
+    #[derive(Debug, Default, Clone, PartialEq)]
+    struct Corrupt;
+
+    impl Reflect for Corrupt {
+        fn type_name(&self) -> &'static str {
+            "Corrupt"
+        }
+
+        fn to_value(&self) -> crate::Value {
+            crate::Value::Unit
+        }
+
+        fn apply(&mut self, _value: &crate::Value) -> Result<(), crate::ReflectError> {
+            Err(crate::ReflectError::OutOfRange("Corrupt"))
+        }
+
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+
+        fn into_any(self: Box<Self>) -> Box<dyn std::any::Any> {
+            self
+        }
+    }
+
+    #[test]
+    fn failed_moves_leave_the_source_unchanged() {
+        let source = world();
+        source.register_component::<Corrupt>();
+        let anchor = source.spawn_with(Position::default());
+        let leader = source.spawn_with(Position { x: 3.0, y: 0.0 });
+        let broken = source.spawn_with(Corrupt);
+        source.set_parent(leader, anchor).unwrap();
+        source.set_parent(broken, leader).unwrap();
+        source.relate::<Targets>(anchor, leader).unwrap();
+
+        let destination = World::new();
+        assert!(source.move_entities(&[leader, broken], &destination).is_err());
+
+        assert_eq!(source.iter_entities(), vec![anchor, leader, broken]);
+        assert_eq!(source.children(anchor), vec![leader]);
+        assert_eq!(source.children(leader), vec![broken]);
+        assert_eq!(source.relation_target::<Targets>(anchor), Some(leader));
+        assert_eq!(destination.entity_count(), 0);
+    }
+}
@@ -0,0 +1,426 @@
+use crate::{
+    Component, ComponentManager, Entity, Reflect, ReflectError, TypeInfo, Value, World,
+};
+use parking_lot::RwLock;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistrationKind {
+    Component,
+    Resource,
+}
+
+pub type ReflectVisitor<'a> = &'a mut dyn FnMut(&dyn Reflect);
+pub type ReflectVisitorMut<'a> = &'a mut dyn FnMut(&mut dyn Reflect);
+pub type ReflectResult<T> = Result<T, ReflectError>;
+
+/// Type-erased accessors for a reflected component type.
+#[derive(Clone, Copy)]
+pub struct ReflectComponentFns {
+    /// Inserts a boxed value of this type, returning true if the entity did not have it before.
+    pub insert: fn(&ComponentManager, Entity, Box<dyn Reflect>) -> ReflectResult<bool>,
+    pub contains: fn(&ComponentManager, Entity) -> bool,
+    pub with: fn(&ComponentManager, Entity, ReflectVisitor) -> bool,
+    pub with_mut: fn(&ComponentManager, Entity, ReflectVisitorMut) -> bool,
+    pub remove: fn(&ComponentManager, Entity) -> Option<Box<dyn Reflect>>,
+}
+
+/// Type-erased accessors for a reflected resource type.
+#[derive(Clone, Copy)]
+pub struct ReflectResourceFns {
+    pub insert: fn(&World, Box<dyn Reflect>) -> Result<(), ReflectError>,
+    pub with: fn(&World, ReflectVisitor) -> bool,
+    pub with_mut: fn(&World, ReflectVisitorMut) -> bool,
+}
+
+/// Everything the registry knows about one reflected type.
+pub struct TypeRegistration {
+    pub type_id: TypeId,
+    /// Short name used in scene files and editors, e.g. `"Transform"`.
+    pub name: &'static str,
+    /// Full Rust path from `std::any::type_name`.
+    pub type_path: &'static str,
+    pub kind: RegistrationKind,
+    pub info: TypeInfo,
+    default_fn: fn() -> Box<dyn Reflect>,
+    component: Option<ReflectComponentFns>,
+    resource: Option<ReflectResourceFns>,
+}
+
+impl TypeRegistration {
+    pub fn default_value(&self) -> Box<dyn Reflect> {
+        (self.default_fn)()
+    }
+
+    /// Builds a new instance from its default, then applies `value` on top.
+    pub fn from_value(&self, value: &Value) -> Result<Box<dyn Reflect>, ReflectError> {
+        let mut instance = self.default_value();
+        instance.apply(value)?;
+        Ok(instance)
+    }
+
+    pub fn component(&self) -> Option<&ReflectComponentFns> {
+        self.component.as_ref()
+    }
+
+    pub fn resource(&self) -> Option<&ReflectResourceFns> {
+        self.resource.as_ref()
+    }
+}
+
+/// Registry of reflected component and resource types, looked up by `TypeId` or name.
+pub struct TypeRegistry {
+    registrations: RwLock<HashMap<TypeId, Arc<TypeRegistration>>>,
+    names: RwLock<HashMap<&'static str, TypeId>>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Self {
+            registrations: RwLock::new(HashMap::new()),
+            names: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn register_component<T: Component + Reflect + Default>(&self) {
+        self.insert::<T>(
+            RegistrationKind::Component,
+            Some(ReflectComponentFns {
+                insert: |components, entity, value| {
+                    let component = value.downcast::<T>().map_err(|_| ReflectError::TypeMismatch {
+                        expected: std::any::type_name::<T>(),
+                        found: "another type".to_string(),
+                    })?;
+                    Ok(components.add_component(entity, *component))
+                },
+                contains: |components, entity| components.has_component::<T>(entity),
+                with: |components, entity, f| {
+                    components.with_component::<T, _>(entity, |component| component.map(|c| f(c)).is_some())
+                },
+                with_mut: |components, entity, f| {
+                    components.with_component_mut::<T, _>(entity, |component| component.map(|c| f(c)).is_some())
+                },
+                remove: |components, entity| {
+                    components
+                        .remove_component::<T>(entity)
+                        .map(|component| Box::new(component) as Box<dyn Reflect>)
+                },
+            }),
+            None,
+        );
+    }
+
+    pub fn register_resource<T: Reflect + Default>(&self) {
+        self.insert::<T>(
+            RegistrationKind::Resource,
+            None,
+            Some(ReflectResourceFns {
+                insert: |world, value| {
+                    let resource = value.downcast::<T>().map_err(|_| ReflectError::TypeMismatch {
+                        expected: std::any::type_name::<T>(),
+                        found: "another type".to_string(),
+                    })?;
+                    world.add_resource(*resource);
+                    Ok(())
+                },
+                with: |world, f| world.with_resource::<T, _>(|resource| resource.map(|r| f(r)).is_some()),
+                with_mut: |world, f| world.with_resource_mut::<T, _>(|resource| resource.map(|r| f(r)).is_some()),
+            }),
+        );
+    }
+
+    fn insert<T: Reflect + Default>(
+        &self,
+        kind: RegistrationKind,
+        component: Option<ReflectComponentFns>,
+        resource: Option<ReflectResourceFns>,
+    ) {
+        let sample = T::default();
+        let registration = TypeRegistration {
+            type_id: TypeId::of::<T>(),
+            name: sample.type_name(),
+            type_path: std::any::type_name::<T>(),
+            kind,
+            info: sample.type_info(),
+            default_fn: || Box::new(T::default()),
+            component,
+            resource,
+        };
+
+        let mut names = self.names.write();
+        if let Some(existing) = names.get(registration.name) {
+            if *existing != registration.type_id {
+                log::warn!(
+                    "Reflected type name '{}' is already registered by another type; '{}' replaces it",
+                    registration.name,
+                    registration.type_path
+                );
+            }
+        }
+        names.insert(registration.name, registration.type_id);
+        self.registrations.write().insert(registration.type_id, Arc::new(registration));
+    }
+
+    /// Copies every registration from `other`, e.g. into a world cloned from it.
+    pub fn extend_from(&self, other: &TypeRegistry) {
+        for registration in other.iter() {
+            self.names.write().insert(registration.name, registration.type_id);
+            self.registrations.write().insert(registration.type_id, registration);
+        }
+    }
+
+    pub fn get(&self, type_id: TypeId) -> Option<Arc<TypeRegistration>> {
+        self.registrations.read().get(&type_id).cloned()
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<Arc<TypeRegistration>> {
+        let type_id = *self.names.read().get(name)?;
+        self.get(type_id)
+    }
+
+    pub fn contains(&self, type_id: TypeId) -> bool {
+        self.registrations.read().contains_key(&type_id)
+    }
+
+    /// All registrations, sorted by name for stable output.
+    pub fn iter(&self) -> Vec<Arc<TypeRegistration>> {
+        let mut registrations: Vec<_> = self.registrations.read().values().cloned().collect();
+        registrations.sort_by_key(|registration| registration.name);
+        registrations
+    }
+
+    pub fn components(&self) -> Vec<Arc<TypeRegistration>> {
+        self.iter()
+            .into_iter()
+            .filter(|registration| registration.kind == RegistrationKind::Component)
+            .collect()
+    }
+
+    pub fn resources(&self) -> Vec<Arc<TypeRegistration>> {
+        self.iter()
+            .into_iter()
+            .filter(|registration| registration.kind == RegistrationKind::Resource)
+            .collect()
+    }
+}
+
+impl Default for TypeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl World {
+    /// Registers `T` for reflection and creates its storage.
+    pub fn register_component<T: Component + Reflect + Default>(&self) {
+        self.type_registry().register_component::<T>();
+        self.components().register::<T>();
+    }
+
+    pub fn register_resource<T: Reflect + Default>(&self) {
+        self.type_registry().register_resource::<T>();
+    }
+
+    fn component_registration(&self, name: &str) -> Result<(Arc<TypeRegistration>, ReflectComponentFns), ReflectError> {
+        let registration = self
+            .type_registry()
+            .get_by_name(name)
+            .ok_or_else(|| ReflectError::UnregisteredType(name.to_string()))?;
+        let fns = *registration
+            .component()
+            .ok_or_else(|| ReflectError::UnregisteredType(name.to_string()))?;
+        Ok((registration, fns))
+    }
+
+    /// Inserts a reflected component, running the same hooks as `add_component`.
+    pub fn add_reflect_component(&self, entity: Entity, component: Box<dyn Reflect>) -> Result<(), ReflectError> {
+        if !self.is_alive(entity) {
+            return Err(ReflectError::DeadEntity(entity));
+        }
+        let type_id = component.as_any().type_id();
+        if let Some(fns) = self.type_registry().get(type_id).and_then(|registration| registration.component().copied()) {
+            if (fns.contains)(self.components(), entity) {
+                self.run_replace_hooks(type_id, entity);
+            }
+        }
+        let added = self.components().add_reflect_component(entity, component, self.type_registry())?;
+        self.run_insert_hooks(type_id, entity, added);
+        Ok(())
+    }
+
+    /// Builds the named component from its default plus `value` and inserts it.
+    pub fn add_component_by_name(&self, entity: Entity, name: &str, value: &Value) -> Result<(), ReflectError> {
+        let (registration, _) = self.component_registration(name)?;
+        self.add_reflect_component(entity, registration.from_value(value)?)
+    }
+
+    /// Removes the named component, running `on_remove` hooks first.
+    pub fn remove_component_by_name(&self, entity: Entity, name: &str) -> Option<Box<dyn Reflect>> {
+        let (registration, fns) = self.component_registration(name).ok()?;
+        if !self.is_alive(entity) || !(fns.contains)(self.components(), entity) {
+            return None;
+        }
+        self.run_remove_hooks(registration.type_id, entity);
+        (fns.remove)(self.components(), entity)
+    }
+
+    pub fn has_component_by_name(&self, entity: Entity, name: &str) -> bool {
+        self.component_registration(name)
+            .map(|(_, fns)| self.is_alive(entity) && (fns.contains)(self.components(), entity))
+            .unwrap_or(false)
+    }
+
+    pub fn with_reflect_component<R>(
+        &self,
+        entity: Entity,
+        name: &str,
+        f: impl FnOnce(&dyn Reflect) -> R,
+    ) -> Option<R> {
+        let (_, fns) = self.component_registration(name).ok()?;
+        if !self.is_alive(entity) {
+            return None;
+        }
+        let mut f = Some(f);
+        let mut result = None;
+        (fns.with)(self.components(), entity, &mut |component| {
+            if let Some(f) = f.take() {
+                result = Some(f(component));
+            }
+        });
+        result
+    }
+
+    /// Edits a component through reflection. `on_replace` hooks run before the edit
+    /// and `on_change` hooks after it, keeping relationships and indices in sync.
+    pub fn with_reflect_component_mut<R>(
+        &self,
+        entity: Entity,
+        name: &str,
+        f: impl FnOnce(&mut dyn Reflect) -> R,
+    ) -> Option<R> {
+        let (registration, fns) = self.component_registration(name).ok()?;
+        if !self.is_alive(entity) {
+            return None;
+        }
+        if !(fns.contains)(self.components(), entity) {
+            return None;
+        }
+        self.run_replace_hooks(registration.type_id, entity);
+        let mut f = Some(f);
+        let mut result = None;
+        (fns.with_mut)(self.components(), entity, &mut |component| {
+            if let Some(f) = f.take() {
+                result = Some(f(component));
+            }
+        });
+        self.run_change_hooks(registration.type_id, entity);
+        result
+    }
+
+    pub fn reflect_component(&self, entity: Entity, name: &str) -> Option<Value> {
+        self.with_reflect_component(entity, name, |component| component.to_value())
+    }
+
+    /// Every registered component on `entity`, as `(name, value)` pairs sorted by name.
+    pub fn reflect_components(&self, entity: Entity) -> Vec<(&'static str, Value)> {
+        self.type_registry()
+            .components()
+            .into_iter()
+            .filter_map(|registration| {
+                self.reflect_component(entity, registration.name)
+                    .map(|value| (registration.name, value))
+            })
+            .collect()
+    }
+
+    /// Applies `value` to the field at `path` of the named component, e.g. `"position.x"`.
+    pub fn set_component_field(&self, entity: Entity, name: &str, path: &str, value: &Value) -> Result<(), ReflectError> {
+        self.component_registration(name)?;
+        self.with_reflect_component_mut(entity, name, |component| component.set_path(path, value))
+            .unwrap_or_else(|| Err(ReflectError::MissingComponent(entity, name.to_string())))
+    }
+
+    fn resource_fns(&self, name: &str) -> Result<ReflectResourceFns, ReflectError> {
+        self.type_registry()
+            .get_by_name(name)
+            .and_then(|registration| registration.resource().copied())
+            .ok_or_else(|| ReflectError::UnregisteredType(name.to_string()))
+    }
+
+    pub fn add_reflect_resource(&self, resource: Box<dyn Reflect>) -> Result<(), ReflectError> {
+        let registration = self
+            .type_registry()
+            .get(resource.as_any().type_id())
+            .ok_or_else(|| ReflectError::UnregisteredType(resource.type_name().to_string()))?;
+        let fns = registration
+            .resource()
+            .ok_or_else(|| ReflectError::UnregisteredType(registration.name.to_string()))?;
+        (fns.insert)(self, resource)
+    }
+
+    pub fn reflect_resource(&self, name: &str) -> Option<Value> {
+        let fns = self.resource_fns(name).ok()?;
+        let mut value = None;
+        (fns.with)(self, &mut |resource| value = Some(resource.to_value()));
+        value
+    }
+
+    pub fn set_resource_field(&self, name: &str, path: &str, value: &Value) -> Result<(), ReflectError> {
+        let fns = self.resource_fns(name)?;
+        let mut result = Err(ReflectError::MissingResource(name.to_string()));
+        (fns.with_mut)(self, &mut |resource| result = resource.set_path(path, value));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Reflect, ReflectError, Value, World};
+
+    #[derive(Debug, Default, Clone, PartialEq, Reflect)]
+    struct Health {
+        current: f32,
+        max: f32,
+    }
+
+    #[derive(Debug, Default, Reflect)]
+    struct Gravity(f32);
+
+    #[test]
+    fn components_can_be_inserted_and_edited_by_name() {
+        let world = World::new();
+        world.register_component::<Health>();
+        let entity = world.spawn().build(&world);
+
+        let value = Value::Struct(vec![("max".to_string(), Value::Float(100.0))]);
+        world.add_component_by_name(entity, "Health", &value).unwrap();
+        world.set_component_field(entity, "Health", "current", &Value::Float(40.0)).unwrap();
+
+        assert_eq!(world.get_component::<Health>(entity), Some(Health { current: 40.0, max: 100.0 }));
+        assert_eq!(world.reflect_component(entity, "Health").unwrap().field("max"), Some(&Value::Float(100.0)));
+        assert!(matches!(
+            world.add_component_by_name(entity, "Mana", &Value::Unit),
+            Err(ReflectError::UnregisteredType(_))
+        ));
+    }
+
+    #[test]
+    fn resources_are_reflected() {
+        let world = World::new();
+        world.register_resource::<Gravity>();
+        world.add_resource(Gravity(9.8));
+
+        world.clear_trackers();
+
+        world.set_resource_field("Gravity", "0", &Value::Float(1.5)).unwrap();
+        assert_eq!(world.reflect_resource("Gravity"), Some(Value::List(vec![Value::Float(1.5)])));
+        assert_eq!(world.type_registry().resources().len(), 1);
+        assert!(world.resource::<Gravity>().unwrap().is_changed());
+
+        world.clear_trackers();
+        world.add_reflect_resource(Box::new(Gravity(3.0))).unwrap();
+        assert!(world.resource::<Gravity>().unwrap().is_added());
+    }
+}
@@ -1,19 +1,38 @@
-use crate::{Component, ComponentManager, Entity, EntityBuilder, EntityManager, ResourceManager};
+use crate::{
+    Component, ComponentManager, ComponentTicks, Entity, EntityBuilder, EntityManager, Indices,
+    NonSend, NonSendMut, ObserverId, Observers, RemovedComponents, Res, ResMut, ResourceError, ResourceManager, Tick,
+    TypeRegistry,
+};
+use std::any::TypeId;
 use std::sync::Arc;
 
 pub struct World {
     entities: Arc<EntityManager>,
     components: Arc<ComponentManager>,
     resources: Arc<ResourceManager>,
+    observers: Arc<Observers>,
+    indices: Arc<Indices>,
+    type_registry: Arc<TypeRegistry>,
 }
 
 impl World {
     pub fn new() -> Self {
-        Self {
+        Self::with_type_registry(Arc::new(TypeRegistry::new()))
+    }
+
+    /// Creates an empty world that shares reflected type registrations with other worlds.
+    pub fn with_type_registry(type_registry: Arc<TypeRegistry>) -> Self {
+        let world = Self {
             entities: Arc::new(EntityManager::new()),
             components: Arc::new(ComponentManager::new()),
             resources: Arc::new(ResourceManager::new()),
-        }
+            observers: Arc::new(Observers::new()),
+            indices: Arc::new(Indices::new()),
+            type_registry,
+        };
+        world.register_builtin_relationships();
+        world.register_builtin_indices();
+        world
     }
 
     pub fn spawn(&self) -> EntityBuilder {
@@ -23,13 +42,22 @@ impl World {
 
     pub fn spawn_with<T: Component>(&self, component: T) -> Entity {
         let entity = self.entities.create();
-        self.components.add_component(entity, component);
+        self.add_component(entity, component);
         entity
     }
 
     pub fn despawn(&self, entity: Entity) -> bool {
+        if !self.entities.is_alive(entity) {
+            return false;
+        }
+
+        for type_id in self.components.component_types_of(entity) {
+            self.run_remove_hooks(type_id, entity);
+        }
+
         if self.entities.destroy(entity) {
             self.components.remove_all_components(entity);
+            self.observers.remove_target(entity);
             true
         } else {
             false
@@ -41,11 +69,57 @@ impl World {
     }
 
     pub fn add_component<T: Component>(&self, entity: Entity, component: T) {
-        if self.entities.is_alive(entity) {
-            self.components.add_component(entity, component);
+        if !self.entities.is_alive(entity) {
+            return;
+        }
+
+        if self.components.has_component::<T>(entity) {
+            self.run_replace_hooks(TypeId::of::<T>(), entity);
+        }
+        let added = self.components.add_component(entity, component);
+        self.run_insert_hooks(TypeId::of::<T>(), entity, added);
+    }
+
+    pub(crate) fn run_replace_hooks(&self, type_id: TypeId, entity: Entity) {
+        if let Some(hooks) = self.components.hooks(type_id) {
+            for hook in &hooks.on_replace {
+                hook(self, entity);
+            }
+        }
+    }
+
+    pub(crate) fn run_change_hooks(&self, type_id: TypeId, entity: Entity) {
+        self.indices.component_changed(self, type_id, entity);
+        if let Some(hooks) = self.components.hooks(type_id) {
+            for hook in &hooks.on_change {
+                hook(self, entity);
+            }
+        }
+    }
+
+    pub(crate) fn run_insert_hooks(&self, type_id: TypeId, entity: Entity, added: bool) {
+        self.indices.component_changed(self, type_id, entity);
+        if let Some(hooks) = self.components.hooks(type_id) {
+            if added {
+                for hook in &hooks.on_add {
+                    hook(self, entity);
+                }
+            }
+            for hook in &hooks.on_insert {
+                hook(self, entity);
+            }
         }
     }
 
+    pub(crate) fn run_remove_hooks(&self, type_id: TypeId, entity: Entity) {
+        if let Some(hooks) = self.components.hooks(type_id) {
+            for hook in &hooks.on_remove {
+                hook(self, entity);
+            }
+        }
+        self.indices.component_removed(type_id, entity);
+    }
+
     pub fn get_component<T: Component + Clone>(&self, entity: Entity) -> Option<T> {
         if self.entities.is_alive(entity) {
             self.components.get_component(entity)
@@ -64,26 +138,145 @@ impl World {
 
     pub fn with_component_mut<T: Component, R>(&self, entity: Entity, f: impl FnOnce(Option<&mut T>) -> R) -> R {
         if self.entities.is_alive(entity) {
-            self.components.with_component_mut(entity, f)
+            let result = self.components.with_component_mut(entity, f);
+            self.indices.component_changed(self, TypeId::of::<T>(), entity);
+            result
         } else {
             f(None)
         }
     }
 
+    /// Marks a component changed as if it had been borrowed with `with_component_mut`,
+    /// for code that mutates it through a `Mut` or untracked borrow.
+    pub fn set_component_changed<T: Component>(&self, entity: Entity) {
+        if self.entities.is_alive(entity) && self.components.has_component::<T>(entity) {
+            self.components.set_changed::<T>(entity);
+            self.indices.component_changed(self, TypeId::of::<T>(), entity);
+        }
+    }
+
     pub fn remove_component<T: Component>(&self, entity: Entity) -> Option<T> {
-        if self.entities.is_alive(entity) {
-            self.components.remove_component(entity)
-        } else {
-            None
+        if !self.has_component::<T>(entity) {
+            return None;
         }
+
+        self.run_remove_hooks(TypeId::of::<T>(), entity);
+        self.components.remove_component(entity)
     }
 
     pub fn has_component<T: Component>(&self, entity: Entity) -> bool {
         self.entities.is_alive(entity) && self.components.has_component::<T>(entity)
     }
 
+    pub fn component_ticks<T: Component>(&self, entity: Entity) -> Option<ComponentTicks> {
+        self.components.component_ticks::<T>(entity)
+    }
+
+    /// Entities that lost `T` since `last_change_tick`, either by removal or despawn.
+    pub fn removed_components<T: Component>(&self) -> RemovedComponents<T> {
+        RemovedComponents::new(self.components.removed_since::<T>(self.last_change_tick()))
+    }
+
+    pub fn change_tick(&self) -> Tick {
+        self.components.change_tick()
+    }
+
+    pub fn increment_change_tick(&self) -> Tick {
+        self.components.increment_change_tick()
+    }
+
+    pub fn last_change_tick(&self) -> Tick {
+        self.components.last_change_tick()
+    }
+
+    pub fn set_last_change_tick(&self, tick: Tick) {
+        self.components.set_last_change_tick(tick);
+    }
+
+    /// Marks everything seen so far as observed, for code that runs outside `EcsSystemRunner`.
+    pub fn clear_trackers(&self) {
+        let tick = self.change_tick();
+        self.set_last_change_tick(tick);
+        self.components.prune_removed(tick);
+        self.increment_change_tick();
+        self.check_change_ticks();
+    }
+
+    /// Clamps stale ticks so they keep reading as old after the change tick wraps.
+    /// Cheap to call every frame; the clamping itself only runs every
+    /// `CHECK_TICK_THRESHOLD` ticks. Returns true if it ran.
+    pub fn check_change_ticks(&self) -> bool {
+        let now = self.change_tick();
+        let checked = self.components.check_change_ticks(now);
+        if checked {
+            self.resources.check_change_ticks(now);
+        }
+        checked
+    }
+
+    /// Observes events of type `E` triggered on `entity`. The observer is dropped when the entity is despawned.
+    pub fn observe<E: Send + Sync + 'static>(
+        &self,
+        entity: Entity,
+        observer: impl Fn(&World, Entity, &E) + Send + Sync + 'static,
+    ) -> ObserverId {
+        self.observers.add(Some(entity), observer)
+    }
+
+    /// Observes events of type `E` triggered on any entity.
+    pub fn add_observer<E: Send + Sync + 'static>(
+        &self,
+        observer: impl Fn(&World, Entity, &E) + Send + Sync + 'static,
+    ) -> ObserverId {
+        self.observers.add(None, observer)
+    }
+
+    pub fn remove_observer(&self, id: ObserverId) -> bool {
+        self.observers.remove(id)
+    }
+
+    /// Runs the observers of `entity` and then the global observers for `event`, immediately.
+    pub fn trigger<E: Send + Sync + 'static>(&self, entity: Entity, event: E) {
+        if self.entities.is_alive(entity) {
+            self.observers.trigger(self, entity, &event);
+        }
+    }
+
     pub fn add_resource<T: Send + Sync + 'static>(&self, resource: T) {
-        self.resources.add(resource);
+        self.resources.insert(resource, self.change_tick());
+    }
+
+    /// Adds `T::default()` unless the resource already exists.
+    pub fn init_resource<T: Default + Send + Sync + 'static>(&self) {
+        if !self.resources.has::<T>() {
+            self.add_resource(T::default());
+        }
+    }
+
+    /// Borrows a resource until the returned guard is dropped.
+    /// Fails with `AlreadyBorrowed` rather than blocking if it is mutably borrowed.
+    pub fn resource<T: Send + Sync + 'static>(&self) -> Result<Res<'_, T>, ResourceError> {
+        Res::fetch(self)
+    }
+
+    /// Mutably borrows a resource until the returned guard is dropped.
+    /// Fails with `AlreadyBorrowed` rather than blocking if it is borrowed at all.
+    pub fn resource_mut<T: Send + Sync + 'static>(&self) -> Result<ResMut<'_, T>, ResourceError> {
+        ResMut::fetch(self)
+    }
+
+    /// Like `resource`, but returns `None` on any error.
+    pub fn get_resource<T: Send + Sync + 'static>(&self) -> Option<Res<'_, T>> {
+        Res::fetch(self).ok()
+    }
+
+    /// Like `resource_mut`, but returns `None` on any error.
+    pub fn get_resource_mut<T: Send + Sync + 'static>(&self) -> Option<ResMut<'_, T>> {
+        ResMut::fetch(self).ok()
+    }
+
+    pub fn resource_ticks<T: Send + Sync + 'static>(&self) -> Option<ComponentTicks> {
+        self.resources.ticks::<T>()
     }
 
     pub fn with_resource<T: Send + Sync + 'static, R>(&self, f: impl FnOnce(Option<&T>) -> R) -> R {
@@ -91,7 +284,32 @@ impl World {
     }
 
     pub fn with_resource_mut<T: Send + Sync + 'static, R>(&self, f: impl FnOnce(Option<&mut T>) -> R) -> R {
-        self.resources.with_resource_mut(f)
+        let mut found = false;
+        let result = self.resources.with_resource_mut(|resource: Option<&mut T>| {
+            found = resource.is_some();
+            f(resource)
+        });
+        if found {
+            self.resources.set_changed::<T>(self.change_tick());
+        }
+        result
+    }
+
+    /// Adds a resource that must stay on the thread that created the world.
+    pub fn add_non_send_resource<T: 'static>(&self, resource: T) -> Result<(), ResourceError> {
+        self.resources.add_non_send(resource)
+    }
+
+    pub fn non_send_resource<T: 'static>(&self) -> Result<NonSend<'_, T>, ResourceError> {
+        NonSend::fetch(self)
+    }
+
+    pub fn non_send_resource_mut<T: 'static>(&self) -> Result<NonSendMut<'_, T>, ResourceError> {
+        NonSendMut::fetch(self)
+    }
+
+    pub fn remove_non_send_resource<T: 'static>(&self) -> Result<Option<T>, ResourceError> {
+        self.resources.remove_non_send()
     }
 
     pub fn remove_resource<T: Send + Sync + 'static>(&self) -> Option<T> {
@@ -130,6 +348,8 @@ impl World {
         self.entities.clear();
         self.components.clear();
         self.resources.clear();
+        self.observers.clear();
+        self.indices.clear_entries();
     }
 
     pub fn entities(&self) -> &EntityManager {
@@ -143,6 +363,22 @@ impl World {
     pub fn resources(&self) -> &ResourceManager {
         &self.resources
     }
+
+    pub fn observers(&self) -> &Observers {
+        &self.observers
+    }
+
+    pub fn indices(&self) -> &Indices {
+        &self.indices
+    }
+
+    pub fn type_registry(&self) -> &TypeRegistry {
+        &self.type_registry
+    }
+
+    pub fn shared_type_registry(&self) -> Arc<TypeRegistry> {
+        self.type_registry.clone()
+    }
 }
 
 impl Default for World {
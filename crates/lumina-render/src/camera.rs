@@ -0,0 +1,433 @@
+//! Cameras
+//!
+//! `Camera2D` and `Camera3D` are ECS components. Each frame `update_cameras`
+//! moves following cameras and clamps them to their bounds, then
+//! `extract_cameras` turns every active camera into a `CameraView`: the pixel
+//! viewport and view-projection matrix renderers draw with. Views are sorted by
+//! `order`, so later cameras (a minimap, say) draw on top of earlier ones.
+
+use crate::Rect;
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4Swizzles};
+use lumina_core::math::{Transform2D, Transform3D};
+use lumina_ecs::{Entity, Query, Reflect, World};
+
+/// Near and far planes of 2D cameras; sprites all sit at z = 0
+const DEPTH_RANGE_2D: f32 = 1000.0;
+
+/// Part of the render target a camera draws into, in fractions of the target size
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct Viewport {
+    /// Normalized rectangle, (0, 0) being the top-left corner of the target
+    pub rect: Rect,
+}
+
+impl Viewport {
+    /// The whole render target
+    pub const FULL: Viewport = Viewport {
+        rect: Rect { position: Vec2::ZERO, size: Vec2::ONE },
+    };
+
+    /// A viewport covering the normalized rectangle `(x, y, width, height)`
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { rect: Rect::new(x, y, width, height) }
+    }
+
+    /// The viewport in pixels for a target of `target_size`
+    pub fn pixel_rect(&self, target_size: Vec2) -> Rect {
+        let min = (self.rect.min() * target_size).clamp(Vec2::ZERO, target_size);
+        let max = (self.rect.max() * target_size).clamp(Vec2::ZERO, target_size);
+        Rect::from_min_max(min.round(), max.round())
+    }
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// Makes a 2D camera track an entity's `Transform2D`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect)]
+pub struct CameraFollow {
+    /// Entity to follow
+    pub target: Entity,
+    /// Offset from the target's position
+    pub offset: Vec2,
+    /// How quickly the camera catches up, per second. Zero snaps to the target.
+    pub smoothing: f32,
+}
+
+/// Orthographic camera for 2D scenes, in the same y-down space as sprites
+#[derive(Debug, Clone, Copy, PartialEq, Reflect)]
+pub struct Camera2D {
+    /// World position shown at the centre of the viewport
+    pub position: Vec2,
+    /// Rotation in radians
+    pub rotation: f32,
+    /// Screen pixels per world unit
+    pub zoom: f32,
+    /// Part of the target this camera draws into
+    pub viewport: Viewport,
+    /// Cameras with a higher order draw later
+    pub order: i32,
+    /// Inactive cameras are skipped by `extract_cameras`
+    pub active: bool,
+    /// Entity to follow, if any
+    pub follow: Option<CameraFollow>,
+    /// World area the visible region is kept inside
+    pub bounds: Option<Rect>,
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            rotation: 0.0,
+            zoom: 1.0,
+            viewport: Viewport::FULL,
+            order: 0,
+            active: true,
+            follow: None,
+            bounds: None,
+        }
+    }
+}
+
+impl Camera2D {
+    /// A camera centred on `position`
+    pub fn new(position: Vec2) -> Self {
+        Self { position, ..Default::default() }
+    }
+
+    /// Set the zoom
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.zoom = zoom;
+        self
+    }
+
+    /// Set the viewport
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Set the draw order
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Follow `target`, catching up at `smoothing` per second
+    pub fn following(mut self, target: Entity, smoothing: f32) -> Self {
+        self.follow = Some(CameraFollow { target, offset: Vec2::ZERO, smoothing });
+        self
+    }
+
+    /// Keep the visible area inside `bounds`
+    pub fn with_bounds(mut self, bounds: Rect) -> Self {
+        self.bounds = Some(bounds);
+        self
+    }
+
+    /// Size of the visible world area for a viewport of `viewport_size` pixels
+    pub fn visible_size(&self, viewport_size: Vec2) -> Vec2 {
+        viewport_size / self.zoom.max(f32::EPSILON)
+    }
+
+    /// View-projection matrix for a viewport of `viewport_size` pixels
+    pub fn view_projection(&self, viewport_size: Vec2) -> Mat4 {
+        let half = self.visible_size(viewport_size) * 0.5;
+        // Bottom and top are swapped so world y grows down the screen
+        let projection = Mat4::orthographic_rh(-half.x, half.x, half.y, -half.y, -DEPTH_RANGE_2D, DEPTH_RANGE_2D);
+        let view = Mat4::from_rotation_z(-self.rotation) * Mat4::from_translation(-self.position.extend(0.0));
+        projection * view
+    }
+
+    /// World position under a point on the render target, in pixels
+    pub fn screen_to_world(&self, screen: Vec2, target_size: Vec2) -> Vec2 {
+        let viewport = self.viewport.pixel_rect(target_size);
+        let ndc = screen_to_ndc(screen, viewport);
+        let world = self.view_projection(viewport.size).inverse() * ndc.extend(0.0).extend(1.0);
+        world.xy()
+    }
+
+    /// Pixel position on the render target of a world position
+    pub fn world_to_screen(&self, world: Vec2, target_size: Vec2) -> Vec2 {
+        let viewport = self.viewport.pixel_rect(target_size);
+        let clip = self.view_projection(viewport.size) * world.extend(0.0).extend(1.0);
+        ndc_to_screen(clip.xy(), viewport)
+    }
+
+    /// Move the position so the visible area stays inside `bounds`.
+    /// On an axis where the bounds are smaller than the view, the camera is centred.
+    pub fn clamp_to_bounds(&mut self, viewport_size: Vec2) {
+        let Some(bounds) = self.bounds else {
+            return;
+        };
+        let half = self.visible_size(viewport_size) * 0.5;
+        let min = bounds.min() + half;
+        let max = bounds.max() - half;
+        let center = bounds.center();
+        self.position = Vec2::new(
+            if min.x <= max.x { self.position.x.clamp(min.x, max.x) } else { center.x },
+            if min.y <= max.y { self.position.y.clamp(min.y, max.y) } else { center.y },
+        );
+    }
+}
+
+/// Perspective camera for 3D scenes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera3D {
+    /// Eye position
+    pub position: Vec3,
+    /// Orientation; the camera looks down its local -Z axis
+    pub rotation: Quat,
+    /// Vertical field of view in radians
+    pub fov_y: f32,
+    /// Near clip plane
+    pub near: f32,
+    /// Far clip plane
+    pub far: f32,
+    /// Part of the target this camera draws into
+    pub viewport: Viewport,
+    /// Cameras with a higher order draw later
+    pub order: i32,
+    /// Inactive cameras are skipped by `extract_cameras`
+    pub active: bool,
+}
+
+impl Default for Camera3D {
+    fn default() -> Self {
+        Self {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            fov_y: 60f32.to_radians(),
+            near: 0.1,
+            far: 1000.0,
+            viewport: Viewport::FULL,
+            order: 0,
+            active: true,
+        }
+    }
+}
+
+/// A ray in world space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    /// Start of the ray
+    pub origin: Vec3,
+    /// Normalized direction
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /// Point at `distance` along the ray
+    pub fn at(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+impl Camera3D {
+    /// A camera at `eye` looking at `target`
+    pub fn look_at(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        Self {
+            position: eye,
+            rotation: Transform3D::look_at(eye, target, up).rotation,
+            ..Default::default()
+        }
+    }
+
+    /// Set the viewport
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    /// Set the draw order
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// View-projection matrix for a viewport of `viewport_size` pixels
+    pub fn view_projection(&self, viewport_size: Vec2) -> Mat4 {
+        let aspect = viewport_size.x / viewport_size.y.max(1.0);
+        let projection = Mat4::perspective_rh(self.fov_y, aspect, self.near, self.far);
+        let view = Mat4::from_rotation_translation(self.rotation, self.position).inverse();
+        projection * view
+    }
+
+    /// Ray from the eye through a point on the render target, in pixels
+    pub fn screen_to_world(&self, screen: Vec2, target_size: Vec2) -> Ray {
+        let viewport = self.viewport.pixel_rect(target_size);
+        let ndc = screen_to_ndc(screen, viewport);
+        let inverse = self.view_projection(viewport.size).inverse();
+        let near = inverse.project_point3(ndc.extend(0.0));
+        let far = inverse.project_point3(ndc.extend(1.0));
+        Ray { origin: near, direction: (far - near).normalize() }
+    }
+
+    /// Pixel position on the render target of a world position, or `None` if it is
+    /// behind the camera
+    pub fn world_to_screen(&self, world: Vec3, target_size: Vec2) -> Option<Vec2> {
+        let viewport = self.viewport.pixel_rect(target_size);
+        let clip = self.view_projection(viewport.size) * world.extend(1.0);
+        (clip.w > 0.0).then(|| ndc_to_screen(clip.xy() / clip.w, viewport))
+    }
+}
+
+fn screen_to_ndc(screen: Vec2, viewport: Rect) -> Vec2 {
+    let t = (screen - viewport.position) / viewport.size.max(Vec2::ONE);
+    Vec2::new(t.x * 2.0 - 1.0, 1.0 - t.y * 2.0)
+}
+
+fn ndc_to_screen(ndc: Vec2, viewport: Rect) -> Vec2 {
+    let t = Vec2::new(ndc.x + 1.0, 1.0 - ndc.y) * 0.5;
+    viewport.position + t * viewport.size
+}
+
+/// What a renderer needs to draw one camera
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraView {
+    /// Viewport on the render target, in pixels
+    pub viewport: Rect,
+    /// View-projection matrix
+    pub view_proj: Mat4,
+    /// Draw order
+    pub order: i32,
+}
+
+impl CameraView {
+    /// A view covering the whole target with the given matrix
+    pub fn full_target(view_proj: Mat4, target_size: Vec2) -> Self {
+        Self {
+            viewport: Rect::from_min_max(Vec2::ZERO, target_size),
+            view_proj,
+            order: 0,
+        }
+    }
+}
+
+/// Move following 2D cameras towards their targets and clamp them to their bounds.
+/// Cameras are only written back when they moved, so `Changed<Camera2D>` stays quiet
+/// for cameras at rest.
+pub fn update_cameras(world: &World, target_size: Vec2, delta_time: f32) {
+    for entity in Query::<&Camera2D>::new(world).entities() {
+        let Some(current) = world.get_component::<Camera2D>(entity) else {
+            continue;
+        };
+
+        let mut camera = current;
+        let target = camera.follow.and_then(|follow| {
+            let transform = world.get_component::<Transform2D>(follow.target)?;
+            Some((transform.position + follow.offset, follow.smoothing))
+        });
+        if let Some((target, smoothing)) = target {
+            // Frame-rate independent exponential approach
+            let t = if smoothing > 0.0 { 1.0 - (-smoothing * delta_time).exp() } else { 1.0 };
+            camera.position = camera.position.lerp(target, t);
+        }
+        let viewport_size = camera.viewport.pixel_rect(target_size).size;
+        camera.clamp_to_bounds(viewport_size);
+
+        if camera != current {
+            world.with_component_mut::<Camera2D, _>(entity, |stored| {
+                if let Some(stored) = stored {
+                    *stored = camera;
+                }
+            });
+        }
+    }
+}
+
+/// Views of every active camera, in draw order. Cameras with the same order keep
+/// storage order, 2D cameras first.
+pub fn extract_cameras(world: &World, target_size: Vec2) -> Vec<CameraView> {
+    let mut views = Vec::new();
+    Query::<&Camera2D>::new(world).for_each(|camera| {
+        if camera.active {
+            let viewport = camera.viewport.pixel_rect(target_size);
+            views.push(CameraView { viewport, view_proj: camera.view_projection(viewport.size), order: camera.order });
+        }
+    });
+    Query::<&Camera3D>::new(world).for_each(|camera| {
+        if camera.active {
+            let viewport = camera.viewport.pixel_rect(target_size);
+            views.push(CameraView { viewport, view_proj: camera.view_projection(viewport.size), order: camera.order });
+        }
+    });
+    views.retain(|view| view.viewport.size.x >= 1.0 && view.viewport.size.y >= 1.0);
+    views.sort_by_key(|view| view.order);
+    views
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lumina_ecs::Changed;
+
+    #[test]
+    fn cameras_follow_clamp_and_convert_coordinates() {
+        let target_size = Vec2::new(800.0, 600.0);
+        let world = World::new();
+        let player = world.spawn().with(Transform2D::new(Vec2::new(100.0, 50.0), 0.0, Vec2::ONE)).build(&world);
+        let main = world
+            .spawn()
+            .with(Camera2D::new(Vec2::ZERO).with_zoom(2.0).following(player, 0.0))
+            .build(&world);
+        world
+            .spawn()
+            .with(Camera2D::default().with_viewport(Viewport::new(0.75, 0.0, 0.25, 0.25)).with_order(1))
+            .build(&world);
+        world.spawn().with(Camera3D { order: -1, active: false, ..Default::default() }).build(&world);
+
+        update_cameras(&world, target_size, 1.0 / 60.0);
+        let camera = world.get_component::<Camera2D>(main).unwrap();
+        assert_eq!(camera.position, Vec2::new(100.0, 50.0));
+
+        // The viewport centre shows the camera position; y grows downwards
+        let centre = camera.screen_to_world(target_size * 0.5, target_size);
+        assert!(centre.abs_diff_eq(camera.position, 1e-3));
+        let below = camera.screen_to_world(Vec2::new(400.0, 500.0), target_size);
+        assert!(below.abs_diff_eq(Vec2::new(100.0, 150.0), 1e-3));
+        let screen = camera.world_to_screen(Vec2::new(150.0, 0.0), target_size);
+        assert!(screen.abs_diff_eq(Vec2::new(500.0, 200.0), 1e-3));
+
+        // 400x300 visible world units must stay inside the bounds
+        let mut clamped = camera.with_bounds(Rect::new(0.0, 0.0, 1000.0, 200.0));
+        clamped.clamp_to_bounds(target_size);
+        assert_eq!(clamped.position, Vec2::new(200.0, 100.0));
+
+        let views = extract_cameras(&world, target_size);
+        assert_eq!(views.len(), 2);
+        assert_eq!(views[1].viewport, Rect::new(600.0, 0.0, 200.0, 150.0));
+
+        let camera = Camera3D::look_at(Vec3::new(0.0, 0.0, 10.0), Vec3::ZERO, Vec3::Y);
+        let ray = camera.screen_to_world(target_size * 0.5, target_size);
+        assert!(ray.direction.abs_diff_eq(-Vec3::Z, 1e-4));
+        let projected = camera.world_to_screen(Vec3::ZERO, target_size).unwrap();
+        assert!(projected.abs_diff_eq(target_size * 0.5, 1e-3));
+        assert!(camera.world_to_screen(Vec3::new(0.0, 0.0, 20.0), target_size).is_none());
+    }
+
+    #[test]
+    fn cameras_at_rest_are_not_marked_changed() {
+        let target_size = Vec2::new(800.0, 600.0);
+        let world = World::new();
+        let player = world.spawn().with(Transform2D::new(Vec2::new(100.0, 50.0), 0.0, Vec2::ONE)).build(&world);
+        let camera = world.spawn().with(Camera2D::new(Vec2::ZERO).following(player, 0.0)).build(&world);
+        world.spawn().with(Camera2D::default()).build(&world);
+
+        update_cameras(&world, target_size, 1.0 / 60.0);
+        world.clear_trackers();
+        update_cameras(&world, target_size, 1.0 / 60.0);
+        assert!(world.query_filtered::<Camera2D, Changed<Camera2D>>().is_empty());
+
+        world.with_component_mut::<Transform2D, _>(player, |transform| transform.unwrap().position.x += 10.0);
+        update_cameras(&world, target_size, 1.0 / 60.0);
+        let changed = world.query_filtered::<Camera2D, Changed<Camera2D>>();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].0, camera);
+        assert_eq!(changed[0].1.position, Vec2::new(110.0, 50.0));
+    }
+}
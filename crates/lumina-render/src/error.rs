@@ -45,6 +45,14 @@ pub enum RenderError {
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
     
+    /// Render graph could not be compiled
+    #[error("Invalid render graph: {0}")]
+    RenderGraph(String),
+    
+    /// Rendered image does not match its golden image
+    #[error("Golden image mismatch: {0}")]
+    GoldenMismatch(String),
+    
     /// Out of memory
     #[error("Out of graphics memory")]
     OutOfMemory,
@@ -53,12 +61,14 @@ pub enum RenderError {
 /// Result type for rendering operations
 pub type RenderResult<T> = Result<T, RenderError>;
 
+#[cfg(feature = "gpu")]
 impl From<wgpu::CreateSurfaceError> for RenderError {
     fn from(err: wgpu::CreateSurfaceError) -> Self {
         RenderError::SurfaceCreation(err.to_string())
     }
 }
 
+#[cfg(feature = "gpu")]
 impl From<wgpu::RequestDeviceError> for RenderError {
     fn from(err: wgpu::RequestDeviceError) -> Self {
         RenderError::DeviceCreation(err.to_string())
@@ -0,0 +1,350 @@
+//! Golden-image comparison for pixel regression tests
+//!
+//! Rendered images are compared against PNGs kept in a golden directory, with a
+//! per-channel tolerance so that small rasterization differences between drivers
+//! and software adapters do not fail a test. Run with `LUMINA_UPDATE_GOLDEN=1` to
+//! write the current output as the new golden images.
+
+use crate::{RenderError, RenderResult};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+/// Environment variable that makes `GoldenImages::check` overwrite the golden images
+pub const UPDATE_GOLDEN_ENV: &str = "LUMINA_UPDATE_GOLDEN";
+
+/// Result of comparing two images
+#[derive(Debug, Clone)]
+pub struct ImageDiff {
+    /// Pixels differing by more than the tolerance in any channel
+    pub mismatched_pixels: usize,
+    /// Largest difference of any channel
+    pub max_difference: u8,
+    /// Mismatched pixels in red over a faded copy of the actual image
+    pub diff: RgbaImage,
+}
+
+impl ImageDiff {
+    /// Fraction of all pixels that mismatched
+    pub fn mismatch_ratio(&self) -> f32 {
+        let total = (self.diff.width() * self.diff.height()).max(1);
+        self.mismatched_pixels as f32 / total as f32
+    }
+}
+
+/// Compare `actual` against `expected`, allowing each channel to differ by `tolerance`
+pub fn compare_images(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> RenderResult<ImageDiff> {
+    if actual.dimensions() != expected.dimensions() {
+        return Err(RenderError::GoldenMismatch(format!(
+            "image is {:?} but the golden image is {:?}",
+            actual.dimensions(),
+            expected.dimensions()
+        )));
+    }
+
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    for ((a, e), d) in actual.pixels().zip(expected.pixels()).zip(diff.pixels_mut()) {
+        let difference = a.0.iter().zip(e.0).map(|(&a, e)| a.abs_diff(e)).max().unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        *d = if difference > tolerance {
+            mismatched_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let luma = (a.0[0] as u32 + a.0[1] as u32 + a.0[2] as u32) / 3;
+            let faded = (luma / 4 + 192) as u8;
+            Rgba([faded, faded, faded, 255])
+        };
+    }
+
+    Ok(ImageDiff {
+        mismatched_pixels,
+        max_difference,
+        diff,
+    })
+}
+
+/// A directory of golden images that rendered output is checked against
+#[derive(Debug, Clone)]
+pub struct GoldenImages {
+    dir: PathBuf,
+    output_dir: PathBuf,
+    tolerance: u8,
+    max_mismatch_ratio: f32,
+}
+
+impl GoldenImages {
+    /// Golden images stored as `<dir>/<name>.png`.
+    ///
+    /// By default channels may differ by 2 and 0.1% of pixels may mismatch. Failed
+    /// checks write the actual and diff images to a `lumina-golden` temp directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            output_dir: std::env::temp_dir().join("lumina-golden"),
+            tolerance: 2,
+            max_mismatch_ratio: 0.001,
+        }
+    }
+
+    /// Write actual and diff images of failed checks to `dir`, e.g. for CI artifacts
+    pub fn with_output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = dir.into();
+        self
+    }
+
+    /// Allow channels to differ by `tolerance` and up to `max_mismatch_ratio` of the
+    /// pixels to exceed it
+    pub fn with_tolerance(mut self, tolerance: u8, max_mismatch_ratio: f32) -> Self {
+        self.tolerance = tolerance;
+        self.max_mismatch_ratio = max_mismatch_ratio;
+        self
+    }
+
+    /// Path of the golden image called `name`
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.png"))
+    }
+
+    /// Compare `image` against the golden image called `name`, or overwrite the golden
+    /// image when `LUMINA_UPDATE_GOLDEN` is set
+    pub fn check(&self, name: &str, image: &RgbaImage) -> RenderResult<()> {
+        let path = self.path(name);
+        if std::env::var_os(UPDATE_GOLDEN_ENV).is_some_and(|value| value != "0") {
+            return save(image, &path);
+        }
+        if !path.exists() {
+            return Err(RenderError::GoldenMismatch(format!(
+                "no golden image at {}, run with {UPDATE_GOLDEN_ENV}=1 to create it",
+                path.display()
+            )));
+        }
+
+        let expected = image::open(&path)
+            .map_err(|err| RenderError::TextureLoad(format!("{}: {}", path.display(), err)))?
+            .to_rgba8();
+        let diff = compare_images(image, &expected, self.tolerance)?;
+        if diff.mismatch_ratio() <= self.max_mismatch_ratio {
+            return Ok(());
+        }
+
+        let actual_path = self.output_dir.join(format!("{name}.actual.png"));
+        let diff_path = self.output_dir.join(format!("{name}.diff.png"));
+        save(image, &actual_path)?;
+        save(&diff.diff, &diff_path)?;
+        Err(RenderError::GoldenMismatch(format!(
+            "{name}: {} pixels ({:.2}%) differ by up to {}, see {} and {}",
+            diff.mismatched_pixels,
+            diff.mismatch_ratio() * 100.0,
+            diff.max_difference,
+            actual_path.display(),
+            diff_path.display()
+        )))
+    }
+}
+
+fn save(image: &RgbaImage, path: &Path) -> RenderResult<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| RenderError::InvalidOperation(format!("{}: {}", parent.display(), err)))?;
+    }
+    image
+        .save(path)
+        .map_err(|err| RenderError::InvalidOperation(format!("{}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Camera2D, FontHandle, Rect, RectStyle, RenderConfig, Renderer, Sprite, SpriteRenderer, TextAlign,
+        TextStyle, TextureManager, TextureOptions, WindowConfig,
+    };
+    use glam::{Vec2, Vec4};
+    use lumina_core::math::Transform2D;
+
+    const SIZE: (u32, u32) = (128, 96);
+
+    fn goldens() -> GoldenImages {
+        GoldenImages::new(concat!(env!("CARGO_MANIFEST_DIR"), "/golden"))
+    }
+
+    /// A headless renderer of the golden image size, see `test_renderer`
+    fn headless() -> Option<Renderer> {
+        crate::renderer::test_renderer(RenderConfig {
+            window: WindowConfig {
+                size: SIZE,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    fn begin_clear_pass<'a>(encoder: &'a mut wgpu::CommandEncoder, renderer: &'a Renderer) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Golden Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &renderer.offscreen.as_ref().unwrap().view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color { r: 0.05, g: 0.05, b: 0.08, a: 1.0 }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Render one UI frame drawn by `draw` and read it back
+    fn render_ui(renderer: &mut Renderer, draw: impl FnOnce(&mut crate::UiRenderer)) -> RgbaImage {
+        renderer.begin_frame().unwrap();
+        draw(renderer.ui_renderer_mut().unwrap());
+        renderer.end_frame(None);
+
+        let mut encoder = renderer.device.create_command_encoder(&Default::default());
+        {
+            let mut render_pass = begin_clear_pass(&mut encoder, renderer);
+            renderer.ui_renderer().unwrap().submit_to_render_pass(&mut render_pass);
+        }
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+        renderer.read_pixels().unwrap()
+    }
+
+    #[test]
+    fn compare_images_counts_pixels_beyond_tolerance() {
+        let expected = RgbaImage::from_pixel(4, 4, Rgba([100, 100, 100, 255]));
+        let mut actual = expected.clone();
+        actual.put_pixel(0, 0, Rgba([102, 100, 100, 255]));
+        actual.put_pixel(3, 3, Rgba([100, 140, 100, 255]));
+
+        let diff = compare_images(&actual, &expected, 2).unwrap();
+        assert_eq!(diff.mismatched_pixels, 1);
+        assert_eq!(diff.max_difference, 40);
+        assert_eq!(diff.diff.get_pixel(3, 3).0, [255, 0, 0, 255]);
+        assert_eq!(diff.mismatch_ratio(), 1.0 / 16.0);
+        assert!(compare_images(&actual, &RgbaImage::new(2, 2), 2).is_err());
+    }
+
+    #[test]
+    fn ui_shapes_match_golden_image() {
+        let Some(mut renderer) = headless() else { return };
+        pollster::block_on(renderer.init_ui_renderer()).unwrap();
+
+        let shapes = render_ui(&mut renderer, |ui| {
+            ui.draw_rect(Rect::new(4.0, 4.0, 40.0, 24.0), Vec4::new(0.9, 0.2, 0.2, 1.0));
+            ui.draw_styled_rect(
+                Rect::new(52.0, 8.0, 68.0, 36.0),
+                &RectStyle::new(Vec4::new(0.2, 0.5, 0.9, 1.0))
+                    .with_radii(10.0)
+                    .with_border(2.0, Vec4::ONE)
+                    .with_shadow(Vec2::new(2.0, 3.0), 6.0, Vec4::new(0.0, 0.0, 0.0, 0.6)),
+            );
+            ui.push_clip(Rect::new(8.0, 52.0, 40.0, 24.0));
+            ui.draw_rounded_rect(Rect::new(0.0, 44.0, 64.0, 48.0), Vec4::new(0.3, 0.8, 0.4, 1.0), 16.0);
+            ui.pop_clip();
+        });
+        goldens().check("ui_shapes", &shapes).unwrap();
+    }
+
+    #[test]
+    fn ui_text_matches_golden_image() {
+        let Some(mut renderer) = headless() else { return };
+        pollster::block_on(renderer.init_ui_renderer()).unwrap();
+
+        let text = render_ui(&mut renderer, |ui| {
+            ui.draw_text("Lumina", Vec2::new(4.0, 4.0), FontHandle(0), 24.0, Vec4::ONE);
+            let style = TextStyle::new(14.0).with_max_width(120.0).with_align(TextAlign::Center);
+            ui.draw_text_styled(
+                "Wrapped and centred text",
+                Vec2::new(4.0, 40.0),
+                FontHandle(0),
+                &style,
+                Vec4::new(1.0, 0.8, 0.3, 1.0),
+            );
+        });
+        goldens().check("ui_text", &text).unwrap();
+    }
+
+    #[test]
+    fn draws_after_clipped_ui_are_not_scissored() {
+        let Some(mut renderer) = headless() else { return };
+        pollster::block_on(renderer.init_ui_renderer()).unwrap();
+
+        renderer.begin_frame().unwrap();
+        let ui = renderer.ui_renderer_mut().unwrap();
+        ui.push_clip(Rect::new(0.0, 0.0, 16.0, 16.0));
+        ui.draw_rect(Rect::new(0.0, 0.0, 16.0, 16.0), Vec4::new(0.9, 0.2, 0.2, 1.0));
+        ui.pop_clip();
+        renderer.end_frame(None);
+
+        let mut textures = TextureManager::new();
+        let white = RgbaImage::from_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        let texture = textures
+            .create_texture(&renderer.device, &renderer.queue, &white, None, &TextureOptions::pixel_art())
+            .unwrap();
+        let mut sprites = SpriteRenderer::new(&renderer.device, Renderer::HEADLESS_FORMAT).unwrap();
+        sprites.batcher_mut().push(
+            Transform2D::new(Vec2::new(96.0, 64.0), 0.0, Vec2::ONE),
+            Sprite::new(texture, Vec2::splat(32.0)),
+        );
+        let screen_size = Vec2::new(SIZE.0 as f32, SIZE.1 as f32);
+        let view_proj = Camera2D::new(screen_size * 0.5).view_projection(screen_size);
+        sprites.prepare(&renderer.device, &renderer.queue, &textures, view_proj, screen_size);
+
+        let mut encoder = renderer.device.create_command_encoder(&Default::default());
+        {
+            let mut render_pass = begin_clear_pass(&mut encoder, &renderer);
+            renderer.ui_renderer().unwrap().submit_to_render_pass(&mut render_pass);
+            sprites.render(&mut render_pass);
+        }
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+
+        let pixels = renderer.read_pixels().unwrap();
+        let [r, g, _, _] = pixels.get_pixel(8, 8).0;
+        assert!(r > g, "clipped UI rect was not drawn");
+        // The sprite lies outside the UI clip rect
+        assert_eq!(pixels.get_pixel(96, 64).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn sprites_match_golden_image() {
+        let Some(renderer) = headless() else { return };
+
+        let mut textures = TextureManager::new();
+        let checker = RgbaImage::from_fn(4, 4, |x, y| {
+            if (x + y) % 2 == 0 {
+                Rgba([255, 255, 255, 255])
+            } else {
+                Rgba([40, 40, 200, 255])
+            }
+        });
+        let texture = textures
+            .create_texture(&renderer.device, &renderer.queue, &checker, None, &TextureOptions::pixel_art())
+            .unwrap();
+        let mut sprites = SpriteRenderer::new(&renderer.device, Renderer::HEADLESS_FORMAT).unwrap();
+        let batcher = sprites.batcher_mut();
+        batcher.push(
+            Transform2D::new(Vec2::new(32.0, 48.0), 0.0, Vec2::ONE),
+            Sprite::new(texture.clone(), Vec2::splat(48.0)),
+        );
+        batcher.push(
+            Transform2D::new(Vec2::new(88.0, 48.0), std::f32::consts::FRAC_PI_4, Vec2::ONE),
+            Sprite::new(texture, Vec2::splat(40.0))
+                .with_color(Vec4::new(1.0, 0.5, 0.5, 1.0))
+                .with_layer(1),
+        );
+        let screen_size = Vec2::new(SIZE.0 as f32, SIZE.1 as f32);
+        let view_proj = Camera2D::new(screen_size * 0.5).view_projection(screen_size);
+        sprites.prepare(&renderer.device, &renderer.queue, &textures, view_proj, screen_size);
+
+        let mut encoder = renderer.device.create_command_encoder(&Default::default());
+        {
+            let mut render_pass = begin_clear_pass(&mut encoder, &renderer);
+            sprites.render(&mut render_pass);
+        }
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+        goldens().check("sprites", &renderer.read_pixels().unwrap()).unwrap();
+    }
+}
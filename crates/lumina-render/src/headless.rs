@@ -0,0 +1,147 @@
+//! Offscreen rendering and pixel readback
+//!
+//! An `OffscreenTarget` stands in for the window surface when rendering headless,
+//! e.g. in tests, on CI machines without a display or for thumbnails.
+
+use crate::{RenderError, RenderResult};
+use glam::UVec2;
+use image::RgbaImage;
+
+/// A texture rendered to instead of a window surface, whose pixels can be read back
+pub struct OffscreenTarget {
+    /// The color texture
+    pub texture: wgpu::Texture,
+    /// View of the whole texture, for use as a color attachment
+    pub view: wgpu::TextureView,
+    /// Texture format
+    pub format: wgpu::TextureFormat,
+    /// Size in pixels
+    pub size: UVec2,
+}
+
+impl OffscreenTarget {
+    /// Create a target of `width`x`height` pixels.
+    ///
+    /// Only 8-bit RGBA and BGRA formats can be read back.
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self {
+            texture,
+            view,
+            format,
+            size: UVec2::new(width.max(1), height.max(1)),
+        }
+    }
+
+    /// Copy the target to CPU memory, waiting for all submitted work to finish.
+    ///
+    /// Pixels are returned as stored, i.e. gamma-encoded for sRGB formats.
+    pub fn read_pixels(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> RenderResult<RgbaImage> {
+        let bgra = match self.format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            format => {
+                return Err(RenderError::InvalidOperation(format!(
+                    "Cannot read back pixels of format {:?}",
+                    format
+                )))
+            }
+        };
+
+        let padded_row = padded_bytes_per_row(self.size.x);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: padded_row as u64 * self.size.y as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(self.size.y),
+                },
+            },
+            self.texture.size(),
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|_| RenderError::InvalidOperation("Readback was never completed".to_string()))?
+            .map_err(|err| RenderError::InvalidOperation(format!("Failed to map readback buffer: {}", err)))?;
+
+        let pixels = unpad_rows(&slice.get_mapped_range(), self.size.x, self.size.y, padded_row, bgra);
+        buffer.unmap();
+        RgbaImage::from_raw(self.size.x, self.size.y, pixels)
+            .ok_or_else(|| RenderError::InvalidOperation("Readback size mismatch".to_string()))
+    }
+}
+
+/// Bytes per row of a 4-byte-per-pixel copy, padded to wgpu's copy alignment
+pub(crate) fn padded_bytes_per_row(width: u32) -> u32 {
+    let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (width * 4).div_ceil(alignment) * alignment
+}
+
+/// Strip row padding from copied texels, swapping BGRA to RGBA if needed
+pub(crate) fn unpad_rows(data: &[u8], width: u32, height: u32, padded_row: u32, bgra: bool) -> Vec<u8> {
+    let row = (width * 4) as usize;
+    let mut pixels = Vec::with_capacity(row * height as usize);
+    for y in 0..height as usize {
+        let start = y * padded_row as usize;
+        pixels.extend_from_slice(&data[start..start + row]);
+    }
+    if bgra {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    pixels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readback_rows_are_unpadded_and_swizzled() {
+        assert_eq!(padded_bytes_per_row(1), 256);
+        assert_eq!(padded_bytes_per_row(64), 256);
+        assert_eq!(padded_bytes_per_row(65), 512);
+
+        // Two 1-pixel rows, each padded to 8 bytes
+        let data = [1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8, 0, 0, 0, 0];
+        assert_eq!(unpad_rows(&data, 1, 2, 8, false), [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(unpad_rows(&data, 1, 2, 8, true), [3, 2, 1, 4, 7, 6, 5, 8]);
+    }
+}
@@ -6,22 +6,40 @@
 //! This crate provides:
 //! - Core rendering infrastructure and pipeline management
 //! - UI rendering capabilities for immediate-mode interfaces
+//! - Sprite rendering with sorting layers, texture batching and atlases
+//! - 2D and 3D cameras with viewports for split-screen and minimaps
+//! - A render graph of named passes with transient targets and post-processing effects
+//! - WGSL shader assets with `#include`/`#define` preprocessing, validation and hot reload
+//! - Materials pairing a shader with uniform parameters and textures
 //! - Text rendering with font management
 //! - Texture and resource management
 //! - Cross-platform window and surface management
+//! - Headless offscreen rendering with pixel readback and golden-image comparison
+//!
+//! Everything that needs the GPU sits behind the default `gpu` feature. With
+//! `default-features = false`, only the camera components, `Rect` and the
+//! configuration types are built, without pulling in wgpu.
 //!
 //! # Architecture
 //!
 //! The rendering system is designed with modularity in mind:
 //! - `Renderer`: Core rendering context and resource management
 //! - `UiRenderer`: Specialized UI rendering with batching and clipping
+//! - `SpriteRenderer`: Layered, texture-batched 2D sprites
+//! - `Camera2D` / `Camera3D`: ECS camera components extracted into `CameraView`s
+//! - `RenderGraph`: Orders passes by the targets they read and write
+//! - `FullscreenEffect`: Bloom, colour grading, vignette, CRT and custom post passes
 //! - `TextRenderer`: Font-based text rendering
+//! - `ShaderManager`: Preprocessed, validated and hot-reloaded WGSL shaders
+//! - `MaterialManager`: Materials that sprites reference by `MaterialHandle`
 //! - `Pipeline`: Shader pipeline management
 //! - `Buffer`: GPU buffer management and utilities
 //!
 //! # Usage
 //!
 //! ```rust,no_run
+//! # #[cfg(feature = "gpu")]
+//! # mod example {
 //! use lumina_render::{Renderer, RenderConfig};
 //!
 //! async fn setup_renderer() -> Result<Renderer, Box<dyn std::error::Error>> {
@@ -29,37 +47,92 @@
 //!     let renderer = Renderer::new(config).await?;
 //!     Ok(renderer)
 //! }
+//! # }
 //! ```
 
 #![warn(missing_docs)]
 
+#[cfg(feature = "gpu")]
 pub mod renderer;
+pub mod camera;
+#[cfg(feature = "gpu")]
+pub mod render_graph;
+#[cfg(feature = "gpu")]
+pub mod post;
+#[cfg(feature = "gpu")]
+pub mod headless;
+#[cfg(feature = "gpu")]
+pub mod golden;
+#[cfg(feature = "gpu")]
 pub mod ui;
+#[cfg(feature = "gpu")]
+pub mod sprite;
+#[cfg(feature = "gpu")]
+pub mod shader;
+#[cfg(feature = "gpu")]
+pub mod material;
+#[cfg(feature = "gpu")]
 pub mod text;
+#[cfg(feature = "gpu")]
 pub mod pipeline;
+#[cfg(feature = "gpu")]
 pub mod buffer;
+#[cfg(feature = "gpu")]
 pub mod texture;
 pub mod error;
+#[cfg(feature = "gpu")]
 pub mod window;
 
 // Re-export commonly used types
+#[cfg(feature = "gpu")]
 pub use renderer::*;
-pub use ui::{UiRenderer, UiVertex, UiUniforms, DrawCommand, FontHandle};
+pub use camera::*;
+#[cfg(feature = "gpu")]
+pub use render_graph::*;
+#[cfg(feature = "gpu")]
+pub use post::*;
+#[cfg(feature = "gpu")]
+pub use headless::*;
+#[cfg(feature = "gpu")]
+pub use golden::*;
+#[cfg(feature = "gpu")]
+pub use ui::{
+    UiRenderer, UiVertex, UiUniforms, ShapeVertex, DrawCommand, FontHandle, CornerRadii, RectBorder,
+    RectShadow, RectStyle,
+};
+#[cfg(feature = "gpu")]
+pub use sprite::*;
+#[cfg(feature = "gpu")]
+pub use shader::*;
+#[cfg(feature = "gpu")]
+pub use material::*;
+#[cfg(feature = "gpu")]
 pub use text::*;
+#[cfg(feature = "gpu")]
 pub use pipeline::*;
+#[cfg(feature = "gpu")]
 pub use buffer::*;
-pub use texture::{TextureManager, Texture};
+#[cfg(feature = "gpu")]
+pub use texture::{
+    decode_image, generate_mipmaps, mip_level_count, ColorSpace, SamplerOptions, Texture, TextureManager,
+    TextureOptions, TextureRef,
+};
 pub use error::*;
+#[cfg(feature = "gpu")]
 pub use window::*;
 
 // Re-export texture handle from texture module to avoid conflicts
+#[cfg(feature = "gpu")]
 pub use texture::TextureHandle;
 
 // Re-export important external types
+#[cfg(feature = "gpu")]
 pub use wgpu;
+#[cfg(feature = "gpu")]
 pub use winit;
 pub use glam::{Vec2, Vec3, Vec4, Mat4};
 
+use lumina_ecs::Reflect;
 use serde::{Deserialize, Serialize};
 
 /// Configuration for the rendering system
@@ -132,7 +205,7 @@ impl Default for WindowConfig {
 }
 
 /// Rectangle representing screen coordinates or texture regions
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Reflect)]
 pub struct Rect {
     /// Position of the rectangle
     pub position: Vec2,
@@ -192,4 +265,16 @@ impl Rect {
     pub fn shrink(&self, amount: f32) -> Self {
         self.expand(-amount)
     }
+
+    /// The overlapping area of two rectangles, or `None` if they don't overlap
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let min = self.min().max(other.min());
+        let max = self.max().min(other.max());
+        (min.x < max.x && min.y < max.y).then(|| Rect::from_min_max(min, max))
+    }
+
+    /// Check if two rectangles overlap by a non-zero area
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.intersection(other).is_some()
+    }
 }
@@ -0,0 +1,323 @@
+//! Materials: a shader with uniform parameters and textures
+//!
+//! A material's parameters are packed, in order, into one uniform buffer laid out
+//! like the WGSL struct returned by `Material::wgsl_struct`. Sprite materials bind
+//! it at `@group(2) @binding(0)` and their textures from `@binding(1)` on, in the
+//! order they were added; see `SPRITE_INCLUDE` for the rest of the interface.
+
+use crate::shader::ShaderHandle;
+use crate::texture::TextureRef;
+use crate::{RenderError, RenderResult};
+use glam::{Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+/// Value of a material parameter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MaterialValue {
+    /// `f32`
+    Float(f32),
+    /// `vec2<f32>`
+    Vec2(Vec2),
+    /// `vec3<f32>`
+    Vec3(Vec3),
+    /// `vec4<f32>`
+    Vec4(Vec4),
+    /// `vec4<f32>` edited as an RGBA color
+    Color(Vec4),
+}
+
+impl MaterialValue {
+    /// WGSL type of the value
+    pub fn wgsl_type(&self) -> &'static str {
+        match self {
+            MaterialValue::Float(_) => "f32",
+            MaterialValue::Vec2(_) => "vec2<f32>",
+            MaterialValue::Vec3(_) => "vec3<f32>",
+            MaterialValue::Vec4(_) | MaterialValue::Color(_) => "vec4<f32>",
+        }
+    }
+
+    /// Whether two values have the same variant
+    pub fn same_type(&self, other: &MaterialValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Alignment in a uniform buffer; a `vec3` is aligned like a `vec4` but only 12 bytes long
+    fn alignment(&self) -> usize {
+        match self {
+            MaterialValue::Float(_) => 4,
+            MaterialValue::Vec2(_) => 8,
+            MaterialValue::Vec3(_) | MaterialValue::Vec4(_) | MaterialValue::Color(_) => 16,
+        }
+    }
+
+    fn components(&self) -> Vec<f32> {
+        match *self {
+            MaterialValue::Float(value) => vec![value],
+            MaterialValue::Vec2(value) => value.to_array().to_vec(),
+            MaterialValue::Vec3(value) => value.to_array().to_vec(),
+            MaterialValue::Vec4(value) | MaterialValue::Color(value) => value.to_array().to_vec(),
+        }
+    }
+}
+
+impl From<f32> for MaterialValue {
+    fn from(value: f32) -> Self {
+        MaterialValue::Float(value)
+    }
+}
+
+impl From<Vec2> for MaterialValue {
+    fn from(value: Vec2) -> Self {
+        MaterialValue::Vec2(value)
+    }
+}
+
+impl From<Vec3> for MaterialValue {
+    fn from(value: Vec3) -> Self {
+        MaterialValue::Vec3(value)
+    }
+}
+
+impl From<Vec4> for MaterialValue {
+    fn from(value: Vec4) -> Self {
+        MaterialValue::Vec4(value)
+    }
+}
+
+/// A named uniform parameter of a material
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialParam {
+    /// Field name in the uniform struct
+    pub name: String,
+    /// Current value
+    pub value: MaterialValue,
+}
+
+/// A shader together with the parameters and textures it is drawn with
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    /// Name shown in editors
+    pub name: String,
+    /// Shader providing `fs_main`
+    pub shader: ShaderHandle,
+    params: Vec<MaterialParam>,
+    textures: Vec<(String, TextureRef)>,
+}
+
+impl Material {
+    /// Create a material without parameters or textures
+    pub fn new(name: impl Into<String>, shader: ShaderHandle) -> Self {
+        Self {
+            name: name.into(),
+            shader,
+            params: Vec::new(),
+            textures: Vec::new(),
+        }
+    }
+
+    /// Add a uniform parameter after the existing ones, or replace one with the same name
+    pub fn with_param(mut self, name: impl Into<String>, value: impl Into<MaterialValue>) -> Self {
+        let (name, value) = (name.into(), value.into());
+        match self.params.iter_mut().find(|param| param.name == name) {
+            Some(param) => param.value = value,
+            None => self.params.push(MaterialParam { name, value }),
+        }
+        self
+    }
+
+    /// Add a texture after the existing ones, or replace one with the same name
+    pub fn with_texture(mut self, name: impl Into<String>, texture: TextureRef) -> Self {
+        self.set_texture(name, texture);
+        self
+    }
+
+    /// Uniform parameters in buffer order
+    pub fn params(&self) -> &[MaterialParam] {
+        &self.params
+    }
+
+    /// Value of a parameter
+    pub fn param(&self, name: &str) -> Option<MaterialValue> {
+        self.params.iter().find(|param| param.name == name).map(|param| param.value)
+    }
+
+    /// Change a parameter's value; its type can't change, as the shader depends on it
+    pub fn set_param(&mut self, name: &str, value: impl Into<MaterialValue>) -> RenderResult<()> {
+        let value = value.into();
+        let param = self
+            .params
+            .iter_mut()
+            .find(|param| param.name == name)
+            .ok_or_else(|| RenderError::InvalidOperation(format!("material {} has no parameter {}", self.name, name)))?;
+        if !param.value.same_type(&value) {
+            return Err(RenderError::InvalidOperation(format!(
+                "material parameter {} is a {}, not a {}",
+                name,
+                param.value.wgsl_type(),
+                value.wgsl_type()
+            )));
+        }
+        param.value = value;
+        Ok(())
+    }
+
+    /// Textures in binding order
+    pub fn textures(&self) -> &[(String, TextureRef)] {
+        &self.textures
+    }
+
+    /// Replace the texture with this name, or add it after the existing ones
+    pub fn set_texture(&mut self, name: impl Into<String>, texture: TextureRef) {
+        let name = name.into();
+        match self.textures.iter_mut().find(|(existing, _)| *existing == name) {
+            Some(slot) => slot.1 = texture,
+            None => self.textures.push((name, texture)),
+        }
+    }
+
+    /// The parameters packed with WGSL uniform buffer layout rules
+    pub fn uniform_data(&self) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::new();
+        for param in &self.params {
+            data.resize(data.len().next_multiple_of(param.value.alignment()), 0);
+            data.extend_from_slice(bytemuck::cast_slice(&param.value.components()));
+        }
+        // Buffers bound as uniforms can't be empty, and 16 bytes is a safe size for any struct
+        data.resize(data.len().next_multiple_of(16).max(16), 0);
+        data
+    }
+
+    /// WGSL declaration of the uniform struct matching `uniform_data`
+    pub fn wgsl_struct(&self, name: &str) -> String {
+        let mut wgsl = format!("struct {name} {{\n");
+        for param in &self.params {
+            wgsl.push_str(&format!("    {}: {},\n", param.name, param.value.wgsl_type()));
+        }
+        if self.params.is_empty() {
+            wgsl.push_str("    _unused: vec4<f32>,\n");
+        }
+        wgsl.push_str("}\n");
+        wgsl
+    }
+}
+
+/// Handle to a material in a `MaterialManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialHandle(pub u32);
+
+/// Owns materials and tracks edits to them, so renderers know when to re-upload
+#[derive(Debug, Default)]
+pub struct MaterialManager {
+    materials: Vec<Option<(Material, u32)>>,
+}
+
+impl MaterialManager {
+    /// Create an empty material manager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a material
+    pub fn add(&mut self, material: Material) -> MaterialHandle {
+        self.materials.push(Some((material, 0)));
+        MaterialHandle((self.materials.len() - 1) as u32)
+    }
+
+    /// Get a material
+    pub fn get(&self, handle: MaterialHandle) -> Option<&Material> {
+        self.materials.get(handle.0 as usize)?.as_ref().map(|(material, _)| material)
+    }
+
+    /// Get a material for editing, marking it changed
+    pub fn get_mut(&mut self, handle: MaterialHandle) -> Option<&mut Material> {
+        let (material, version) = self.materials.get_mut(handle.0 as usize)?.as_mut()?;
+        *version = version.wrapping_add(1);
+        Some(material)
+    }
+
+    /// Incremented by each `get_mut`
+    pub fn version(&self, handle: MaterialHandle) -> Option<u32> {
+        self.materials.get(handle.0 as usize)?.as_ref().map(|(_, version)| *version)
+    }
+
+    /// Remove a material, returning it
+    pub fn remove(&mut self, handle: MaterialHandle) -> Option<Material> {
+        self.materials.get_mut(handle.0 as usize)?.take().map(|(material, _)| material)
+    }
+
+    /// All materials with their handles
+    pub fn iter(&self) -> impl Iterator<Item = (MaterialHandle, &Material)> {
+        self.materials.iter().enumerate().filter_map(|(index, slot)| {
+            slot.as_ref().map(|(material, _)| (MaterialHandle(index as u32), material))
+        })
+    }
+
+    /// Number of materials
+    pub fn len(&self) -> usize {
+        self.materials.iter().flatten().count()
+    }
+
+    /// Whether there are no materials
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::{ShaderDefines, ShaderManager};
+
+    #[test]
+    fn material_uniforms_follow_wgsl_layout() {
+        let material = Material::new("glow", ShaderHandle(0))
+            .with_param("strength", 2.0)
+            .with_param("offset", Vec3::new(1.0, 2.0, 3.0))
+            .with_param("speed", 4.0)
+            .with_param("tint", MaterialValue::Color(Vec4::ONE));
+
+        let data: Vec<f32> = bytemuck::pod_collect_to_vec(&material.uniform_data());
+        // strength, padding to the vec3, offset with speed packed into its last 4 bytes, tint
+        assert_eq!(data, [2.0, 0.0, 0.0, 0.0, 1.0, 2.0, 3.0, 4.0, 1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(Material::new("empty", ShaderHandle(0)).uniform_data().len(), 16);
+
+        let mut materials = MaterialManager::new();
+        let handle = materials.add(material);
+        assert_eq!(materials.version(handle), Some(0));
+        let material = materials.get_mut(handle).unwrap();
+        material.set_param("speed", 8.0).unwrap();
+        assert!(material.set_param("speed", Vec2::ONE).is_err());
+        assert!(material.set_param("missing", 1.0).is_err());
+        assert_eq!(materials.version(handle), Some(1));
+        assert_eq!(materials.get(handle).unwrap().param("speed"), Some(MaterialValue::Float(8.0)));
+    }
+
+    #[test]
+    fn sprite_material_shader_validates() {
+        let material = Material::new("dissolve", ShaderHandle(0))
+            .with_param("tint", MaterialValue::Color(Vec4::ONE))
+            .with_param("threshold", 0.5)
+            .with_texture("noise", TextureRef::untracked(crate::TextureHandle(0)));
+        let source = format!(
+            "#include \"{}\"\n{}\n{}",
+            crate::shader::SPRITE_INCLUDE,
+            material.wgsl_struct("Dissolve"),
+            "@group(2) @binding(0) var<uniform> material: Dissolve;
+@group(2) @binding(1) var noise: texture_2d<f32>;
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(texture_data, texture_sampler, input.tex_coords) * input.color;
+    if (textureSample(noise, texture_sampler, input.tex_coords).r < material.threshold) {
+        discard;
+    }
+    return color * material.tint;
+}
+"
+        );
+
+        let mut shaders = ShaderManager::new();
+        shaders.add("dissolve.wgsl", source, ShaderDefines::new()).unwrap();
+    }
+}
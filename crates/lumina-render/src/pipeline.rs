@@ -13,7 +13,12 @@ pub struct Pipeline {
 }
 
 impl Pipeline {
-    /// Create a new graphics pipeline
+    /// Create a new graphics pipeline drawing into render passes with `sample_count`
+    /// samples per pixel (1 without MSAA).
+    ///
+    /// `shader` is usually taken from `ShaderManager::module`, so it has been
+    /// preprocessed and validated.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         device: &wgpu::Device,
         layout: wgpu::PipelineLayout,
@@ -22,6 +27,7 @@ impl Pipeline {
         fragment_entry: &str,
         vertex_buffers: &[wgpu::VertexBufferLayout],
         targets: &[Option<wgpu::ColorTargetState>],
+        sample_count: u32,
     ) -> RenderResult<Self> {
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("Pipeline"),
@@ -47,7 +53,7 @@ impl Pipeline {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -0,0 +1,523 @@
+//! Post-processing effects
+//!
+//! A `FullscreenEffect` is a render graph pass drawing one fullscreen triangle
+//! that samples the pass's inputs. Every built-in effect is one, and custom
+//! effects are written the same way: a WGSL fragment shader appended to
+//! `POST_PRELUDE`, reading its inputs as `input0`, `input1`, ...
+
+use crate::render_graph::{RenderContext, RenderGraph, RenderNode, TargetDesc};
+use crate::shader::{PreprocessedShader, ShaderDefines, ShaderManager, POST_INCLUDE};
+use crate::{RenderError, RenderResult};
+use bytemuck::{Pod, Zeroable};
+use glam::Vec4;
+use image::RgbaImage;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// WGSL shared by every fullscreen effect: `post_sampler`, the `post` uniforms,
+/// the `vs_fullscreen` vertex shader and sRGB conversion helpers
+pub const POST_PRELUDE: &str = include_str!("shaders/post/prelude.wgsl");
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct PostUniforms {
+    resolution: [f32; 2],
+    texel_size: [f32; 2],
+    params: [[f32; 4]; 2],
+    options: [f32; 4],
+}
+
+/// Parameters of an effect, shared with the pass so they can change while it is in a graph
+#[derive(Debug, Clone, Default)]
+pub struct EffectParams(Arc<Mutex<[Vec4; 2]>>);
+
+impl EffectParams {
+    /// Parameters with the given initial values
+    pub fn new(params0: Vec4, params1: Vec4) -> Self {
+        Self(Arc::new(Mutex::new([params0, params1])))
+    }
+
+    /// Read `params0` or `params1`
+    pub fn get(&self, index: usize) -> Vec4 {
+        self.0.lock()[index]
+    }
+
+    /// Replace `params0` or `params1`
+    pub fn set(&self, index: usize, value: Vec4) {
+        self.0.lock()[index] = value;
+    }
+}
+
+/// GPU objects created the first time an effect runs
+struct EffectGpu {
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    /// Views of the effect's own textures, bound after the graph inputs
+    texture_views: Vec<wgpu::TextureView>,
+    /// Pipelines by output format and sample count
+    pipelines: HashMap<(wgpu::TextureFormat, u32), wgpu::RenderPipeline>,
+}
+
+/// A render graph pass drawing a fullscreen fragment shader over its first output
+pub struct FullscreenEffect {
+    label: String,
+    source: String,
+    entry_point: String,
+    inputs: usize,
+    params: EffectParams,
+    /// Extra textures bound by name after the inputs, uploaded on first run
+    textures: Vec<(String, RgbaImage)>,
+    gpu: Option<EffectGpu>,
+    /// Set when the shader failed to build, so it isn't retried every frame
+    failed: bool,
+}
+
+impl FullscreenEffect {
+    /// An effect running `source`'s `fs_main` over `inputs` graph inputs.
+    ///
+    /// `source` is appended to `POST_PRELUDE` and may read `input0` up to
+    /// `input{inputs - 1}` with `post_sampler`, and its parameters as `post.params0`
+    /// and `post.params1`.
+    pub fn new(label: impl Into<String>, source: impl Into<String>, inputs: usize) -> Self {
+        Self {
+            label: label.into(),
+            source: source.into(),
+            entry_point: "fs_main".to_string(),
+            inputs,
+            params: EffectParams::default(),
+            textures: Vec::new(),
+            gpu: None,
+            failed: false,
+        }
+    }
+
+    /// Use a fragment entry point other than `fs_main`
+    pub fn with_entry_point(mut self, entry_point: impl Into<String>) -> Self {
+        self.entry_point = entry_point.into();
+        self
+    }
+
+    /// Set the initial parameters
+    pub fn with_params(self, params0: Vec4, params1: Vec4) -> Self {
+        self.params.set(0, params0);
+        self.params.set(1, params1);
+        self
+    }
+
+    /// Bind `image` as a `texture_2d<f32>` called `name`, after the inputs
+    pub fn with_texture(mut self, name: impl Into<String>, image: RgbaImage) -> Self {
+        self.textures.push((name.into(), image));
+        self
+    }
+
+    /// Handle for changing the parameters after the effect was added to a graph
+    pub fn params(&self) -> EffectParams {
+        self.params.clone()
+    }
+
+    /// The complete WGSL module, preprocessed and validated: prelude, texture declarations
+    /// and the effect source. Errors in the effect source are reported as `<label>.wgsl:line:column`.
+    pub fn shader(&self) -> RenderResult<PreprocessedShader> {
+        let file = format!("{}.wgsl", self.label);
+        let mut shaders = ShaderManager::new();
+        shaders.add_include(file.as_str(), self.source.as_str());
+
+        let mut source = format!("#include \"{POST_INCLUDE}\"\n");
+        let names = (0..self.inputs)
+            .map(|index| format!("input{index}"))
+            .chain(self.textures.iter().map(|(name, _)| name.clone()));
+        for (binding, name) in names.enumerate() {
+            source.push_str(&format!("@group(1) @binding({binding})\nvar {name}: texture_2d<f32>;\n"));
+        }
+        source.push_str(&format!("#include \"{file}\"\n"));
+        shaders.preprocess(self.label.as_str(), source, ShaderDefines::new())
+    }
+
+    /// Darkens the corners by `intensity`, starting `radius` from the centre (1.0 being
+    /// the corners) over `softness`
+    pub fn vignette(intensity: f32, radius: f32, softness: f32) -> Self {
+        Self::new("Vignette", include_str!("shaders/post/vignette.wgsl"), 1)
+            .with_params(Vec4::new(intensity, radius, softness, 0.0), Vec4::ZERO)
+    }
+
+    /// Snaps the image to cells of `pixel_size` output pixels
+    pub fn pixelate(pixel_size: f32) -> Self {
+        Self::new("Pixelate", include_str!("shaders/post/pixelate.wgsl"), 1)
+            .with_params(Vec4::new(pixel_size, 0.0, 0.0, 0.0), Vec4::ZERO)
+    }
+
+    /// Curved-screen CRT look with scanlines and colour fringes `fringe` pixels wide
+    pub fn crt(curvature: f32, scanline_intensity: f32, fringe: f32) -> Self {
+        Self::new("CRT", include_str!("shaders/post/crt.wgsl"), 1)
+            .with_params(Vec4::new(curvature, scanline_intensity, fringe, 0.0), Vec4::ZERO)
+    }
+
+    /// Colour grading through a lookup table: `size` slices of `size`x`size` texels laid out
+    /// left to right, blue selecting the slice (see `identity_lut`). `strength` blends
+    /// between the original and the graded colour.
+    pub fn color_grading(lut: RgbaImage, strength: f32) -> RenderResult<Self> {
+        let size = lut.height();
+        if size < 2 || lut.width() != size * size {
+            return Err(RenderError::TextureLoad(format!(
+                "colour grading LUT must be size² x size texels, got {}x{}",
+                lut.width(),
+                lut.height()
+            )));
+        }
+        Ok(Self::new("Color Grading", include_str!("shaders/post/color_grading.wgsl"), 1)
+            .with_texture("lut", lut)
+            .with_params(Vec4::new(size as f32, strength, 0.0, 0.0), Vec4::ZERO))
+    }
+
+    fn create_gpu(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> RenderResult<EffectGpu> {
+        let shader = self.shader()?.create_module(device);
+
+        let uniform_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Uniform Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let texture_entries: Vec<_> = (0..self.inputs + self.textures.len())
+            .map(|binding| wgpu::BindGroupLayoutEntry {
+                binding: binding as u32,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                },
+                count: None,
+            })
+            .collect();
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Texture Bind Group Layout"),
+            entries: &texture_entries,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Pipeline Layout"),
+            bind_group_layouts: &[&uniform_layout, &texture_layout],
+            push_constant_ranges: &[],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Uniform Buffer"),
+            size: std::mem::size_of::<PostUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Uniform Bind Group"),
+            layout: &uniform_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // Lookup tables hold data, not colours, so they are not sRGB-decoded
+        let texture_views = self
+            .textures
+            .iter()
+            .map(|(name, image)| {
+                let size = wgpu::Extent3d {
+                    width: image.width(),
+                    height: image.height(),
+                    depth_or_array_layers: 1,
+                };
+                let texture = device.create_texture(&wgpu::TextureDescriptor {
+                    label: Some(name),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                });
+                queue.write_texture(
+                    texture.as_image_copy(),
+                    image.as_raw(),
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * image.width()),
+                        rows_per_image: Some(image.height()),
+                    },
+                    size,
+                );
+                texture.create_view(&wgpu::TextureViewDescriptor::default())
+            })
+            .collect();
+
+        Ok(EffectGpu {
+            shader,
+            pipeline_layout,
+            texture_layout,
+            uniform_buffer,
+            uniform_bind_group,
+            texture_views,
+            pipelines: HashMap::new(),
+        })
+    }
+}
+
+impl EffectGpu {
+    fn pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        label: &str,
+        entry_point: &str,
+        format: wgpu::TextureFormat,
+        multisample: wgpu::MultisampleState,
+    ) -> &wgpu::RenderPipeline {
+        self.pipelines.entry((format, multisample.count)).or_insert_with(|| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&self.pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &self.shader,
+                    entry_point: "vs_fullscreen",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &self.shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample,
+                multiview: None,
+            })
+        })
+    }
+}
+
+impl RenderNode for FullscreenEffect {
+    fn run(&mut self, context: &mut RenderContext<'_>) {
+        if self.gpu.is_none() {
+            if self.failed {
+                return;
+            }
+            match self.create_gpu(context.device, context.queue) {
+                Ok(gpu) => self.gpu = Some(gpu),
+                Err(err) => {
+                    log::error!("Failed to build post effect {}: {}", self.label, err);
+                    self.failed = true;
+                    return;
+                }
+            }
+        }
+        let gpu = self.gpu.as_mut().expect("effect GPU objects were just created");
+
+        let output = context.output(0);
+        let first_input = (self.inputs > 0).then(|| context.input(0));
+        let params = *self.params.0.lock();
+        let uniforms = PostUniforms {
+            resolution: output.size.as_vec2().to_array(),
+            texel_size: (1.0 / first_input.map_or(output.size, |input| input.size).as_vec2()).to_array(),
+            params: [params[0].to_array(), params[1].to_array()],
+            options: [first_input.map_or(0.0, |input| input.format.is_srgb() as u32 as f32), 0.0, 0.0, 0.0],
+        };
+        context.queue.write_buffer(&gpu.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+
+        let views: Vec<_> = (0..self.inputs)
+            .map(|index| context.input(index).view)
+            .chain(gpu.texture_views.iter())
+            .collect();
+        let entries: Vec<_> = views
+            .iter()
+            .enumerate()
+            .map(|(binding, view)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            })
+            .collect();
+        let texture_bind_group = context.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Texture Bind Group"),
+            layout: &gpu.texture_layout,
+            entries: &entries,
+        });
+
+        let multisample = context.multisample_state();
+        gpu.pipeline(context.device, &self.label, &self.entry_point, output.format, multisample);
+        let pipeline = &gpu.pipelines[&(output.format, multisample.count)];
+        let mut render_pass = context.begin_render_pass(&self.label, wgpu::Color::BLACK);
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &gpu.uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, &texture_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// A lookup table that leaves colours unchanged, as a starting point for grading in an
+/// image editor
+pub fn identity_lut(size: u32) -> RgbaImage {
+    let scale = 255.0 / (size.max(2) - 1) as f32;
+    let channel = |c: u32| (c as f32 * scale).round() as u8;
+    RgbaImage::from_fn(size * size, size, |x, y| {
+        image::Rgba([channel(x % size), channel(y), channel(x / size), 255])
+    })
+}
+
+/// Settings for `add_bloom`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    /// Brightness above which pixels bloom
+    pub threshold: f32,
+    /// Width of the soft ramp around the threshold
+    pub knee: f32,
+    /// How strongly the blurred highlights are added back
+    pub intensity: f32,
+    /// Resolution of the blur targets relative to the surface
+    pub resolution_scale: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            knee: 0.2,
+            intensity: 0.7,
+            resolution_scale: 0.5,
+        }
+    }
+}
+
+/// Add bloom from `input` to `output`: a threshold pass, a separable blur at reduced
+/// resolution and a composite. Passes and targets are prefixed with `name`.
+pub fn add_bloom(graph: &mut RenderGraph, name: &str, input: &str, output: &str, settings: BloomSettings) {
+    let source = include_str!("shaders/post/bloom.wgsl");
+    let bright = format!("{name}.bright");
+    let blur_x = format!("{name}.blur_x");
+    let blur_y = format!("{name}.blur_y");
+    let half = TargetDesc::scaled(settings.resolution_scale);
+
+    graph
+        .add_target(bright.as_str(), half)
+        .add_target(blur_x.as_str(), half)
+        .add_target(blur_y.as_str(), half)
+        .add_pass(
+            format!("{name}.threshold"),
+            &[input],
+            &[&bright],
+            FullscreenEffect::new("Bloom Threshold", source, 1)
+                .with_entry_point("fs_threshold")
+                .with_params(Vec4::new(settings.threshold, settings.knee, 0.0, 0.0), Vec4::ZERO),
+        )
+        .add_pass(
+            format!("{name}.blur_x"),
+            &[&bright],
+            &[&blur_x],
+            FullscreenEffect::new("Bloom Blur", source, 1)
+                .with_entry_point("fs_blur")
+                .with_params(Vec4::new(1.0, 0.0, 0.0, 0.0), Vec4::ZERO),
+        )
+        .add_pass(
+            format!("{name}.blur_y"),
+            &[&blur_x],
+            &[&blur_y],
+            FullscreenEffect::new("Bloom Blur", source, 1)
+                .with_entry_point("fs_blur")
+                .with_params(Vec4::new(0.0, 1.0, 0.0, 0.0), Vec4::ZERO),
+        )
+        .add_pass(
+            format!("{name}.composite"),
+            &[input, &blur_y],
+            &[output],
+            FullscreenEffect::new("Bloom Composite", source, 2)
+                .with_entry_point("fs_composite")
+                .with_params(Vec4::new(settings.intensity, 0.0, 0.0, 0.0), Vec4::ZERO),
+        );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_lut_and_bloom_passes() {
+        let lut = identity_lut(4);
+        assert_eq!(lut.dimensions(), (16, 4));
+        // Slice 2 (blue 170), column 1 (red 85), row 3 (green 255)
+        assert_eq!(lut.get_pixel(2 * 4 + 1, 3).0, [85, 255, 170, 255]);
+        assert!(FullscreenEffect::color_grading(lut, 1.0).is_ok());
+        assert!(FullscreenEffect::color_grading(RgbaImage::new(8, 8), 1.0).is_err());
+
+        let grading = FullscreenEffect::color_grading(identity_lut(2), 1.0).unwrap();
+        let shader = grading.shader().unwrap();
+        assert!(shader.source.contains("@group(1) @binding(0)\nvar input0: texture_2d<f32>;"));
+        assert!(shader.source.contains("@group(1) @binding(1)\nvar lut: texture_2d<f32>;"));
+
+        let mut graph = RenderGraph::new();
+        graph
+            .add_target("scene", TargetDesc::FULL)
+            .add_pass("sprites", &[], &["scene"], |_: &mut RenderContext<'_>| {});
+        add_bloom(&mut graph, "bloom", "scene", RenderGraph::SURFACE, BloomSettings::default());
+        assert_eq!(
+            graph.execution_order().unwrap(),
+            ["sprites", "bloom.threshold", "bloom.blur_x", "bloom.blur_y", "bloom.composite"]
+        );
+    }
+
+    #[test]
+    fn effect_shaders_are_validated_with_source_lines() {
+        for effect in [
+            FullscreenEffect::vignette(0.5, 0.8, 0.3),
+            FullscreenEffect::pixelate(4.0),
+            FullscreenEffect::crt(0.1, 0.3, 1.0),
+            FullscreenEffect::new("Bloom", include_str!("shaders/post/bloom.wgsl"), 2),
+        ] {
+            if let Err(err) = effect.shader() {
+                panic!("{err}");
+            }
+        }
+
+        let broken = "@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return missing;\n}\n";
+        let err = FullscreenEffect::new("Broken", broken, 1).shader().unwrap_err();
+        assert!(err.to_string().contains("Broken.wgsl:3:12"), "{err}");
+    }
+}
@@ -0,0 +1,648 @@
+//! Render graph
+//!
+//! A frame is described as named passes that read and write named render
+//! targets. Compiling the graph orders the passes so every target is written
+//! before it is read, drops passes whose output never reaches the surface, and
+//! works out how long each transient target lives so targets whose lifetimes
+//! don't overlap share one pooled texture.
+//!
+//! Passes are `RenderNode`s: the built-in post effects in `post`, or any
+//! closure taking a `RenderContext`.
+//!
+//! With MSAA every target written by a pass has a multisampled companion texture.
+//! Passes draw into it and it is resolved into the target at the end of each pass, so
+//! later passes sample the resolved texture as usual.
+
+use crate::{RenderError, RenderResult};
+use glam::UVec2;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+/// Size of a transient render target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetSize {
+    /// Same size as the surface
+    Full,
+    /// The surface size multiplied by a factor, e.g. 0.5 for half resolution
+    Scaled(f32),
+    /// A fixed size in pixels
+    Fixed(u32, u32),
+}
+
+impl TargetSize {
+    /// Size in pixels for a surface of `surface_size`, at least 1x1
+    pub fn resolve(&self, surface_size: UVec2) -> UVec2 {
+        match *self {
+            TargetSize::Full => surface_size,
+            TargetSize::Scaled(factor) => (surface_size.as_vec2() * factor).round().as_uvec2(),
+            TargetSize::Fixed(width, height) => UVec2::new(width, height),
+        }
+        .max(UVec2::ONE)
+    }
+}
+
+/// Description of a transient render target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetDesc {
+    /// Texture format; `None` uses the surface format
+    pub format: Option<wgpu::TextureFormat>,
+    /// Texture size
+    pub size: TargetSize,
+}
+
+impl TargetDesc {
+    /// A surface-sized target in the surface format
+    pub const FULL: TargetDesc = TargetDesc { format: None, size: TargetSize::Full };
+
+    /// A target in the surface format scaled relative to the surface
+    pub fn scaled(factor: f32) -> Self {
+        Self { format: None, size: TargetSize::Scaled(factor) }
+    }
+
+    /// Use a specific texture format, e.g. `Rgba16Float` for HDR
+    pub fn with_format(mut self, format: wgpu::TextureFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+}
+
+impl Default for TargetDesc {
+    fn default() -> Self {
+        Self::FULL
+    }
+}
+
+/// A render target as seen by a running pass
+#[derive(Debug, Clone, Copy)]
+pub struct TargetView<'a> {
+    /// View to render into or sample from
+    pub view: &'a wgpu::TextureView,
+    /// Texture format
+    pub format: wgpu::TextureFormat,
+    /// Size in pixels
+    pub size: UVec2,
+}
+
+/// Everything a pass needs while it runs
+pub struct RenderContext<'a> {
+    /// Device, for creating pipelines and bind groups
+    pub device: &'a wgpu::Device,
+    /// Queue, for uploading uniforms
+    pub queue: &'a wgpu::Queue,
+    /// Encoder shared by every pass in the frame
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    inputs: Vec<TargetView<'a>>,
+    outputs: Vec<TargetView<'a>>,
+    /// Multisampled texture drawn into for each output when `sample_count > 1`
+    msaa_outputs: Vec<Option<&'a wgpu::TextureView>>,
+    sample_count: u32,
+    /// Whether each output is written for the first time this frame
+    first_writes: Vec<bool>,
+}
+
+impl<'a> RenderContext<'a> {
+    /// The `index`th declared input
+    pub fn input(&self, index: usize) -> TargetView<'a> {
+        self.inputs[index]
+    }
+
+    /// The `index`th declared output
+    pub fn output(&self, index: usize) -> TargetView<'a> {
+        self.outputs[index]
+    }
+
+    /// Number of declared inputs
+    pub fn input_count(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Samples per pixel of the attachments returned by `color_attachment`
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Multisample state for pipelines drawing into this pass's outputs
+    pub fn multisample_state(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        }
+    }
+
+    /// Color attachment for output `index`: cleared to `clear` if no earlier pass wrote
+    /// it this frame, otherwise loaded so this pass draws on top. With MSAA this draws into
+    /// the output's multisampled texture and resolves into the output.
+    pub fn color_attachment(&self, index: usize, clear: wgpu::Color) -> wgpu::RenderPassColorAttachment<'a> {
+        let load = if self.first_writes[index] { wgpu::LoadOp::Clear(clear) } else { wgpu::LoadOp::Load };
+        let output = self.outputs[index].view;
+        let (view, resolve_target) = match self.msaa_outputs[index] {
+            Some(msaa_view) => (msaa_view, Some(output)),
+            None => (output, None),
+        };
+        wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: wgpu::Operations { load, store: wgpu::StoreOp::Store },
+        }
+    }
+
+    /// Begin a render pass drawing into every output, see `color_attachment`
+    pub fn begin_render_pass(&mut self, label: &str, clear: wgpu::Color) -> wgpu::RenderPass<'_> {
+        let attachments: Vec<_> = (0..self.outputs.len())
+            .map(|index| Some(self.color_attachment(index, clear)))
+            .collect();
+        self.encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &attachments,
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+}
+
+/// A pass in the render graph
+pub trait RenderNode: 'static {
+    /// Record the pass's commands into `context.encoder`
+    fn run(&mut self, context: &mut RenderContext<'_>);
+}
+
+impl<F: FnMut(&mut RenderContext<'_>) + 'static> RenderNode for F {
+    fn run(&mut self, context: &mut RenderContext<'_>) {
+        self(context)
+    }
+}
+
+struct PassEntry {
+    name: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    node: Box<dyn RenderNode>,
+}
+
+/// Execution plan produced by compiling a graph
+#[derive(Debug, Clone, PartialEq)]
+struct CompiledGraph {
+    /// Indices of the passes to run, in order
+    order: Vec<usize>,
+    /// Pooled texture slot of each transient target
+    slots: HashMap<String, usize>,
+    /// Description of each slot
+    slot_descs: Vec<TargetDesc>,
+    /// (pass, target) pairs where the pass is the first to write the target this frame
+    first_writes: HashSet<(usize, String)>,
+}
+
+struct PooledTexture {
+    format: wgpu::TextureFormat,
+    size: UVec2,
+    sample_count: u32,
+    view: wgpu::TextureView,
+    /// Multisampled companion resolved into `view`, when `sample_count > 1`
+    msaa_view: Option<wgpu::TextureView>,
+}
+
+impl PooledTexture {
+    fn matches(&self, format: wgpu::TextureFormat, size: UVec2, sample_count: u32) -> bool {
+        (self.format, self.size, self.sample_count) == (format, size, sample_count)
+    }
+}
+
+/// Named passes over named render targets, compiled into an ordered frame
+pub struct RenderGraph {
+    targets: HashMap<String, TargetDesc>,
+    passes: Vec<PassEntry>,
+    compiled: Option<CompiledGraph>,
+    pool: Vec<PooledTexture>,
+    /// Multisampled texture resolved into the surface
+    surface_msaa: Option<(wgpu::TextureFormat, UVec2, u32, wgpu::TextureView)>,
+}
+
+impl RenderGraph {
+    /// The target the graph presents: the swapchain texture or headless output
+    pub const SURFACE: &'static str = "surface";
+
+    /// Create an empty graph
+    pub fn new() -> Self {
+        Self {
+            targets: HashMap::new(),
+            passes: Vec::new(),
+            compiled: None,
+            pool: Vec::new(),
+            surface_msaa: None,
+        }
+    }
+
+    /// Declare a transient render target, replacing any target with the same name
+    pub fn add_target(&mut self, name: impl Into<String>, desc: TargetDesc) -> &mut Self {
+        self.targets.insert(name.into(), desc);
+        self.compiled = None;
+        self
+    }
+
+    /// Add a pass reading `inputs` and writing `outputs`.
+    ///
+    /// Passes writing the same target run in the order they were added, each drawing on
+    /// top of the last; passes reading a target run after all of its writers.
+    pub fn add_pass(
+        &mut self,
+        name: impl Into<String>,
+        inputs: &[&str],
+        outputs: &[&str],
+        node: impl RenderNode,
+    ) -> &mut Self {
+        self.passes.push(PassEntry {
+            name: name.into(),
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            outputs: outputs.iter().map(|s| s.to_string()).collect(),
+            node: Box::new(node),
+        });
+        self.compiled = None;
+        self
+    }
+
+    /// Remove a pass by name, returning whether it existed
+    pub fn remove_pass(&mut self, name: &str) -> bool {
+        let before = self.passes.len();
+        self.passes.retain(|pass| pass.name != name);
+        self.compiled = None;
+        self.passes.len() != before
+    }
+
+    /// Whether a pass with this name exists
+    pub fn has_pass(&self, name: &str) -> bool {
+        self.passes.iter().any(|pass| pass.name == name)
+    }
+
+    /// Names of the passes that run each frame, in execution order
+    pub fn execution_order(&mut self) -> RenderResult<Vec<&str>> {
+        self.compile()?;
+        let compiled = self.compiled.as_ref().expect("graph was just compiled");
+        Ok(compiled.order.iter().map(|&index| self.passes[index].name.as_str()).collect())
+    }
+
+    /// Number of pooled textures the compiled graph needs
+    pub fn texture_slot_count(&mut self) -> RenderResult<usize> {
+        self.compile()?;
+        Ok(self.compiled.as_ref().map_or(0, |compiled| compiled.slot_descs.len()))
+    }
+
+    /// Validate the graph and plan its execution, if it changed since the last compile
+    pub fn compile(&mut self) -> RenderResult<()> {
+        if self.compiled.is_none() {
+            self.compiled = Some(compile(&self.targets, &self.passes)?);
+        }
+        Ok(())
+    }
+
+    /// Record every pass into one command buffer and submit it, rendering the
+    /// `SURFACE` target into `surface`.
+    ///
+    /// Passes draw with `sample_count` samples per pixel, see `RenderContext::color_attachment`.
+    pub fn execute(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        surface: &wgpu::TextureView,
+        surface_format: wgpu::TextureFormat,
+        surface_size: UVec2,
+        sample_count: u32,
+    ) -> RenderResult<()> {
+        self.compile()?;
+        let compiled = self.compiled.as_ref().expect("graph was just compiled");
+        let sample_count = sample_count.max(1);
+
+        // Reuse pooled textures whose format, size and sample count still match
+        self.pool.truncate(compiled.slot_descs.len());
+        for (slot, desc) in compiled.slot_descs.iter().enumerate() {
+            let format = desc.format.unwrap_or(surface_format);
+            let size = desc.size.resolve(surface_size);
+            if !self.pool.get(slot).is_some_and(|texture| texture.matches(format, size, sample_count)) {
+                let texture = create_target(device, format, size, sample_count);
+                if slot < self.pool.len() {
+                    self.pool[slot] = texture;
+                } else {
+                    self.pool.push(texture);
+                }
+            }
+        }
+
+        let surface_key = (surface_format, surface_size, sample_count);
+        if sample_count == 1 {
+            self.surface_msaa = None;
+        } else if self.surface_msaa.as_ref().map(|&(format, size, count, _)| (format, size, count)) != Some(surface_key) {
+            let view = create_msaa_view(device, surface_format, surface_size, sample_count);
+            self.surface_msaa = Some((surface_format, surface_size, sample_count, view));
+        }
+
+        let surface_target = TargetView { view: surface, format: surface_format, size: surface_size };
+        let surface_msaa = self.surface_msaa.as_ref().map(|(_, _, _, view)| view);
+        let pool = &self.pool;
+        let target = |name: &str| -> (TargetView<'_>, Option<&wgpu::TextureView>) {
+            match compiled.slots.get(name) {
+                Some(&slot) => {
+                    let texture = &pool[slot];
+                    let view = TargetView { view: &texture.view, format: texture.format, size: texture.size };
+                    (view, texture.msaa_view.as_ref())
+                }
+                None => (surface_target, surface_msaa),
+            }
+        };
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Graph Encoder"),
+        });
+        for &index in &compiled.order {
+            let pass = &mut self.passes[index];
+            let mut context = RenderContext {
+                device,
+                queue,
+                encoder: &mut encoder,
+                inputs: pass.inputs.iter().map(|name| target(name).0).collect(),
+                outputs: pass.outputs.iter().map(|name| target(name).0).collect(),
+                msaa_outputs: pass.outputs.iter().map(|name| target(name).1).collect(),
+                sample_count,
+                first_writes: pass
+                    .outputs
+                    .iter()
+                    .map(|name| compiled.first_writes.contains(&(index, name.clone())))
+                    .collect(),
+            };
+            pass.node.run(&mut context);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn create_target(device: &wgpu::Device, format: wgpu::TextureFormat, size: UVec2, sample_count: u32) -> PooledTexture {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Render Graph Target"),
+        size: wgpu::Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let msaa_view = (sample_count > 1).then(|| create_msaa_view(device, format, size, sample_count));
+    PooledTexture { format, size, sample_count, view, msaa_view }
+}
+
+fn create_msaa_view(device: &wgpu::Device, format: wgpu::TextureFormat, size: UVec2, sample_count: u32) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("Render Graph MSAA Target"),
+            size: wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn compile(targets: &HashMap<String, TargetDesc>, passes: &[PassEntry]) -> RenderResult<CompiledGraph> {
+    let error = |message: String| Err(RenderError::RenderGraph(message));
+
+    let mut names = HashSet::new();
+    let mut writers: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut readers: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, pass) in passes.iter().enumerate() {
+        if !names.insert(pass.name.as_str()) {
+            return error(format!("duplicate pass '{}'", pass.name));
+        }
+        for name in pass.inputs.iter().chain(&pass.outputs) {
+            if name != RenderGraph::SURFACE && !targets.contains_key(name) {
+                return error(format!("pass '{}' uses undeclared target '{}'", pass.name, name));
+            }
+        }
+        for name in &pass.inputs {
+            if name == RenderGraph::SURFACE {
+                return error(format!("pass '{}' reads the surface, which can only be written", pass.name));
+            }
+            if pass.outputs.contains(name) {
+                return error(format!("pass '{}' both reads and writes '{}'", pass.name, name));
+            }
+            readers.entry(name).or_default().push(index);
+        }
+        for name in &pass.outputs {
+            writers.entry(name).or_default().push(index);
+        }
+    }
+    for (name, pass_indices) in &readers {
+        if !writers.contains_key(name) {
+            return error(format!("'{}' is read by '{}' but never written", name, passes[pass_indices[0]].name));
+        }
+    }
+    if !writers.contains_key(RenderGraph::SURFACE) {
+        return error("no pass writes the surface".to_string());
+    }
+
+    // Writers of a target run in insertion order, and before all of its readers
+    let mut edges = vec![Vec::new(); passes.len()];
+    for (name, writer_indices) in &writers {
+        for pair in writer_indices.windows(2) {
+            edges[pair[0]].push(pair[1]);
+        }
+        let last_writer = *writer_indices.last().expect("writers are never empty");
+        for &reader in readers.get(name).into_iter().flatten() {
+            edges[last_writer].push(reader);
+        }
+    }
+
+    // Kahn's algorithm, preferring earlier-added passes so independent passes keep their order
+    let mut in_degree = vec![0; passes.len()];
+    for &to in edges.iter().flatten() {
+        in_degree[to] += 1;
+    }
+    let mut ready: BinaryHeap<_> = (0..passes.len()).filter(|&i| in_degree[i] == 0).map(Reverse).collect();
+    let mut sorted = Vec::with_capacity(passes.len());
+    while let Some(Reverse(index)) = ready.pop() {
+        sorted.push(index);
+        for &to in &edges[index] {
+            in_degree[to] -= 1;
+            if in_degree[to] == 0 {
+                ready.push(Reverse(to));
+            }
+        }
+    }
+    if sorted.len() != passes.len() {
+        let stuck: Vec<_> = (0..passes.len())
+            .filter(|&i| in_degree[i] > 0)
+            .map(|i| passes[i].name.as_str())
+            .collect();
+        return error(format!("cycle between passes {}", stuck.join(", ")));
+    }
+
+    // Walk backwards from the surface, keeping passes whose output is still needed
+    let mut needed: HashSet<&str> = HashSet::from([RenderGraph::SURFACE]);
+    let mut order: Vec<usize> = Vec::new();
+    for &index in sorted.iter().rev() {
+        let pass = &passes[index];
+        if pass.outputs.iter().any(|name| needed.contains(name.as_str())) {
+            needed.extend(pass.inputs.iter().map(String::as_str));
+            order.push(index);
+        }
+    }
+    order.reverse();
+
+    // Lifetime of each transient target, as positions in `order`
+    let mut last_use: HashMap<&str, usize> = HashMap::new();
+    for (position, &index) in order.iter().enumerate() {
+        for name in passes[index].inputs.iter().chain(&passes[index].outputs) {
+            last_use.insert(name, position);
+        }
+    }
+
+    // Hand out pooled slots on first write and return them after the last use
+    let mut slots = HashMap::new();
+    let mut slot_descs: Vec<TargetDesc> = Vec::new();
+    let mut free: Vec<usize> = Vec::new();
+    let mut first_writes = HashSet::new();
+    for (position, &index) in order.iter().enumerate() {
+        let pass = &passes[index];
+        for name in &pass.outputs {
+            if first_writes.iter().any(|(_, written): &(usize, String)| written == name) {
+                continue;
+            }
+            first_writes.insert((index, name.clone()));
+            if name == RenderGraph::SURFACE {
+                continue;
+            }
+            let desc = targets[name];
+            let slot = match free.iter().position(|&slot| slot_descs[slot] == desc) {
+                Some(free_index) => free.swap_remove(free_index),
+                None => {
+                    slot_descs.push(desc);
+                    slot_descs.len() - 1
+                }
+            };
+            slots.insert(name.clone(), slot);
+        }
+        for name in pass.inputs.iter().chain(&pass.outputs) {
+            if last_use.get(name.as_str()) == Some(&position) {
+                if let Some(&slot) = slots.get(name) {
+                    free.push(slot);
+                }
+            }
+        }
+    }
+
+    Ok(CompiledGraph { order, slots, slot_descs, first_writes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noop(_: &mut RenderContext<'_>) {}
+
+    #[test]
+    fn passes_are_ordered_culled_and_targets_aliased() {
+        let mut graph = RenderGraph::new();
+        graph
+            .add_target("scene", TargetDesc::FULL)
+            .add_target("graded", TargetDesc::FULL)
+            .add_target("bright", TargetDesc::scaled(0.5))
+            .add_target("blurred", TargetDesc::scaled(0.5))
+            .add_target("unused", TargetDesc::FULL);
+
+        // Added out of order on purpose
+        graph
+            .add_pass("composite", &["graded", "blurred"], &[RenderGraph::SURFACE], noop)
+            .add_pass("grade", &["scene"], &["graded"], noop)
+            .add_pass("sprites", &[], &["scene"], noop)
+            .add_pass("ui", &[], &["scene"], noop)
+            .add_pass("threshold", &["scene"], &["bright"], noop)
+            .add_pass("blur", &["bright"], &["blurred"], noop)
+            .add_pass("debug", &["scene"], &["unused"], noop);
+
+        assert_eq!(
+            graph.execution_order().unwrap(),
+            vec!["sprites", "ui", "grade", "threshold", "blur", "composite"]
+        );
+
+        // Overlapping lifetimes get separate textures; the unread "debug" pass is culled
+        let compiled = graph.compiled.as_ref().unwrap();
+        assert_ne!(compiled.slots["scene"], compiled.slots["graded"]);
+        assert_ne!(compiled.slots["bright"], compiled.slots["blurred"]);
+        assert!(!compiled.slots.contains_key("unused"));
+        assert!(compiled.first_writes.contains(&(2, "scene".to_string())));
+        assert!(!compiled.first_writes.contains(&(3, "scene".to_string())));
+
+        // A chain of full-size effects only ever needs two textures
+        let mut chain = RenderGraph::new();
+        chain
+            .add_target("a", TargetDesc::FULL)
+            .add_target("b", TargetDesc::FULL)
+            .add_target("c", TargetDesc::FULL)
+            .add_pass("draw", &[], &["a"], noop)
+            .add_pass("vignette", &["a"], &["b"], noop)
+            .add_pass("crt", &["b"], &["c"], noop)
+            .add_pass("present", &["c"], &[RenderGraph::SURFACE], noop);
+        assert_eq!(chain.texture_slot_count().unwrap(), 2);
+
+        chain.add_pass("loop", &["c"], &["a"], noop);
+        assert!(matches!(chain.compile(), Err(RenderError::RenderGraph(_))));
+        chain.remove_pass("loop");
+        chain.add_pass("bad", &["missing"], &["a"], noop);
+        assert!(chain.compile().is_err());
+    }
+
+    #[test]
+    fn passes_draw_multisampled_and_resolve_into_their_targets() {
+        use crate::{FullscreenEffect, RenderConfig, WindowConfig};
+
+        let config = RenderConfig {
+            msaa_samples: 4,
+            window: WindowConfig {
+                size: (32, 32),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let Some(renderer) = crate::renderer::test_renderer(config) else { return };
+        assert_eq!(renderer.sample_count, 4, "4x MSAA is guaranteed for Rgba8UnormSrgb");
+
+        let fill = "@fragment
+fn fs_main(input: FullscreenVertex) -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+}";
+        let mut graph = RenderGraph::new();
+        graph
+            .add_target("scene", TargetDesc::FULL)
+            .add_pass("fill", &[], &["scene"], FullscreenEffect::new("Fill", fill, 0))
+            .add_pass("check", &[], &["scene"], |context: &mut RenderContext<'_>| {
+                assert_eq!(context.sample_count(), 4);
+                assert!(context.color_attachment(0, wgpu::Color::BLACK).resolve_target.is_some());
+            })
+            .add_pass("vignette", &["scene"], &[RenderGraph::SURFACE], FullscreenEffect::vignette(0.0, 0.5, 0.5));
+
+        renderer.render_graph_offscreen(&mut graph).unwrap();
+        let pixels = renderer.read_pixels().unwrap();
+        assert_eq!(pixels.get_pixel(16, 16).0, [255, 0, 0, 255]);
+        assert_eq!(pixels.get_pixel(0, 31).0, [255, 0, 0, 255]);
+    }
+}
@@ -3,8 +3,9 @@
 //! This module provides the main `Renderer` struct that manages WGPU device,
 //! surface, and core rendering resources.
 
-use crate::{RenderConfig, RenderError, RenderResult, UiRenderer};
-use glam::Vec2;
+use crate::{OffscreenTarget, RenderConfig, RenderError, RenderGraph, RenderResult, UiRenderer};
+use glam::{UVec2, Vec2};
+use image::RgbaImage;
 use std::sync::Arc;
 
 /// Main rendering system
@@ -21,10 +22,19 @@ pub struct Renderer {
     pub surface: Option<wgpu::Surface<'static>>,
     /// Surface configuration
     pub surface_config: Option<wgpu::SurfaceConfiguration>,
+    /// Target rendered to instead of a surface in headless mode
+    pub offscreen: Option<OffscreenTarget>,
     /// UI renderer
     pub ui_renderer: Option<UiRenderer>,
     /// Current screen size
     pub screen_size: Vec2,
+    /// Samples per pixel of the frame's color attachment: `msaa_samples` clamped to what
+    /// the target format supports
+    pub sample_count: u32,
+    /// Multisampled color target resolved into the frame when `sample_count > 1`
+    msaa_view: Option<wgpu::TextureView>,
+    /// Configuration the renderer was created or last reconfigured with
+    config: RenderConfig,
 }
 
 impl Renderer {
@@ -36,22 +46,16 @@ impl Renderer {
             ..Default::default()
         });
 
-        // Request adapter
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None,
-                force_fallback_adapter: false,
-            })
-            .await
-            .ok_or(RenderError::AdapterNotFound)?;
+        let adapter = Self::request_adapter(&instance).await?;
 
-        // Request device and queue
+        // Request device and queue. Adapter-specific format features unlock MSAA sample
+        // counts other than 4.
         let (device, queue) = adapter
             .request_device(
                 &wgpu::DeviceDescriptor {
                     label: Some("Lumina Render Device"),
-                    required_features: wgpu::Features::empty(),
+                    required_features: adapter.features()
+                        & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
                     required_limits: wgpu::Limits::default(),
                 },
                 None,
@@ -67,11 +71,37 @@ impl Renderer {
             queue,
             surface: None,
             surface_config: None,
+            offscreen: None,
             ui_renderer: None,
             screen_size,
+            sample_count: 1,
+            msaa_view: None,
+            config,
         })
     }
 
+    /// Create a renderer without a window, drawing into an offscreen target of the
+    /// configured window size that can be read back with `read_pixels`
+    pub async fn new_headless(config: RenderConfig) -> RenderResult<Self> {
+        let mut renderer = Self::new(config).await?;
+        renderer.offscreen = Some(OffscreenTarget::new(
+            &renderer.device,
+            renderer.screen_size.x as u32,
+            renderer.screen_size.y as u32,
+            Self::HEADLESS_FORMAT,
+        ));
+        renderer.update_msaa();
+        Ok(renderer)
+    }
+
+    /// Format of the offscreen target in headless mode
+    pub const HEADLESS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// Whether this renderer draws into an offscreen target
+    pub fn is_headless(&self) -> bool {
+        self.offscreen.is_some()
+    }
+
     /// Create a surface for rendering to a window
     pub fn create_surface(&mut self, window: Arc<winit::window::Window>) -> RenderResult<()> {
         let surface = self.instance.create_surface(window)?;
@@ -89,8 +119,12 @@ impl Renderer {
             format: surface_format,
             width: self.screen_size.x as u32,
             height: self.screen_size.y as u32,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
+            present_mode: select_present_mode(
+                self.config.vsync,
+                self.config.target_fps,
+                &surface_caps.present_modes,
+            ),
+            alpha_mode: select_alpha_mode(&surface_caps.alpha_modes),
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
@@ -99,18 +133,131 @@ impl Renderer {
 
         self.surface = Some(surface);
         self.surface_config = Some(config);
+        self.update_msaa();
 
         Ok(())
     }
 
+    /// The configuration the renderer was created or last reconfigured with
+    pub fn config(&self) -> &RenderConfig {
+        &self.config
+    }
+
+    /// Apply `vsync`, `target_fps` and `msaa_samples` from `config` without recreating
+    /// the renderer. The backend and window settings only take effect on creation.
+    pub fn reconfigure(&mut self, config: RenderConfig) {
+        self.config = config;
+
+        if let (Some(surface), Some(surface_config)) = (&self.surface, &mut self.surface_config) {
+            let caps = surface.get_capabilities(&self.adapter);
+            surface_config.present_mode =
+                select_present_mode(self.config.vsync, self.config.target_fps, &caps.present_modes);
+            surface.configure(&self.device, surface_config);
+        }
+
+        self.update_msaa();
+    }
+
+    /// Turn vsync on or off
+    pub fn set_vsync(&mut self, vsync: bool) {
+        let config = RenderConfig { vsync, ..self.config.clone() };
+        self.reconfigure(config);
+    }
+
+    /// Change the MSAA sample count (1 disables MSAA)
+    pub fn set_msaa_samples(&mut self, msaa_samples: u32) {
+        let config = RenderConfig { msaa_samples, ..self.config.clone() };
+        self.reconfigure(config);
+    }
+
+    /// Multisample state for pipelines drawing into passes begun with `color_attachment`
+    pub fn multisample_state(&self) -> wgpu::MultisampleState {
+        wgpu::MultisampleState {
+            count: self.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        }
+    }
+
+    /// Color attachment drawing into `target`, the frame's view. With MSAA this renders
+    /// into the multisampled target and resolves into `target` at the end of the pass.
+    ///
+    /// `clear` clears the attachment, `None` keeps what earlier passes drew.
+    pub fn color_attachment<'a>(
+        &'a self,
+        target: &'a wgpu::TextureView,
+        clear: Option<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'a> {
+        let (view, resolve_target) = match &self.msaa_view {
+            Some(msaa_view) => (msaa_view, Some(target)),
+            None => (target, None),
+        };
+        wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target,
+            ops: wgpu::Operations {
+                load: clear.map_or(wgpu::LoadOp::Load, wgpu::LoadOp::Clear),
+                store: wgpu::StoreOp::Store,
+            },
+        }
+    }
+
+    /// Pick the sample count for the current target and recreate the multisampled target
+    fn update_msaa(&mut self) {
+        let Some(target) = self.target_config() else {
+            return;
+        };
+
+        let features = self.device.features();
+        let flags = if features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+            self.adapter.get_texture_format_features(target.format).flags
+        } else {
+            target.format.guaranteed_format_features(features).flags
+        };
+        let sample_count = select_sample_count(self.config.msaa_samples, flags);
+        if sample_count != self.config.msaa_samples.max(1) && sample_count != self.sample_count {
+            log::warn!(
+                "{} MSAA samples are not supported for {:?}, using {}",
+                self.config.msaa_samples,
+                target.format,
+                sample_count
+            );
+        }
+
+        self.sample_count = sample_count;
+        self.msaa_view = (sample_count > 1).then(|| {
+            self.device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("MSAA Color Target"),
+                    size: wgpu::Extent3d {
+                        width: target.width.max(1),
+                        height: target.height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: target.format,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        if let Some(ui_renderer) = &mut self.ui_renderer {
+            ui_renderer.set_sample_count(&self.device, sample_count);
+        }
+    }
+
     /// Initialize UI renderer
     pub async fn init_ui_renderer(&mut self) -> RenderResult<()> {
-        if let Some(config) = &self.surface_config {
-            let ui_renderer = UiRenderer::new(
+        if let Some(config) = self.target_config() {
+            let mut ui_renderer = UiRenderer::new(
                 &self.device,
                 &self.queue,
-                config.clone(),
+                config,
             ).await?;
+            ui_renderer.set_sample_count(&self.device, self.sample_count);
             self.ui_renderer = Some(ui_renderer);
         }
         Ok(())
@@ -124,28 +271,39 @@ impl Renderer {
             config.width = new_size.x as u32;
             config.height = new_size.y as u32;
             surface.configure(&self.device, config);
-            
-            if let Some(ui_renderer) = &mut self.ui_renderer {
-                ui_renderer.resize(new_size);
-            }
+        }
+
+        if let Some(target) = &mut self.offscreen {
+            *target = OffscreenTarget::new(&self.device, new_size.x as u32, new_size.y as u32, target.format);
+        }
+
+        if self.sample_count > 1 {
+            self.update_msaa();
+        }
+
+        if let Some(ui_renderer) = &mut self.ui_renderer {
+            ui_renderer.resize(new_size);
         }
     }
 
     /// Begin a new frame
+    ///
+    /// In headless mode there is no surface texture and the frame is drawn into `offscreen`.
     pub fn begin_frame(&mut self) -> RenderResult<Option<wgpu::SurfaceTexture>> {
-        if let Some(surface) = &self.surface {
-            let output = surface.get_current_texture().map_err(|e| {
+        let output = match &self.surface {
+            Some(surface) => Some(surface.get_current_texture().map_err(|e| {
                 RenderError::InvalidOperation(format!("Failed to get surface texture: {}", e))
-            })?;
-            
+            })?),
+            None => None,
+        };
+
+        if output.is_some() || self.offscreen.is_some() {
             if let Some(ui_renderer) = &mut self.ui_renderer {
                 ui_renderer.begin_frame(&self.queue);
             }
-            
-            Ok(Some(output))
-        } else {
-            Ok(None)
         }
+
+        Ok(output)
     }
 
     /// End the current frame
@@ -159,6 +317,57 @@ impl Renderer {
         }
     }
 
+    /// Run a render graph into a frame returned by `begin_frame`. Passes draw with
+    /// `sample_count` samples, like passes begun with `color_attachment`.
+    pub fn render_graph(&self, graph: &mut RenderGraph, output: &wgpu::SurfaceTexture) -> RenderResult<()> {
+        let config = self.surface_config.as_ref().ok_or_else(|| {
+            RenderError::InvalidOperation("Cannot render a graph without a surface".to_string())
+        })?;
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        graph.execute(
+            &self.device,
+            &self.queue,
+            &view,
+            config.format,
+            UVec2::new(output.texture.width(), output.texture.height()),
+            self.sample_count,
+        )
+    }
+
+    /// Run a render graph into the offscreen target in headless mode
+    pub fn render_graph_offscreen(&self, graph: &mut RenderGraph) -> RenderResult<()> {
+        let target = self.offscreen_target()?;
+        graph.execute(&self.device, &self.queue, &target.view, target.format, target.size, self.sample_count)
+    }
+
+    /// Read back the offscreen target, waiting for submitted work to finish
+    pub fn read_pixels(&self) -> RenderResult<RgbaImage> {
+        self.offscreen_target()?.read_pixels(&self.device, &self.queue)
+    }
+
+    fn offscreen_target(&self) -> RenderResult<&OffscreenTarget> {
+        self.offscreen.as_ref().ok_or_else(|| {
+            RenderError::InvalidOperation("Renderer is not headless".to_string())
+        })
+    }
+
+    /// Configuration of the render target: the surface's, or one describing the
+    /// offscreen target in headless mode
+    fn target_config(&self) -> Option<wgpu::SurfaceConfiguration> {
+        self.surface_config.clone().or_else(|| {
+            self.offscreen.as_ref().map(|target| wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                format: target.format,
+                width: target.size.x,
+                height: target.size.y,
+                present_mode: wgpu::PresentMode::Fifo,
+                alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+                view_formats: vec![],
+                desired_maximum_frame_latency: 2,
+            })
+        })
+    }
+
     /// Get a reference to the UI renderer
     pub fn ui_renderer(&self) -> Option<&UiRenderer> {
         self.ui_renderer.as_ref()
@@ -169,6 +378,27 @@ impl Renderer {
         self.ui_renderer.as_mut()
     }
 
+    /// Request a hardware adapter, falling back to a software adapter (WARP, llvmpipe,
+    /// SwiftShader, ...) when there is none
+    async fn request_adapter(instance: &wgpu::Instance) -> RenderResult<wgpu::Adapter> {
+        for force_fallback_adapter in [false, true] {
+            let adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: None,
+                    force_fallback_adapter,
+                })
+                .await;
+            if let Some(adapter) = adapter {
+                if force_fallback_adapter {
+                    log::warn!("No hardware graphics adapter found, using {}", adapter.get_info().name);
+                }
+                return Ok(adapter);
+            }
+        }
+        Err(RenderError::AdapterNotFound)
+    }
+
     fn backends_from_preference(preference: crate::BackendPreference) -> wgpu::Backends {
         use crate::BackendPreference;
         match preference {
@@ -179,4 +409,133 @@ impl Renderer {
             BackendPreference::Auto => wgpu::Backends::all(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Present mode for `vsync` and `target_fps`, falling back to what the surface supports.
+///
+/// Vsync prefers `Fifo`. Without vsync, a frame cap prefers `Mailbox` (no tearing, low
+/// latency) and an uncapped frame rate (`target_fps == 0`) prefers `Immediate`.
+pub fn select_present_mode(vsync: bool, target_fps: u32, available: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    use wgpu::PresentMode::{Fifo, FifoRelaxed, Immediate, Mailbox};
+    let preferred: &[wgpu::PresentMode] = match (vsync, target_fps) {
+        (true, _) => &[Fifo, FifoRelaxed, Mailbox],
+        (false, 0) => &[Immediate, Mailbox, FifoRelaxed, Fifo],
+        (false, _) => &[Mailbox, Immediate, FifoRelaxed, Fifo],
+    };
+    preferred
+        .iter()
+        .copied()
+        .find(|mode| available.contains(mode))
+        .or_else(|| available.first().copied())
+        .unwrap_or(Fifo)
+}
+
+/// Opaque compositing when the surface supports it, otherwise its first alpha mode
+pub fn select_alpha_mode(available: &[wgpu::CompositeAlphaMode]) -> wgpu::CompositeAlphaMode {
+    if available.contains(&wgpu::CompositeAlphaMode::Opaque) {
+        wgpu::CompositeAlphaMode::Opaque
+    } else {
+        available.first().copied().unwrap_or(wgpu::CompositeAlphaMode::Auto)
+    }
+}
+
+/// The largest supported sample count not above `requested`, or 1 if the format cannot
+/// be multisampled and resolved
+pub fn select_sample_count(requested: u32, flags: wgpu::TextureFormatFeatureFlags) -> u32 {
+    if !flags.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE) {
+        return 1;
+    }
+    [16, 8, 4, 2]
+        .into_iter()
+        .find(|&count| count <= requested && flags.sample_count_supported(count))
+        .unwrap_or(1)
+}
+
+/// A headless renderer for GPU tests. Without an adapter this panics, unless
+/// `LUMINA_SKIP_GPU_TESTS` is set, in which case it returns `None` and the test is skipped
+#[cfg(test)]
+pub(crate) fn test_renderer(config: RenderConfig) -> Option<Renderer> {
+    match pollster::block_on(Renderer::new_headless(config)) {
+        Ok(renderer) => Some(renderer),
+        Err(err) if std::env::var_os("LUMINA_SKIP_GPU_TESTS").is_some() => {
+            log::warn!("Skipping GPU test: {}", err);
+            None
+        }
+        Err(err) => panic!("no graphics adapter for GPU tests ({err}); set LUMINA_SKIP_GPU_TESTS to skip them"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Rect, WindowConfig};
+    use glam::Vec4;
+    use wgpu::PresentMode;
+
+    #[test]
+    fn present_alpha_and_sample_counts_fall_back_to_supported_values() {
+        let all = [PresentMode::Fifo, PresentMode::Mailbox, PresentMode::Immediate];
+        assert_eq!(select_present_mode(true, 60, &all), PresentMode::Fifo);
+        assert_eq!(select_present_mode(false, 60, &all), PresentMode::Mailbox);
+        assert_eq!(select_present_mode(false, 0, &all), PresentMode::Immediate);
+        assert_eq!(select_present_mode(false, 0, &[PresentMode::Fifo]), PresentMode::Fifo);
+        assert_eq!(select_present_mode(false, 60, &[PresentMode::Fifo, PresentMode::Immediate]), PresentMode::Immediate);
+
+        use wgpu::CompositeAlphaMode as Alpha;
+        assert_eq!(select_alpha_mode(&[Alpha::PreMultiplied, Alpha::Opaque]), Alpha::Opaque);
+        assert_eq!(select_alpha_mode(&[Alpha::PreMultiplied]), Alpha::PreMultiplied);
+
+        use wgpu::TextureFormatFeatureFlags as Flags;
+        let x4 = Flags::MULTISAMPLE_X4 | Flags::MULTISAMPLE_RESOLVE;
+        assert_eq!(select_sample_count(8, x4), 4);
+        assert_eq!(select_sample_count(4, x4 | Flags::MULTISAMPLE_X8), 4);
+        assert_eq!(select_sample_count(2, x4), 1);
+        assert_eq!(select_sample_count(4, Flags::MULTISAMPLE_X4), 1);
+    }
+
+    #[test]
+    fn msaa_resolves_into_the_frame_and_can_be_reconfigured() {
+        let config = RenderConfig {
+            msaa_samples: 4,
+            window: WindowConfig {
+                size: (64, 64),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let Some(mut renderer) = test_renderer(config) else { return };
+        pollster::block_on(renderer.init_ui_renderer()).unwrap();
+
+        let render = |renderer: &mut Renderer| {
+            renderer.begin_frame().unwrap();
+            let ui = renderer.ui_renderer_mut().unwrap();
+            ui.draw_rounded_rect(Rect::new(8.0, 8.0, 48.0, 48.0), Vec4::new(1.0, 0.0, 0.0, 1.0), 12.0);
+            renderer.end_frame(None);
+
+            let mut encoder = renderer.device.create_command_encoder(&Default::default());
+            {
+                let target = &renderer.offscreen.as_ref().unwrap().view;
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(renderer.color_attachment(target, Some(wgpu::Color::BLACK)))],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                renderer.ui_renderer().unwrap().submit_to_render_pass(&mut render_pass);
+            }
+            renderer.queue.submit(std::iter::once(encoder.finish()));
+            renderer.read_pixels().unwrap()
+        };
+
+        let multisampled = render(&mut renderer);
+        assert!(renderer.sample_count > 1, "4x MSAA is guaranteed for Rgba8UnormSrgb");
+        assert_eq!(multisampled.get_pixel(32, 32).0, [255, 0, 0, 255]);
+        assert_eq!(multisampled.get_pixel(1, 1).0, [0, 0, 0, 255]);
+
+        renderer.set_msaa_samples(1);
+        assert_eq!(renderer.sample_count, 1);
+        let single = render(&mut renderer);
+        assert_eq!(single.get_pixel(32, 32).0, [255, 0, 0, 255]);
+    }
+}
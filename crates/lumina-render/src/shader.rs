@@ -0,0 +1,529 @@
+//! WGSL shader assets
+//!
+//! Shaders are loaded from files or registered as strings, run through a small
+//! preprocessor and validated with naga before a `wgpu::ShaderModule` is created,
+//! so errors name the original file and line. The preprocessor understands:
+//!
+//! - `#include "name"`: paste another file, at most once per shader. The name is
+//!   looked up next to the including file, then among the includes registered with
+//!   `ShaderManager::add_include`
+//! - `#define NAME value` and `#undef NAME`: replace the identifier `NAME` from then on
+//! - `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif`
+//!
+//! `ShaderManager::poll_changes` reloads shaders whose files changed on disk.
+
+use crate::{RenderError, RenderResult};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Handle to a shader in a `ShaderManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShaderHandle(pub u32);
+
+/// Defines a shader is preprocessed with, before its own `#define`s
+pub type ShaderDefines = BTreeMap<String, String>;
+
+/// Include providing the sprite vertex stage and bindings to sprite materials
+pub const SPRITE_INCLUDE: &str = "lumina/sprite.wgsl";
+/// Include providing `POST_PRELUDE` to post-processing effects
+pub const POST_INCLUDE: &str = "lumina/post.wgsl";
+
+/// File and line a line of preprocessed source came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LineOrigin {
+    file: usize,
+    line: u32,
+}
+
+/// Shader source after preprocessing
+#[derive(Debug, Clone, Default)]
+pub struct PreprocessedShader {
+    /// WGSL handed to naga and wgpu
+    pub source: String,
+    /// Names of the files and includes the source was assembled from, the shader first
+    pub files: Vec<String>,
+    /// Files on disk the source was read from, for hot reload
+    pub dependencies: Vec<PathBuf>,
+    lines: Vec<LineOrigin>,
+}
+
+impl PreprocessedShader {
+    /// File name and 1-based line that produced the 1-based `line` of `source`
+    pub fn origin(&self, line: u32) -> Option<(&str, u32)> {
+        let origin = self.lines.get(line.checked_sub(1)? as usize)?;
+        Some((&self.files[origin.file], origin.line))
+    }
+
+    /// Parse and validate the source, reporting errors as `file:line:column: message`
+    pub fn validate(&self) -> RenderResult<naga::Module> {
+        let module = naga::front::wgsl::parse_str(&self.source)
+            .map_err(|err| self.error(err.location(&self.source), err.message().to_string()))?;
+
+        naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::default())
+            .validate(&module)
+            .map_err(|err| {
+                // The interesting part of a validation error is usually its innermost cause
+                let mut message = err.as_inner().to_string();
+                let mut source = std::error::Error::source(err.as_inner());
+                while let Some(cause) = source {
+                    message = format!("{message}: {cause}");
+                    source = cause.source();
+                }
+                self.error(err.location(&self.source), message)
+            })?;
+
+        Ok(module)
+    }
+
+    /// Create the shader module. The source has already been validated, so wgpu
+    /// doesn't get to report errors without the original file and line.
+    pub fn create_module(&self, device: &wgpu::Device) -> wgpu::ShaderModule {
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(&self.files[0]),
+            source: wgpu::ShaderSource::Wgsl(self.source.as_str().into()),
+        })
+    }
+
+    fn error(&self, location: Option<naga::SourceLocation>, message: String) -> RenderError {
+        let origin = location.and_then(|location| {
+            self.origin(location.line_number)
+                .map(|(file, line)| (file, line, location.line_position))
+        });
+        match origin {
+            Some((file, line, column)) => {
+                RenderError::ShaderCompilation(format!("{file}:{line}:{column}: {message}"))
+            }
+            None => RenderError::ShaderCompilation(format!("{}: {message}", self.files[0])),
+        }
+    }
+}
+
+/// Nesting state of one `#ifdef`/`#ifndef`
+struct Condition {
+    parent_active: bool,
+    active: bool,
+    seen_else: bool,
+}
+
+struct Preprocessor<'a> {
+    includes: &'a HashMap<String, String>,
+    defines: ShaderDefines,
+    /// Files and includes already pasted, by canonical path or include name
+    included: HashSet<String>,
+    output: PreprocessedShader,
+}
+
+impl<'a> Preprocessor<'a> {
+    fn process(&mut self, name: String, path: Option<&Path>, source: &str) -> RenderResult<()> {
+        let file = self.output.files.len();
+        self.output.files.push(name);
+        let error = |output: &PreprocessedShader, line: usize, message: String| {
+            RenderError::ShaderCompilation(format!("{}:{}: {message}", output.files[file], line + 1))
+        };
+
+        let mut conditions: Vec<Condition> = Vec::new();
+        for (index, text) in source.lines().enumerate() {
+            let active = conditions.last().is_none_or(|condition| condition.active);
+            let Some(directive) = text.trim_start().strip_prefix('#') else {
+                if active {
+                    self.output.source.push_str(&substitute(text, &self.defines));
+                    self.output.source.push('\n');
+                    self.output.lines.push(LineOrigin { file, line: index as u32 + 1 });
+                }
+                continue;
+            };
+
+            let (keyword, argument) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+            let argument = argument.trim();
+            match keyword {
+                "ifdef" | "ifndef" => {
+                    let defined = self.defines.contains_key(argument);
+                    conditions.push(Condition {
+                        parent_active: active,
+                        active: active && defined == (keyword == "ifdef"),
+                        seen_else: false,
+                    });
+                }
+                "else" => {
+                    let condition = conditions
+                        .last_mut()
+                        .filter(|condition| !condition.seen_else)
+                        .ok_or_else(|| error(&self.output, index, "#else without #ifdef".to_string()))?;
+                    condition.seen_else = true;
+                    condition.active = condition.parent_active && !condition.active;
+                }
+                "endif" => {
+                    conditions
+                        .pop()
+                        .ok_or_else(|| error(&self.output, index, "#endif without #ifdef".to_string()))?;
+                }
+                _ if !active => {}
+                "define" => {
+                    let (define, value) = argument.split_once(char::is_whitespace).unwrap_or((argument, ""));
+                    if !is_identifier(define) {
+                        return Err(error(&self.output, index, format!("invalid define name `{define}`")));
+                    }
+                    self.defines.insert(define.to_string(), value.trim().to_string());
+                }
+                "undef" => {
+                    self.defines.remove(argument);
+                }
+                "include" => {
+                    let target = argument
+                        .strip_prefix('"')
+                        .and_then(|rest| rest.strip_suffix('"'))
+                        .ok_or_else(|| error(&self.output, index, "expected #include \"name\"".to_string()))?;
+                    if !self.include(target, path)? {
+                        return Err(error(&self.output, index, format!("cannot find include \"{target}\"")));
+                    }
+                }
+                _ => return Err(error(&self.output, index, format!("unknown directive #{keyword}"))),
+            }
+        }
+
+        if !conditions.is_empty() {
+            return Err(RenderError::ShaderCompilation(format!(
+                "{}: unterminated #ifdef",
+                self.output.files[file]
+            )));
+        }
+        Ok(())
+    }
+
+    /// Paste `target`, returning false if it cannot be found
+    fn include(&mut self, target: &str, from: Option<&Path>) -> RenderResult<bool> {
+        if let Some(path) = from.and_then(Path::parent).map(|dir| dir.join(target)) {
+            if path.is_file() {
+                let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+                if self.included.insert(key.to_string_lossy().into_owned()) {
+                    let source = read_source(&path)?;
+                    self.output.dependencies.push(path.clone());
+                    self.process(path.display().to_string(), Some(&path), &source)?;
+                }
+                return Ok(true);
+            }
+        }
+
+        let includes = self.includes;
+        match includes.get(target) {
+            Some(source) => {
+                if self.included.insert(target.to_string()) {
+                    self.process(target.to_string(), None, source)?;
+                }
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+/// Replace every identifier in `line` that is defined with a non-empty value
+fn substitute(line: &str, defines: &ShaderDefines) -> String {
+    if defines.is_empty() {
+        return line.to_string();
+    }
+
+    let mut output = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let identifier = &rest[..end];
+        match defines.get(identifier) {
+            Some(value) if !value.is_empty() => output.push_str(value),
+            _ => output.push_str(identifier),
+        }
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn read_source(path: &Path) -> RenderResult<String> {
+    std::fs::read_to_string(path)
+        .map_err(|err| RenderError::ResourceNotFound(format!("{}: {}", path.display(), err)))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Where a shader's top-level source comes from
+enum ShaderOrigin {
+    File(PathBuf),
+    Source { name: String, source: String },
+}
+
+struct ShaderEntry {
+    origin: ShaderOrigin,
+    defines: ShaderDefines,
+    shader: PreprocessedShader,
+    /// Modification time of every file the shader was read from, when last loaded
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+    module: Option<wgpu::ShaderModule>,
+    generation: u32,
+}
+
+/// Loads, preprocesses, validates and hot-reloads WGSL shaders
+pub struct ShaderManager {
+    shaders: Vec<ShaderEntry>,
+    includes: HashMap<String, String>,
+}
+
+impl ShaderManager {
+    /// Create a shader manager with the built-in `SPRITE_INCLUDE` and `POST_INCLUDE`
+    pub fn new() -> Self {
+        let mut manager = Self {
+            shaders: Vec::new(),
+            includes: HashMap::new(),
+        };
+        manager.add_include(SPRITE_INCLUDE, include_str!("shaders/include/sprite.wgsl"));
+        manager.add_include(POST_INCLUDE, crate::POST_PRELUDE);
+        manager
+    }
+
+    /// Register source that shaders can `#include` by `name`
+    pub fn add_include(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.includes.insert(name.into(), source.into());
+    }
+
+    /// Load a shader file; it is reloaded by `poll_changes` when it or a file it
+    /// includes changes
+    pub fn load(&mut self, path: impl AsRef<Path>, defines: ShaderDefines) -> RenderResult<ShaderHandle> {
+        self.insert(ShaderOrigin::File(path.as_ref().to_path_buf()), defines)
+    }
+
+    /// Add a shader from a string, e.g. one embedded with `include_str!`
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        source: impl Into<String>,
+        defines: ShaderDefines,
+    ) -> RenderResult<ShaderHandle> {
+        let origin = ShaderOrigin::Source {
+            name: name.into(),
+            source: source.into(),
+        };
+        self.insert(origin, defines)
+    }
+
+    /// The preprocessed source of a shader
+    pub fn get(&self, handle: ShaderHandle) -> Option<&PreprocessedShader> {
+        self.shaders.get(handle.0 as usize).map(|entry| &entry.shader)
+    }
+
+    /// Incremented each time the shader is reloaded, so pipelines using it know to rebuild
+    pub fn generation(&self, handle: ShaderHandle) -> u32 {
+        self.shaders.get(handle.0 as usize).map_or(0, |entry| entry.generation)
+    }
+
+    /// The shader module, created on first use after each (re)load
+    pub fn module(&mut self, device: &wgpu::Device, handle: ShaderHandle) -> Option<&wgpu::ShaderModule> {
+        let entry = self.shaders.get_mut(handle.0 as usize)?;
+        let shader = &entry.shader;
+        Some(entry.module.get_or_insert_with(|| shader.create_module(device)))
+    }
+
+    /// Preprocess and validate a shader without keeping it, for shaders that are built
+    /// at runtime or embedded in the engine and never reloaded
+    pub fn preprocess(
+        &self,
+        name: impl Into<String>,
+        source: impl Into<String>,
+        defines: ShaderDefines,
+    ) -> RenderResult<PreprocessedShader> {
+        let origin = ShaderOrigin::Source {
+            name: name.into(),
+            source: source.into(),
+        };
+        Self::build(&origin, &defines, &self.includes)
+    }
+
+    /// Reload shaders whose files changed on disk since they were last loaded.
+    ///
+    /// Returns the shaders that were reloaded, or failed to reload and keep their
+    /// previous version.
+    pub fn poll_changes(&mut self) -> Vec<(ShaderHandle, RenderResult<()>)> {
+        let mut reloaded = Vec::new();
+        for (index, entry) in self.shaders.iter_mut().enumerate() {
+            let changed = entry.modified.iter().any(|(path, time)| modified(path) != *time);
+            if !changed {
+                continue;
+            }
+
+            let result = Self::build(&entry.origin, &entry.defines, &self.includes);
+            let handle = ShaderHandle(index as u32);
+            match result {
+                Ok(shader) => {
+                    entry.modified = modification_times(&shader);
+                    entry.shader = shader;
+                    entry.module = None;
+                    entry.generation += 1;
+                    log::info!("Reloaded shader {}", entry.shader.files[0]);
+                    reloaded.push((handle, Ok(())));
+                }
+                Err(err) => {
+                    // Don't retry until the files change again
+                    for (path, time) in &mut entry.modified {
+                        *time = modified(path);
+                    }
+                    log::error!("Failed to reload shader: {}", err);
+                    reloaded.push((handle, Err(err)));
+                }
+            }
+        }
+        reloaded
+    }
+
+    fn insert(&mut self, origin: ShaderOrigin, defines: ShaderDefines) -> RenderResult<ShaderHandle> {
+        let shader = Self::build(&origin, &defines, &self.includes)?;
+        self.shaders.push(ShaderEntry {
+            modified: modification_times(&shader),
+            origin,
+            defines,
+            shader,
+            module: None,
+            generation: 0,
+        });
+        Ok(ShaderHandle((self.shaders.len() - 1) as u32))
+    }
+
+    fn build(
+        origin: &ShaderOrigin,
+        defines: &ShaderDefines,
+        includes: &HashMap<String, String>,
+    ) -> RenderResult<PreprocessedShader> {
+        let mut preprocessor = Preprocessor {
+            includes,
+            defines: defines.clone(),
+            included: HashSet::new(),
+            output: PreprocessedShader::default(),
+        };
+        match origin {
+            ShaderOrigin::File(path) => {
+                let source = read_source(path)?;
+                preprocessor.output.dependencies.push(path.clone());
+                preprocessor.process(path.display().to_string(), Some(path), &source)?;
+            }
+            ShaderOrigin::Source { name, source } => {
+                preprocessor.process(name.clone(), None, source)?;
+            }
+        }
+        preprocessor.output.validate()?;
+        Ok(preprocessor.output)
+    }
+}
+
+impl Default for ShaderManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Module for a shader embedded in the engine, validated so that a broken built-in
+/// shader is reported with its file and line
+pub(crate) fn builtin_module(device: &wgpu::Device, name: &str, source: &str) -> RenderResult<wgpu::ShaderModule> {
+    let shader = ShaderManager::new().preprocess(name, source, ShaderDefines::new())?;
+    Ok(shader.create_module(device))
+}
+
+fn modification_times(shader: &PreprocessedShader) -> Vec<(PathBuf, Option<SystemTime>)> {
+    shader
+        .dependencies
+        .iter()
+        .map(|path| (path.clone(), modified(path)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAGMENT: &str = "\
+#include \"common.wgsl\"
+#ifdef TINTED
+const TINT: vec4<f32> = vec4<f32>(COLOR);
+#else
+const TINT: vec4<f32> = vec4<f32>(1.0);
+#endif
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return TINT * SCALE;
+}
+";
+
+    #[test]
+    fn preprocessor_includes_defines_and_maps_errors_to_lines() {
+        let mut shaders = ShaderManager::new();
+        shaders.add_include("common.wgsl", "#include \"common.wgsl\"\n#define SCALE 0.5\nconst ONE: f32 = 1.0;\n");
+
+        let defines = ShaderDefines::from([
+            ("TINTED".to_string(), String::new()),
+            ("COLOR".to_string(), "1.0, 0.0, 0.0, 1.0".to_string()),
+        ]);
+        let handle = shaders.add("tinted.wgsl", FRAGMENT, defines).unwrap();
+        let shader = shaders.get(handle).unwrap();
+        assert_eq!(shader.files, ["tinted.wgsl", "common.wgsl"]);
+        assert!(shader.source.contains("vec4<f32>(1.0, 0.0, 0.0, 1.0)"));
+        assert!(shader.source.contains("return TINT * 0.5;"));
+        assert!(!shader.source.contains("vec4<f32>(1.0);"));
+        assert_eq!(shader.origin(1), Some(("common.wgsl", 3)));
+        assert_eq!(shader.origin(2), Some(("tinted.wgsl", 3)));
+
+        let broken = FRAGMENT.replace("TINT * SCALE", "TINT * missing");
+        let err = shaders.add("broken.wgsl", broken, ShaderDefines::new()).unwrap_err();
+        assert!(err.to_string().contains("broken.wgsl:9:"), "{err}");
+
+        let err = shaders.add("unclosed.wgsl", "#ifdef A\n", ShaderDefines::new()).unwrap_err();
+        assert!(err.to_string().contains("unterminated #ifdef"), "{err}");
+        let err = shaders.add("missing.wgsl", "\n#include \"nope.wgsl\"\n", ShaderDefines::new()).unwrap_err();
+        assert!(err.to_string().contains("missing.wgsl:2: cannot find include"), "{err}");
+    }
+
+    #[test]
+    fn changed_shader_files_are_reloaded() {
+        let dir = std::env::temp_dir().join(format!("lumina-shader-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.wgsl");
+        let common = dir.join("common.wgsl");
+        std::fs::write(&common, "const VALUE: f32 = 1.0;\n").unwrap();
+        std::fs::write(&main, "#include \"common.wgsl\"\nconst DOUBLE: f32 = VALUE * 2.0;\n").unwrap();
+
+        let mut shaders = ShaderManager::new();
+        let handle = shaders.load(&main, ShaderDefines::new()).unwrap();
+        assert!(shaders.poll_changes().is_empty());
+
+        // Set the time explicitly, file systems may not tick between two writes
+        let touch = |path: &Path, contents: &str, seconds: u64| {
+            std::fs::write(path, contents).unwrap();
+            let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(seconds);
+            std::fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+        };
+
+        touch(&common, "const VALUE: f32 = 3.0;\n", 1_000);
+        let reloaded = shaders.poll_changes();
+        assert_eq!(reloaded.len(), 1);
+        assert!(reloaded[0].1.is_ok());
+        assert_eq!(shaders.generation(handle), 1);
+        assert!(shaders.get(handle).unwrap().source.contains("3.0"));
+
+        // A broken edit keeps the last good version
+        touch(&common, "const VALUE: f32 = ;\n", 2_000);
+        let reloaded = shaders.poll_changes();
+        assert!(reloaded[0].1.is_err());
+        assert_eq!(shaders.generation(handle), 1);
+        assert!(shaders.get(handle).unwrap().source.contains("3.0"));
+        assert!(shaders.poll_changes().is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
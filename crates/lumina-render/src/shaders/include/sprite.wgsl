@@ -0,0 +1,38 @@
+// Sprite vertex stage and bindings shared by sprite material shaders.
+// Materials add `@group(2) @binding(0)` uniforms, their textures from binding 1 on,
+// and an `fs_main` taking `VertexOutput`.
+
+struct UiUniforms {
+    view_proj: mat4x4<f32>,
+    screen_size: vec2<f32>,
+}
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) tex_coords: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> uniforms: UiUniforms;
+
+@group(1) @binding(0)
+var texture_sampler: sampler;
+
+@group(1) @binding(1)
+var texture_data: texture_2d<f32>;
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.clip_position = uniforms.view_proj * vec4<f32>(input.position, 0.0, 1.0);
+    output.color = input.color;
+    output.tex_coords = input.tex_coords;
+    return output;
+}
@@ -0,0 +1,37 @@
+// Bloom: keep the bright parts, blur them in two separable passes, add them back.
+// params0: x threshold, y soft knee (threshold pass); xy direction (blur pass);
+//          x intensity (composite pass)
+
+@fragment
+fn fs_threshold(input: FullscreenVertex) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input0, post_sampler, input.uv, 0.0).rgb;
+    let brightness = max(color.r, max(color.g, color.b));
+    let threshold = post.params0.x;
+    let knee = max(post.params0.y, 0.0001);
+    // Quadratic ramp over the knee instead of a hard cut-off
+    let soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+    let contribution = max(soft * soft / (4.0 * knee), brightness - threshold) / max(brightness, 0.0001);
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_blur(input: FullscreenVertex) -> @location(0) vec4<f32> {
+    // 9-tap Gaussian folded into 5 bilinear samples
+    let step = post.params0.xy * post.texel_size;
+    let near = step * 1.3846153846;
+    let far = step * 3.2307692308;
+
+    var color = textureSampleLevel(input0, post_sampler, input.uv, 0.0).rgb * 0.2270270270;
+    color += textureSampleLevel(input0, post_sampler, input.uv + near, 0.0).rgb * 0.3162162162;
+    color += textureSampleLevel(input0, post_sampler, input.uv - near, 0.0).rgb * 0.3162162162;
+    color += textureSampleLevel(input0, post_sampler, input.uv + far, 0.0).rgb * 0.0702702703;
+    color += textureSampleLevel(input0, post_sampler, input.uv - far, 0.0).rgb * 0.0702702703;
+    return vec4<f32>(color, 1.0);
+}
+
+@fragment
+fn fs_composite(input: FullscreenVertex) -> @location(0) vec4<f32> {
+    let scene = textureSampleLevel(input0, post_sampler, input.uv, 0.0);
+    let bloom = textureSampleLevel(input1, post_sampler, input.uv, 0.0).rgb;
+    return vec4<f32>(scene.rgb + bloom * post.params0.x, scene.a);
+}
@@ -0,0 +1,27 @@
+// Colour grading through a lookup table stored as a strip of `size` slices of
+// size x size texels, blue selecting the slice.
+// params0: x LUT size, y strength
+
+fn sample_lut(color: vec3<f32>) -> vec3<f32> {
+    let size = post.params0.x;
+    let scaled = clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)) * (size - 1.0);
+    let slice = floor(scaled.b);
+    let next = min(slice + 1.0, size - 1.0);
+    let strip = vec2<f32>(size * size, size);
+    // Sample texel centres so red and green interpolate within a slice only
+    let within = scaled.rg + 0.5;
+    let a = textureSampleLevel(lut, post_sampler, vec2<f32>(slice * size + within.x, within.y) / strip, 0.0).rgb;
+    let b = textureSampleLevel(lut, post_sampler, vec2<f32>(next * size + within.x, within.y) / strip, 0.0).rgb;
+    return mix(a, b, scaled.b - slice);
+}
+
+@fragment
+fn fs_main(input: FullscreenVertex) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input0, post_sampler, input.uv, 0.0);
+    // LUTs are authored against gamma-encoded colour
+    let srgb_input = post.options.x > 0.5;
+    let encoded = select(color.rgb, linear_to_srgb(color.rgb), srgb_input);
+    let graded = sample_lut(encoded);
+    let decoded = select(graded, srgb_to_linear(graded), srgb_input);
+    return vec4<f32>(mix(color.rgb, decoded, post.params0.y), color.a);
+}
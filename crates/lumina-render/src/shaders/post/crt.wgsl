@@ -0,0 +1,20 @@
+// Old monitor look: curved screen, scanlines and colour fringing.
+// params0: x curvature, y scanline intensity, z fringe offset in pixels
+
+@fragment
+fn fs_main(input: FullscreenVertex) -> @location(0) vec4<f32> {
+    let centered = input.uv * 2.0 - 1.0;
+    let uv = (centered + centered * centered.yx * centered.yx * post.params0.x) * 0.5 + 0.5;
+
+    let fringe = vec2<f32>(post.params0.z * post.texel_size.x, 0.0);
+    let color = vec3<f32>(
+        textureSampleLevel(input0, post_sampler, uv + fringe, 0.0).r,
+        textureSampleLevel(input0, post_sampler, uv, 0.0).g,
+        textureSampleLevel(input0, post_sampler, uv - fringe, 0.0).b,
+    );
+
+    // One dark line every other output row
+    let scanline = 1.0 - post.params0.y * (0.5 + 0.5 * cos(uv.y * post.resolution.y * 3.14159265));
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    return vec4<f32>(select(vec3<f32>(0.0), color * scanline, inside), 1.0);
+}
@@ -0,0 +1,9 @@
+// Snaps the image to a coarse pixel grid.
+// params0: x cell size in output pixels
+
+@fragment
+fn fs_main(input: FullscreenVertex) -> @location(0) vec4<f32> {
+    let cell = max(post.params0.x, 1.0) / post.resolution;
+    let uv = (floor(input.uv / cell) + 0.5) * cell;
+    return textureSampleLevel(input0, post_sampler, uv, 0.0);
+}
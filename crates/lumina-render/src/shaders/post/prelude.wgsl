@@ -0,0 +1,48 @@
+// Shared declarations for fullscreen post effects. The effect's own source is appended
+// after this prelude and the texture declarations generated for its inputs.
+
+struct PostUniforms {
+    // Output size in pixels
+    resolution: vec2<f32>,
+    // Size of one texel of the first input
+    texel_size: vec2<f32>,
+    // Effect parameters
+    params0: vec4<f32>,
+    params1: vec4<f32>,
+    // x: 1.0 if the first input is sRGB-encoded, so samples come back linear
+    options: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var post_sampler: sampler;
+
+@group(0) @binding(1)
+var<uniform> post: PostUniforms;
+
+struct FullscreenVertex {
+    @builtin(position) position: vec4<f32>,
+    // (0, 0) at the top-left corner of the output
+    @location(0) uv: vec2<f32>,
+}
+
+// One triangle covering the whole output
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> FullscreenVertex {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    var output: FullscreenVertex;
+    output.position = vec4<f32>(uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0), 0.0, 1.0);
+    output.uv = uv;
+    return output;
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
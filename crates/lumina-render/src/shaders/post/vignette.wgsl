@@ -0,0 +1,11 @@
+// Darkens the corners.
+// params0: x intensity, y radius where darkening starts, z softness
+
+@fragment
+fn fs_main(input: FullscreenVertex) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(input0, post_sampler, input.uv, 0.0);
+    // 0 at the centre, 1 in the corners
+    let distance = length(input.uv - vec2<f32>(0.5)) * 1.4142135;
+    let falloff = smoothstep(post.params0.y, post.params0.y + max(post.params0.z, 0.0001), distance);
+    return vec4<f32>(color.rgb * (1.0 - post.params0.x * falloff), color.a);
+}
@@ -1,4 +1,4 @@
-// Solid color shader for UI elements
+// Shape shader for UI elements: solid quads, SDF rounded rectangles and their shadows
 
 struct UiUniforms {
     view_proj: mat4x4<f32>,
@@ -7,14 +7,23 @@ struct UiUniforms {
 
 struct VertexInput {
     @location(0) position: vec2<f32>,
-    @location(1) tex_coords: vec2<f32>,
-    @location(2) color: vec4<f32>,
+    @location(1) local: vec2<f32>,
+    @location(2) half_size: vec2<f32>,
+    @location(3) color: vec4<f32>,
+    @location(4) radii: vec4<f32>,
+    @location(5) border_color: vec4<f32>,
+    // x: border width, y: shadow blur, z: kind (0 solid, 1 rounded rect, 2 shadow)
+    @location(6) params: vec4<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
-    @location(1) tex_coords: vec2<f32>,
+    @location(1) local: vec2<f32>,
+    @location(2) half_size: vec2<f32>,
+    @location(3) radii: vec4<f32>,
+    @location(4) border_color: vec4<f32>,
+    @location(5) params: vec4<f32>,
 }
 
 @group(0) @binding(0)
@@ -23,18 +32,50 @@ var<uniform> uniforms: UiUniforms;
 @vertex
 fn vs_main(input: VertexInput) -> VertexOutput {
     var output: VertexOutput;
-    
+
     // Convert screen coordinates to normalized device coordinates
     let screen_pos = vec4<f32>(input.position, 0.0, 1.0);
     output.clip_position = uniforms.view_proj * screen_pos;
-    
+
     output.color = input.color;
-    output.tex_coords = input.tex_coords;
-    
+    output.local = input.local;
+    output.half_size = input.half_size;
+    output.radii = input.radii;
+    output.border_color = input.border_color;
+    output.params = input.params;
+
     return output;
 }
 
+// Signed distance to a rectangle centred on the origin. Screen space is y-down, so the
+// radii (top-left, top-right, bottom-right, bottom-left) are picked by the quadrant of `p`.
+fn rounded_box_sdf(p: vec2<f32>, half_size: vec2<f32>, radii: vec4<f32>) -> f32 {
+    let side = select(radii.xw, radii.yz, p.x > 0.0);
+    let r = select(side.x, side.y, p.y > 0.0);
+    let q = abs(p) - half_size + vec2<f32>(r);
+    return min(max(q.x, q.y), 0.0) + length(max(q, vec2<f32>(0.0))) - r;
+}
+
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    return input.color;
-}
\ No newline at end of file
+    let d = rounded_box_sdf(input.local, input.half_size, input.radii);
+    // Derivatives must be taken in uniform control flow, before branching on the kind
+    let aa = max(fwidth(d), 0.0001) * 0.5;
+    let kind = input.params.z;
+
+    if (kind < 0.5) {
+        return input.color;
+    }
+
+    if (kind > 1.5) {
+        let blur = max(input.params.y, 1.0) * 0.5;
+        let alpha = 1.0 - smoothstep(-blur, blur, d);
+        return vec4<f32>(input.color.rgb, input.color.a * alpha);
+    }
+
+    let coverage = 1.0 - smoothstep(-aa, aa, d);
+    let border = input.params.x;
+    let inside_border = 1.0 - smoothstep(-aa, aa, d + border);
+    let color = select(input.color, mix(input.border_color, input.color, inside_border), border > 0.0);
+    return vec4<f32>(color.rgb, color.a * coverage);
+}
@@ -0,0 +1,1017 @@
+//! 2D sprite rendering
+//!
+//! Sprites are collected on the CPU into a `SpriteDrawList`: quads sorted by
+//! layer (and optionally by y within a layer) and grouped into one batch per
+//! run of sprites sharing a texture and material. `SpriteRenderer` uploads the
+//! list and issues one draw call per batch, once for each camera view.
+//!
+//! Sprites with a `Material` are drawn with the material's shader instead of the
+//! built-in one; `SpriteRenderer::prepare_materials` builds their pipelines.
+
+use crate::material::{Material, MaterialHandle, MaterialManager};
+use crate::shader::{builtin_module, ShaderManager};
+use crate::texture::{TextureHandle, TextureManager, TextureRef};
+use crate::{CameraView, Rect, RenderError, RenderResult, UiUniforms, UiVertex};
+use glam::{Mat4, Vec2, Vec4};
+use lumina_core::math::Transform2D;
+use lumina_ecs::{Query, World};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// A textured quad drawn in world space
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sprite {
+    /// Texture to sample, kept loaded while the sprite exists
+    pub texture: TextureRef,
+    /// Size of the quad before the transform's scale is applied
+    pub size: Vec2,
+    /// Tint multiplied with the texture color
+    pub color: Vec4,
+    /// Region of the texture to draw, in normalized UV coordinates.
+    /// `None` draws the whole texture.
+    pub region: Option<Rect>,
+    /// Point of the quad placed at the transform's position, from (0, 0)
+    /// at the top-left corner to (1, 1) at the bottom-right
+    pub pivot: Vec2,
+    /// Mirror the texture horizontally
+    pub flip_x: bool,
+    /// Mirror the texture vertically
+    pub flip_y: bool,
+    /// Sorting layer; higher layers draw on top
+    pub layer: i32,
+    /// Whether the sprite is drawn at all
+    pub visible: bool,
+    /// Material replacing the built-in sprite shader
+    pub material: Option<MaterialHandle>,
+}
+
+impl Sprite {
+    /// Create a sprite covering the whole texture, centered on its transform
+    pub fn new(texture: TextureRef, size: Vec2) -> Self {
+        Self {
+            texture,
+            size,
+            color: Vec4::ONE,
+            region: None,
+            pivot: Vec2::splat(0.5),
+            flip_x: false,
+            flip_y: false,
+            layer: 0,
+            visible: true,
+            material: None,
+        }
+    }
+
+    /// Set the tint color
+    pub fn with_color(mut self, color: Vec4) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Set the texture region
+    pub fn with_region(mut self, region: Rect) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Set the pivot
+    pub fn with_pivot(mut self, pivot: Vec2) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    /// Set the sorting layer
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// Draw the sprite with a material
+    pub fn with_material(mut self, material: MaterialHandle) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Set horizontal and vertical flipping
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Corner UVs in the order top-left, top-right, bottom-right, bottom-left
+    fn uvs(&self) -> [[f32; 2]; 4] {
+        let region = self.region.unwrap_or(Rect::new(0.0, 0.0, 1.0, 1.0));
+        let (mut u0, mut v0) = (region.min().x, region.min().y);
+        let (mut u1, mut v1) = (region.max().x, region.max().y);
+        if self.flip_x {
+            std::mem::swap(&mut u0, &mut u1);
+        }
+        if self.flip_y {
+            std::mem::swap(&mut v0, &mut v1);
+        }
+        [[u0, v0], [u1, v0], [u1, v1], [u0, v1]]
+    }
+}
+
+/// A grid of equally sized frames packed into one texture
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextureAtlas {
+    /// Texture holding the frames, kept loaded while the atlas exists
+    pub texture: TextureRef,
+    /// Size of the whole texture in pixels
+    pub texture_size: Vec2,
+    /// Size of one frame in pixels
+    pub tile_size: Vec2,
+    /// Number of frame columns
+    pub columns: u32,
+    /// Number of frame rows
+    pub rows: u32,
+    /// Gap between neighbouring frames in pixels
+    pub padding: Vec2,
+    /// Offset of the first frame from the texture's top-left corner in pixels
+    pub offset: Vec2,
+}
+
+impl TextureAtlas {
+    /// Create an atlas of `columns` x `rows` frames with no padding
+    pub fn from_grid(texture: TextureRef, texture_size: Vec2, tile_size: Vec2, columns: u32, rows: u32) -> Self {
+        Self {
+            texture,
+            texture_size,
+            tile_size,
+            columns,
+            rows,
+            padding: Vec2::ZERO,
+            offset: Vec2::ZERO,
+        }
+    }
+
+    /// Number of frames in the atlas
+    pub fn len(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    /// Whether the atlas has no frames
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The normalized UV region of a frame, counted row by row from the top-left
+    pub fn frame(&self, index: usize) -> Option<Rect> {
+        if index >= self.len() {
+            return None;
+        }
+        let column = (index as u32 % self.columns) as f32;
+        let row = (index as u32 / self.columns) as f32;
+        let min = self.offset + Vec2::new(column, row) * (self.tile_size + self.padding);
+        Some(Rect::from_min_max(min / self.texture_size, (min + self.tile_size) / self.texture_size))
+    }
+
+    /// A sprite showing one frame of the atlas
+    pub fn sprite(&self, index: usize, size: Vec2) -> Option<Sprite> {
+        self.frame(index).map(|region| Sprite::new(self.texture.clone(), size).with_region(region))
+    }
+}
+
+/// A run of consecutive indices drawn with one texture and material
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpriteBatch {
+    /// Texture bound for the batch
+    pub texture: TextureHandle,
+    /// Material drawn with, or `None` for the built-in sprite shader
+    pub material: Option<MaterialHandle>,
+    /// Range into `SpriteDrawList::indices`
+    pub indices: Range<u32>,
+}
+
+/// Sorted and batched sprite geometry for one frame
+#[derive(Debug, Clone, Default)]
+pub struct SpriteDrawList {
+    /// Four vertices per sprite
+    pub vertices: Vec<UiVertex>,
+    /// Six indices per sprite
+    pub indices: Vec<u32>,
+    /// Draw calls in submission order
+    pub batches: Vec<SpriteBatch>,
+}
+
+impl SpriteDrawList {
+    /// Number of sprites in the list
+    pub fn sprite_count(&self) -> usize {
+        self.vertices.len() / 4
+    }
+
+    /// Whether there is nothing to draw
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.batches.clear();
+    }
+
+    fn push(&mut self, transform: &Transform2D, sprite: &Sprite) {
+        let base = self.vertices.len() as u32;
+        let min = -sprite.pivot * sprite.size;
+        let max = min + sprite.size;
+        let corners = [min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)];
+        let color = sprite.color.to_array();
+        for (corner, uv) in corners.into_iter().zip(sprite.uvs()) {
+            self.vertices.push(UiVertex {
+                position: transform.transform_point(corner).to_array(),
+                tex_coords: uv,
+                color,
+            });
+        }
+
+        let start = self.indices.len() as u32;
+        self.indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+        match self.batches.last_mut() {
+            Some(batch) if batch.texture == sprite.texture.handle() && batch.material == sprite.material => {
+                batch.indices.end += 6
+            }
+            _ => self.batches.push(SpriteBatch {
+                texture: sprite.texture.handle(),
+                material: sprite.material,
+                indices: start..start + 6,
+            }),
+        }
+    }
+}
+
+/// Collects sprites for a frame and turns them into a `SpriteDrawList`.
+///
+/// Sprites are ordered by layer. Within a y-sorted layer, sprites with a
+/// larger y (further down the screen) draw later; within other layers,
+/// sprites are grouped by texture and then material to keep batches large,
+/// keeping submission order for sprites that share both.
+#[derive(Debug, Default)]
+pub struct SpriteBatcher {
+    queued: Vec<(Transform2D, Sprite)>,
+    y_sorted_layers: HashSet<i32>,
+    draw_list: SpriteDrawList,
+}
+
+impl SpriteBatcher {
+    /// Create an empty batcher
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable or disable y-sorting for a layer
+    pub fn set_y_sort(&mut self, layer: i32, enabled: bool) {
+        if enabled {
+            self.y_sorted_layers.insert(layer);
+        } else {
+            self.y_sorted_layers.remove(&layer);
+        }
+    }
+
+    /// Queue a sprite for this frame
+    pub fn push(&mut self, transform: Transform2D, sprite: Sprite) {
+        if sprite.visible {
+            self.queued.push((transform, sprite));
+        }
+    }
+
+    /// Queue every entity with both a `Sprite` and a `Transform2D`
+    pub fn extract(&mut self, world: &World) {
+        Query::<(&Transform2D, &Sprite)>::new(world).for_each(|(transform, sprite)| {
+            self.push(*transform, sprite.clone());
+        });
+    }
+
+    /// Number of sprites queued since the last build
+    pub fn len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Whether no sprites are queued
+    pub fn is_empty(&self) -> bool {
+        self.queued.is_empty()
+    }
+
+    /// Sort and batch the queued sprites, emptying the queue
+    pub fn build(&mut self) -> &SpriteDrawList {
+        let y_sorted = &self.y_sorted_layers;
+        self.queued.sort_by(|(a_transform, a), (b_transform, b)| {
+            a.layer.cmp(&b.layer).then_with(|| {
+                if y_sorted.contains(&a.layer) {
+                    a_transform.position.y.total_cmp(&b_transform.position.y)
+                } else {
+                    a.texture.handle().0.cmp(&b.texture.handle().0).then_with(|| a.material.cmp(&b.material))
+                }
+            })
+        });
+
+        self.draw_list.clear();
+        for (transform, sprite) in self.queued.drain(..) {
+            self.draw_list.push(&transform, &sprite);
+        }
+        &self.draw_list
+    }
+
+    /// The list produced by the last `build`
+    pub fn draw_list(&self) -> &SpriteDrawList {
+        &self.draw_list
+    }
+}
+
+/// GPU state of one material, rebuilt when its shader reloads or it is edited
+struct MaterialGpu {
+    shader_generation: u32,
+    version: u32,
+    textures: Vec<TextureHandle>,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    /// `None` after one of `textures` was invalidated
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+/// GPU side of sprite rendering: uploads a `SpriteDrawList` and draws its batches
+pub struct SpriteRenderer {
+    batcher: SpriteBatcher,
+    shader: wgpu::ShaderModule,
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    /// One `UiUniforms` per view, `uniform_stride` bytes apart
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
+    uniform_bind_group: wgpu::BindGroup,
+    uniform_stride: u64,
+    /// Views the uniform buffer can hold
+    view_capacity: usize,
+    /// Pixel viewport of each view prepared by the last `prepare`; `None` keeps the pass's own
+    viewports: Vec<Option<Rect>>,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_groups: HashMap<TextureHandle, wgpu::BindGroup>,
+    material_gpu: HashMap<MaterialHandle, MaterialGpu>,
+    /// Shader generation of materials whose pipeline failed to build, so they aren't retried every frame
+    failed_materials: HashMap<MaterialHandle, u32>,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    /// Sprites the current buffers can hold
+    capacity: usize,
+    /// Batches uploaded by the last `prepare`, minus those without a texture
+    batches: Vec<SpriteBatch>,
+}
+
+impl SpriteRenderer {
+    /// Create a sprite renderer drawing into targets of `format`
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> RenderResult<Self> {
+        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Sprite Uniform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<UiUniforms>() as u64),
+                },
+                count: None,
+            }],
+        });
+
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let uniform_stride = (std::mem::size_of::<UiUniforms>() as u64).next_multiple_of(alignment);
+        let view_capacity = 4;
+        let (uniform_buffer, uniform_bind_group) =
+            Self::create_uniforms(device, &uniform_bind_group_layout, uniform_stride, view_capacity);
+
+        let texture_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Sprite Texture Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sprite Pipeline Layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout, &texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = builtin_module(device, "shaders/texture.wgsl", include_str!("shaders/texture.wgsl"))?;
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, &shader, format, 1);
+
+        let capacity = 1024;
+        let (vertex_buffer, index_buffer) = Self::create_buffers(device, capacity);
+
+        Ok(Self {
+            batcher: SpriteBatcher::new(),
+            shader,
+            pipeline,
+            pipeline_layout,
+            format,
+            sample_count: 1,
+            uniform_buffer,
+            uniform_bind_group_layout,
+            uniform_bind_group,
+            uniform_stride,
+            view_capacity,
+            viewports: Vec::new(),
+            texture_bind_group_layout,
+            texture_bind_groups: HashMap::new(),
+            material_gpu: HashMap::new(),
+            failed_materials: HashMap::new(),
+            vertex_buffer,
+            index_buffer,
+            capacity,
+            batches: Vec::new(),
+        })
+    }
+
+    /// Rebuild the pipeline for render passes with `sample_count` samples per pixel
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        if sample_count != self.sample_count {
+            self.sample_count = sample_count;
+            self.pipeline =
+                Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, sample_count);
+            // Rebuilt by the next `prepare_materials`
+            self.material_gpu.clear();
+            self.failed_materials.clear();
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Sprite Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[UiVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+        })
+    }
+
+    fn create_uniforms(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        stride: u64,
+        views: usize,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Uniform Buffer"),
+            size: stride * views as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sprite Uniform Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<UiUniforms>() as u64),
+                }),
+            }],
+        });
+        (buffer, bind_group)
+    }
+
+    fn create_buffers(device: &wgpu::Device, capacity: usize) -> (wgpu::Buffer, wgpu::Buffer) {
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Vertex Buffer"),
+            size: (std::mem::size_of::<UiVertex>() * 4 * capacity) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Index Buffer"),
+            size: (std::mem::size_of::<u32>() * 6 * capacity) as u64,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        (vertex_buffer, index_buffer)
+    }
+
+    /// The CPU batcher sprites are queued into
+    pub fn batcher(&self) -> &SpriteBatcher {
+        &self.batcher
+    }
+
+    /// Mutable access to the CPU batcher
+    pub fn batcher_mut(&mut self) -> &mut SpriteBatcher {
+        &mut self.batcher
+    }
+
+    /// Build the draw list from the queued sprites and upload it, to be drawn once with
+    /// `view_proj` over the whole render pass.
+    ///
+    /// Batches whose texture is not loaded in `textures` are skipped.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        textures: &TextureManager,
+        view_proj: Mat4,
+        screen_size: Vec2,
+    ) {
+        let view = CameraView::full_target(view_proj, screen_size);
+        self.prepare_views(device, queue, textures, &[view], false);
+    }
+
+    /// Like `prepare`, but the sprites are drawn once per camera, in the given order and
+    /// each inside its own viewport. Viewports must lie within the render target.
+    pub fn prepare_cameras(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        textures: &TextureManager,
+        views: &[CameraView],
+    ) {
+        self.prepare_views(device, queue, textures, views, true);
+    }
+
+    fn prepare_views(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        textures: &TextureManager,
+        views: &[CameraView],
+        set_viewports: bool,
+    ) {
+        // Handles aren't reused, so entries for freed textures would only leak
+        self.texture_bind_groups.retain(|handle, _| textures.get_texture(*handle).is_some());
+        let draw_list = self.batcher.build();
+        self.batches.clear();
+        self.viewports.clear();
+        if draw_list.is_empty() || views.is_empty() {
+            return;
+        }
+
+        if draw_list.sprite_count() > self.capacity {
+            self.capacity = draw_list.sprite_count().next_power_of_two();
+            (self.vertex_buffer, self.index_buffer) = Self::create_buffers(device, self.capacity);
+        }
+
+        if views.len() > self.view_capacity {
+            self.view_capacity = views.len().next_power_of_two();
+            (self.uniform_buffer, self.uniform_bind_group) =
+                Self::create_uniforms(device, &self.uniform_bind_group_layout, self.uniform_stride, self.view_capacity);
+        }
+        for (index, view) in views.iter().enumerate() {
+            let uniforms = UiUniforms {
+                view_proj: view.view_proj.to_cols_array_2d(),
+                screen_size: view.viewport.size.to_array(),
+                _padding: [0.0, 0.0],
+            };
+            queue.write_buffer(&self.uniform_buffer, index as u64 * self.uniform_stride, bytemuck::cast_slice(&[uniforms]));
+            self.viewports.push(set_viewports.then_some(view.viewport));
+        }
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&draw_list.vertices));
+        queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&draw_list.indices));
+
+        for batch in &draw_list.batches {
+            let Some(texture) = textures.get_texture(batch.texture) else {
+                log::warn!("Skipping sprites using missing texture {:?}", batch.texture);
+                continue;
+            };
+            let layout = &self.texture_bind_group_layout;
+            self.texture_bind_groups.entry(batch.texture).or_insert_with(|| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Sprite Texture Bind Group"),
+                    layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::TextureView(&texture.view),
+                        },
+                    ],
+                })
+            });
+            self.batches.push(batch.clone());
+        }
+    }
+
+    /// Forget the cached bind group of a texture, e.g. after it was replaced
+    pub fn invalidate_texture(&mut self, texture: TextureHandle) {
+        self.texture_bind_groups.remove(&texture);
+        for gpu in self.material_gpu.values_mut() {
+            if gpu.textures.contains(&texture) {
+                gpu.bind_group = None;
+            }
+        }
+    }
+
+    /// Create or update the GPU state of every material in `materials`: pipelines are
+    /// rebuilt after their shader was reloaded, and uniforms re-uploaded after an edit.
+    /// Call before `render` when sprites use materials.
+    ///
+    /// Sprites whose material has no pipeline, e.g. because its shader doesn't match
+    /// the sprite interface, are skipped; the error is logged.
+    pub fn prepare_materials(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        shaders: &mut ShaderManager,
+        materials: &MaterialManager,
+        textures: &TextureManager,
+    ) {
+        self.material_gpu.retain(|handle, _| materials.get(*handle).is_some());
+        self.failed_materials.retain(|handle, _| materials.get(*handle).is_some());
+
+        for (handle, material) in materials.iter() {
+            let generation = shaders.generation(material.shader);
+            let version = materials.version(handle).unwrap_or(0);
+            let texture_handles: Vec<TextureHandle> = material.textures().iter().map(|(_, texture)| texture.handle()).collect();
+            let data = material.uniform_data();
+            if self.failed_materials.get(&handle) == Some(&generation) {
+                continue;
+            }
+
+            let mut gpu = match self.material_gpu.remove(&handle) {
+                Some(gpu)
+                    if gpu.shader_generation == generation
+                        && gpu.textures.len() == texture_handles.len()
+                        && gpu.uniform_buffer.size() == data.len() as u64 =>
+                {
+                    gpu
+                }
+                _ => match self.create_material_gpu(device, shaders, material, texture_handles.len(), data.len()) {
+                    Ok(gpu) => gpu,
+                    Err(err) => {
+                        log::error!("Failed to build material {}: {}", material.name, err);
+                        self.failed_materials.insert(handle, generation);
+                        continue;
+                    }
+                },
+            };
+            self.failed_materials.remove(&handle);
+
+            if gpu.textures.iter().any(|texture| textures.get_texture(*texture).is_none()) {
+                gpu.bind_group = None;
+            }
+            if gpu.bind_group.is_none() || gpu.version != version {
+                queue.write_buffer(&gpu.uniform_buffer, 0, &data);
+                gpu.version = version;
+                gpu.textures = texture_handles;
+                gpu.bind_group = Self::create_material_bind_group(device, &gpu, textures);
+                if gpu.bind_group.is_none() {
+                    log::warn!("Skipping sprites using material {} with a missing texture", material.name);
+                }
+            }
+            self.material_gpu.insert(handle, gpu);
+        }
+    }
+
+    fn create_material_gpu(
+        &self,
+        device: &wgpu::Device,
+        shaders: &mut ShaderManager,
+        material: &Material,
+        texture_count: usize,
+        uniform_size: usize,
+    ) -> RenderResult<MaterialGpu> {
+        let mut entries = vec![wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }];
+        entries.extend((1..=texture_count as u32).map(|binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        }));
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Sprite Material Bind Group Layout"),
+            entries: &entries,
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Sprite Material Pipeline Layout"),
+            bind_group_layouts: &[
+                &self.uniform_bind_group_layout,
+                &self.texture_bind_group_layout,
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        // The shader validated on its own, but may still not fit the sprite pipeline
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let pipeline = shaders
+            .module(device, material.shader)
+            .map(|module| Self::create_pipeline(device, &pipeline_layout, module, self.format, self.sample_count));
+        let error = pollster::block_on(device.pop_error_scope());
+        let pipeline = pipeline.ok_or_else(|| RenderError::ResourceNotFound(format!("shader {:?}", material.shader)))?;
+        if let Some(error) = error {
+            return Err(RenderError::ShaderCompilation(error.to_string()));
+        }
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Sprite Material Uniform Buffer"),
+            size: uniform_size as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Ok(MaterialGpu {
+            shader_generation: shaders.generation(material.shader),
+            version: 0,
+            textures: Vec::new(),
+            bind_group_layout,
+            pipeline,
+            uniform_buffer,
+            bind_group: None,
+        })
+    }
+
+    /// The material's bind group, or `None` if one of its textures isn't loaded
+    fn create_material_bind_group(
+        device: &wgpu::Device,
+        gpu: &MaterialGpu,
+        textures: &TextureManager,
+    ) -> Option<wgpu::BindGroup> {
+        let views = gpu
+            .textures
+            .iter()
+            .map(|handle| textures.get_texture(*handle).map(|texture| &texture.view))
+            .collect::<Option<Vec<_>>>()?;
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: gpu.uniform_buffer.as_entire_binding(),
+        }];
+        entries.extend(views.into_iter().zip(1..).map(|(view, binding)| wgpu::BindGroupEntry {
+            binding,
+            resource: wgpu::BindingResource::TextureView(view),
+        }));
+        Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Sprite Material Bind Group"),
+            layout: &gpu.bind_group_layout,
+            entries: &entries,
+        }))
+    }
+
+    /// Draw the batches uploaded by the last `prepare` or `prepare_cameras`, once per view
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.batches.is_empty() {
+            return;
+        }
+
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+
+        for (index, viewport) in self.viewports.iter().enumerate() {
+            if let Some(viewport) = viewport {
+                let Rect { position, size } = *viewport;
+                render_pass.set_viewport(position.x, position.y, size.x, size.y, 0.0, 1.0);
+            }
+            let offset = (index as u64 * self.uniform_stride) as wgpu::DynamicOffset;
+            render_pass.set_bind_group(0, &self.uniform_bind_group, &[offset]);
+            // The pipeline bound so far, by material
+            let mut bound: Option<Option<MaterialHandle>> = None;
+            for batch in &self.batches {
+                let Some(bind_group) = self.texture_bind_groups.get(&batch.texture) else {
+                    continue;
+                };
+                let pipeline = match batch.material {
+                    Some(material) => match self.material_gpu.get(&material) {
+                        Some(MaterialGpu { pipeline, bind_group: Some(material_bind_group), .. }) => {
+                            render_pass.set_bind_group(2, material_bind_group, &[]);
+                            pipeline
+                        }
+                        _ => continue,
+                    },
+                    None => &self.pipeline,
+                };
+                if bound != Some(batch.material) {
+                    render_pass.set_pipeline(pipeline);
+                    bound = Some(batch.material);
+                }
+                render_pass.set_bind_group(1, bind_group, &[]);
+                render_pass.draw_indexed(batch.indices.clone(), 0, 0..1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32, y: f32) -> Transform2D {
+        Transform2D::new(Vec2::new(x, y), 0.0, Vec2::ONE)
+    }
+
+    #[test]
+    fn sprites_sort_by_layer_and_batch_by_texture() {
+        let (a, b) = (TextureHandle(0), TextureHandle(1));
+        let sprite = |texture| Sprite::new(TextureRef::untracked(texture), Vec2::ONE);
+        let mut batcher = SpriteBatcher::new();
+        batcher.set_y_sort(1, true);
+        batcher.push(at(0.0, 0.0), sprite(b));
+        batcher.push(at(0.0, 0.0), sprite(a));
+        batcher.push(at(0.0, 0.0), sprite(b));
+        batcher.push(at(0.0, 9.0), sprite(a).with_layer(1));
+        batcher.push(at(0.0, 3.0), sprite(b).with_layer(1));
+        batcher.push(at(0.0, 5.0), sprite(a).with_layer(-1));
+
+        let list = batcher.build();
+        let batches: Vec<_> = list.batches.iter().map(|batch| (batch.texture, batch.indices.len())).collect();
+        // Layer -1 and layer 0 share texture `a` at their boundary, as do layers 0 and 1 with `b`.
+        assert_eq!(batches, vec![(a, 12), (b, 18), (a, 6)]);
+        assert_eq!(list.sprite_count(), 6);
+        assert!(batcher.is_empty());
+    }
+
+    #[test]
+    fn sprite_quads_apply_pivot_flip_and_region() {
+        let atlas = TextureAtlas::from_grid(TextureRef::untracked(TextureHandle(0)), Vec2::new(64.0, 32.0), Vec2::splat(16.0), 4, 2);
+        assert_eq!(atlas.frame(5), Some(Rect::from_min_max(Vec2::new(0.25, 0.5), Vec2::new(0.5, 1.0))));
+        assert_eq!(atlas.frame(8), None);
+
+        let sprite = atlas
+            .sprite(5, Vec2::new(4.0, 2.0))
+            .unwrap()
+            .with_pivot(Vec2::ZERO)
+            .with_flip(true, false)
+            .with_color(Vec4::new(1.0, 0.0, 0.0, 1.0));
+        let mut batcher = SpriteBatcher::new();
+        batcher.push(Transform2D::new(Vec2::new(10.0, 10.0), 0.0, Vec2::splat(2.0)), sprite);
+
+        let vertices = &batcher.build().vertices;
+        assert_eq!(vertices[0].position, [10.0, 10.0]);
+        assert_eq!(vertices[2].position, [18.0, 14.0]);
+        assert_eq!(vertices[0].tex_coords, [0.5, 0.5]);
+        assert_eq!(vertices[2].tex_coords, [0.25, 1.0]);
+        assert_eq!(vertices[1].color, [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn material_sprites_use_their_shader_and_follow_edits() {
+        use crate::{Material, MaterialValue, RenderConfig, Renderer, ShaderDefines, TextureOptions, WindowConfig};
+
+        let config = RenderConfig {
+            window: WindowConfig {
+                size: (32, 32),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let Some(renderer) = crate::renderer::test_renderer(config) else { return };
+
+        let mut shaders = ShaderManager::new();
+        let fill = shaders
+            .add(
+                "fill.wgsl",
+                "#include \"lumina/sprite.wgsl\"
+struct Fill { color: vec4<f32> }
+@group(2) @binding(0) var<uniform> material: Fill;
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return material.color;
+}
+",
+                ShaderDefines::new(),
+            )
+            .unwrap();
+        // Valid WGSL, but without the sprite vertex stage
+        let unfit = shaders
+            .add(
+                "unfit.wgsl",
+                "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }",
+                ShaderDefines::new(),
+            )
+            .unwrap();
+
+        let mut materials = MaterialManager::new();
+        let color = |r: f32, g: f32, b: f32| MaterialValue::Color(Vec4::new(r, g, b, 1.0));
+        let green = materials.add(Material::new("fill", fill).with_param("color", color(0.0, 1.0, 0.0)));
+        let broken = materials.add(Material::new("unfit", unfit));
+
+        let mut textures = TextureManager::new();
+        let white = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        let texture = textures
+            .create_texture(&renderer.device, &renderer.queue, &white, None, &TextureOptions::pixel_art())
+            .unwrap();
+
+        let mut sprites = SpriteRenderer::new(&renderer.device, Renderer::HEADLESS_FORMAT).unwrap();
+        let mut render = |sprites: &mut SpriteRenderer, materials: &MaterialManager| {
+            sprites.prepare_materials(&renderer.device, &renderer.queue, &mut shaders, materials, &textures);
+            let batcher = sprites.batcher_mut();
+            batcher.push(at(8.0, 16.0), Sprite::new(texture.clone(), Vec2::splat(16.0)).with_material(green));
+            batcher.push(at(24.0, 16.0), Sprite::new(texture.clone(), Vec2::splat(16.0)).with_material(broken));
+            let view_proj = Mat4::orthographic_rh(0.0, 32.0, 32.0, 0.0, -1.0, 1.0);
+            sprites.prepare(&renderer.device, &renderer.queue, &textures, view_proj, Vec2::splat(32.0));
+
+            let mut encoder = renderer.device.create_command_encoder(&Default::default());
+            {
+                let target = &renderer.offscreen.as_ref().unwrap().view;
+                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: None,
+                    color_attachments: &[Some(renderer.color_attachment(target, Some(wgpu::Color::BLACK)))],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                sprites.render(&mut render_pass);
+            }
+            renderer.queue.submit(std::iter::once(encoder.finish()));
+            renderer.read_pixels().unwrap()
+        };
+
+        let pixels = render(&mut sprites, &materials);
+        assert_eq!(pixels.get_pixel(8, 16).0, [0, 255, 0, 255]);
+        // Sprites whose material failed to build are skipped
+        assert_eq!(pixels.get_pixel(24, 16).0, [0, 0, 0, 255]);
+
+        materials.get_mut(green).unwrap().set_param("color", color(0.0, 0.0, 1.0)).unwrap();
+        let pixels = render(&mut sprites, &materials);
+        assert_eq!(pixels.get_pixel(8, 16).0, [0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn freed_textures_leave_the_bind_group_cache() {
+        use crate::TextureOptions;
+
+        let Some(renderer) = crate::renderer::test_renderer(crate::RenderConfig::default()) else { return };
+        let mut textures = TextureManager::new();
+        let white = image::RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        let texture = textures
+            .create_texture(&renderer.device, &renderer.queue, &white, None, &TextureOptions::pixel_art())
+            .unwrap();
+        let handle = texture.handle();
+
+        let mut sprites = SpriteRenderer::new(&renderer.device, crate::Renderer::HEADLESS_FORMAT).unwrap();
+        let view_proj = Mat4::IDENTITY;
+        sprites.batcher_mut().push(at(0.0, 0.0), Sprite::new(texture, Vec2::ONE));
+        sprites.prepare(&renderer.device, &renderer.queue, &textures, view_proj, Vec2::ONE);
+        assert!(sprites.texture_bind_groups.contains_key(&handle));
+
+        // The queued sprite held the last reference
+        assert_eq!(textures.collect_unused(), vec![handle]);
+        sprites.prepare(&renderer.device, &renderer.queue, &textures, view_proj, Vec2::ONE);
+        assert!(sprites.texture_bind_groups.is_empty());
+    }
+}
@@ -1,112 +1,969 @@
 //! Text rendering implementation
 //!
 //! Provides font loading, glyph caching, and text rendering capabilities.
+//!
+//! Fonts are parsed and rasterised with fontdue. Layout (kerning, line
+//! breaking and alignment) runs on the CPU in `FontLibrary` and never touches
+//! the GPU; glyph bitmaps are only rasterised into the shelf-packed
+//! `GlyphAtlas` when a `TextRenderer` prepares a frame.
+
+use crate::shader::builtin_module;
+use crate::ui::{draw_clip_batches, push_clip_batch, ClipBatch};
+use crate::{FontHandle, Rect, RenderError, RenderResult, UiUniforms, UiVertex};
+use glam::{Mat4, Vec2, Vec4};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// DejaVu Sans, used when no other font is loaded. See `assets/fonts/LICENSE-DejaVu.txt`.
+pub const DEFAULT_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
 
-use crate::{RenderResult, FontHandle};
-use glam::{Vec2, Vec4};
+/// Width and height of the glyph atlas texture in pixels
+pub const GLYPH_ATLAS_SIZE: u32 = 1024;
+
+/// Glyphs the text vertex buffer holds per frame
+const MAX_GLYPHS: usize = 8192;
 
 /// Text rendering system
 pub struct TextRenderer {
-    /// Font cache
-    fonts: Vec<Font>,
-    /// Glyph cache
-    glyph_cache: GlyphCache,
+    /// Fonts, layout and the glyph atlas
+    library: FontLibrary,
+    /// Glyphs queued this frame
+    quads: Vec<GlyphQuad>,
+    /// Vertices built from `quads` by the last `prepare`
+    vertices: Vec<UiVertex>,
+    /// Indices built from `quads` by the last `prepare`
+    indices: Vec<u32>,
+    /// Draw calls built by the last `prepare`, split at clip changes
+    batches: Vec<ClipBatch>,
+    /// First index of each queued glyph, plus the end, built by the last `prepare`
+    glyph_indices: Vec<u32>,
+    /// Clip applied to text queued from now on
+    clip: Option<Rect>,
+    /// Screen size passed to the last `prepare`, bounding the scissor rects
+    target_size: Vec2,
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    uniform_buffer: wgpu::Buffer,
+    uniform_bind_group: wgpu::BindGroup,
+    atlas_texture: wgpu::Texture,
+    atlas_bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
 }
 
 /// Font data and metrics
 pub struct Font {
     /// Font name
     pub name: String,
-    /// Font data
-    pub data: Vec<u8>,
-    /// Font metrics
-    pub metrics: fontdue::Metrics,
+    /// Parsed font
+    pub font: fontdue::Font,
+}
+
+impl Font {
+    /// Vertical metrics for a pixel size
+    pub fn line_metrics(&self, size: f32) -> fontdue::LineMetrics {
+        self.font.horizontal_line_metrics(size).unwrap_or(fontdue::LineMetrics {
+            ascent: size * 0.8,
+            descent: -size * 0.2,
+            line_gap: 0.0,
+            new_line_size: size,
+        })
+    }
+}
+
+/// Identifies one rasterised glyph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    /// Font the glyph comes from
+    pub font: FontHandle,
+    /// The character
+    pub ch: char,
+    /// Pixel size it was rasterised at
+    pub size: u32,
 }
 
 /// Glyph cache for efficient text rendering
 pub struct GlyphCache {
     /// Cached glyphs
-    glyphs: std::collections::HashMap<(FontHandle, char, u32), CachedGlyph>,
+    glyphs: HashMap<GlyphKey, CachedGlyph>,
+    /// Atlas the glyph bitmaps are packed into
+    atlas: GlyphAtlas,
 }
 
 /// A cached glyph with rendering data
 pub struct CachedGlyph {
     /// Glyph metrics
     pub metrics: fontdue::Metrics,
-    /// Glyph bitmap data
-    pub bitmap: Vec<u8>,
-    /// Texture coordinates if uploaded to GPU
-    pub texture_coords: Option<[f32; 4]>,
+    /// Pixel rectangle `[x, y, width, height]` in the atlas, if the glyph has a bitmap
+    pub atlas_rect: Option<[u32; 4]>,
+}
+
+/// A single-channel coverage texture filled with glyphs shelf by shelf.
+///
+/// When a frame's glyphs do not fit, `TextRenderer` clears it once and
+/// refills it with just the glyphs that frame draws.
+pub struct GlyphAtlas {
+    size: u32,
+    pixels: Vec<u8>,
+    shelves: Vec<Shelf>,
+    next_y: u32,
+    /// Rows changed since the last upload
+    dirty_rows: Option<(u32, u32)>,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    x: u32,
+}
+
+/// Gap left around every glyph so linear filtering does not bleed
+const GLYPH_PADDING: u32 = 1;
+
+impl GlyphAtlas {
+    /// Create an empty atlas of `size` x `size` pixels
+    pub fn new(size: u32) -> Self {
+        Self {
+            size,
+            pixels: vec![0; (size * size) as usize],
+            shelves: Vec::new(),
+            next_y: 0,
+            dirty_rows: None,
+        }
+    }
+
+    /// Width and height in pixels
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Coverage values, one byte per pixel, row by row
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Reserve a `width` x `height` region, returning its top-left corner
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (padded_width, padded_height) = (width + GLYPH_PADDING, height + GLYPH_PADDING);
+        if padded_width > self.size {
+            return None;
+        }
+
+        // Use the lowest shelf that fits without wasting more than half its height
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| {
+                padded_height <= shelf.height
+                    && padded_height * 2 >= shelf.height
+                    && shelf.x + padded_width <= self.size
+            })
+            .min_by_key(|shelf| shelf.height);
+        let shelf = match shelf {
+            Some(shelf) => shelf,
+            None => {
+                if self.next_y + padded_height > self.size {
+                    return None;
+                }
+                self.shelves.push(Shelf {
+                    y: self.next_y,
+                    height: padded_height,
+                    x: 0,
+                });
+                self.next_y += padded_height;
+                self.shelves.last_mut().unwrap()
+            }
+        };
+
+        let position = (shelf.x, shelf.y);
+        shelf.x += padded_width;
+        Some(position)
+    }
+
+    /// Copy a coverage bitmap into a region returned by `allocate`
+    pub fn write(&mut self, x: u32, y: u32, width: u32, bitmap: &[u8]) {
+        if width == 0 {
+            return;
+        }
+        let height = bitmap.len() as u32 / width;
+        for (row, line) in bitmap.chunks_exact(width as usize).enumerate() {
+            let start = ((y + row as u32) * self.size + x) as usize;
+            self.pixels[start..start + width as usize].copy_from_slice(line);
+        }
+        self.mark_dirty(y, y + height);
+    }
+
+    /// Remove every glyph
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+        self.shelves.clear();
+        self.next_y = 0;
+        self.mark_dirty(0, self.size);
+    }
+
+    fn mark_dirty(&mut self, start: u32, end: u32) {
+        self.dirty_rows = Some(match self.dirty_rows {
+            Some((dirty_start, dirty_end)) => (dirty_start.min(start), dirty_end.max(end)),
+            None => (start, end),
+        });
+    }
+
+    /// Rows changed since the last call, as a half-open range
+    pub fn take_dirty_rows(&mut self) -> Option<(u32, u32)> {
+        self.dirty_rows.take()
+    }
+}
+
+/// Horizontal placement of each line within the layout box
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextAlign {
+    /// Lines start at the left edge
+    #[default]
+    Left,
+    /// Lines are centered
+    Center,
+    /// Lines end at the right edge
+    Right,
+}
+
+/// Parameters for laying out a block of text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    /// Font size in pixels; rounded to whole pixels
+    pub size: f32,
+    /// Wrap lines that would be wider than this
+    pub max_width: Option<f32>,
+    /// Line alignment
+    pub align: TextAlign,
+    /// Multiplier on the font's line height
+    pub line_spacing: f32,
+}
+
+impl TextStyle {
+    /// Unwrapped, left-aligned text at `size` pixels
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            max_width: None,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+        }
+    }
+
+    /// Wrap at `max_width`
+    pub fn with_max_width(mut self, max_width: f32) -> Self {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    /// Set the alignment
+    pub fn with_align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+        self
+    }
+}
+
+/// A glyph placed by `FontLibrary::layout`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutGlyph {
+    /// Glyph to draw
+    pub key: GlyphKey,
+    /// Top-left corner of the glyph bitmap, relative to the layout origin
+    pub position: Vec2,
+    /// Size of the glyph bitmap
+    pub size: Vec2,
+    /// Line the glyph is on
+    pub line: usize,
+}
+
+/// Positioned glyphs for a block of text
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextLayout {
+    /// Glyphs with a visible bitmap, in text order
+    pub glyphs: Vec<LayoutGlyph>,
+    /// Width of each line, excluding trailing whitespace
+    pub line_widths: Vec<f32>,
+    /// Bounding size of the block
+    pub size: Vec2,
+}
+
+/// One line during layout: characters with their pen positions
+#[derive(Default)]
+struct Line {
+    chars: Vec<(char, f32)>,
+    width: f32,
+}
+
+/// Loaded fonts and the glyph cache
+pub struct FontLibrary {
+    fonts: Vec<Font>,
+    glyph_cache: GlyphCache,
+}
+
+impl FontLibrary {
+    /// Create a library with an empty atlas of `atlas_size` x `atlas_size` pixels
+    pub fn new(atlas_size: u32) -> Self {
+        Self {
+            fonts: Vec::new(),
+            glyph_cache: GlyphCache {
+                glyphs: HashMap::new(),
+                atlas: GlyphAtlas::new(atlas_size),
+            },
+        }
+    }
+
+    /// Load a TrueType or OpenType font from bytes
+    pub fn load_font(&mut self, name: String, font_data: &[u8]) -> RenderResult<FontHandle> {
+        let font = fontdue::Font::from_bytes(font_data, fontdue::FontSettings::default())
+            .map_err(|err| RenderError::FontLoad(format!("{}: {}", name, err)))?;
+        self.fonts.push(Font { name, font });
+        Ok(FontHandle((self.fonts.len() - 1) as u32))
+    }
+
+    /// Load the embedded default font
+    pub fn load_default_font(&mut self) -> RenderResult<FontHandle> {
+        self.load_font("DejaVu Sans".to_string(), DEFAULT_FONT)
+    }
+
+    /// Get font by handle
+    pub fn get_font(&self, handle: FontHandle) -> Option<&Font> {
+        self.fonts.get(handle.0 as usize)
+    }
+
+    /// The glyph atlas
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.glyph_cache.atlas
+    }
+
+    /// Lay out `text`, breaking lines at `\n` and, when `max_width` is set,
+    /// at whitespace (or anywhere inside words too long for a line).
+    pub fn layout(&self, text: &str, font: FontHandle, style: &TextStyle) -> TextLayout {
+        let Some(face) = self.get_font(font) else {
+            return TextLayout::default();
+        };
+        let px = style.size.round().max(1.0);
+        let line_metrics = face.line_metrics(px);
+        let line_height = line_metrics.new_line_size * style.line_spacing;
+
+        let lines = Self::break_lines(&face.font, text, px, style.max_width);
+        let block_width = style
+            .max_width
+            .unwrap_or_else(|| lines.iter().map(|line| line.width).fold(0.0, f32::max));
+
+        let mut layout = TextLayout::default();
+        for (index, line) in lines.iter().enumerate() {
+            let offset = match style.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (block_width - line.width) * 0.5,
+                TextAlign::Right => block_width - line.width,
+            };
+            let baseline = index as f32 * line_height + line_metrics.ascent;
+            for &(ch, x) in &line.chars {
+                let metrics = face.font.metrics(ch, px);
+                if metrics.width == 0 || metrics.height == 0 {
+                    continue;
+                }
+                layout.glyphs.push(LayoutGlyph {
+                    key: GlyphKey {
+                        font,
+                        ch,
+                        size: px as u32,
+                    },
+                    position: Vec2::new(
+                        (offset + x + metrics.xmin as f32).round(),
+                        (baseline - (metrics.ymin + metrics.height as i32) as f32).round(),
+                    ),
+                    size: Vec2::new(metrics.width as f32, metrics.height as f32),
+                    line: index,
+                });
+            }
+            layout.line_widths.push(line.width);
+        }
+        layout.size = Vec2::new(block_width, lines.len() as f32 * line_height);
+        layout
+    }
+
+    fn break_lines(font: &fontdue::Font, text: &str, px: f32, max_width: Option<f32>) -> Vec<Line> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let chars: Vec<char> = paragraph.chars().filter(|&ch| ch != '\r').collect();
+            let mut line = Line::default();
+            let mut pen = 0.0;
+            let mut previous = None;
+            // Index in `line.chars` just after the last whitespace
+            let mut break_at = None;
+            let mut index = 0;
+
+            while index < chars.len() {
+                let ch = chars[index];
+                let kern = previous.and_then(|left| font.horizontal_kern(left, ch, px)).unwrap_or(0.0);
+                let advance = font.metrics(ch, px).advance_width;
+                let x = pen + kern;
+
+                let overflows = max_width.is_some_and(|max| x + advance > max);
+                if overflows && !ch.is_whitespace() && !line.chars.is_empty() {
+                    // Move the partial word to the next line, or break inside it if there is nowhere else
+                    let split = break_at.filter(|&at| at < line.chars.len()).unwrap_or(line.chars.len());
+                    let moved = line.chars.len() - split;
+                    line.chars.truncate(split);
+                    lines.push(std::mem::take(&mut line));
+                    index -= moved;
+                    pen = 0.0;
+                    previous = None;
+                    break_at = None;
+                    continue;
+                }
+
+                line.chars.push((ch, x));
+                if ch.is_whitespace() {
+                    break_at = Some(line.chars.len());
+                } else {
+                    line.width = x + advance;
+                }
+                pen = x + advance;
+                previous = Some(ch);
+                index += 1;
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    /// Size of `text` when laid out with `style`
+    pub fn measure(&self, text: &str, font: FontHandle, style: &TextStyle) -> Vec2 {
+        self.layout(text, font, style).size
+    }
+
+    /// Rasterise a glyph into the atlas if needed, returning its atlas rectangle.
+    /// Returns `None` if the glyph has no bitmap or does not fit even in an empty atlas.
+    ///
+    /// A full atlas is cleared to make room, which invalidates rectangles returned
+    /// earlier; use `rasterize_all` for glyphs that are drawn together.
+    pub fn rasterize(&mut self, key: GlyphKey) -> Option<[u32; 4]> {
+        self.try_rasterize(key).unwrap_or_else(|AtlasFull| {
+            log::debug!("Glyph atlas full, clearing it");
+            self.clear_glyphs();
+            self.try_rasterize(key).ok().flatten()
+        })
+    }
+
+    /// Rasterise every glyph in `keys` so that all of them are in the atlas at once.
+    ///
+    /// If they do not fit next to the glyphs already cached, the atlas is cleared
+    /// once and refilled with just these. Glyphs that still do not fit are left out.
+    pub fn rasterize_all(&mut self, keys: &[GlyphKey]) {
+        if keys.iter().all(|&key| self.try_rasterize(key).is_ok()) {
+            return;
+        }
+        log::debug!("Glyph atlas full, refilling it with the glyphs in use");
+        self.clear_glyphs();
+        let missing = keys.iter().filter(|&&key| self.try_rasterize(key).is_err()).count();
+        if missing > 0 {
+            log::warn!("{} glyphs do not fit in the glyph atlas", missing);
+        }
+    }
+
+    /// Atlas rectangle of a glyph already rasterised, without touching the atlas
+    pub fn glyph_rect(&self, key: GlyphKey) -> Option<[u32; 4]> {
+        self.glyph_cache.glyphs.get(&key).and_then(|glyph| glyph.atlas_rect)
+    }
+
+    fn clear_glyphs(&mut self) {
+        self.glyph_cache.atlas.clear();
+        self.glyph_cache.glyphs.clear();
+    }
+
+    /// Rasterise a glyph unless it is cached, failing without side effects if the atlas is full
+    fn try_rasterize(&mut self, key: GlyphKey) -> Result<Option<[u32; 4]>, AtlasFull> {
+        if let Some(glyph) = self.glyph_cache.glyphs.get(&key) {
+            return Ok(glyph.atlas_rect);
+        }
+        let Some(font) = self.fonts.get(key.font.0 as usize) else {
+            return Ok(None);
+        };
+        let (metrics, bitmap) = font.font.rasterize(key.ch, key.size as f32);
+        let (width, height) = (metrics.width as u32, metrics.height as u32);
+
+        let atlas_rect = if width == 0 || height == 0 {
+            None
+        } else {
+            let atlas = &mut self.glyph_cache.atlas;
+            let (x, y) = atlas.allocate(width, height).ok_or(AtlasFull)?;
+            atlas.write(x, y, width, &bitmap);
+            Some([x, y, width, height])
+        };
+        self.glyph_cache.glyphs.insert(key, CachedGlyph { metrics, atlas_rect });
+        Ok(atlas_rect)
+    }
+}
+
+/// The glyph atlas has no room left for a glyph
+struct AtlasFull;
+
+/// A glyph queued for drawing
+struct GlyphQuad {
+    key: GlyphKey,
+    position: Vec2,
+    size: Vec2,
+    color: Vec4,
+    clip: Option<Rect>,
 }
 
 impl TextRenderer {
-    /// Create a new text renderer
+    /// Create a new text renderer with the default font loaded as `FontHandle(0)`
     pub fn new(
-        _device: &wgpu::Device,
+        device: &wgpu::Device,
         _queue: &wgpu::Queue,
-        _format: wgpu::TextureFormat,
+        format: wgpu::TextureFormat,
     ) -> RenderResult<Self> {
+        let mut library = FontLibrary::new(GLYPH_ATLAS_SIZE);
+        library.load_default_font()?;
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Uniform Buffer"),
+            size: std::mem::size_of::<UiUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let uniform_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Text Uniform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let uniform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Text Uniform Bind Group"),
+            layout: &uniform_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let atlas_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size: wgpu::Extent3d {
+                width: GLYPH_ATLAS_SIZE,
+                height: GLYPH_ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let atlas_view = atlas_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let atlas_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Glyph Atlas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let atlas_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Text Atlas Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let atlas_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Text Atlas Bind Group"),
+            layout: &atlas_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::Sampler(&atlas_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&atlas_view),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout, &atlas_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = builtin_module(device, "shaders/text.wgsl", include_str!("shaders/text.wgsl"))?;
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, &shader, format, 1);
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Vertex Buffer"),
+            size: (std::mem::size_of::<UiVertex>() * 4 * MAX_GLYPHS) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Text Index Buffer"),
+            size: (std::mem::size_of::<u32>() * 6 * MAX_GLYPHS) as u64,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         Ok(Self {
-            fonts: Vec::new(),
-            glyph_cache: GlyphCache {
-                glyphs: std::collections::HashMap::new(),
+            library,
+            quads: Vec::new(),
+            batches: Vec::new(),
+            glyph_indices: Vec::new(),
+            clip: None,
+            target_size: Vec2::ZERO,
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            pipeline,
+            pipeline_layout,
+            shader,
+            format,
+            sample_count: 1,
+            uniform_buffer,
+            uniform_bind_group,
+            atlas_texture,
+            atlas_bind_group,
+            vertex_buffer,
+            index_buffer,
+        })
+    }
+
+    /// Rebuild the pipeline for render passes with `sample_count` samples per pixel
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        if sample_count != self.sample_count {
+            self.sample_count = sample_count;
+            self.pipeline =
+                Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, sample_count);
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[UiVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
             },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
         })
     }
 
     /// Load a font from bytes
     pub fn load_font(&mut self, name: String, font_data: Vec<u8>) -> RenderResult<FontHandle> {
-        // TODO: Implement proper font loading
-        let metrics = fontdue::Metrics {
-            xmin: 0,
-            ymin: 0,
-            width: 12,
-            height: 16,
-            advance_width: 8.0,
-            advance_height: 16.0,
-            bounds: fontdue::OutlineBounds {
-                xmin: 0.0,
-                ymin: 0.0,
-                width: 12.0,
-                height: 16.0,
-            },
-        };
-        
-        self.fonts.push(Font {
-            name,
-            data: font_data,
-            metrics,
-        });
-
-        Ok(FontHandle((self.fonts.len() - 1) as u32))
+        self.library.load_font(name, &font_data)
     }
 
-    /// Load default system font
+    /// Load the embedded default font
     pub fn load_default_font(&mut self) -> RenderResult<FontHandle> {
-        // For now, use dummy font data - in a real implementation,
-        // we would load a system font or embedded font
-        let font_data = vec![0u8; 1024]; // Dummy font data
-        self.load_font("Default".to_string(), font_data)
+        self.library.load_default_font()
+    }
+
+    /// Fonts and layout
+    pub fn library(&self) -> &FontLibrary {
+        &self.library
     }
 
-    /// Render text to the UI renderer
+    /// Discard the glyphs queued for the previous frame
+    pub fn begin_frame(&mut self) {
+        self.quads.clear();
+        self.clip = None;
+    }
+
+    /// Clip text queued from now on to `clip`; glyphs entirely outside it are dropped
+    pub fn set_clip(&mut self, clip: Option<Rect>) {
+        self.clip = clip;
+    }
+
+    /// Queue single-line text with its top-left corner at `position`
     pub fn draw_text(&mut self, text: &str, position: Vec2, font: FontHandle, size: f32, color: Vec4) {
-        // TODO: Implement actual text rendering
-        // This would rasterize glyphs, upload to texture atlas, and generate quads
-        let _ = (text, position, font, size, color);
+        self.draw_layout(&self.library.layout(text, font, &TextStyle::new(size)), position, color);
+    }
+
+    /// Queue text laid out with `style`
+    pub fn draw_text_styled(&mut self, text: &str, position: Vec2, font: FontHandle, style: &TextStyle, color: Vec4) {
+        self.draw_layout(&self.library.layout(text, font, style), position, color);
+    }
+
+    /// Queue a layout produced by `FontLibrary::layout`
+    pub fn draw_layout(&mut self, layout: &TextLayout, position: Vec2, color: Vec4) {
+        let clip = self.clip;
+        self.quads.extend(
+            layout
+                .glyphs
+                .iter()
+                .map(|glyph| GlyphQuad {
+                    key: glyph.key,
+                    position: position + glyph.position,
+                    size: glyph.size,
+                    color,
+                    clip,
+                })
+                .filter(|quad| {
+                    clip.is_none_or(|clip| {
+                        clip.intersects(&Rect::from_min_max(quad.position, quad.position + quad.size))
+                    })
+                }),
+        );
     }
 
     /// Get text dimensions for layout calculations
-    pub fn measure_text(&mut self, text: &str, _font: FontHandle, size: f32) -> Vec2 {
-        // TODO: Implement text measurement
-        // For now, return approximate dimensions
-        let char_width = size * 0.6;
-        let line_height = size;
-        Vec2::new(text.len() as f32 * char_width, line_height)
+    pub fn measure_text(&mut self, text: &str, font: FontHandle, size: f32) -> Vec2 {
+        self.library.measure(text, font, &TextStyle::new(size))
     }
 
     /// Get font by handle
     pub fn get_font(&self, handle: FontHandle) -> Option<&Font> {
-        self.fonts.get(handle.0 as usize)
+        self.library.get_font(handle)
+    }
+
+    /// Rasterise the queued glyphs, upload changed atlas rows and build this frame's geometry
+    pub fn prepare(&mut self, queue: &wgpu::Queue, view_proj: Mat4, screen_size: Vec2) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.batches.clear();
+        self.target_size = screen_size;
+        if self.quads.len() > MAX_GLYPHS {
+            log::warn!("Dropping {} glyphs over the per-frame limit", self.quads.len() - MAX_GLYPHS);
+        }
+
+        self.glyph_indices.clear();
+
+        // Rasterise the whole frame first: refilling a full atlas moves glyphs around
+        let mut seen = HashSet::new();
+        let keys: Vec<GlyphKey> = self
+            .quads
+            .iter()
+            .take(MAX_GLYPHS)
+            .map(|quad| quad.key)
+            .filter(|key| seen.insert(*key))
+            .collect();
+        self.library.rasterize_all(&keys);
+
+        let atlas_size = self.library.atlas().size() as f32;
+        for quad in self.quads.iter().take(MAX_GLYPHS) {
+            self.glyph_indices.push(self.indices.len() as u32);
+            let Some([x, y, width, height]) = self.library.glyph_rect(quad.key) else {
+                continue;
+            };
+            let uv_min = [x as f32 / atlas_size, y as f32 / atlas_size];
+            let uv_max = [(x + width) as f32 / atlas_size, (y + height) as f32 / atlas_size];
+            let (min, max) = (quad.position, quad.position + quad.size);
+            let color = quad.color.to_array();
+            let base = self.vertices.len() as u32;
+            self.vertices.extend_from_slice(&[
+                UiVertex { position: [min.x, min.y], tex_coords: uv_min, color },
+                UiVertex { position: [max.x, min.y], tex_coords: [uv_max[0], uv_min[1]], color },
+                UiVertex { position: [max.x, max.y], tex_coords: uv_max, color },
+                UiVertex { position: [min.x, max.y], tex_coords: [uv_min[0], uv_max[1]], color },
+            ]);
+            let start = self.indices.len() as u32;
+            self.indices.extend_from_slice(&[base, base + 1, base + 2, base + 2, base + 3, base]);
+            push_clip_batch(&mut self.batches, quad.clip, start..start + 6);
+        }
+        self.glyph_indices.push(self.indices.len() as u32);
+
+        let atlas = &mut self.library.glyph_cache.atlas;
+        if let Some((start, end)) = atlas.take_dirty_rows() {
+            let size = atlas.size();
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &self.atlas_texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x: 0, y: start, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &atlas.pixels()[(start * size) as usize..(end * size) as usize],
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(size),
+                    rows_per_image: Some(end - start),
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: end - start,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        if self.indices.is_empty() {
+            return;
+        }
+        let uniforms = UiUniforms {
+            view_proj: view_proj.to_cols_array_2d(),
+            screen_size: screen_size.to_array(),
+            _padding: [0.0, 0.0],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&self.indices));
     }
-}
\ No newline at end of file
+
+    /// Number of glyphs queued so far this frame
+    pub fn queued_glyphs(&self) -> usize {
+        self.quads.len()
+    }
+
+    /// Draw the glyphs uploaded by the last `prepare`
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.render_glyphs(render_pass, 0..self.quads.len());
+    }
+
+    /// Draw the queued glyphs in `glyphs`, numbered in the order they were queued
+    pub fn render_glyphs<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, glyphs: Range<usize>) {
+        let index = |glyph: usize| {
+            let last = self.glyph_indices.len().saturating_sub(1);
+            self.glyph_indices.get(glyph.min(last)).copied().unwrap_or(0)
+        };
+        let indices = index(glyphs.start)..index(glyphs.end);
+        if indices.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.uniform_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        draw_clip_batches(render_pass, &self.batches, indices, self.target_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_kerns_wraps_and_aligns() {
+        let mut library = FontLibrary::new(256);
+        let font = library.load_default_font().unwrap();
+        assert!(library.load_font("Broken".to_string(), &[0u8; 64]).is_err());
+
+        let style = TextStyle::new(20.0);
+        let kerned = library.measure("AV", font, &style).x;
+        let separate = library.measure("A", font, &style).x + library.measure("V", font, &style).x;
+        assert!(kerned < separate);
+
+        // Multi-byte characters count once each
+        assert!(library.measure("é", font, &style).x < library.measure("ee", font, &style).x);
+
+        let width = library.measure("hello", font, &style).x.max(library.measure("world", font, &style).x);
+        let wrapped = library.layout("hello world", font, &style.with_max_width(width + 5.0));
+        assert_eq!(wrapped.line_widths.len(), 2);
+        assert_eq!(wrapped.glyphs.iter().filter(|glyph| glyph.line == 1).count(), 5);
+
+        let broken = library.layout("abcdefghij", font, &style.with_max_width(width * 0.5));
+        assert!(broken.line_widths.len() > 1);
+        assert!(broken.line_widths.iter().all(|&line| line <= width * 0.5));
+
+        let centered = library.layout("hi", font, &style.with_max_width(200.0).with_align(TextAlign::Center));
+        let left = library.layout("hi", font, &style.with_max_width(200.0));
+        let shift = centered.glyphs[0].position.x - left.glyphs[0].position.x;
+        assert!((shift - (200.0 - left.line_widths[0]) / 2.0).abs() <= 1.0);
+        assert_eq!(library.layout("a\nb", font, &style).line_widths.len(), 2);
+    }
+
+    #[test]
+    fn glyph_atlas_packs_without_overlap() {
+        let mut library = FontLibrary::new(64);
+        let font = library.load_default_font().unwrap();
+        let mut rects = Vec::new();
+        for ch in "ABCDEFGH".chars() {
+            rects.push(library.rasterize(GlyphKey { font, ch, size: 14 }).unwrap());
+        }
+        for (index, a) in rects.iter().enumerate() {
+            for b in &rects[index + 1..] {
+                let apart = a[0] + a[2] <= b[0] || b[0] + b[2] <= a[0] || a[1] + a[3] <= b[1] || b[1] + b[3] <= a[1];
+                assert!(apart, "{:?} overlaps {:?}", a, b);
+            }
+        }
+        assert!(library.atlas().pixels().iter().any(|&coverage| coverage > 0));
+        assert_eq!(library.rasterize(GlyphKey { font, ch: ' ', size: 14 }), None);
+
+        // Filling the atlas clears it instead of failing
+        for size in 20..40 {
+            assert!(library.rasterize(GlyphKey { font, ch: 'W', size }).is_some());
+        }
+    }
+
+    #[test]
+    fn overflowing_frames_refill_the_atlas_once() {
+        let mut library = FontLibrary::new(64);
+        let font = library.load_default_font().unwrap();
+        for ch in "ABCDEFGH".chars() {
+            library.rasterize(GlyphKey { font, ch, size: 14 });
+        }
+
+        let frame: Vec<GlyphKey> = (16..21).map(|size| GlyphKey { font, ch: 'W', size }).collect();
+        library.rasterize_all(&frame);
+        // The old glyphs made way, and every glyph of the frame is in the atlas together
+        assert_eq!(library.glyph_rect(GlyphKey { font, ch: 'A', size: 14 }), None);
+        let rects: Vec<_> = frame.iter().map(|&key| library.glyph_rect(key).unwrap()).collect();
+        for (index, a) in rects.iter().enumerate() {
+            for b in &rects[index + 1..] {
+                let apart = a[0] + a[2] <= b[0] || b[0] + b[2] <= a[0] || a[1] + a[3] <= b[1] || b[1] + b[3] <= a[1];
+                assert!(apart, "{:?} overlaps {:?}", a, b);
+            }
+        }
+    }
+}
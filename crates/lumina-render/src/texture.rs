@@ -1,17 +1,166 @@
 //! Texture management and loading
 //!
 //! Provides utilities for loading, creating, and managing textures.
+//! Images are decoded with the `image` crate (PNG, JPEG, BMP and TGA), mip
+//! chains are generated on the CPU. Loading returns a reference-counted
+//! `TextureRef`; once every clone of it is dropped, `collect_unused` frees the
+//! texture.
 
-use crate::{RenderResult, RenderError};
+use crate::{RenderError, RenderResult};
+use image::RgbaImage;
+use std::sync::{Arc, Weak};
 
 /// Texture handle
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TextureHandle(pub u32);
 
+/// Reference-counted handle to a loaded texture, returned by `TextureManager`'s loaders.
+///
+/// The texture stays loaded while any clone exists; once the last one is
+/// dropped, `TextureManager::collect_unused` frees it. `Sprite`, `TextureAtlas`
+/// and `Material` store a `TextureRef`, so textures they draw with stay alive.
+/// The plain `TextureHandle` it hands out does not.
+#[derive(Debug, Clone)]
+pub struct TextureRef {
+    handle: TextureHandle,
+    token: Arc<()>,
+}
+
+impl TextureRef {
+    /// The non-owning handle, for use in draw calls and components
+    pub fn handle(&self) -> TextureHandle {
+        self.handle
+    }
+
+    /// Number of live references to the texture
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.token)
+    }
+
+    /// A reference not tracked by any manager, for CPU-only tests
+    #[cfg(test)]
+    pub(crate) fn untracked(handle: TextureHandle) -> Self {
+        Self {
+            handle,
+            token: Arc::new(()),
+        }
+    }
+}
+
+impl PartialEq for TextureRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.handle == other.handle
+    }
+}
+
+impl Eq for TextureRef {}
+
+/// How texel values are interpreted when sampled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// Color data, decoded from sRGB to linear when sampled
+    #[default]
+    Srgb,
+    /// Non-color data such as normal maps or masks, sampled as stored
+    Linear,
+}
+
+/// Filtering and addressing used when sampling a texture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerOptions {
+    /// Filter used when the texture is magnified
+    pub mag_filter: wgpu::FilterMode,
+    /// Filter used when the texture is minified
+    pub min_filter: wgpu::FilterMode,
+    /// Filter used between mip levels
+    pub mipmap_filter: wgpu::FilterMode,
+    /// Addressing for coordinates outside [0, 1], on every axis
+    pub address_mode: wgpu::AddressMode,
+}
+
+impl SamplerOptions {
+    /// Smooth filtering, clamped at the edges
+    pub const LINEAR: Self = Self {
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        address_mode: wgpu::AddressMode::ClampToEdge,
+    };
+
+    /// Unfiltered texels for pixel art, clamped at the edges
+    pub const NEAREST: Self = Self {
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        address_mode: wgpu::AddressMode::ClampToEdge,
+    };
+
+    /// The same filtering, tiling the texture outside [0, 1]
+    pub fn repeating(mut self) -> Self {
+        self.address_mode = wgpu::AddressMode::Repeat;
+        self
+    }
+
+    fn create_sampler(&self, device: &wgpu::Device, label: Option<&str>) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label,
+            address_mode_u: self.address_mode,
+            address_mode_v: self.address_mode,
+            address_mode_w: self.address_mode,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.mipmap_filter,
+            ..Default::default()
+        })
+    }
+}
+
+impl Default for SamplerOptions {
+    fn default() -> Self {
+        Self::LINEAR
+    }
+}
+
+/// Options for creating a texture
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TextureOptions {
+    /// Color space of the texel data
+    pub color_space: ColorSpace,
+    /// Generate a full mip chain on the CPU
+    pub mipmaps: bool,
+    /// Sampler settings
+    pub sampler: SamplerOptions,
+}
+
+impl TextureOptions {
+    /// Options for pixel art: nearest filtering and no mipmaps
+    pub fn pixel_art() -> Self {
+        Self {
+            color_space: ColorSpace::Srgb,
+            mipmaps: false,
+            sampler: SamplerOptions::NEAREST,
+        }
+    }
+
+    fn format(&self) -> wgpu::TextureFormat {
+        match self.color_space {
+            ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
 /// Texture manager
 pub struct TextureManager {
-    /// Loaded textures
-    textures: Vec<Texture>,
+    /// Loaded textures, indexed by handle. Slots are not reused, so a stale
+    /// handle never refers to a different texture.
+    textures: Vec<Option<TextureSlot>>,
+}
+
+struct TextureSlot {
+    texture: Texture,
+    /// Shared with every `TextureRef` to the texture
+    token: Weak<()>,
 }
 
 /// A GPU texture with metadata
@@ -26,6 +175,10 @@ pub struct Texture {
     pub dimensions: (u32, u32),
     /// Format
     pub format: wgpu::TextureFormat,
+    /// Number of mip levels
+    pub mip_level_count: u32,
+    /// Number of array layers; 1 unless created as a texture array
+    pub layers: u32,
 }
 
 impl TextureManager {
@@ -36,80 +189,211 @@ impl TextureManager {
         }
     }
 
-    /// Load a texture from bytes
+    /// Load a texture from encoded image bytes as sRGB color with mipmaps
     pub fn load_texture(
         &mut self,
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         data: &[u8],
         label: Option<&str>,
-    ) -> RenderResult<TextureHandle> {
-        // TODO: Implement proper texture loading
-        // For now, create a simple 1x1 white texture
-        let dimensions = (1, 1);
-        let white_pixel = [255u8; 4]; // RGBA white
+    ) -> RenderResult<TextureRef> {
+        let options = TextureOptions {
+            mipmaps: true,
+            ..Default::default()
+        };
+        self.load_texture_with(device, queue, data, label, &options)
+    }
+
+    /// Load a texture from encoded image bytes
+    pub fn load_texture_with(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        data: &[u8],
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> RenderResult<TextureRef> {
+        let image = decode_image(data)?;
+        self.create_texture(device, queue, &image, label, options)
+    }
+
+    /// Create a texture from decoded RGBA pixels
+    pub fn create_texture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: &RgbaImage,
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> RenderResult<TextureRef> {
+        self.create_layers(device, queue, std::slice::from_ref(image), label, options, false)
+    }
+
+    /// Load several equally sized images as the layers of one texture array
+    pub fn load_texture_array(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layers: &[&[u8]],
+        label: Option<&str>,
+        options: &TextureOptions,
+    ) -> RenderResult<TextureRef> {
+        let images = layers
+            .iter()
+            .map(|data| decode_image(data))
+            .collect::<RenderResult<Vec<_>>>()?;
+        self.create_layers(device, queue, &images, label, options, true)
+    }
+
+    fn create_layers(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        images: &[RgbaImage],
+        label: Option<&str>,
+        options: &TextureOptions,
+        array: bool,
+    ) -> RenderResult<TextureRef> {
+        let first = images
+            .first()
+            .ok_or_else(|| RenderError::TextureLoad("No image data".to_string()))?;
+        let dimensions = first.dimensions();
+        if dimensions.0 == 0 || dimensions.1 == 0 {
+            return Err(RenderError::TextureLoad("Image has no pixels".to_string()));
+        }
+        if let Some(image) = images.iter().find(|image| image.dimensions() != dimensions) {
+            return Err(RenderError::TextureLoad(format!(
+                "Texture array layers must match: {:?} vs {:?}",
+                dimensions,
+                image.dimensions()
+            )));
+        }
+
+        let mip_level_count = if options.mipmaps {
+            mip_level_count(dimensions.0, dimensions.1)
+        } else {
+            1
+        };
+        let format = options.format();
+        let layers = images.len() as u32;
 
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label,
             size: wgpu::Extent3d {
                 width: dimensions.0,
                 height: dimensions.1,
-                depth_or_array_layers: 1,
+                depth_or_array_layers: layers,
             },
-            mip_level_count: 1,
+            mip_level_count,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format,
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[],
         });
 
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &white_pixel,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4),
-                rows_per_image: Some(1),
-            },
-            wgpu::Extent3d {
-                width: dimensions.0,
-                height: dimensions.1,
-                depth_or_array_layers: 1,
-            },
-        );
-
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
+        for (layer, image) in images.iter().enumerate() {
+            let levels = if options.mipmaps {
+                generate_mipmaps(image, options.color_space)
+            } else {
+                vec![image.clone()]
+            };
+            for (level, pixels) in levels.iter().enumerate() {
+                let (width, height) = pixels.dimensions();
+                queue.write_texture(
+                    wgpu::ImageCopyTexture {
+                        texture: &texture,
+                        mip_level: level as u32,
+                        origin: wgpu::Origin3d {
+                            x: 0,
+                            y: 0,
+                            z: layer as u32,
+                        },
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    pixels.as_raw(),
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(4 * width),
+                        rows_per_image: Some(height),
+                    },
+                    wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                );
+            }
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(if array {
+                wgpu::TextureViewDimension::D2Array
+            } else {
+                wgpu::TextureViewDimension::D2
+            }),
             ..Default::default()
         });
+        let sampler = options.sampler.create_sampler(device, label);
 
-        let tex = Texture {
-            texture,
-            view,
-            sampler,
-            dimensions,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-        };
-
-        self.textures.push(tex);
-        Ok(TextureHandle((self.textures.len() - 1) as u32))
+        let handle = TextureHandle(self.textures.len() as u32);
+        let token = Arc::new(());
+        self.textures.push(Some(TextureSlot {
+            texture: Texture {
+                texture,
+                view,
+                sampler,
+                dimensions,
+                format,
+                mip_level_count,
+                layers,
+            },
+            token: Arc::downgrade(&token),
+        }));
+        Ok(TextureRef { handle, token })
     }
 
     /// Get a texture by handle
     pub fn get_texture(&self, handle: TextureHandle) -> Option<&Texture> {
-        self.textures.get(handle.0 as usize)
+        self.textures
+            .get(handle.0 as usize)
+            .and_then(|slot| slot.as_ref())
+            .map(|slot| &slot.texture)
+    }
+
+    /// Free a texture now, even if references to it remain.
+    /// Returns false if it was not loaded.
+    pub fn unload(&mut self, handle: TextureHandle) -> bool {
+        self.textures
+            .get_mut(handle.0 as usize)
+            .and_then(Option::take)
+            .map(|slot| slot.texture.texture.destroy())
+            .is_some()
+    }
+
+    /// Free every texture whose `TextureRef`s have all been dropped, returning their handles.
+    pub fn collect_unused(&mut self) -> Vec<TextureHandle> {
+        let unused: Vec<_> = self
+            .textures
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.as_ref().is_some_and(|slot| slot.token.strong_count() == 0))
+            .map(|(index, _)| TextureHandle(index as u32))
+            .collect();
+        for handle in &unused {
+            self.unload(*handle);
+        }
+        unused
+    }
+
+    /// Number of loaded textures
+    pub fn len(&self) -> usize {
+        self.textures.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    /// Whether no textures are loaded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
@@ -117,4 +401,167 @@ impl Default for TextureManager {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+/// Decode PNG, JPEG, BMP or TGA bytes to RGBA8
+pub fn decode_image(data: &[u8]) -> RenderResult<RgbaImage> {
+    // TGA has no magic number, so anything unrecognised is tried as TGA
+    let format = image::guess_format(data).unwrap_or(image::ImageFormat::Tga);
+    image::load_from_memory_with_format(data, format)
+        .map(|image| image.to_rgba8())
+        .map_err(|err| RenderError::TextureLoad(err.to_string()))
+}
+
+/// Number of mip levels in a full chain down to 1x1
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+/// Build a full mip chain, starting with a copy of `image`.
+///
+/// Each level box-filters the one above it. On odd sizes the box covers
+/// fractional texels, so edge rows and columns still contribute. sRGB data is
+/// averaged in linear space so that downsampled textures do not darken, and
+/// color is weighted by alpha so transparent texels do not bleed into edges.
+pub fn generate_mipmaps(image: &RgbaImage, color_space: ColorSpace) -> Vec<RgbaImage> {
+    let mut levels = vec![image.clone()];
+    while let Some(previous) = levels.last() {
+        let (width, height) = previous.dimensions();
+        if width == 1 && height == 1 {
+            break;
+        }
+        let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+        let scale_x = width as f32 / next_width as f32;
+        let scale_y = height as f32 / next_height as f32;
+        let next = RgbaImage::from_fn(next_width, next_height, |x, y| {
+            let (x0, x1) = (x as f32 * scale_x, (x + 1) as f32 * scale_x);
+            let (y0, y1) = (y as f32 * scale_y, (y + 1) as f32 * scale_y);
+            let mut color = [0.0f32; 3];
+            let mut unweighted = [0.0f32; 3];
+            let mut alpha = 0.0f32;
+            let mut coverage = 0.0f32;
+            for sy in y0 as u32..(y1.ceil() as u32).min(height) {
+                let weight_y = y1.min(sy as f32 + 1.0) - y0.max(sy as f32);
+                for sx in x0 as u32..(x1.ceil() as u32).min(width) {
+                    let weight = weight_y * (x1.min(sx as f32 + 1.0) - x0.max(sx as f32));
+                    let pixel = previous.get_pixel(sx, sy);
+                    let texel_alpha = to_linear(pixel[3], 3, color_space);
+                    for channel in 0..3 {
+                        let value = to_linear(pixel[channel], channel, color_space);
+                        color[channel] += value * texel_alpha * weight;
+                        unweighted[channel] += value * weight;
+                    }
+                    alpha += texel_alpha * weight;
+                    coverage += weight;
+                }
+            }
+            image::Rgba(std::array::from_fn(|channel| match channel {
+                3 => from_linear(alpha / coverage, 3, color_space),
+                // Fully transparent boxes have no weighted color, so keep the plain average
+                _ if alpha <= 0.0 => from_linear(unweighted[channel] / coverage, channel, color_space),
+                _ => from_linear(color[channel] / alpha, channel, color_space),
+            }))
+        });
+        levels.push(next);
+    }
+    levels
+}
+
+fn to_linear(value: u8, channel: usize, color_space: ColorSpace) -> f32 {
+    let value = value as f32 / 255.0;
+    if color_space == ColorSpace::Srgb && channel < 3 {
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    } else {
+        value
+    }
+}
+
+fn from_linear(value: f32, channel: usize, color_space: ColorSpace) -> u8 {
+    let value = if color_space == ColorSpace::Srgb && channel < 3 {
+        if value <= 0.0031308 {
+            value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        }
+    } else {
+        value
+    };
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_images_and_builds_mip_chains() {
+        let mut png = Vec::new();
+        let source = RgbaImage::from_fn(5, 2, |x, _| image::Rgba([if x % 2 == 0 { 255 } else { 0 }, 0, 0, 255]));
+        source
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let decoded = decode_image(&png).unwrap();
+        assert_eq!(decoded, source);
+        assert!(decode_image(b"not an image").is_err());
+
+        assert_eq!(mip_level_count(5, 2), 3);
+        let linear = generate_mipmaps(&decoded, ColorSpace::Linear);
+        let sizes: Vec<_> = linear.iter().map(|level| level.dimensions()).collect();
+        assert_eq!(sizes, vec![(5, 2), (2, 1), (1, 1)]);
+        // Each texel of the 2-wide level covers two and a half source columns, so the last column counts
+        assert_eq!(linear[1].get_pixel(0, 0)[0], 153);
+        assert_eq!(linear[1].get_pixel(1, 0)[0], 153);
+
+        // Averaging black and white in linear light is brighter than averaging the sRGB values (153)
+        let srgb = generate_mipmaps(&decoded, ColorSpace::Srgb);
+        assert_eq!(srgb[1].get_pixel(0, 0)[0], 203);
+        assert_eq!(srgb[1].get_pixel(0, 0)[3], 255);
+    }
+
+    #[test]
+    fn transparent_texels_do_not_tint_mips() {
+        let source = RgbaImage::from_fn(2, 1, |x, _| {
+            if x == 0 {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([0, 255, 0, 0])
+            }
+        });
+        let levels = generate_mipmaps(&source, ColorSpace::Srgb);
+        assert_eq!(levels[1].get_pixel(0, 0).0, [255, 0, 0, 128]);
+
+        let clear = RgbaImage::from_pixel(2, 2, image::Rgba([0, 0, 255, 0]));
+        assert_eq!(generate_mipmaps(&clear, ColorSpace::Linear)[1].get_pixel(0, 0).0, [0, 0, 255, 0]);
+    }
+
+    #[test]
+    fn textures_are_freed_after_the_last_reference() {
+        let Some(renderer) = crate::renderer::test_renderer(crate::RenderConfig::default()) else { return };
+        let mut textures = TextureManager::new();
+        let pixel = RgbaImage::from_pixel(1, 1, image::Rgba([255, 255, 255, 255]));
+        let create = |textures: &mut TextureManager| {
+            textures
+                .create_texture(&renderer.device, &renderer.queue, &pixel, None, &TextureOptions::default())
+                .unwrap()
+        };
+        let kept = create(&mut textures);
+        let reference = create(&mut textures);
+        let handle = reference.handle();
+
+        let copy = reference.clone();
+        assert_eq!(reference.ref_count(), 2);
+        drop(reference);
+        assert!(textures.collect_unused().is_empty());
+
+        drop(copy);
+        assert_eq!(textures.collect_unused(), vec![handle]);
+        assert!(textures.get_texture(handle).is_none());
+        assert!(textures.get_texture(kept.handle()).is_some());
+        assert!(textures.unload(kept.handle()));
+        assert!(textures.is_empty());
+    }
+}
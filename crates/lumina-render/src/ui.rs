@@ -4,9 +4,15 @@
 //! It handles batching, clipping, and efficient rendering of UI primitives like rectangles,
 //! text, and textured quads.
 
-use crate::{Rect, RenderError, RenderResult};
+use crate::shader::builtin_module;
+use crate::{Rect, RenderResult, TextRenderer, TextStyle};
 use glam::{Vec2, Vec4, Mat4};
 use bytemuck::{Pod, Zeroable};
+use std::ops::Range;
+
+/// Quads the shape buffers hold per frame; a shadowed rect takes two.
+/// Vertex indices are `u16`, so this must stay at or below 16384.
+const MAX_SHAPE_QUADS: usize = 8192;
 
 /// Main UI renderer using WGPU
 pub struct UiRenderer {
@@ -26,18 +32,30 @@ pub struct UiRenderer {
     solid_pipeline: wgpu::RenderPipeline,
     /// Render pipeline for textured quads
     texture_pipeline: wgpu::RenderPipeline,
-    /// Current frame's vertices
-    vertices: Vec<UiVertex>,
-    /// Current frame's indices
-    indices: Vec<u16>,
+    /// Layout of `solid_pipeline`, kept to rebuild it
+    solid_pipeline_layout: wgpu::PipelineLayout,
+    /// Layout of `texture_pipeline`, kept to rebuild it
+    texture_pipeline_layout: wgpu::PipelineLayout,
+    /// Shader of `solid_pipeline`, kept to rebuild it
+    solid_shader: wgpu::ShaderModule,
+    /// Shader of `texture_pipeline`, kept to rebuild it
+    texture_shader: wgpu::ShaderModule,
+    /// Samples per pixel of the render passes the pipelines draw into
+    sample_count: u32,
+    /// Current frame's shape geometry
+    shapes: ShapeBatch,
     /// Screen size
     screen_size: Vec2,
     /// View matrix
     view_matrix: Mat4,
     /// Projection matrix
     projection_matrix: Mat4,
-    /// Current clip stack
+    /// Current clip stack, each entry already intersected with the one below
     clip_stack: Vec<Rect>,
+    /// Glyph atlas text, interleaved with the shapes in `layers` order
+    text_renderer: TextRenderer,
+    /// Runs of shapes and text in the order they were queued
+    layers: Vec<UiLayer>,
 }
 
 /// Vertex data for UI rendering
@@ -52,6 +70,142 @@ pub struct UiVertex {
     pub color: [f32; 4],
 }
 
+/// Vertex for the UI shape pipeline
+///
+/// Besides its position each vertex carries the whole shape, so the fragment shader can
+/// evaluate a rounded-rectangle distance field and every shape still goes out in one draw.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct ShapeVertex {
+    /// Position in screen space
+    pub position: [f32; 2],
+    /// Position relative to the shape's centre
+    pub local: [f32; 2],
+    /// Half the shape's size
+    pub half_size: [f32; 2],
+    /// Fill color, or the shadow color for shadow quads
+    pub color: [f32; 4],
+    /// Corner radii: top-left, top-right, bottom-right, bottom-left
+    pub radii: [f32; 4],
+    /// Border color
+    pub border_color: [f32; 4],
+    /// Border width, shadow blur, shape kind and an unused lane
+    pub params: [f32; 4],
+}
+
+/// Shape kinds understood by `solid.wgsl`
+const KIND_SOLID: f32 = 0.0;
+const KIND_ROUNDED: f32 = 1.0;
+const KIND_SHADOW: f32 = 2.0;
+
+/// Extra pixels around a rounded rect so its anti-aliased edge isn't cut off
+const AA_PADDING: f32 = 1.0;
+
+/// Radius of each corner of a rectangle
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CornerRadii {
+    /// Top-left radius
+    pub top_left: f32,
+    /// Top-right radius
+    pub top_right: f32,
+    /// Bottom-right radius
+    pub bottom_right: f32,
+    /// Bottom-left radius
+    pub bottom_left: f32,
+}
+
+impl CornerRadii {
+    /// The same radius on every corner
+    pub fn all(radius: f32) -> Self {
+        Self::new(radius, radius, radius, radius)
+    }
+
+    /// Radii in clockwise order starting at the top-left corner
+    pub fn new(top_left: f32, top_right: f32, bottom_right: f32, bottom_left: f32) -> Self {
+        Self { top_left, top_right, bottom_right, bottom_left }
+    }
+
+    /// Whether every corner is square
+    pub fn is_zero(&self) -> bool {
+        self.to_array().iter().all(|&r| r <= 0.0)
+    }
+
+    /// Clamp each radius to `0..=max`
+    pub fn clamped(&self, max: f32) -> Self {
+        let [tl, tr, br, bl] = self.to_array().map(|r| r.clamp(0.0, max.max(0.0)));
+        Self::new(tl, tr, br, bl)
+    }
+
+    /// Radii in the order the shader expects
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.top_left, self.top_right, self.bottom_right, self.bottom_left]
+    }
+}
+
+impl From<f32> for CornerRadii {
+    fn from(radius: f32) -> Self {
+        Self::all(radius)
+    }
+}
+
+/// Border drawn inside the edge of a rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RectBorder {
+    /// Border width in pixels
+    pub width: f32,
+    /// Border color
+    pub color: Vec4,
+}
+
+/// Drop shadow drawn behind a rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RectShadow {
+    /// Offset of the shadow from the rectangle
+    pub offset: Vec2,
+    /// Width of the soft edge in pixels
+    pub blur: f32,
+    /// Shadow color
+    pub color: Vec4,
+}
+
+/// How `UiRenderer::draw_styled_rect` fills, outlines and shades a rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RectStyle {
+    /// Fill color
+    pub fill: Vec4,
+    /// Corner radii, clamped to half the rectangle's shorter side when drawn
+    pub corner_radii: CornerRadii,
+    /// Optional border
+    pub border: Option<RectBorder>,
+    /// Optional drop shadow
+    pub shadow: Option<RectShadow>,
+}
+
+impl RectStyle {
+    /// A square-cornered rectangle filled with `fill`
+    pub fn new(fill: Vec4) -> Self {
+        Self { fill, corner_radii: CornerRadii::default(), border: None, shadow: None }
+    }
+
+    /// Set the corner radii
+    pub fn with_radii(mut self, radii: impl Into<CornerRadii>) -> Self {
+        self.corner_radii = radii.into();
+        self
+    }
+
+    /// Add a border; a width of zero removes it
+    pub fn with_border(mut self, width: f32, color: Vec4) -> Self {
+        self.border = (width > 0.0).then_some(RectBorder { width, color });
+        self
+    }
+
+    /// Add a drop shadow
+    pub fn with_shadow(mut self, offset: Vec2, blur: f32, color: Vec4) -> Self {
+        self.shadow = Some(RectShadow { offset, blur: blur.max(0.0), color });
+        self
+    }
+}
+
 /// Uniform data for shaders
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Pod, Zeroable)]
@@ -113,14 +267,14 @@ impl UiRenderer {
         // Create buffers
         let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("UI Vertex Buffer"),
-            size: std::mem::size_of::<UiVertex>() as u64 * 10000, // Reserve space for many vertices
+            size: (std::mem::size_of::<ShapeVertex>() * 4 * MAX_SHAPE_QUADS) as u64,
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
         
         let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("UI Index Buffer"),
-            size: std::mem::size_of::<u16>() as u64 * 15000, // Reserve space for many indices
+            size: (std::mem::size_of::<u16>() * 6 * MAX_SHAPE_QUADS) as u64,
             usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -161,17 +315,6 @@ impl UiRenderer {
             ],
         });
         
-        // Create shaders
-        let solid_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("UI Solid Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/solid.wgsl").into()),
-        });
-        
-        let texture_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("UI Texture Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/texture.wgsl").into()),
-        });
-        
         // Create texture bind group layout
         let texture_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("UI Texture Bind Group Layout"),
@@ -208,20 +351,93 @@ impl UiRenderer {
             push_constant_ranges: &[],
         });
         
+        let solid_shader = builtin_module(device, "shaders/solid.wgsl", include_str!("shaders/solid.wgsl"))?;
+        let texture_shader = builtin_module(device, "shaders/texture.wgsl", include_str!("shaders/texture.wgsl"))?;
+        let (solid_pipeline, texture_pipeline) = Self::create_pipelines(
+            device,
+            &solid_pipeline_layout,
+            &solid_shader,
+            &texture_pipeline_layout,
+            &texture_shader,
+            config.format,
+            1,
+        );
+        
+        let text_renderer = TextRenderer::new(device, queue, config.format)?;
+
+        // Create matrices
+        let view_matrix = Mat4::IDENTITY;
+        let projection_matrix = Mat4::orthographic_rh(
+            0.0, screen_size.x,
+            screen_size.y, 0.0,
+            -1.0, 1.0,
+        );
+        
+        Ok(Self {
+            config,
+            current_pass: None,
+            vertex_buffer,
+            index_buffer,
+            uniform_buffer,
+            uniform_bind_group,
+            solid_pipeline,
+            texture_pipeline,
+            solid_pipeline_layout,
+            texture_pipeline_layout,
+            solid_shader,
+            texture_shader,
+            sample_count: 1,
+            shapes: ShapeBatch::default(),
+            screen_size,
+            view_matrix,
+            projection_matrix,
+            clip_stack: Vec::new(),
+            text_renderer,
+            layers: Vec::new(),
+        })
+    }
+    
+    /// Rebuild the pipelines for render passes with `sample_count` samples per pixel
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        if sample_count == self.sample_count {
+            return;
+        }
+        self.sample_count = sample_count;
+        (self.solid_pipeline, self.texture_pipeline) = Self::create_pipelines(
+            device,
+            &self.solid_pipeline_layout,
+            &self.solid_shader,
+            &self.texture_pipeline_layout,
+            &self.texture_shader,
+            self.config.format,
+            sample_count,
+        );
+        self.text_renderer.set_sample_count(device, sample_count);
+    }
+
+    fn create_pipelines(
+        device: &wgpu::Device,
+        solid_layout: &wgpu::PipelineLayout,
+        solid_shader: &wgpu::ShaderModule,
+        texture_layout: &wgpu::PipelineLayout,
+        texture_shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
         // Create render pipelines
         let solid_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("UI Solid Pipeline"),
-            layout: Some(&solid_pipeline_layout),
+            layout: Some(solid_layout),
             vertex: wgpu::VertexState {
-                module: &solid_shader,
+                module: solid_shader,
                 entry_point: "vs_main",
-                buffers: &[UiVertex::desc()],
+                buffers: &[ShapeVertex::desc()],
             },
             fragment: Some(wgpu::FragmentState {
-                module: &solid_shader,
+                module: solid_shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
+                    format,
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -237,7 +453,7 @@ impl UiRenderer {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -246,17 +462,17 @@ impl UiRenderer {
         
         let texture_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: Some("UI Texture Pipeline"),
-            layout: Some(&texture_pipeline_layout),
+            layout: Some(texture_layout),
             vertex: wgpu::VertexState {
-                module: &texture_shader,
+                module: texture_shader,
                 entry_point: "vs_main",
                 buffers: &[UiVertex::desc()],
             },
             fragment: Some(wgpu::FragmentState {
-                module: &texture_shader,
+                module: texture_shader,
                 entry_point: "fs_main",
                 targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
+                    format,
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
@@ -272,44 +488,22 @@ impl UiRenderer {
             },
             depth_stencil: None,
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
             multiview: None,
         });
-        
-        // Create matrices
-        let view_matrix = Mat4::IDENTITY;
-        let projection_matrix = Mat4::orthographic_rh(
-            0.0, screen_size.x,
-            screen_size.y, 0.0,
-            -1.0, 1.0,
-        );
-        
-        Ok(Self {
-            config,
-            current_pass: None,
-            vertex_buffer,
-            index_buffer,
-            uniform_buffer,
-            uniform_bind_group,
-            solid_pipeline,
-            texture_pipeline,
-            vertices: Vec::new(),
-            indices: Vec::new(),
-            screen_size,
-            view_matrix,
-            projection_matrix,
-            clip_stack: Vec::new(),
-        })
+
+        (solid_pipeline, texture_pipeline)
     }
     
     /// Begin a new frame
     pub fn begin_frame(&mut self, queue: &wgpu::Queue) {
-        self.vertices.clear();
-        self.indices.clear();
+        self.shapes.clear();
         self.clip_stack.clear();
+        self.text_renderer.begin_frame();
+        self.layers.clear();
         
         // Update uniforms
         let uniforms = UiUniforms {
@@ -323,18 +517,40 @@ impl UiRenderer {
     
     /// End the current frame and submit all draw commands
     pub fn end_frame(&mut self, queue: &wgpu::Queue) {
+        if self.shapes.dropped > 0 {
+            log::warn!("Dropped {} UI quads over the per-frame limit", self.shapes.dropped);
+        }
+        // Text queued straight through `text_renderer_mut` goes on top
+        self.record_text();
         // Update vertex and index buffers
-        if !self.vertices.is_empty() {
-            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.vertices));
+        if !self.shapes.vertices.is_empty() {
+            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.shapes.vertices));
         }
-        if !self.indices.is_empty() {
-            queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&self.indices));
+        if !self.shapes.indices.is_empty() {
+            queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&self.shapes.indices));
         }
+        self.text_renderer
+            .prepare(queue, self.projection_matrix * self.view_matrix, self.screen_size);
     }
     
     /// Submit the rendered UI to a render pass
+    ///
+    /// Shapes and text are drawn in the order they were queued, so a panel drawn after
+    /// a label covers it. Each run of geometry sharing a clip rect is one draw call
+    /// with its own scissor rect.
     pub fn submit_to_render_pass<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
-        if self.vertices.is_empty() || self.indices.is_empty() {
+        for layer in &self.layers {
+            match layer.kind {
+                UiLayerKind::Shapes => self.submit_shapes(render_pass, layer.range.clone()),
+                UiLayerKind::Text => self
+                    .text_renderer
+                    .render_glyphs(render_pass, layer.range.start as usize..layer.range.end as usize),
+            }
+        }
+    }
+
+    fn submit_shapes<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, indices: Range<u32>) {
+        if self.shapes.vertices.is_empty() || indices.is_empty() {
             return;
         }
         
@@ -346,44 +562,94 @@ impl UiRenderer {
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
         
-        // Draw all vertices
-        let num_indices = self.indices.len() as u32;
-        if num_indices > 0 {
-            render_pass.draw_indexed(0..num_indices, 0, 0..1);
-        }
+        draw_clip_batches(render_pass, &self.shapes.batches, indices, self.screen_size);
     }
     
     /// Draw a colored rectangle
     pub fn draw_rect(&mut self, bounds: Rect, color: Vec4) {
-        self.add_quad(bounds, color, [0.0, 0.0], [1.0, 1.0]);
+        self.shapes.push_rect(bounds, color);
+        self.record_shapes();
     }
     
     /// Draw a rectangle with rounded corners
-    pub fn draw_rounded_rect(&mut self, bounds: Rect, color: Vec4, _border_radius: f32) {
-        // For now, just draw a regular rectangle
-        // TODO: Implement proper rounded rectangle rendering
-        self.draw_rect(bounds, color);
+    pub fn draw_rounded_rect(&mut self, bounds: Rect, color: Vec4, border_radius: f32) {
+        self.draw_styled_rect(bounds, &RectStyle::new(color).with_radii(border_radius));
+    }
+
+    /// Draw a rectangle with per-corner radii, a border and a drop shadow
+    pub fn draw_styled_rect(&mut self, bounds: Rect, style: &RectStyle) {
+        self.shapes.push_styled_rect(bounds, style);
+        self.record_shapes();
     }
     
     /// Draw a textured rectangle
     pub fn draw_textured_rect(&mut self, bounds: Rect, _texture: TextureHandle, color: Vec4) {
-        self.add_quad(bounds, color, [0.0, 0.0], [1.0, 1.0]);
+        self.draw_rect(bounds, color);
     }
     
-    /// Draw text
-    pub fn draw_text(&mut self, _text: &str, _position: Vec2, _font: FontHandle, _size: f32, _color: Vec4) {
-        // TODO: Implement text rendering
-        // This will delegate to text renderer
+    /// Draw single-line text with its top-left corner at `position`
+    pub fn draw_text(&mut self, text: &str, position: Vec2, font: FontHandle, size: f32, color: Vec4) {
+        self.text_renderer.draw_text(text, position, font, size, color);
+        self.record_text();
+    }
+
+    /// Draw text wrapped and aligned according to `style`
+    pub fn draw_text_styled(&mut self, text: &str, position: Vec2, font: FontHandle, style: &TextStyle, color: Vec4) {
+        self.text_renderer.draw_text_styled(text, position, font, style, color);
+        self.record_text();
+    }
+
+    fn record_shapes(&mut self) {
+        push_layer(&mut self.layers, UiLayerKind::Shapes, self.shapes.indices.len() as u32);
+    }
+
+    fn record_text(&mut self) {
+        push_layer(&mut self.layers, UiLayerKind::Text, self.text_renderer.queued_glyphs() as u32);
+    }
+
+    /// Size of single-line text
+    pub fn measure_text(&self, text: &str, font: FontHandle, size: f32) -> Vec2 {
+        self.text_renderer.library().measure(text, font, &TextStyle::new(size))
+    }
+
+    /// The text renderer, for loading fonts. `FontHandle(0)` is the embedded default font.
+    pub fn text_renderer_mut(&mut self) -> &mut TextRenderer {
+        &mut self.text_renderer
     }
     
-    /// Set clip rectangle
+    /// Clip everything drawn until the matching `pop_clip` to `bounds`, intersected with
+    /// the current clip
     pub fn push_clip(&mut self, bounds: Rect) {
-        self.clip_stack.push(bounds);
+        let clip = match self.current_clip() {
+            // A clip outside its parent hides everything; keep it as an empty rect
+            Some(parent) => parent.intersection(&bounds).unwrap_or(Rect::new(bounds.position.x, bounds.position.y, 0.0, 0.0)),
+            None => bounds,
+        };
+        self.clip_stack.push(clip);
+        self.apply_clip();
     }
     
     /// Remove the last clip rectangle
     pub fn pop_clip(&mut self) {
         self.clip_stack.pop();
+        self.apply_clip();
+    }
+
+    fn apply_clip(&mut self) {
+        let clip = self.current_clip();
+        self.shapes.clip = clip;
+        self.text_renderer.set_clip(clip);
+    }
+
+    /// Apply a draw command
+    pub fn execute(&mut self, command: &DrawCommand) {
+        match command {
+            DrawCommand::Rect { bounds, color, border_radius } => self.draw_rounded_rect(*bounds, *color, *border_radius),
+            DrawCommand::TexturedRect { bounds, texture, color } => self.draw_textured_rect(*bounds, *texture, *color),
+            DrawCommand::Text { text, position, font, size, color } => self.draw_text(text, *position, *font, *size, *color),
+            DrawCommand::PushClip { bounds } => self.push_clip(*bounds),
+            DrawCommand::PopClip => self.pop_clip(),
+        }
     }
     
     /// Get the current clip rectangle
@@ -404,40 +670,214 @@ impl UiRenderer {
         self.config.width = new_size.x as u32;
         self.config.height = new_size.y as u32;
     }
-    
-    /// Add a quad to the vertex buffer
-    fn add_quad(&mut self, bounds: Rect, color: Vec4, uv_min: [f32; 2], uv_max: [f32; 2]) {
+}
+
+/// Which renderer a `UiLayer` draws with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UiLayerKind {
+    /// Shape indices
+    Shapes,
+    /// Glyphs, numbered in the order they were queued
+    Text,
+}
+
+/// A run of consecutive shapes or text
+#[derive(Debug, Clone, PartialEq)]
+struct UiLayer {
+    kind: UiLayerKind,
+    range: Range<u32>,
+}
+
+/// Extend the last layer if it has the same kind, otherwise start a new one where
+/// the previous layer of that kind ended. Nothing is added if `end` adds nothing.
+fn push_layer(layers: &mut Vec<UiLayer>, kind: UiLayerKind, end: u32) {
+    let start = layers.iter().rev().find(|layer| layer.kind == kind).map_or(0, |layer| layer.range.end);
+    if end <= start {
+        return;
+    }
+    match layers.last_mut() {
+        Some(last) if last.kind == kind => last.range.end = end,
+        _ => layers.push(UiLayer { kind, range: start..end }),
+    }
+}
+
+/// A run of indices drawn with one scissor rect
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ClipBatch {
+    /// Clip rect in screen space, or `None` for the whole target
+    pub clip: Option<Rect>,
+    /// Index range of the draw call
+    pub indices: Range<u32>,
+}
+
+/// Append `indices` to the last batch if it has the same clip, otherwise start a new one
+pub(crate) fn push_clip_batch(batches: &mut Vec<ClipBatch>, clip: Option<Rect>, indices: Range<u32>) {
+    match batches.last_mut() {
+        Some(last) if last.clip == clip && last.indices.end == indices.start => last.indices.end = indices.end,
+        _ => batches.push(ClipBatch { clip, indices }),
+    }
+}
+
+/// Scissor rect `[x, y, width, height]` in pixels for `clip`, limited to the target.
+/// `None` if nothing inside the clip is visible.
+pub(crate) fn scissor_rect(clip: Option<Rect>, target_size: Vec2) -> Option<[u32; 4]> {
+    let target = Rect::new(0.0, 0.0, target_size.x.floor(), target_size.y.floor());
+    let visible = match clip {
+        Some(clip) => clip.intersection(&target)?,
+        None => target,
+    };
+    // Round outwards so partially covered pixels at the edge still draw
+    let min = visible.min().floor();
+    let max = visible.max().ceil().min(target.max());
+    let size = max - min;
+    (size.x >= 1.0 && size.y >= 1.0).then_some([min.x as u32, min.y as u32, size.x as u32, size.y as u32])
+}
+
+/// Issue one draw per batch overlapping `indices`, setting its scissor rect and skipping
+/// batches clipped away. The scissor is reset to the whole target afterwards so later
+/// draws in the same pass aren't clipped.
+pub(crate) fn draw_clip_batches(
+    render_pass: &mut wgpu::RenderPass<'_>,
+    batches: &[ClipBatch],
+    indices: Range<u32>,
+    target_size: Vec2,
+) {
+    let mut scissored = false;
+    for batch in batches {
+        let start = batch.indices.start.max(indices.start);
+        let end = batch.indices.end.min(indices.end);
+        if start >= end {
+            continue;
+        }
+        if let Some([x, y, width, height]) = scissor_rect(batch.clip, target_size) {
+            render_pass.set_scissor_rect(x, y, width, height);
+            render_pass.draw_indexed(start..end, 0, 0..1);
+            scissored = true;
+        }
+    }
+    if scissored {
+        if let Some([x, y, width, height]) = scissor_rect(None, target_size) {
+            render_pass.set_scissor_rect(x, y, width, height);
+        }
+    }
+}
+
+/// Shape geometry queued for one frame
+#[derive(Debug, Default)]
+struct ShapeBatch {
+    vertices: Vec<ShapeVertex>,
+    indices: Vec<u16>,
+    /// Draw calls split at clip changes
+    batches: Vec<ClipBatch>,
+    /// Clip applied to quads pushed from now on
+    clip: Option<Rect>,
+    /// Quads skipped because the buffers were full
+    dropped: usize,
+}
+
+impl ShapeBatch {
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.batches.clear();
+        self.clip = None;
+        self.dropped = 0;
+    }
+
+    /// Add a solid quad
+    fn push_rect(&mut self, bounds: Rect, color: Vec4) {
+        let params = [0.0, 0.0, KIND_SOLID, 0.0];
+        self.push_quad(bounds.center(), bounds.size * 0.5, 0.0, CornerRadii::default(), color, Vec4::ZERO, params);
+    }
+
+    /// Add a rounded rect, preceded by its shadow so the shadow ends up underneath
+    fn push_styled_rect(&mut self, bounds: Rect, style: &RectStyle) {
+        let half_size = bounds.size * 0.5;
+        let radii = style.corner_radii.clamped(half_size.x.min(half_size.y));
+
+        if let Some(shadow) = style.shadow {
+            let padding = shadow.blur * 0.5 + AA_PADDING;
+            let params = [0.0, shadow.blur, KIND_SHADOW, 0.0];
+            self.push_quad(bounds.center() + shadow.offset, half_size, padding, radii, shadow.color, Vec4::ZERO, params);
+        }
+
+        match style.border {
+            None if radii.is_zero() => self.push_rect(bounds, style.fill),
+            border => {
+                let (width, color) = border.map_or((0.0, Vec4::ZERO), |b| (b.width, b.color));
+                let params = [width, 0.0, KIND_ROUNDED, 0.0];
+                self.push_quad(bounds.center(), half_size, AA_PADDING, radii, style.fill, color, params);
+            }
+        }
+    }
+
+    /// Add a quad covering a shape plus `padding` pixels on every side
+    #[allow(clippy::too_many_arguments)]
+    fn push_quad(
+        &mut self,
+        center: Vec2,
+        half_size: Vec2,
+        padding: f32,
+        radii: CornerRadii,
+        color: Vec4,
+        border_color: Vec4,
+        params: [f32; 4],
+    ) {
+        let extent = half_size + Vec2::splat(padding);
+        if let Some(clip) = self.clip {
+            if !clip.intersects(&Rect::from_min_max(center - extent, center + extent)) {
+                return;
+            }
+        }
+
+        if self.vertices.len() / 4 >= MAX_SHAPE_QUADS {
+            self.dropped += 1;
+            return;
+        }
+
         let base_index = self.vertices.len() as u16;
-        
-        // Add vertices for the quad
-        self.vertices.extend_from_slice(&[
-            UiVertex {
-                position: [bounds.position.x, bounds.position.y],
-                tex_coords: [uv_min[0], uv_min[1]],
-                color: color.to_array(),
-            },
-            UiVertex {
-                position: [bounds.position.x + bounds.size.x, bounds.position.y],
-                tex_coords: [uv_max[0], uv_min[1]],
-                color: color.to_array(),
-            },
-            UiVertex {
-                position: [bounds.position.x + bounds.size.x, bounds.position.y + bounds.size.y],
-                tex_coords: [uv_max[0], uv_max[1]],
-                color: color.to_array(),
-            },
-            UiVertex {
-                position: [bounds.position.x, bounds.position.y + bounds.size.y],
-                tex_coords: [uv_min[0], uv_max[1]],
+
+        for corner in [Vec2::new(-1.0, -1.0), Vec2::new(1.0, -1.0), Vec2::new(1.0, 1.0), Vec2::new(-1.0, 1.0)] {
+            let local = corner * extent;
+            self.vertices.push(ShapeVertex {
+                position: (center + local).to_array(),
+                local: local.to_array(),
+                half_size: half_size.to_array(),
                 color: color.to_array(),
-            },
-        ]);
+                radii: radii.to_array(),
+                border_color: border_color.to_array(),
+                params,
+            });
+        }
         
         // Add indices for two triangles
+        let start = self.indices.len() as u32;
         self.indices.extend_from_slice(&[
             base_index, base_index + 1, base_index + 2,
             base_index + 2, base_index + 3, base_index,
         ]);
+        push_clip_batch(&mut self.batches, self.clip, start..start + 6);
+    }
+}
+
+impl ShapeVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x2,
+        3 => Float32x4,
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32x4,
+    ];
+
+    /// Get the vertex buffer layout descriptor
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ShapeVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
     }
 }
 
@@ -469,4 +909,100 @@ impl UiVertex {
             ],
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn styled_rects_queue_shadow_then_padded_shape() {
+        let mut batch = ShapeBatch::default();
+        let bounds = Rect::new(10.0, 20.0, 100.0, 40.0);
+        let style = RectStyle::new(Vec4::ONE)
+            .with_radii(CornerRadii::new(4.0, 50.0, 0.0, 8.0))
+            .with_border(2.0, Vec4::new(1.0, 0.0, 0.0, 1.0))
+            .with_shadow(Vec2::new(0.0, 4.0), 8.0, Vec4::new(0.0, 0.0, 0.0, 0.5));
+        batch.push_styled_rect(bounds, &style);
+
+        assert_eq!(batch.vertices.len(), 8);
+        assert_eq!(batch.indices, vec![0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4]);
+
+        let shadow = &batch.vertices[0];
+        assert_eq!(shadow.params[2], KIND_SHADOW);
+        assert_eq!(shadow.position, [10.0 - 5.0, 24.0 - 5.0]);
+
+        let fill = &batch.vertices[4];
+        assert_eq!(fill.params, [2.0, 0.0, KIND_ROUNDED, 0.0]);
+        assert_eq!(fill.position, [9.0, 19.0]);
+        assert_eq!(fill.local, [-51.0, -21.0]);
+        assert_eq!(fill.half_size, [50.0, 20.0]);
+        // Radii are clamped to half the shorter side
+        assert_eq!(fill.radii, [4.0, 20.0, 0.0, 8.0]);
+
+        batch.clear();
+        batch.push_styled_rect(bounds, &RectStyle::new(Vec4::ONE));
+        assert_eq!(batch.vertices[0].params[2], KIND_SOLID);
+        assert_eq!(batch.vertices[0].position, [10.0, 20.0]);
+    }
+
+    #[test]
+    fn clipping_culls_quads_and_splits_batches() {
+        let mut batch = ShapeBatch::default();
+        batch.push_rect(Rect::new(0.0, 0.0, 10.0, 10.0), Vec4::ONE);
+
+        batch.clip = Some(Rect::new(100.0, 100.0, 50.0, 50.0));
+        batch.push_rect(Rect::new(0.0, 0.0, 10.0, 10.0), Vec4::ONE); // fully outside: culled
+        batch.push_rect(Rect::new(90.0, 90.0, 20.0, 20.0), Vec4::ONE);
+        batch.push_rect(Rect::new(120.0, 120.0, 20.0, 20.0), Vec4::ONE);
+
+        batch.clip = None;
+        batch.push_rect(Rect::new(0.0, 0.0, 10.0, 10.0), Vec4::ONE);
+
+        assert_eq!(batch.vertices.len(), 16);
+        let ranges: Vec<_> = batch.batches.iter().map(|b| (b.clip.is_some(), b.indices.clone())).collect();
+        assert_eq!(ranges, vec![(false, 0..6), (true, 6..18), (false, 18..24)]);
+
+        let screen = Vec2::new(800.0, 600.0);
+        assert_eq!(scissor_rect(None, screen), Some([0, 0, 800, 600]));
+        assert_eq!(scissor_rect(Some(Rect::new(10.5, -5.0, 20.0, 20.0)), screen), Some([10, 0, 21, 15]));
+        assert_eq!(scissor_rect(Some(Rect::new(900.0, 0.0, 20.0, 20.0)), screen), None);
+    }
+
+    #[test]
+    fn quads_past_the_buffer_capacity_are_dropped() {
+        let mut batch = ShapeBatch::default();
+        let style = RectStyle::new(Vec4::ONE).with_shadow(Vec2::ONE, 4.0, Vec4::ZERO);
+        for _ in 0..MAX_SHAPE_QUADS / 2 + 1 {
+            batch.push_styled_rect(Rect::new(0.0, 0.0, 10.0, 10.0), &style);
+        }
+
+        assert_eq!(batch.vertices.len(), 4 * MAX_SHAPE_QUADS);
+        assert_eq!(batch.indices.len(), 6 * MAX_SHAPE_QUADS);
+        assert_eq!(batch.indices.iter().max(), Some(&((4 * MAX_SHAPE_QUADS - 1) as u16)));
+        assert_eq!(batch.dropped, 2);
+    }
+
+    #[test]
+    fn layers_keep_shapes_and_text_in_submission_order() {
+        let mut layers = Vec::new();
+        push_layer(&mut layers, UiLayerKind::Shapes, 6);
+        push_layer(&mut layers, UiLayerKind::Shapes, 12);
+        push_layer(&mut layers, UiLayerKind::Text, 5);
+        // Text with no visible glyphs adds nothing
+        push_layer(&mut layers, UiLayerKind::Text, 5);
+        push_layer(&mut layers, UiLayerKind::Shapes, 18);
+        push_layer(&mut layers, UiLayerKind::Text, 9);
+
+        let runs: Vec<_> = layers.iter().map(|layer| (layer.kind, layer.range.clone())).collect();
+        assert_eq!(
+            runs,
+            vec![
+                (UiLayerKind::Shapes, 0..12),
+                (UiLayerKind::Text, 0..5),
+                (UiLayerKind::Shapes, 12..18),
+                (UiLayerKind::Text, 5..9),
+            ]
+        );
+    }
+}
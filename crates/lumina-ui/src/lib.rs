@@ -103,6 +103,11 @@ pub trait Widget: std::fmt::Debug {
         Vec::new()
     }
     
+    /// Whether children are clipped to this widget's bounds
+    fn clips_children(&self) -> bool {
+        false
+    }
+    
     /// Add a child widget
     fn add_child(&mut self, _child_id: WidgetId) {
         // Default implementation does nothing
@@ -343,8 +348,12 @@ impl UiFramework {
     fn render_widget_hierarchy(&mut self, widget_id: WidgetId, layout_cache: &std::collections::HashMap<WidgetId, layout::LayoutResult>, hierarchy: &std::collections::HashMap<WidgetId, Vec<WidgetId>>) {
         if let Some(layout) = layout_cache.get(&widget_id) {
             if let Some(widget) = self.state.widgets.get(&widget_id) {
+                let clip = widget.clips_children();
                 if let Some(renderer) = &mut self.renderer {
                     widget.render(renderer, layout.bounds);
+                    if clip {
+                        renderer.push_clip(layout.bounds);
+                    }
                 }
                 
                 // Render children
@@ -353,6 +362,12 @@ impl UiFramework {
                         self.render_widget_hierarchy(child_id, layout_cache, hierarchy);
                     }
                 }
+
+                if clip {
+                    if let Some(renderer) = &mut self.renderer {
+                        renderer.pop_clip();
+                    }
+                }
             }
         }
     }
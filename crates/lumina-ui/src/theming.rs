@@ -253,10 +253,17 @@ pub struct ButtonVariant {
     pub disabled: ButtonStateColors,
     /// Border radius
     pub border_radius: f32,
+    /// Border width, drawn when the border color differs from the background
+    #[serde(default = "default_button_border_width")]
+    pub border_width: f32,
     /// Padding
     pub padding: [f32; 4], // [top, right, bottom, left]
 }
 
+fn default_button_border_width() -> f32 {
+    1.0
+}
+
 /// Button state colors
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ButtonStateColors {
@@ -440,6 +447,7 @@ impl Theme {
                             border: Vec4::new(0.2, 0.2, 0.3, 1.0),
                         },
                         border_radius: 6.0,
+                        border_width: 1.0,
                         padding: [8.0, 16.0, 8.0, 16.0],
                     },
                     secondary: ButtonVariant {
@@ -464,6 +472,7 @@ impl Theme {
                             border: Vec4::new(0.2, 0.2, 0.25, 1.0),
                         },
                         border_radius: 6.0,
+                        border_width: 1.0,
                         padding: [8.0, 16.0, 8.0, 16.0],
                     },
                     ghost: ButtonVariant {
@@ -488,6 +497,7 @@ impl Theme {
                             border: Vec4::new(0.0, 0.0, 0.0, 0.0),
                         },
                         border_radius: 6.0,
+                        border_width: 1.0,
                         padding: [8.0, 16.0, 8.0, 16.0],
                     },
                     danger: ButtonVariant {
@@ -512,6 +522,7 @@ impl Theme {
                             border: Vec4::new(0.3, 0.15, 0.15, 1.0),
                         },
                         border_radius: 6.0,
+                        border_width: 1.0,
                         padding: [8.0, 16.0, 8.0, 16.0],
                     },
                 },
@@ -590,4 +601,4 @@ impl Default for Theme {
     fn default() -> Self {
         Self::dark()
     }
-}
\ No newline at end of file
+}
@@ -6,6 +6,7 @@ use crate::{
     layout::LayoutResult,
 };
 use glam::{Vec2, Vec4};
+use lumina_render::RectStyle;
 use serde::{Deserialize, Serialize};
 
 /// Button widget for user interactions
@@ -274,23 +275,20 @@ impl Widget for Button {
         let theme = Theme::default();
         let (bg_color, text_color, border_color) = self.get_current_colors(&theme);
         
-        // Get border radius
-        let border_radius = match self.variant {
-            ButtonVariant::Primary => theme.components.button.primary.border_radius,
-            ButtonVariant::Secondary => theme.components.button.secondary.border_radius,
-            ButtonVariant::Ghost => theme.components.button.ghost.border_radius,
-            ButtonVariant::Danger => theme.components.button.danger.border_radius,
+        let variant_theme = match self.variant {
+            ButtonVariant::Primary => &theme.components.button.primary,
+            ButtonVariant::Secondary => &theme.components.button.secondary,
+            ButtonVariant::Ghost => &theme.components.button.ghost,
+            ButtonVariant::Danger => &theme.components.button.danger,
         };
         
-        // Draw background
-        if bg_color.w > 0.0 { // Only draw if not transparent
-            renderer.draw_rounded_rect(bounds, bg_color, border_radius);
+        // Draw background and border; a border matching the background would be invisible
+        let mut style = RectStyle::new(bg_color).with_radii(variant_theme.border_radius);
+        if variant_theme.border_width > 0.0 && border_color.w > 0.0 && border_color != bg_color {
+            style = style.with_border(variant_theme.border_width, border_color);
         }
-        
-        // Draw border (if visible)
-        if border_color.w > 0.0 && border_color != bg_color {
-            // TODO: Implement border rendering
-            // For now, just draw a slightly smaller rectangle on top
+        if bg_color.w > 0.0 || style.border.is_some() {
+            renderer.draw_styled_rect(bounds, &style);
         }
         
         // Draw text
@@ -56,6 +56,10 @@ impl Widget for Container {
         self.children.clone()
     }
     
+    fn clips_children(&self) -> bool {
+        true
+    }
+    
     fn add_child(&mut self, child_id: WidgetId) {
         self.children.push(child_id);
     }
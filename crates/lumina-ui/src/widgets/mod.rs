@@ -16,7 +16,8 @@ pub use canvas::*;
 pub use container::*;
 
 use crate::{WidgetId, LayoutConstraints, layout::LayoutResult};
-use glam::Vec2;
+use glam::{Vec2, Vec4};
+use lumina_render::{RectShadow, RectStyle};
 use serde::{Deserialize, Serialize};
 
 /// Base widget properties that all widgets share
@@ -211,6 +212,39 @@ impl Margin {
     }
 }
 
+impl WidgetStyle {
+    /// Background, border and shadow as a renderer style, or `None` if there is nothing to draw
+    pub fn rect_style(&self) -> Option<RectStyle> {
+        if self.background_color.is_none() && self.border_color.is_none() && self.shadow.is_none() {
+            return None;
+        }
+
+        let opacity = self.opacity.unwrap_or(1.0);
+        let fade = |color: [f32; 4]| Vec4::from(color) * Vec4::new(1.0, 1.0, 1.0, opacity);
+
+        let mut style = RectStyle::new(fade(self.background_color.unwrap_or([0.0; 4])))
+            .with_radii(self.border_radius.unwrap_or(0.0));
+        if let Some(color) = self.border_color {
+            style = style.with_border(self.border_width.unwrap_or(1.0), fade(color));
+        }
+        style.shadow = self.shadow.as_ref().map(|shadow| RectShadow {
+            color: fade(shadow.color),
+            ..shadow.into()
+        });
+        Some(style)
+    }
+}
+
+impl From<&Shadow> for RectShadow {
+    fn from(shadow: &Shadow) -> Self {
+        Self {
+            offset: shadow.offset,
+            blur: shadow.blur_radius,
+            color: Vec4::from(shadow.color),
+        }
+    }
+}
+
 /// Widget animation state
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum AnimationState {
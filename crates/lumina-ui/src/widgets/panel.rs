@@ -67,10 +67,9 @@ impl Widget for Panel {
             return;
         }
         
-        // Draw panel background if specified
-        if let Some(bg_color) = self.base.style.background_color {
-            let border_radius = self.base.style.border_radius.unwrap_or(0.0);
-            renderer.draw_rounded_rect(bounds, bg_color.into(), border_radius);
+        // Draw panel background, border and shadow if specified
+        if let Some(style) = self.base.style.rect_style() {
+            renderer.draw_styled_rect(bounds, &style);
         }
     }
     
@@ -78,6 +77,10 @@ impl Widget for Panel {
         self.children.clone()
     }
     
+    fn clips_children(&self) -> bool {
+        true
+    }
+    
     fn add_child(&mut self, child_id: WidgetId) {
         self.children.push(child_id);
     }
@@ -1,6 +1,9 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use uuid::Uuid;
 
+pub mod play;
 pub mod project;
 pub mod websocket;
 pub mod api;
@@ -12,12 +15,15 @@ pub use websocket::*;
 #[derive(Clone)]
 pub struct AppState {
     pub project_manager: Arc<RwLock<project::ProjectManager>>,
+    /// Live preview of each project that has one
+    pub previews: Arc<RwLock<HashMap<Uuid, play::Preview>>>,
 }
 
 impl AppState {
     pub fn new() -> Self {
         Self {
             project_manager: Arc::new(RwLock::new(project::ProjectManager::new())),
+            previews: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
\ No newline at end of file
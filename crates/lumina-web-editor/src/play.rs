@@ -0,0 +1,348 @@
+use crate::project::{
+    ColliderComponent, ColliderShape, GameObject, PhysicsBodyType, Scene, SpriteComponent, Transform,
+};
+use lumina_core::math::{Vec2, Vec4};
+use lumina_ecs::{
+    Entity, Name, Prefab, PrefabOverride, Reflect, ReflectError, SerializationError, SnapshotFilter, Tags, Value, World, WorldSnapshot,
+};
+use lumina_render::Camera2D;
+use std::collections::HashMap;
+
+/// Identifies the scene object an entity was created from.
+/// Its name and tags are stored in the ECS `Name` and `Tags` components.
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct SceneObject {
+    pub object_id: String,
+    pub scripts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct ObjectTransform {
+    pub position: Vec2,
+    pub rotation: f32,
+    pub scale: Vec2,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct ObjectSprite {
+    pub asset_id: String,
+    pub color: Vec4,
+    pub visible: bool,
+    pub layer: i32,
+    pub material: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect)]
+pub enum ColliderKind {
+    #[default]
+    Rectangle,
+    Circle,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Reflect)]
+pub enum BodyKind {
+    #[default]
+    Static,
+    Dynamic,
+    Kinematic,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct ObjectCollider {
+    pub kind: ColliderKind,
+    /// Width and height for rectangles, radius in `x` for circles
+    pub size: Vec2,
+    pub is_sensor: bool,
+    pub body: BodyKind,
+}
+
+/// Registers the scene components so worlds can be snapshotted and restored
+pub fn register_scene_types(world: &World) {
+    world.register_component::<SceneObject>();
+    world.register_component::<ObjectTransform>();
+    world.register_component::<ObjectSprite>();
+    world.register_component::<ObjectCollider>();
+    world.register_component::<Camera2D>();
+}
+
+/// Builds an ECS world from a scene, one entity per game object plus a `Camera2D`
+/// entity for the scene camera. Objects linked to a prefab are instantiated from `prefabs`.
+pub fn scene_to_world(scene: &Scene, prefabs: &HashMap<String, Prefab>) -> World {
+    let world = World::new();
+    register_scene_types(&world);
+
+    let mut entities = HashMap::new();
+    for object in &scene.game_objects {
+        let entity = spawn_game_object(&world, object, prefabs);
+        entities.insert(object.id, entity);
+    }
+
+    let (x, y) = scene.camera.position;
+    let mut camera = Camera2D::new(Vec2::new(x, y)).with_zoom(scene.camera.zoom);
+    if let Some(&target) = scene.camera.follow_target.and_then(|id| entities.get(&id)) {
+        camera = camera.following(target, 0.0);
+    }
+    world.spawn_with(camera);
+    world
+}
+
+/// Builds a prefab from a template object.
+/// Instances replace its ID, name and position with their own.
+pub fn game_object_prefab(prefab_id: &str, template: &GameObject) -> Prefab {
+    let mut prefab = Prefab::new(prefab_id)
+        .with(SceneObject {
+            object_id: String::new(),
+            scripts: template.scripts.clone(),
+        })
+        .with(Name::new(template.name.clone()))
+        .with(Tags::new(template.tags.iter().cloned()))
+        .with(object_transform(&template.transform));
+    if let Some(sprite) = &template.sprite {
+        prefab = prefab.with(object_sprite(sprite));
+    }
+    if let Some(collider) = &template.collider {
+        prefab = prefab.with(object_collider(collider));
+    }
+    prefab
+}
+
+/// Entities of the scene objects in `world`, keyed by object ID
+pub fn scene_entities(world: &World) -> HashMap<String, Entity> {
+    world
+        .query::<SceneObject>()
+        .into_iter()
+        .map(|(entity, object)| (object.object_id, entity))
+        .collect()
+}
+
+/// Copies an entity's components back into the game object it was spawned from
+pub fn read_game_object(world: &World, entity: Entity, object: &mut GameObject) {
+    if let Some(scene_object) = world.get_component::<SceneObject>(entity) {
+        object.scripts = scene_object.scripts;
+    }
+    if let Some(name) = world.get_component::<Name>(entity) {
+        object.name = name.as_str().to_string();
+    }
+    if let Some(tags) = world.get_component::<Tags>(entity) {
+        object.tags = tags.iter().map(str::to_string).collect();
+    }
+    if let Some(transform) = world.get_component::<ObjectTransform>(entity) {
+        object.transform = Transform {
+            position: (transform.position.x, transform.position.y),
+            rotation: transform.rotation,
+            scale: (transform.scale.x, transform.scale.y),
+        };
+    }
+
+    object.sprite = world.get_component::<ObjectSprite>(entity).map(|sprite| SpriteComponent {
+        asset_id: sprite.asset_id,
+        color: (sprite.color.x, sprite.color.y, sprite.color.z, sprite.color.w),
+        visible: sprite.visible,
+        layer: sprite.layer,
+        material: sprite.material,
+    });
+
+    object.collider = world.get_component::<ObjectCollider>(entity).map(|collider| ColliderComponent {
+        shape: match collider.kind {
+            ColliderKind::Rectangle => ColliderShape::Rectangle {
+                width: collider.size.x,
+                height: collider.size.y,
+            },
+            ColliderKind::Circle => ColliderShape::Circle { radius: collider.size.x },
+        },
+        is_sensor: collider.is_sensor,
+        physics_body: match collider.body {
+            BodyKind::Static => PhysicsBodyType::Static,
+            BodyKind::Dynamic => PhysicsBodyType::Dynamic,
+            BodyKind::Kinematic => PhysicsBodyType::Kinematic,
+        },
+    });
+}
+
+/// Instantiates `prefab` for a linked object, then applies the object's ID, name
+/// and position followed by the overrides recorded in its link.
+pub fn spawn_prefab_instance(world: &World, object: &GameObject, prefab: &Prefab) -> Result<Entity, ReflectError> {
+    let entity = world.instantiate(prefab)?;
+    let (x, y) = object.transform.position;
+    let own = [
+        ("SceneObject", "object_id", Value::String(object.id.to_string())),
+        ("Name", "", Name::new(object.name.clone()).to_value()),
+        ("ObjectTransform", "position", Vec2::new(x, y).to_value()),
+    ]
+    .map(|(component, path, value)| PrefabOverride {
+        component: component.to_string(),
+        path: path.to_string(),
+        value,
+    });
+
+    let result = own
+        .into_iter()
+        .chain(object.prefab.iter().flat_map(|link| link.overrides.iter().cloned()))
+        .try_for_each(|entry| world.set_prefab_override(entity, &entry.component, &entry.path, entry.value));
+    if let Err(err) = result {
+        world.despawn_recursive(entity);
+        return Err(err);
+    }
+    Ok(entity)
+}
+
+fn spawn_game_object(world: &World, object: &GameObject, prefabs: &HashMap<String, Prefab>) -> Entity {
+    if let Some(link) = &object.prefab {
+        match prefabs.get(&link.prefab_id) {
+            Some(prefab) => match spawn_prefab_instance(world, object, prefab) {
+                Ok(entity) => return entity,
+                Err(err) => log::warn!("Failed to instantiate prefab {} for {}: {}", link.prefab_id, object.name, err),
+            },
+            None => log::warn!("{} links to missing prefab {}", object.name, link.prefab_id),
+        }
+    }
+
+    let mut builder = world
+        .spawn()
+        .with(SceneObject {
+            object_id: object.id.to_string(),
+            scripts: object.scripts.clone(),
+        })
+        .with(Name::new(object.name.clone()))
+        .with(Tags::new(object.tags.iter().cloned()))
+        .with(object_transform(&object.transform));
+    if let Some(sprite) = &object.sprite {
+        builder = builder.with(object_sprite(sprite));
+    }
+    if let Some(collider) = &object.collider {
+        builder = builder.with(object_collider(collider));
+    }
+    builder.build(world)
+}
+
+fn object_transform(transform: &Transform) -> ObjectTransform {
+    ObjectTransform {
+        position: Vec2::new(transform.position.0, transform.position.1),
+        rotation: transform.rotation,
+        scale: Vec2::new(transform.scale.0, transform.scale.1),
+    }
+}
+
+fn object_sprite(sprite: &SpriteComponent) -> ObjectSprite {
+    let (r, g, b, a) = sprite.color;
+    ObjectSprite {
+        asset_id: sprite.asset_id.clone(),
+        color: Vec4::new(r, g, b, a),
+        visible: sprite.visible,
+        layer: sprite.layer,
+        material: sprite.material.clone(),
+    }
+}
+
+fn object_collider(collider: &ColliderComponent) -> ObjectCollider {
+    let (kind, size) = match collider.shape {
+        ColliderShape::Rectangle { width, height } => (ColliderKind::Rectangle, Vec2::new(width, height)),
+        ColliderShape::Circle { radius } => (ColliderKind::Circle, Vec2::new(radius, radius)),
+    };
+    let body = match collider.physics_body {
+        PhysicsBodyType::Static => BodyKind::Static,
+        PhysicsBodyType::Dynamic => BodyKind::Dynamic,
+        PhysicsBodyType::Kinematic => BodyKind::Kinematic,
+    };
+    ObjectCollider {
+        kind,
+        size,
+        is_sensor: collider.is_sensor,
+        body,
+    }
+}
+
+/// Remembers the edit-time state of a world while the game runs in it.
+///
+/// Pressing Play starts a session; Stop restores every entity, component
+/// and registered resource to how it was, keeping entity IDs so editor
+/// selections stay valid.
+pub struct PlaySession {
+    edit_state: WorldSnapshot,
+}
+
+impl PlaySession {
+    pub fn start(world: &World) -> Self {
+        Self {
+            edit_state: world.snapshot(&SnapshotFilter::all()),
+        }
+    }
+
+    pub fn stop(self, world: &World) -> Result<(), SerializationError> {
+        world.restore_snapshot(&self.edit_state)
+    }
+}
+
+/// A scene world shown in the live preview
+pub struct Preview {
+    pub scene_name: String,
+    pub world: World,
+    /// Set while the game is playing
+    session: Option<PlaySession>,
+}
+
+impl Preview {
+    pub fn new(scene_name: impl Into<String>, world: World) -> Self {
+        Self {
+            scene_name: scene_name.into(),
+            world,
+            session: None,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.session.is_some()
+    }
+
+    /// Presses Play. A game that is already playing restarts from the edit state.
+    pub fn play(&mut self) -> Result<(), SerializationError> {
+        if let Some(session) = self.session.take() {
+            session.stop(&self.world)?;
+        }
+        self.session = Some(PlaySession::start(&self.world));
+        Ok(())
+    }
+
+    /// Presses Stop, restoring the edit state. Returns false if the game wasn't playing.
+    pub fn stop(&mut self) -> Result<bool, SerializationError> {
+        match self.session.take() {
+            Some(session) => session.stop(&self.world).map(|()| true),
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{GameTemplate, ProjectManager};
+
+    #[test]
+    fn play_session_restores_edit_state() {
+        let mut manager = ProjectManager::new();
+        let project = manager.create_project("Shooter".to_string(), GameTemplate::ArcadeShooter);
+        let world = manager.project_to_world(&project.id, "main").unwrap();
+        assert_eq!(world.entity_count(), 4);
+
+        let player = world.find_by_name("Player Ship").unwrap();
+        let (camera, follow) = world.query::<Camera2D>()[0];
+        assert_eq!(follow.follow.map(|follow| follow.target), Some(player));
+        assert_eq!(lumina_render::extract_cameras(&world, Vec2::new(800.0, 600.0)).len(), 1);
+        let session = PlaySession::start(&world);
+
+        world.with_component_mut::<ObjectTransform, _>(player, |transform| {
+            transform.unwrap().position.x += 100.0;
+        });
+        let bullet = world.spawn_with(ObjectTransform::default());
+        let enemy = world.entities_with_tag("Enemy")[0];
+        world.despawn(enemy);
+
+        session.stop(&world).unwrap();
+        assert_eq!(world.entity_count(), 4);
+        assert_eq!(world.get_component::<Camera2D>(camera), Some(follow));
+        assert!(world.is_alive(enemy));
+        assert_eq!(world.entities_with_tag("Enemy").len(), 2);
+        assert_eq!(world.get_component::<ObjectTransform>(player).unwrap().position, Vec2::new(0.0, -100.0));
+        assert!(!world.is_alive(bullet));
+    }
+}
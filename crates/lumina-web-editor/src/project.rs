@@ -1,5 +1,7 @@
+use crate::play;
+use lumina_core::math::{Vec2, Vec3, Vec4};
 use lumina_core::{VisualScript, visual_scripting::*};
-use lumina_ecs::World;
+use lumina_ecs::{Prefab, PrefabOverride, Reflect, Tags, Value, World};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -14,6 +16,12 @@ pub struct Project {
     pub scenes: HashMap<String, Scene>,
     pub assets: HashMap<String, Asset>,
     pub scripts: HashMap<String, VisualScript>,
+    /// Reusable object definitions, keyed by prefab ID
+    #[serde(default)]
+    pub prefabs: HashMap<String, Prefab>,
+    /// Materials sprites can be drawn with, keyed by material ID
+    #[serde(default)]
+    pub materials: HashMap<String, MaterialAsset>,
     pub settings: ProjectSettings,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub modified_at: chrono::DateTime<chrono::Utc>,
@@ -48,6 +56,80 @@ pub struct GameObject {
     pub collider: Option<ColliderComponent>,
     pub scripts: Vec<String>, // Script IDs
     pub tags: Vec<String>,
+    /// Set when the object was created from a project prefab
+    #[serde(default)]
+    pub prefab: Option<PrefabLink>,
+}
+
+/// Links a game object to the prefab it was created from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefabLink {
+    pub prefab_id: String,
+    /// Component fields this object sets itself, applied over the prefab.
+    /// The object's ID, name and position always belong to the instance.
+    pub overrides: Vec<PrefabOverride>,
+}
+
+impl GameObject {
+    pub fn new(name: &str, position: (f32, f32)) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            transform: Transform {
+                position,
+                rotation: 0.0,
+                scale: (1.0, 1.0),
+            },
+            sprite: None,
+            collider: None,
+            scripts: Vec::new(),
+            tags: Vec::new(),
+            prefab: None,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: f32) -> Self {
+        self.transform.rotation = rotation;
+        self
+    }
+
+    pub fn with_sprite(mut self, asset_id: &str, color: (f32, f32, f32, f32), layer: i32) -> Self {
+        self.sprite = Some(SpriteComponent {
+            asset_id: asset_id.to_string(),
+            color,
+            visible: true,
+            layer,
+            material: None,
+        });
+        self
+    }
+
+    /// Draws the object's sprite with a project material; does nothing without a sprite
+    pub fn with_material(mut self, material_id: &str) -> Self {
+        if let Some(sprite) = &mut self.sprite {
+            sprite.material = Some(material_id.to_string());
+        }
+        self
+    }
+
+    pub fn with_collider(mut self, shape: ColliderShape, is_sensor: bool, physics_body: PhysicsBodyType) -> Self {
+        self.collider = Some(ColliderComponent {
+            shape,
+            is_sensor,
+            physics_body,
+        });
+        self
+    }
+
+    pub fn with_scripts(mut self, scripts: &[&str]) -> Self {
+        self.scripts = scripts.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn with_tags(mut self, tags: &[&str]) -> Self {
+        self.tags = tags.iter().map(|t| t.to_string()).collect();
+        self
+    }
 }
 
 /// Transform component for positioning objects
@@ -65,6 +147,9 @@ pub struct SpriteComponent {
     pub color: (f32, f32, f32, f32), // RGBA
     pub visible: bool,
     pub layer: i32,
+    /// Material ID; `None` draws the plain sprite
+    #[serde(default)]
+    pub material: Option<String>,
 }
 
 /// Physics collider component
@@ -96,6 +181,16 @@ pub struct Camera {
     pub follow_target: Option<Uuid>, // GameObject ID to follow
 }
 
+impl Camera {
+    pub fn following(follow_target: Option<Uuid>) -> Self {
+        Self {
+            position: (0.0, 0.0),
+            zoom: 1.0,
+            follow_target,
+        }
+    }
+}
+
 /// Asset (image, sound, etc.) in the project
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Asset {
@@ -111,6 +206,8 @@ pub enum AssetType {
     Image,
     Audio,
     Font,
+    /// WGSL source, which may `#include` other shader assets by file name
+    Shader,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,6 +217,202 @@ pub struct AssetMetadata {
     pub duration: Option<f32>, // For audio in seconds
 }
 
+/// A shader asset together with the parameters and textures it is drawn with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterialAsset {
+    pub name: String,
+    /// Shader asset ID
+    pub shader: String,
+    /// Uniform parameters, in the order the shader's material struct declares them
+    pub params: Vec<MaterialParam>,
+    /// Image asset IDs, in binding order
+    pub textures: Vec<MaterialTexture>,
+}
+
+/// Value of a material parameter, stored the way `lumina_render::MaterialValue` serializes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MaterialValue {
+    Float(f32),
+    Vec2(Vec2),
+    Vec3(Vec3),
+    Vec4(Vec4),
+    /// A `vec4<f32>` edited as an RGBA color
+    Color(Vec4),
+}
+
+impl MaterialValue {
+    pub fn same_type(&self, other: &MaterialValue) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl From<f32> for MaterialValue {
+    fn from(value: f32) -> Self {
+        MaterialValue::Float(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialParam {
+    pub name: String,
+    pub value: MaterialValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaterialTexture {
+    pub name: String,
+    pub asset_id: String,
+}
+
+impl MaterialAsset {
+    pub fn new(name: &str, shader: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            shader: shader.to_string(),
+            params: Vec::new(),
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn with_param(mut self, name: &str, value: impl Into<MaterialValue>) -> Self {
+        self.params.push(MaterialParam {
+            name: name.to_string(),
+            value: value.into(),
+        });
+        self
+    }
+
+    pub fn with_texture(mut self, name: &str, asset_id: &str) -> Self {
+        self.textures.push(MaterialTexture {
+            name: name.to_string(),
+            asset_id: asset_id.to_string(),
+        });
+        self
+    }
+
+    /// Changes a parameter from the inspector; its type is fixed by the shader
+    pub fn set_param(&mut self, name: &str, value: MaterialValue) -> Result<(), String> {
+        let param = self
+            .params
+            .iter_mut()
+            .find(|param| param.name == name)
+            .ok_or_else(|| format!("Material {} has no parameter {}", self.name, name))?;
+        if !param.value.same_type(&value) {
+            return Err(format!("Parameter {} can't change type", name));
+        }
+        param.value = value;
+        Ok(())
+    }
+
+    /// Points a texture slot at another image asset
+    pub fn set_texture(&mut self, name: &str, asset_id: &str) -> Result<(), String> {
+        let texture = self
+            .textures
+            .iter_mut()
+            .find(|texture| texture.name == name)
+            .ok_or_else(|| format!("Material {} has no texture {}", self.name, name))?;
+        texture.asset_id = asset_id.to_string();
+        Ok(())
+    }
+}
+
+impl Project {
+    /// Adds or replaces a prefab built from a template object
+    pub fn add_prefab(&mut self, prefab_id: &str, template: &GameObject) {
+        self.prefabs.insert(prefab_id.to_string(), play::game_object_prefab(prefab_id, template));
+    }
+
+    /// Creates a new game object from a prefab, or an empty object if the prefab doesn't exist
+    pub fn instantiate_prefab(&self, prefab_id: &str, name: &str, position: (f32, f32)) -> GameObject {
+        let mut object = GameObject::new(name, position);
+        if self.prefabs.contains_key(prefab_id) {
+            object.prefab = Some(PrefabLink {
+                prefab_id: prefab_id.to_string(),
+                overrides: Vec::new(),
+            });
+            if let Err(err) = self.refresh_prefab_instance(&mut object) {
+                log::warn!("Failed to instantiate prefab {}: {}", prefab_id, err);
+                object.prefab = None;
+            }
+        }
+        object
+    }
+
+    /// Sets a component field on a prefab instance and records it so prefab updates keep it.
+    /// An empty `path` overrides the whole component.
+    pub fn override_prefab_field(&self, object: &mut GameObject, component: &str, path: &str, value: Value) -> Result<(), String> {
+        let mut updated = object.clone();
+        let link = updated
+            .prefab
+            .as_mut()
+            .ok_or_else(|| format!("{} is not a prefab instance", object.name))?;
+        link.overrides.retain(|entry| entry.component != component || entry.path != path);
+        link.overrides.push(PrefabOverride {
+            component: component.to_string(),
+            path: path.to_string(),
+            value,
+        });
+
+        self.refresh_prefab_instance(&mut updated)?;
+        *object = updated;
+        Ok(())
+    }
+
+    /// Replaces a prefab with one built from `template` and pushes it to its instances in
+    /// every scene, keeping their overrides. Returns how many instances were updated.
+    pub fn update_prefab(&mut self, prefab_id: &str, template: &GameObject) -> Result<usize, String> {
+        if !self.prefabs.contains_key(prefab_id) {
+            return Err(format!("Prefab {} not found", prefab_id));
+        }
+        let prefab = play::game_object_prefab(prefab_id, template);
+
+        let mut updated = 0;
+        let mut scenes = HashMap::new();
+        for (scene_name, scene) in &self.scenes {
+            let world = play::scene_to_world(scene, &self.prefabs);
+            updated += world.sync_prefab(&prefab).map_err(|err| err.to_string())?;
+
+            let entities = play::scene_entities(&world);
+            let mut game_objects = scene.game_objects.clone();
+            let linked = game_objects
+                .iter_mut()
+                .filter(|object| object.prefab.as_ref().is_some_and(|link| link.prefab_id == prefab_id));
+            for object in linked {
+                if let Some(&entity) = entities.get(&object.id.to_string()) {
+                    play::read_game_object(&world, entity, object);
+                }
+            }
+            scenes.insert(scene_name.clone(), game_objects);
+        }
+
+        for (scene_name, scene) in &mut self.scenes {
+            if let Some(game_objects) = scenes.remove(scene_name) {
+                scene.game_objects = game_objects;
+            }
+        }
+        self.prefabs.insert(prefab_id.to_string(), prefab);
+        Ok(updated)
+    }
+
+    /// Rebuilds a linked object from its prefab and overrides
+    fn refresh_prefab_instance(&self, object: &mut GameObject) -> Result<(), String> {
+        let link = object
+            .prefab
+            .as_ref()
+            .ok_or_else(|| format!("{} is not a prefab instance", object.name))?;
+        let prefab = self
+            .prefabs
+            .get(&link.prefab_id)
+            .ok_or_else(|| format!("Prefab {} not found", link.prefab_id))?;
+
+        let world = World::new();
+        play::register_scene_types(&world);
+        let entity = play::spawn_prefab_instance(&world, object, prefab).map_err(|err| err.to_string())?;
+        play::read_game_object(&world, entity, object);
+        Ok(())
+    }
+}
+
 /// Project-wide settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProjectSettings {
@@ -167,6 +460,8 @@ impl ProjectManager {
             scenes: HashMap::new(),
             assets: HashMap::new(),
             scripts: HashMap::new(),
+            prefabs: HashMap::new(),
+            materials: HashMap::new(),
             settings: ProjectSettings::default(),
             created_at: now,
             modified_at: now,
@@ -176,62 +471,23 @@ impl ProjectManager {
         match template {
             GameTemplate::Platformer2D => {
                 // Create a basic platformer scene with a player and some platforms
-                let player_id = Uuid::new_v4();
-                let platform_id = Uuid::new_v4();
+                let player = GameObject::new("Player", (0.0, 100.0))
+                    .with_sprite("player_sprite", (1.0, 1.0, 1.0, 1.0), 1)
+                    .with_collider(ColliderShape::Rectangle { width: 32.0, height: 48.0 }, false, PhysicsBodyType::Dynamic)
+                    .with_scripts(&["player_movement"])
+                    .with_tags(&["Player"]);
+                project.add_prefab("platform", &GameObject::new("Platform", (0.0, 0.0))
+                    .with_sprite("platform_sprite", (0.8, 0.8, 0.8, 1.0), 0)
+                    .with_collider(ColliderShape::Rectangle { width: 200.0, height: 32.0 }, false, PhysicsBodyType::Static)
+                    .with_tags(&["Platform"]));
 
                 project.scenes.insert("main".to_string(), Scene {
                     name: "Level 1".to_string(),
+                    camera: Camera::following(Some(player.id)),
                     game_objects: vec![
-                        GameObject {
-                            id: player_id,
-                            name: "Player".to_string(),
-                            transform: Transform {
-                                position: (0.0, 100.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "player_sprite".to_string(),
-                                color: (1.0, 1.0, 1.0, 1.0),
-                                visible: true,
-                                layer: 1,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 32.0, height: 48.0 },
-                                is_sensor: false,
-                                physics_body: PhysicsBodyType::Dynamic,
-                            }),
-                            scripts: vec!["player_movement".to_string()],
-                            tags: vec!["Player".to_string()],
-                        },
-                        GameObject {
-                            id: platform_id,
-                            name: "Platform".to_string(),
-                            transform: Transform {
-                                position: (0.0, 0.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "platform_sprite".to_string(),
-                                color: (0.8, 0.8, 0.8, 1.0),
-                                visible: true,
-                                layer: 0,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 200.0, height: 32.0 },
-                                is_sensor: false,
-                                physics_body: PhysicsBodyType::Static,
-                            }),
-                            scripts: Vec::new(),
-                            tags: vec!["Platform".to_string()],
-                        },
+                        player,
+                        project.instantiate_prefab("platform", "Platform", (0.0, 0.0)),
                     ],
-                    camera: Camera {
-                        position: (0.0, 0.0),
-                        zoom: 1.0,
-                        follow_target: Some(player_id),
-                    },
                     background: None,
                 });
 
@@ -241,108 +497,34 @@ impl ProjectManager {
             },
             GameTemplate::TopDownAdventure => {
                 // Create a top-down adventure scene with a player and some environment
-                let player_id = Uuid::new_v4();
-                let wall_id = Uuid::new_v4();
-                let tree_id = Uuid::new_v4();
-                let npc_id = Uuid::new_v4();
+                let player = GameObject::new("Player", (0.0, 0.0))
+                    .with_sprite("player_topdown", (0.2, 0.8, 0.2, 1.0), 2) // Green player
+                    .with_collider(ColliderShape::Circle { radius: 16.0 }, false, PhysicsBodyType::Dynamic)
+                    .with_scripts(&["topdown_movement"])
+                    .with_tags(&["Player"]);
+                let wall = GameObject::new("Wall", (100.0, 0.0))
+                    .with_sprite("wall_sprite", (0.5, 0.3, 0.1, 1.0), 1) // Brown wall
+                    .with_collider(ColliderShape::Rectangle { width: 32.0, height: 64.0 }, false, PhysicsBodyType::Static)
+                    .with_tags(&["Wall"]);
+                project.add_prefab("tree", &GameObject::new("Tree", (0.0, 0.0))
+                    .with_sprite("tree_sprite", (0.1, 0.7, 0.1, 1.0), 1) // Dark green tree
+                    .with_collider(ColliderShape::Circle { radius: 20.0 }, false, PhysicsBodyType::Static)
+                    .with_tags(&["Environment"]));
+                let npc = GameObject::new("Village NPC", (50.0, -50.0))
+                    .with_sprite("npc_sprite", (0.8, 0.6, 0.4, 1.0), 2) // Skin tone
+                    .with_collider(ColliderShape::Circle { radius: 14.0 }, true, PhysicsBodyType::Static)
+                    .with_scripts(&["npc_dialogue"])
+                    .with_tags(&["NPC"]);
 
                 project.scenes.insert("main".to_string(), Scene {
                     name: "Forest Area".to_string(),
+                    camera: Camera::following(Some(player.id)),
                     game_objects: vec![
-                        GameObject {
-                            id: player_id,
-                            name: "Player".to_string(),
-                            transform: Transform {
-                                position: (0.0, 0.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "player_topdown".to_string(),
-                                color: (0.2, 0.8, 0.2, 1.0), // Green player
-                                visible: true,
-                                layer: 2,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Circle { radius: 16.0 },
-                                is_sensor: false,
-                                physics_body: PhysicsBodyType::Dynamic,
-                            }),
-                            scripts: vec!["topdown_movement".to_string()],
-                            tags: vec!["Player".to_string()],
-                        },
-                        GameObject {
-                            id: wall_id,
-                            name: "Wall".to_string(),
-                            transform: Transform {
-                                position: (100.0, 0.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "wall_sprite".to_string(),
-                                color: (0.5, 0.3, 0.1, 1.0), // Brown wall
-                                visible: true,
-                                layer: 1,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 32.0, height: 64.0 },
-                                is_sensor: false,
-                                physics_body: PhysicsBodyType::Static,
-                            }),
-                            scripts: Vec::new(),
-                            tags: vec!["Wall".to_string()],
-                        },
-                        GameObject {
-                            id: tree_id,
-                            name: "Tree".to_string(),
-                            transform: Transform {
-                                position: (-80.0, 50.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "tree_sprite".to_string(),
-                                color: (0.1, 0.7, 0.1, 1.0), // Dark green tree
-                                visible: true,
-                                layer: 1,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Circle { radius: 20.0 },
-                                is_sensor: false,
-                                physics_body: PhysicsBodyType::Static,
-                            }),
-                            scripts: Vec::new(),
-                            tags: vec!["Environment".to_string()],
-                        },
-                        GameObject {
-                            id: npc_id,
-                            name: "Village NPC".to_string(),
-                            transform: Transform {
-                                position: (50.0, -50.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "npc_sprite".to_string(),
-                                color: (0.8, 0.6, 0.4, 1.0), // Skin tone
-                                visible: true,
-                                layer: 2,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Circle { radius: 14.0 },
-                                is_sensor: true,
-                                physics_body: PhysicsBodyType::Static,
-                            }),
-                            scripts: vec!["npc_dialogue".to_string()],
-                            tags: vec!["NPC".to_string()],
-                        },
+                        player,
+                        wall,
+                        project.instantiate_prefab("tree", "Tree", (-80.0, 50.0)),
+                        npc,
                     ],
-                    camera: Camera {
-                        position: (0.0, 0.0),
-                        zoom: 1.0,
-                        follow_target: Some(player_id),
-                    },
                     background: Some("forest_background".to_string()),
                 });
 
@@ -354,104 +536,39 @@ impl ProjectManager {
             },
             GameTemplate::PuzzleGame => {
                 // Create a puzzle game scene with a game board and pieces
-                let board_id = Uuid::new_v4();
-                let piece1_id = Uuid::new_v4();
-                let piece2_id = Uuid::new_v4();
-                let piece3_id = Uuid::new_v4();
+                let board = GameObject::new("Game Board", (0.0, 0.0))
+                    .with_sprite("puzzle_board", (0.9, 0.9, 0.9, 1.0), 0) // Light gray board
+                    .with_scripts(&["puzzle_manager"])
+                    .with_tags(&["Board"]);
+                project.add_prefab("puzzle_piece", &GameObject::new("Piece", (0.0, 0.0))
+                    .with_sprite("puzzle_piece", (1.0, 1.0, 1.0, 1.0), 1)
+                    .with_collider(ColliderShape::Rectangle { width: 32.0, height: 32.0 }, true, PhysicsBodyType::Kinematic)
+                    .with_scripts(&["draggable_piece"])
+                    .with_tags(&["PuzzlePiece"]));
+
+                let mut game_objects = vec![board];
+                for (name, x, color, tag) in [
+                    ("Red Piece", -60.0, Vec4::new(1.0, 0.2, 0.2, 1.0), "Red"),
+                    ("Blue Piece", 0.0, Vec4::new(0.2, 0.2, 1.0, 1.0), "Blue"),
+                    ("Green Piece", 60.0, Vec4::new(0.2, 1.0, 0.2, 1.0), "Green"),
+                ] {
+                    let mut piece = project.instantiate_prefab("puzzle_piece", name, (x, 60.0));
+                    let overridden = project
+                        .override_prefab_field(&mut piece, "ObjectSprite", "color", color.to_value())
+                        .and_then(|_| {
+                            let tags = Tags::new(["PuzzlePiece", tag]);
+                            project.override_prefab_field(&mut piece, "Tags", "", tags.to_value())
+                        });
+                    if let Err(err) = overridden {
+                        log::warn!("Failed to customize {}: {}", name, err);
+                    }
+                    game_objects.push(piece);
+                }
 
                 project.scenes.insert("main".to_string(), Scene {
                     name: "Puzzle Board".to_string(),
-                    game_objects: vec![
-                        GameObject {
-                            id: board_id,
-                            name: "Game Board".to_string(),
-                            transform: Transform {
-                                position: (0.0, 0.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "puzzle_board".to_string(),
-                                color: (0.9, 0.9, 0.9, 1.0), // Light gray board
-                                visible: true,
-                                layer: 0,
-                            }),
-                            collider: None,
-                            scripts: vec!["puzzle_manager".to_string()],
-                            tags: vec!["Board".to_string()],
-                        },
-                        GameObject {
-                            id: piece1_id,
-                            name: "Red Piece".to_string(),
-                            transform: Transform {
-                                position: (-60.0, 60.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "puzzle_piece".to_string(),
-                                color: (1.0, 0.2, 0.2, 1.0), // Red
-                                visible: true,
-                                layer: 1,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 32.0, height: 32.0 },
-                                is_sensor: true,
-                                physics_body: PhysicsBodyType::Kinematic,
-                            }),
-                            scripts: vec!["draggable_piece".to_string()],
-                            tags: vec!["PuzzlePiece".to_string(), "Red".to_string()],
-                        },
-                        GameObject {
-                            id: piece2_id,
-                            name: "Blue Piece".to_string(),
-                            transform: Transform {
-                                position: (0.0, 60.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "puzzle_piece".to_string(),
-                                color: (0.2, 0.2, 1.0, 1.0), // Blue
-                                visible: true,
-                                layer: 1,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 32.0, height: 32.0 },
-                                is_sensor: true,
-                                physics_body: PhysicsBodyType::Kinematic,
-                            }),
-                            scripts: vec!["draggable_piece".to_string()],
-                            tags: vec!["PuzzlePiece".to_string(), "Blue".to_string()],
-                        },
-                        GameObject {
-                            id: piece3_id,
-                            name: "Green Piece".to_string(),
-                            transform: Transform {
-                                position: (60.0, 60.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "puzzle_piece".to_string(),
-                                color: (0.2, 1.0, 0.2, 1.0), // Green
-                                visible: true,
-                                layer: 1,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 32.0, height: 32.0 },
-                                is_sensor: true,
-                                physics_body: PhysicsBodyType::Kinematic,
-                            }),
-                            scripts: vec!["draggable_piece".to_string()],
-                            tags: vec!["PuzzlePiece".to_string(), "Green".to_string()],
-                        },
-                    ],
-                    camera: Camera {
-                        position: (0.0, 0.0),
-                        zoom: 1.0,
-                        follow_target: None,
-                    },
+                    game_objects,
+                    camera: Camera::following(None),
                     background: None,
                 });
 
@@ -463,85 +580,26 @@ impl ProjectManager {
             },
             GameTemplate::ArcadeShooter => {
                 // Create an arcade shooter scene with player ship and enemies
-                let player_id = Uuid::new_v4();
-                let enemy1_id = Uuid::new_v4();
-                let enemy2_id = Uuid::new_v4();
+                let player = GameObject::new("Player Ship", (0.0, -100.0))
+                    .with_sprite("player_ship", (0.3, 0.8, 1.0, 1.0), 2) // Light blue ship
+                    .with_collider(ColliderShape::Rectangle { width: 24.0, height: 32.0 }, false, PhysicsBodyType::Dynamic)
+                    .with_scripts(&["player_ship_control", "ship_shooter"])
+                    .with_tags(&["Player", "Ship"]);
+                project.add_prefab("enemy_ship", &GameObject::new("Enemy Ship", (0.0, 0.0))
+                    .with_rotation(180.0)
+                    .with_sprite("enemy_ship", (1.0, 0.3, 0.3, 1.0), 2) // Red enemy
+                    .with_collider(ColliderShape::Rectangle { width: 20.0, height: 24.0 }, false, PhysicsBodyType::Dynamic)
+                    .with_scripts(&["enemy_ai", "enemy_shooter"])
+                    .with_tags(&["Enemy", "Ship"]));
 
                 project.scenes.insert("main".to_string(), Scene {
                     name: "Space Battle".to_string(),
+                    camera: Camera::following(Some(player.id)),
                     game_objects: vec![
-                        GameObject {
-                            id: player_id,
-                            name: "Player Ship".to_string(),
-                            transform: Transform {
-                                position: (0.0, -100.0),
-                                rotation: 0.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "player_ship".to_string(),
-                                color: (0.3, 0.8, 1.0, 1.0), // Light blue ship
-                                visible: true,
-                                layer: 2,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 24.0, height: 32.0 },
-                                is_sensor: false,
-                                physics_body: PhysicsBodyType::Dynamic,
-                            }),
-                            scripts: vec!["player_ship_control".to_string(), "ship_shooter".to_string()],
-                            tags: vec!["Player".to_string(), "Ship".to_string()],
-                        },
-                        GameObject {
-                            id: enemy1_id,
-                            name: "Enemy Ship 1".to_string(),
-                            transform: Transform {
-                                position: (-50.0, 100.0),
-                                rotation: 180.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "enemy_ship".to_string(),
-                                color: (1.0, 0.3, 0.3, 1.0), // Red enemy
-                                visible: true,
-                                layer: 2,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 20.0, height: 24.0 },
-                                is_sensor: false,
-                                physics_body: PhysicsBodyType::Dynamic,
-                            }),
-                            scripts: vec!["enemy_ai".to_string(), "enemy_shooter".to_string()],
-                            tags: vec!["Enemy".to_string(), "Ship".to_string()],
-                        },
-                        GameObject {
-                            id: enemy2_id,
-                            name: "Enemy Ship 2".to_string(),
-                            transform: Transform {
-                                position: (50.0, 120.0),
-                                rotation: 180.0,
-                                scale: (1.0, 1.0),
-                            },
-                            sprite: Some(SpriteComponent {
-                                asset_id: "enemy_ship".to_string(),
-                                color: (1.0, 0.3, 0.3, 1.0), // Red enemy
-                                visible: true,
-                                layer: 2,
-                            }),
-                            collider: Some(ColliderComponent {
-                                shape: ColliderShape::Rectangle { width: 20.0, height: 24.0 },
-                                is_sensor: false,
-                                physics_body: PhysicsBodyType::Dynamic,
-                            }),
-                            scripts: vec!["enemy_ai".to_string(), "enemy_shooter".to_string()],
-                            tags: vec!["Enemy".to_string(), "Ship".to_string()],
-                        },
+                        player,
+                        project.instantiate_prefab("enemy_ship", "Enemy Ship 1", (-50.0, 100.0)),
+                        project.instantiate_prefab("enemy_ship", "Enemy Ship 2", (50.0, 120.0)),
                     ],
-                    camera: Camera {
-                        position: (0.0, 0.0),
-                        zoom: 1.0,
-                        follow_target: Some(player_id),
-                    },
                     background: Some("space_background".to_string()),
                 });
 
@@ -560,11 +618,7 @@ impl ProjectManager {
                 project.scenes.insert("main".to_string(), Scene {
                     name: "Main Scene".to_string(),
                     game_objects: Vec::new(),
-                    camera: Camera {
-                        position: (0.0, 0.0),
-                        zoom: 1.0,
-                        follow_target: None,
-                    },
+                    camera: Camera::following(None),
                     background: None,
                 });
             }
@@ -604,17 +658,50 @@ impl ProjectManager {
         }
     }
 
+    /// Changes a material parameter sent by the inspector
+    pub fn set_material_param(
+        &mut self,
+        project_id: &Uuid,
+        material_id: &str,
+        name: &str,
+        value: MaterialValue,
+    ) -> Result<(), String> {
+        self.edit_material(project_id, material_id, |material| material.set_param(name, value))
+    }
+
+    /// Changes a material texture sent by the inspector
+    pub fn set_material_texture(
+        &mut self,
+        project_id: &Uuid,
+        material_id: &str,
+        name: &str,
+        asset_id: &str,
+    ) -> Result<(), String> {
+        self.edit_material(project_id, material_id, |material| material.set_texture(name, asset_id))
+    }
+
+    fn edit_material(
+        &mut self,
+        project_id: &Uuid,
+        material_id: &str,
+        edit: impl FnOnce(&mut MaterialAsset) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let project = self.projects.get_mut(project_id).ok_or("Project not found")?;
+        let material = project
+            .materials
+            .get_mut(material_id)
+            .ok_or_else(|| format!("Material {} not found", material_id))?;
+        edit(material)?;
+        project.modified_at = chrono::Utc::now();
+        Ok(())
+    }
+
     /// Convert project to Lumina ECS World for game execution
     pub fn project_to_world(&self, project_id: &Uuid, scene_name: &str) -> Option<World> {
         let project = self.get_project(project_id)?;
         let scene = project.scenes.get(scene_name)?;
 
-        let world = World::new();
-        
-        // TODO: Convert scene game objects to ECS entities with components
-        // This would create entities in the world based on the scene configuration
-        
-        Some(world)
+        Some(play::scene_to_world(scene, &project.prefabs))
     }
 }
 
@@ -622,4 +709,100 @@ impl Default for ProjectManager {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piece(project: &Project, name: &str) -> GameObject {
+        project.scenes["main"]
+            .game_objects
+            .iter()
+            .find(|object| object.name == name)
+            .cloned()
+            .unwrap()
+    }
+
+    #[test]
+    fn prefab_updates_propagate_and_keep_overrides() {
+        let mut manager = ProjectManager::new();
+        let mut project = manager.create_project("Puzzle".to_string(), GameTemplate::PuzzleGame);
+        let red = piece(&project, "Red Piece");
+        assert_eq!(red.sprite.as_ref().unwrap().color, (1.0, 0.2, 0.2, 1.0));
+        assert_eq!(red.tags, ["PuzzlePiece", "Red"]);
+        assert_eq!(red.prefab.as_ref().unwrap().overrides.len(), 2);
+
+        let template = GameObject::new("Piece", (0.0, 0.0))
+            .with_sprite("rounded_piece", (0.5, 0.5, 0.5, 1.0), 3)
+            .with_scripts(&["draggable_piece", "snapping"])
+            .with_tags(&["Piece"]);
+        assert_eq!(project.update_prefab("puzzle_piece", &template), Ok(3));
+
+        let red = piece(&project, "Red Piece");
+        let sprite = red.sprite.as_ref().unwrap();
+        assert_eq!((sprite.asset_id.as_str(), sprite.layer), ("rounded_piece", 3));
+        assert_eq!(sprite.color, (1.0, 0.2, 0.2, 1.0));
+        assert_eq!(red.tags, ["PuzzlePiece", "Red"]);
+        assert_eq!(red.scripts, ["draggable_piece", "snapping"]);
+        assert!(red.collider.is_none());
+        assert_eq!(red.transform.position, (-60.0, 60.0));
+        assert!(project.update_prefab("missing", &template).is_err());
+
+        let mut blue = piece(&project, "Blue Piece");
+        project.override_prefab_field(&mut blue, "ObjectSprite", "layer", Value::Int(5)).unwrap();
+        assert_eq!(blue.sprite.as_ref().unwrap().layer, 5);
+        assert!(project.override_prefab_field(&mut blue, "ObjectSprite", "missing", Value::Int(5)).is_err());
+        assert_eq!(blue.prefab.as_ref().unwrap().overrides.len(), 3);
+
+        manager.update_project(&project.id, project.clone()).unwrap();
+        let world = manager.project_to_world(&project.id, "main").unwrap();
+        assert_eq!(world.prefab_instances("puzzle_piece").len(), 3);
+        let green = world.find_by_name("Green Piece").unwrap();
+        assert_eq!(world.entities_with_tag("Green"), vec![green]);
+    }
+
+    #[test]
+    fn prefab_instances_take_rotation_from_the_prefab() {
+        let mut manager = ProjectManager::new();
+        let project = manager.create_project("Shooter".to_string(), GameTemplate::ArcadeShooter);
+        let enemy = piece(&project, "Enemy Ship 2");
+        assert_eq!(enemy.transform.rotation, 180.0);
+        assert_eq!(enemy.transform.position, (50.0, 120.0));
+        assert_eq!(enemy.tags, ["Enemy", "Ship"]);
+
+        let json = serde_json::to_string(&project).unwrap();
+        let loaded: Project = serde_json::from_str(&json).unwrap();
+        let respawned = loaded.instantiate_prefab("enemy_ship", "Enemy Ship 3", (0.0, 140.0));
+        assert_eq!(respawned.transform.rotation, 180.0);
+        assert_eq!(respawned.sprite.as_ref().unwrap().layer, 2);
+        assert!(respawned.prefab.is_some());
+        assert_eq!(loaded.scenes["main"].game_objects[2].prefab.as_ref().unwrap().prefab_id, "enemy_ship");
+    }
+
+    #[test]
+    fn material_edits_are_stored_in_the_project() {
+        let mut manager = ProjectManager::new();
+        let mut project = manager.create_project("Glow".to_string(), GameTemplate::PuzzleGame);
+        project.materials.insert(
+            "glow".to_string(),
+            MaterialAsset::new("Glow", "glow_shader")
+                .with_param("tint", MaterialValue::Color(Vec4::ONE))
+                .with_param("strength", 0.5)
+                .with_texture("noise", "noise_a"),
+        );
+        let id = project.id;
+        manager.update_project(&id, project).unwrap();
+
+        let red = MaterialValue::Color(Vec4::new(1.0, 0.0, 0.0, 1.0));
+        manager.set_material_param(&id, "glow", "tint", red).unwrap();
+        manager.set_material_texture(&id, "glow", "noise", "noise_b").unwrap();
+        assert!(manager.set_material_param(&id, "glow", "tint", MaterialValue::Float(1.0)).is_err());
+        assert!(manager.set_material_param(&id, "glow", "missing", red).is_err());
+        assert!(manager.set_material_texture(&id, "missing", "noise", "noise_b").is_err());
+
+        let json = serde_json::to_value(&manager.get_project(&id).unwrap().materials["glow"]).unwrap();
+        assert_eq!(json["params"][0]["value"], serde_json::json!({ "Color": [1.0, 0.0, 0.0, 1.0] }));
+        assert_eq!(json["params"][1]["value"], serde_json::json!({ "Float": 0.5 }));
+        assert_eq!(json["textures"][0]["asset_id"], "noise_b");
+    }
+}
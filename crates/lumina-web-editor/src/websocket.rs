@@ -1,8 +1,10 @@
 use axum::extract::ws::{Message, WebSocket};
-use futures::{sink::SinkExt, stream::StreamExt};
+use futures::{sink::{Sink, SinkExt}, stream::StreamExt};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::play::Preview;
+use crate::project::MaterialValue;
 use crate::AppState;
 
 /// Messages sent between client and server via WebSocket
@@ -27,6 +29,13 @@ pub enum WebSocketMessage {
         command: PreviewCommandType,
     },
     
+    /// Debug dump of a scene's ECS world, sent in reply to an `Inspect` command
+    WorldReport {
+        project_id: String,
+        scene_name: String,
+        report: serde_json::Value,
+    },
+    
     /// Error messages
     Error { message: String },
     
@@ -64,6 +73,26 @@ pub enum ProjectUpdateType {
         change_type: ChangeType,
     },
     
+    /// Material was added/edited/removed
+    MaterialChanged {
+        material_id: String,
+        change_type: ChangeType,
+    },
+    
+    /// Client changed a material parameter; the server stores it and replies with `MaterialChanged`
+    MaterialParamEdited {
+        material_id: String,
+        name: String,
+        value: MaterialValue,
+    },
+    
+    /// Client pointed a material texture at another image asset
+    MaterialTextureEdited {
+        material_id: String,
+        name: String,
+        asset_id: String,
+    },
+    
     /// Project settings changed
     SettingsChanged,
 }
@@ -99,6 +128,9 @@ pub enum PreviewCommandType {
         input_type: String,
         value: serde_json::Value,
     },
+    
+    /// Request a dump of the scene's entities, components and storages
+    Inspect { scene_name: String },
 }
 
 /// Handle WebSocket connection for a specific project
@@ -211,6 +243,26 @@ async fn handle_websocket_message(
             handle_preview_command(command, project_id, state, sender).await;
         }
         
+        WebSocketMessage::ProjectUpdate {
+            update: update @ (ProjectUpdateType::MaterialParamEdited { .. } | ProjectUpdateType::MaterialTextureEdited { .. }),
+            ..
+        } => {
+            let response = match apply_material_edit(update, project_id, state).await {
+                Ok(material_id) => WebSocketMessage::ProjectUpdate {
+                    project_id: project_id.to_string(),
+                    update: ProjectUpdateType::MaterialChanged {
+                        material_id,
+                        change_type: ChangeType::Modified,
+                    },
+                },
+                Err(message) => WebSocketMessage::Error { message },
+            };
+            
+            if let Ok(msg) = serde_json::to_string(&response) {
+                let _ = sender.send(Message::Text(msg)).await;
+            }
+        }
+        
         WebSocketMessage::ProjectUpdate { update, .. } => {
             // TODO: Apply the update to the project
             // This would modify the project state and potentially
@@ -224,29 +276,74 @@ async fn handle_websocket_message(
     }
 }
 
+/// Store a material edit in the project, returning the material's ID
+async fn apply_material_edit(update: ProjectUpdateType, project_id: &str, state: &AppState) -> Result<String, String> {
+    let id = Uuid::parse_str(project_id).map_err(|_| "Invalid project ID".to_string())?;
+    let mut project_manager = state.project_manager.write().await;
+    match update {
+        ProjectUpdateType::MaterialParamEdited { material_id, name, value } => {
+            project_manager.set_material_param(&id, &material_id, &name, value)?;
+            Ok(material_id)
+        }
+        ProjectUpdateType::MaterialTextureEdited { material_id, name, asset_id } => {
+            project_manager.set_material_texture(&id, &material_id, &name, &asset_id)?;
+            Ok(material_id)
+        }
+        _ => Err("Not a material edit".to_string()),
+    }
+}
+
+/// Start playing a scene in the project's preview, remembering its edit state.
+/// The scene's world is built from the project unless it is already being previewed.
+async fn start_preview(state: &AppState, project_id: &str, scene_name: &str) -> Result<(), String> {
+    let id = Uuid::parse_str(project_id).map_err(|_| "Invalid project ID".to_string())?;
+    let mut previews = state.previews.write().await;
+    if previews.get(&id).is_none_or(|preview| preview.scene_name != scene_name) {
+        let world = state
+            .project_manager
+            .read()
+            .await
+            .project_to_world(&id, scene_name)
+            .ok_or_else(|| format!("Scene {} not found", scene_name))?;
+        previews.insert(id, Preview::new(scene_name, world));
+    }
+    let preview = previews.get_mut(&id).expect("preview was just inserted");
+    preview.play().map_err(|err| err.to_string())
+}
+
+/// Stop the project's preview, restoring the scene to its edit state
+async fn stop_preview(state: &AppState, project_id: &str) -> Result<(), String> {
+    let id = Uuid::parse_str(project_id).map_err(|_| "Invalid project ID".to_string())?;
+    let mut previews = state.previews.write().await;
+    let stopped = match previews.get_mut(&id) {
+        Some(preview) => preview.stop().map_err(|err| err.to_string())?,
+        None => false,
+    };
+    if stopped {
+        Ok(())
+    } else {
+        Err("No preview is playing".to_string())
+    }
+}
+
 /// Handle preview commands (start, stop, pause, etc.)
-async fn handle_preview_command(
+async fn handle_preview_command<S: Sink<Message> + Unpin>(
     command: PreviewCommandType,
     project_id: &str,
     state: &AppState,
-    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    sender: &mut S,
 ) {
     match command {
         PreviewCommandType::Start { scene_name } => {
             log::info!("Starting preview for project {} scene {}", project_id, scene_name);
             
-            // TODO: Convert project to runnable game
-            // 1. Get project from manager
-            // 2. Convert scene to ECS world
-            // 3. Start game loop
-            // 4. Send preview updates back to client
-            
-            // For now, just acknowledge the command
-            let response = WebSocketMessage::PreviewCommand {
-                project_id: project_id.to_string(),
-                command: PreviewCommandType::Start {
-                    scene_name: scene_name.clone(),
+            // TODO: Run the game loop and send preview updates back to the client
+            let response = match start_preview(state, project_id, &scene_name).await {
+                Ok(()) => WebSocketMessage::PreviewCommand {
+                    project_id: project_id.to_string(),
+                    command: PreviewCommandType::Start { scene_name },
                 },
+                Err(message) => WebSocketMessage::Error { message },
             };
             
             if let Ok(msg) = serde_json::to_string(&response) {
@@ -256,7 +353,18 @@ async fn handle_preview_command(
         
         PreviewCommandType::Stop => {
             log::info!("Stopping preview for project {}", project_id);
-            // TODO: Stop the game loop
+            
+            let response = match stop_preview(state, project_id).await {
+                Ok(()) => WebSocketMessage::PreviewCommand {
+                    project_id: project_id.to_string(),
+                    command: PreviewCommandType::Stop,
+                },
+                Err(message) => WebSocketMessage::Error { message },
+            };
+            
+            if let Ok(msg) = serde_json::to_string(&response) {
+                let _ = sender.send(Message::Text(msg)).await;
+            }
         }
         
         PreviewCommandType::Pause => {
@@ -278,5 +386,90 @@ async fn handle_preview_command(
             log::info!("Injecting input {} = {:?} for project {}", input_type, value, project_id);
             // TODO: Inject input into the running game
         }
+        
+        PreviewCommandType::Inspect { scene_name } => {
+            let report = match Uuid::parse_str(project_id) {
+                Ok(id) => state
+                    .project_manager
+                    .read()
+                    .await
+                    .project_to_world(&id, &scene_name)
+                    .map(|world| world.inspect().to_json()),
+                Err(_) => None,
+            };
+            
+            let response = match report {
+                Some(report) => WebSocketMessage::WorldReport {
+                    project_id: project_id.to_string(),
+                    scene_name,
+                    report,
+                },
+                None => WebSocketMessage::Error {
+                    message: format!("Scene {} not found", scene_name),
+                },
+            };
+            
+            if let Ok(msg) = serde_json::to_string(&response) {
+                let _ = sender.send(Message::Text(msg)).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::play::ObjectTransform;
+    use crate::project::GameTemplate;
+    use lumina_core::math::Vec2;
+
+    async fn send(command: PreviewCommandType, project_id: &str, state: &AppState) -> WebSocketMessage {
+        let (mut sender, mut receiver) = futures::channel::mpsc::unbounded();
+        handle_preview_command(command, project_id, state, &mut sender).await;
+        drop(sender);
+        match receiver.next().await {
+            Some(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text reply, got {:?}", other),
+        }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn preview_start_and_stop_restore_the_scene() {
+        let state = AppState::new();
+        let project = state
+            .project_manager
+            .write()
+            .await
+            .create_project("Shooter".to_string(), GameTemplate::ArcadeShooter);
+        let project_id = project.id.to_string();
+
+        let start = PreviewCommandType::Start { scene_name: "main".to_string() };
+        let reply = send(start.clone(), &project_id, &state).await;
+        assert!(matches!(reply, WebSocketMessage::PreviewCommand { command: PreviewCommandType::Start { .. }, .. }));
+
+        let player = {
+            let previews = state.previews.read().await;
+            let world = &previews[&project.id].world;
+            let player = world.find_by_name("Player Ship").unwrap();
+            world.with_component_mut::<ObjectTransform, _>(player, |transform| transform.unwrap().position.x += 100.0);
+            world.despawn(world.entities_with_tag("Enemy")[0]);
+            player
+        };
+
+        let reply = send(PreviewCommandType::Stop, &project_id, &state).await;
+        assert!(matches!(reply, WebSocketMessage::PreviewCommand { command: PreviewCommandType::Stop, .. }));
+        {
+            let previews = state.previews.read().await;
+            let preview = &previews[&project.id];
+            assert!(!preview.is_playing());
+            let transform = preview.world.get_component::<ObjectTransform>(player).unwrap();
+            assert_eq!(transform.position, Vec2::new(0.0, -100.0));
+            assert_eq!(preview.world.entities_with_tag("Enemy").len(), 2);
+        }
+
+        let reply = send(PreviewCommandType::Stop, &project_id, &state).await;
+        assert!(matches!(reply, WebSocketMessage::Error { .. }));
+        let reply = send(PreviewCommandType::Start { scene_name: "missing".to_string() }, &project_id, &state).await;
+        assert!(matches!(reply, WebSocketMessage::Error { .. }));
+    }
+}
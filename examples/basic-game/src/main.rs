@@ -5,7 +5,14 @@ use lumina_core::{
     math::Vec2,
     Result,
 };
-use lumina_ecs::{EcsSystemRunner, World, make_system};
+use lumina_ecs::{in_state, make_system, on_timer, EcsSystemRunner, OnEnter, Query, World};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum GameState {
+    Playing,
+    Paused,
+}
 
 #[derive(Debug, Clone)]
 struct Position(Vec2);
@@ -41,9 +48,16 @@ impl App for BasicGameApp {
             .with(Player { speed: 200.0 })
             .build(&world);
         
-        ecs.add_system(make_system(player_movement_system));
-        ecs.add_system(make_system(movement_system));
-        ecs.add_system(make_system(debug_system));
+        ecs.add_state(GameState::Playing);
+        ecs.add_system_to(OnEnter(GameState::Paused), make_system(|_, _| {
+            println!("⏸️  Paused - press P to resume");
+            Ok(())
+        }));
+        ecs.add_system(make_system(pause_system));
+        ecs.add_system(make_system(player_movement_system)).in_set("gameplay");
+        ecs.add_system(movement_system).in_set("gameplay");
+        ecs.add_system(make_system(debug_system)).run_if(on_timer(Duration::from_secs(1)));
+        ecs.configure_set("gameplay").run_if(in_state(GameState::Playing));
         
         engine.add_system(ecs)?;
         
@@ -68,6 +82,17 @@ impl App for BasicGameApp {
     }
 }
 
+fn pause_system(world: &World, context: &lumina_core::engine::SystemContext) -> Result<()> {
+    if context.input.is_key_just_pressed(&Key::P) {
+        let next = match world.state::<GameState>() {
+            Some(GameState::Playing) => GameState::Paused,
+            _ => GameState::Playing,
+        };
+        world.set_next_state(next);
+    }
+    Ok(())
+}
+
 fn player_movement_system(world: &World, context: &lumina_core::engine::SystemContext) -> Result<()> {
     let input = &context.input;
     let time = context.time.read();
@@ -104,30 +129,22 @@ fn player_movement_system(world: &World, context: &lumina_core::engine::SystemCo
     Ok(())
 }
 
-fn movement_system(world: &World, context: &lumina_core::engine::SystemContext) -> Result<()> {
-    let time = context.time.read();
-    let dt = time.delta_seconds();
+fn movement_system(
+    context: &lumina_core::engine::SystemContext,
+    bodies: Query<(&mut Position, &Velocity)>,
+) -> Result<()> {
+    let dt = context.time.read().delta_seconds();
     
     // Update positions based on velocities
-    for (entity, velocity) in world.query::<Velocity>() {
-        world.with_component_mut::<Position, ()>(entity, |position_opt| {
-            if let Some(position) = position_opt {
-                position.0 += velocity.0 * dt;
-            }
-        });
-    }
+    bodies.for_each(|(mut position, velocity)| position.0 += velocity.0 * dt);
     
     Ok(())
 }
 
-fn debug_system(world: &World, context: &lumina_core::engine::SystemContext) -> Result<()> {
-    let time = context.time.read();
-    
-    if time.frame_count() % 60 == 0 {
-        for (entity, position) in world.query::<Position>() {
-            if world.has_component::<Player>(entity) {
-                println!("Player position: ({:.1}, {:.1})", position.0.x, position.0.y);
-            }
+fn debug_system(world: &World, _context: &lumina_core::engine::SystemContext) -> Result<()> {
+    for (entity, position) in world.query::<Position>() {
+        if world.has_component::<Player>(entity) {
+            println!("Player position: ({:.1}, {:.1})", position.0.x, position.0.y);
         }
     }
     
@@ -150,7 +167,7 @@ fn main() -> Result<()> {
     let runner = AppRunner::with_config(app, config);
     
     println!("🚀 Starting Basic Game");
-    println!("Controls: WASD or Arrow Keys to move, ESC to quit");
+    println!("Controls: WASD or Arrow Keys to move, P to pause, ESC to quit");
     
     runner.run()
 }